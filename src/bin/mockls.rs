@@ -11,7 +11,7 @@ use std::collections::HashMap;
 use std::io::{Read, Write};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use clap::Parser;
 use serde::{Deserialize, Serialize};
@@ -49,6 +49,12 @@ struct Args {
     #[arg(long)]
     diagnostics_on_save: bool,
 
+    /// Only publish diagnostics on `workspace/didChangeWatchedFiles`, for
+    /// each changed file (simulates servers that re-check files when their
+    /// file watcher fires).
+    #[arg(long)]
+    diagnostics_on_watched_files: bool,
+
     /// Close stdout after n responses (simulate crash).
     #[arg(long)]
     drop_after: Option<u64>,
@@ -78,6 +84,78 @@ struct Args {
     /// notifications (simulates a server doing work without progress).
     #[arg(long)]
     cpu_busy: Option<u64>,
+
+    /// Also publish diagnostics for this sibling file name whenever an open
+    /// document's diagnostics are published (simulates servers that report
+    /// project-wide diagnostics, including files that were never opened).
+    #[arg(long)]
+    publish_related: Option<String>,
+
+    /// Publish an error diagnostic on every line containing this text.
+    #[arg(long)]
+    error_marker: Option<String>,
+
+    /// Advertise `inlayHintProvider` and answer with a type hint after each
+    /// `NAME=` assignment.
+    #[arg(long)]
+    inlay_hints: bool,
+
+    /// Advertise `foldingRangeProvider`: runs of `#` comment lines fold as
+    /// `comment`, `{ ... }` blocks as `region`.
+    #[arg(long)]
+    folding_ranges: bool,
+
+    /// Advertise `renameProvider.prepareProvider`, answer `prepareRename`
+    /// with the range of the word under the cursor, and answer `rename` by
+    /// replacing every occurrence of that word in the document.
+    #[arg(long)]
+    prepare_rename: bool,
+
+    /// Advertise `codeLensProvider` with resolve: each function gets a lens
+    /// whose `N references` title is only filled in by `codeLens/resolve`.
+    #[arg(long)]
+    code_lens: bool,
+
+    /// Advertise incremental text sync (`change: 2`) and apply the ranged
+    /// edits in `didChange` to the stored document.
+    #[arg(long)]
+    incremental_sync: bool,
+
+    /// Send this text as a `window/showMessage` warning after `initialized`.
+    #[arg(long)]
+    show_message: Option<String>,
+
+    /// Offer the error-marker quickfix as a `mockls.removeMarker` command
+    /// that sends its edit via `workspace/applyEdit` (as gopls does),
+    /// instead of a lazily-resolved edit.
+    #[arg(long)]
+    command_fixes: bool,
+
+    /// Offer a `source.organizeImports` action that sorts the first block
+    /// of `import ` lines, when asked for that kind and the block is unsorted.
+    #[arg(long)]
+    organize_imports: bool,
+
+    /// Write this line to stderr at startup.
+    #[arg(long)]
+    stderr_line: Option<String>,
+
+    /// Answer every `textDocument/definition` with `null`, as servers do
+    /// when they cannot resolve the position.
+    #[arg(long)]
+    no_definition: bool,
+
+    /// Report indexing progress for N milliseconds after each `didOpen`,
+    /// answering `hover` and `definition` with `null` until it ends
+    /// (simulates servers that re-index when a file is opened).
+    #[arg(long, default_value_t = 0)]
+    index_on_open: u64,
+
+    /// Publish a second round N milliseconds after each publication, adding
+    /// an error on the first line (simulates slower checks, like flycheck,
+    /// reporting after the fast ones).
+    #[arg(long)]
+    late_diagnostics: Option<u64>,
 }
 
 /// A JSON-RPC request.
@@ -154,6 +232,11 @@ struct MockServer {
     writer: Writer,
     shutdown_flag: Arc<AtomicBool>,
     next_request_id: Arc<AtomicU64>,
+    /// End of the indexing started by `--index-on-open`, if any.
+    indexing_until: Option<Instant>,
+    /// `workspaceFolders` sent with `initialize`, echoed by
+    /// `mockls.workspaceFolders`.
+    workspace_folders: Value,
 }
 
 impl MockServer {
@@ -166,6 +249,8 @@ impl MockServer {
             writer,
             shutdown_flag: Arc::new(AtomicBool::new(false)),
             next_request_id: Arc::new(AtomicU64::new(1)),
+            indexing_until: None,
+            workspace_folders: Value::Null,
         }
     }
 
@@ -240,13 +325,45 @@ impl MockServer {
         }
 
         let result = match method {
-            "initialize" => Some(self.handle_initialize()),
+            "initialize" => {
+                self.workspace_folders = request.params["workspaceFolders"].clone();
+                Some(self.handle_initialize())
+            }
             "shutdown" => Some(Value::Null),
+            "textDocument/hover" | "textDocument/definition" if self.is_indexing() => {
+                Some(Value::Null)
+            }
             "textDocument/hover" => self.handle_hover(&request.params),
+            "textDocument/definition" if self.args.no_definition => Some(Value::Null),
             "textDocument/definition" => self.handle_definition(&request.params),
             "textDocument/references" => self.handle_references(&request.params),
+            "textDocument/documentHighlight" => self.handle_document_highlight(&request.params),
             "textDocument/documentSymbol" => self.handle_document_symbols(&request.params),
             "workspace/symbol" => Some(self.handle_workspace_symbols(&request.params)),
+            "textDocument/completion" => self.handle_completion(&request.params),
+            "textDocument/codeAction" => Some(self.handle_code_action(&request.params)),
+            "codeAction/resolve" => Some(self.handle_code_action_resolve(&request.params)),
+            "workspace/executeCommand" => Some(self.handle_execute_command(&request.params)),
+            "completionItem/resolve" => Some(Self::handle_completion_resolve(&request.params)),
+            "textDocument/selectionRange" => self.handle_selection_range(&request.params),
+            "textDocument/foldingRange" if self.args.folding_ranges => {
+                self.handle_folding_range(&request.params)
+            }
+            "textDocument/prepareRename" if self.args.prepare_rename => {
+                Some(self.handle_prepare_rename(&request.params))
+            }
+            "textDocument/rename" if self.args.prepare_rename => {
+                self.handle_rename(&request.params)
+            }
+            "textDocument/codeLens" if self.args.code_lens => {
+                self.handle_code_lens(&request.params)
+            }
+            "codeLens/resolve" if self.args.code_lens => {
+                Some(self.handle_code_lens_resolve(&request.params))
+            }
+            "textDocument/inlayHint" if self.args.inlay_hints => {
+                self.handle_inlay_hint(&request.params)
+            }
             _ => {
                 self.send_response(&Response {
                     jsonrpc: "2.0".to_string(),
@@ -273,12 +390,21 @@ impl MockServer {
         }
     }
 
+    /// Whether diagnostics are published on `didOpen`/`didChange`.
+    const fn publishes_on_sync(&self) -> bool {
+        !self.args.no_diagnostics
+            && !self.args.diagnostics_on_save
+            && !self.args.diagnostics_on_watched_files
+    }
+
     fn handle_notification(&mut self, method: &str, params: &Value) {
+        if method == "initialized" {
+            self.send_show_message();
+        }
+
         match method {
-            "initialized" => {
-                if self.args.indexing_delay > 0 {
-                    self.start_indexing_simulation();
-                }
+            "initialized" if self.args.indexing_delay > 0 => {
+                self.start_indexing_simulation();
             }
             "textDocument/didOpen" => {
                 if let Some(td) = params.get("textDocument") {
@@ -292,9 +418,12 @@ impl MockServer {
                     self.documents.insert(uri.to_string(), text.to_string());
                     self.versions.insert(uri.to_string(), version);
 
-                    if !self.args.no_diagnostics && !self.args.diagnostics_on_save {
+                    if self.publishes_on_sync() {
                         self.publish_diagnostics(uri);
                     }
+                    if self.args.index_on_open > 0 {
+                        self.start_open_indexing();
+                    }
                 }
             }
             "textDocument/didChange" => {
@@ -306,14 +435,25 @@ impl MockServer {
                         .and_then(|v| i32::try_from(v).ok())
                         .unwrap_or(1);
                     self.versions.insert(uri.to_string(), version);
-                    if let Some(text) = params
+                    let changes = params
                         .get("contentChanges")
                         .and_then(Value::as_array)
-                        .and_then(|arr| arr.last())
-                        .and_then(|c| c.get("text"))
-                        .and_then(Value::as_str)
-                    {
-                        self.documents.insert(uri.to_string(), text.to_string());
+                        .map(Vec::as_slice)
+                        .unwrap_or_default();
+                    for change in changes {
+                        let Some(text) = change.get("text").and_then(Value::as_str) else {
+                            continue;
+                        };
+                        let updated = match change.get("range") {
+                            Some(range) if self.args.incremental_sync => self
+                                .documents
+                                .get(uri)
+                                .and_then(|content| apply_range_edit(content, range, text)),
+                            _ => Some(text.to_string()),
+                        };
+                        if let Some(updated) = updated {
+                            self.documents.insert(uri.to_string(), updated);
+                        }
                     }
 
                     // Simulate CPU-bound work without any notifications
@@ -326,7 +466,7 @@ impl MockServer {
 
                     if self.args.progress_on_change {
                         self.simulate_progress_around_diagnostics(uri);
-                    } else if !self.args.no_diagnostics && !self.args.diagnostics_on_save {
+                    } else if self.publishes_on_sync() {
                         self.publish_diagnostics(uri);
                     }
                 }
@@ -334,7 +474,15 @@ impl MockServer {
             "textDocument/didSave" => {
                 if let Some(td) = params.get("textDocument") {
                     let uri = td.get("uri").and_then(Value::as_str).unwrap_or_default();
-                    if !self.args.no_diagnostics {
+                    if !self.args.no_diagnostics && !self.args.diagnostics_on_watched_files {
+                        self.publish_diagnostics(uri);
+                    }
+                }
+            }
+            "workspace/didChangeWatchedFiles" if self.args.diagnostics_on_watched_files => {
+                let changes = params.get("changes").and_then(Value::as_array);
+                for change in changes.into_iter().flatten() {
+                    if let Some(uri) = change.get("uri").and_then(Value::as_str) {
                         self.publish_diagnostics(uri);
                     }
                 }
@@ -359,8 +507,12 @@ impl MockServer {
             "hoverProvider": true,
             "definitionProvider": true,
             "referencesProvider": true,
+            "documentHighlightProvider": true,
             "documentSymbolProvider": true,
             "workspaceSymbolProvider": true,
+            "completionProvider": { "triggerCharacters": ["."], "resolveProvider": true },
+            "codeActionProvider": { "resolveProvider": true },
+            "selectionRangeProvider": true,
             "textDocumentSync": {
                 "openClose": true,
                 "change": 1,
@@ -368,6 +520,26 @@ impl MockServer {
             }
         });
 
+        if self.args.inlay_hints {
+            capabilities["inlayHintProvider"] = Value::Bool(true);
+        }
+
+        if self.args.folding_ranges {
+            capabilities["foldingRangeProvider"] = Value::Bool(true);
+        }
+
+        if self.args.incremental_sync {
+            capabilities["textDocumentSync"]["change"] = Value::from(2);
+        }
+
+        if self.args.code_lens {
+            capabilities["codeLensProvider"] = serde_json::json!({ "resolveProvider": true });
+        }
+
+        if self.args.prepare_rename {
+            capabilities["renameProvider"] = serde_json::json!({ "prepareProvider": true });
+        }
+
         if self.args.workspace_folders {
             capabilities["workspace"] = serde_json::json!({
                 "workspaceFolders": {
@@ -448,6 +620,35 @@ impl MockServer {
         Some(Value::Array(locations))
     }
 
+    /// Highlights every occurrence of the word in the document: `Write` when
+    /// directly followed by `=`, `Read` otherwise. Returns `null` off a word.
+    fn handle_document_highlight(&self, params: &Value) -> Option<Value> {
+        let (uri, line, col) = extract_position(params)?;
+        let content = self.documents.get(uri)?;
+        let word = extract_word(content, line, col)?;
+
+        let mut highlights = Vec::new();
+        for (line_idx, line_text) in content.lines().enumerate() {
+            let mut start = 0;
+            while let Some(pos) = line_text[start..].find(&word) {
+                let col_idx = start + pos;
+                let end = col_idx + word.len();
+                let kind = if line_text[end..].starts_with('=') {
+                    3
+                } else {
+                    2
+                };
+                highlights.push(serde_json::json!({
+                    "range": location_json(uri, line_idx, col_idx, end)["range"],
+                    "kind": kind
+                }));
+                start = end;
+            }
+        }
+
+        Some(Value::Array(highlights))
+    }
+
     fn handle_document_symbols(&self, params: &Value) -> Option<Value> {
         let uri = params
             .get("textDocument")
@@ -458,6 +659,392 @@ impl MockServer {
         Some(Value::Array(extract_symbols(content)))
     }
 
+    /// Offers every symbol in the document plus a fixed set of keywords.
+    /// When triggered by `.`, offers a single `member` field instead.
+    fn handle_completion(&self, params: &Value) -> Option<Value> {
+        let (uri, _, _) = extract_position(params)?;
+        let content = self.documents.get(uri)?;
+
+        let trigger = params
+            .get("context")
+            .and_then(|c| c.get("triggerCharacter"))
+            .and_then(Value::as_str);
+        if trigger == Some(".") {
+            let items = serde_json::json!([{ "label": "member", "kind": 5 }]);
+            return Some(serde_json::json!({ "isIncomplete": false, "items": items }));
+        }
+
+        let mut items: Vec<Value> = extract_symbols(content)
+            .iter()
+            .filter_map(|sym| {
+                let name = sym.get("name").and_then(Value::as_str)?;
+                // SymbolKind -> CompletionItemKind
+                let kind = match sym.get("kind").and_then(Value::as_u64)? {
+                    12 => 3,  // Function
+                    14 => 21, // Constant
+                    _ => 6,   // Variable
+                };
+                Some(serde_json::json!({ "label": name, "kind": kind }))
+            })
+            .collect();
+        for keyword in ["if", "for", "while"] {
+            items.push(serde_json::json!({ "label": keyword, "kind": 14 }));
+        }
+
+        Some(serde_json::json!({ "isIncomplete": false, "items": items }))
+    }
+
+    /// Fills in documentation for an item from `handle_completion`.
+    fn handle_completion_resolve(params: &Value) -> Value {
+        let mut item = params.clone();
+        let label = params["label"].as_str().unwrap_or_default();
+        item["documentation"] = serde_json::json!({
+            "kind": "markdown",
+            "value": format!("mockls docs for `{label}`")
+        });
+        item
+    }
+
+    /// Offers a lazily-resolved quickfix for each marked error in the
+    /// request context, plus a command that has no edits.
+    fn handle_code_action(&self, params: &Value) -> Value {
+        let uri = params
+            .get("textDocument")
+            .and_then(|td| td.get("uri"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+
+        let only = params["context"]["only"].as_array();
+        if only.is_some_and(|kinds| kinds.iter().any(|k| k == "source.organizeImports")) {
+            let offered = self.args.organize_imports && self.organize_imports_edit(uri).is_some();
+            return Value::Array(if offered {
+                vec![serde_json::json!({
+                    "title": "Organize imports",
+                    "kind": "source.organizeImports",
+                    "data": { "uri": uri, "organize": true },
+                })]
+            } else {
+                Vec::new()
+            });
+        }
+
+        let diagnostics = params
+            .get("context")
+            .and_then(|c| c.get("diagnostics"))
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+
+        let mut actions: Vec<Value> = diagnostics
+            .iter()
+            .filter(|d| d.get("message").and_then(Value::as_str) == Some("mockls: marked error"))
+            .map(|d| {
+                let line = &d["range"]["start"]["line"];
+                let mut action = serde_json::json!({
+                    "title": "Remove error marker",
+                    "kind": "quickfix",
+                    "isPreferred": true,
+                    "diagnostics": [d],
+                });
+                if self.args.command_fixes {
+                    action["command"] = serde_json::json!({
+                        "title": "Remove error marker",
+                        "command": "mockls.removeMarker",
+                        "arguments": [uri, line]
+                    });
+                } else {
+                    action["data"] = serde_json::json!({ "uri": uri, "line": line });
+                }
+                action
+            })
+            .collect();
+        actions.push(serde_json::json!({
+            "title": "Explain with mockls",
+            "command": "mockls.explain"
+        }));
+        Value::Array(actions)
+    }
+
+    /// Fills in the edit for an action from `handle_code_action`.
+    fn handle_code_action_resolve(&self, params: &Value) -> Value {
+        let mut action = params.clone();
+        let uri = params["data"]["uri"].as_str().unwrap_or_default();
+        let edit = if params["data"]["organize"] == true {
+            self.organize_imports_edit(uri)
+        } else {
+            let line = params["data"]["line"].as_u64().unwrap_or_default();
+            self.marker_edit(uri, line)
+        };
+        if let Some(edit) = edit {
+            action["edit"] = edit;
+        }
+        action
+    }
+
+    /// The edit sorting the first block of `import ` lines in `uri`, if
+    /// there is one and it is out of order.
+    fn organize_imports_edit(&self, uri: &str) -> Option<Value> {
+        let lines: Vec<&str> = self.documents.get(uri)?.lines().collect();
+        let start = lines.iter().position(|l| l.starts_with("import "))?;
+        let len = lines[start..]
+            .iter()
+            .take_while(|l| l.starts_with("import "))
+            .count();
+        let block = &lines[start..start + len];
+        if block.is_sorted() {
+            return None;
+        }
+
+        let mut sorted = block.to_vec();
+        sorted.sort_unstable();
+        let mut new_text = sorted.join("\n");
+        new_text.push('\n');
+        Some(serde_json::json!({
+            "changes": {
+                uri: [{
+                    "range": {
+                        "start": { "line": start, "character": 0 },
+                        "end": { "line": start + len, "character": 0 }
+                    },
+                    "newText": new_text
+                }]
+            }
+        }))
+    }
+
+    /// Runs `mockls.removeMarker` by asking the client to apply its edit
+    /// and `mockls.echo` by returning its arguments; other commands do
+    /// nothing.
+    fn handle_execute_command(&self, params: &Value) -> Value {
+        if params["command"].as_str() == Some("mockls.echo") {
+            return params["arguments"].clone();
+        }
+        if params["command"].as_str() == Some("mockls.workspaceFolders") {
+            return self.workspace_folders.clone();
+        }
+        if params["command"].as_str() == Some("mockls.removeMarker") {
+            let uri = params["arguments"][0].as_str().unwrap_or_default();
+            let line = params["arguments"][1].as_u64().unwrap_or_default();
+            if let Some(edit) = self.marker_edit(uri, line) {
+                let req_id = self.next_request_id.fetch_add(1, Ordering::SeqCst);
+                send_message(
+                    &self.writer,
+                    &serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": req_id,
+                        "method": "workspace/applyEdit",
+                        "params": { "label": "Remove error marker", "edit": edit }
+                    }),
+                );
+            }
+        }
+        Value::Null
+    }
+
+    /// The edit deleting the error marker on `line` of `uri`, if present.
+    fn marker_edit(&self, uri: &str, line: u64) -> Option<Value> {
+        let marker = self.args.error_marker.as_deref().unwrap_or_default();
+        let start = self
+            .documents
+            .get(uri)
+            .and_then(|c| c.lines().nth(usize::try_from(line).ok()?))
+            .and_then(|l| l.find(marker))?;
+
+        Some(serde_json::json!({
+            "changes": {
+                uri: [{
+                    "range": {
+                        "start": { "line": line, "character": start },
+                        "end": { "line": line, "character": start + marker.len() }
+                    },
+                    "newText": ""
+                }]
+            }
+        }))
+    }
+
+    /// Selects the word under each position, nested inside its whole line.
+    fn handle_selection_range(&self, params: &Value) -> Option<Value> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|td| td.get("uri"))
+            .and_then(Value::as_str)?;
+        let content = self.documents.get(uri)?;
+
+        let ranges = params
+            .get("positions")
+            .and_then(Value::as_array)?
+            .iter()
+            .map(|pos| {
+                let line = pos["line"].as_u64().unwrap_or_default();
+                let col = usize::try_from(pos["character"].as_u64().unwrap_or_default())
+                    .unwrap_or_default();
+                let text = usize::try_from(line)
+                    .ok()
+                    .and_then(|l| content.lines().nth(l))
+                    .unwrap_or_default();
+                let bytes = text.as_bytes();
+                let start = (0..col.min(bytes.len()))
+                    .rev()
+                    .find(|&i| !is_word_char(bytes[i]))
+                    .map_or(0, |i| i + 1);
+                let end = (col.min(bytes.len())..bytes.len())
+                    .find(|&i| !is_word_char(bytes[i]))
+                    .unwrap_or(bytes.len());
+                serde_json::json!({
+                    "range": {
+                        "start": { "line": line, "character": start },
+                        "end": { "line": line, "character": end }
+                    },
+                    "parent": {
+                        "range": {
+                            "start": { "line": line, "character": 0 },
+                            "end": { "line": line, "character": text.len() }
+                        }
+                    }
+                })
+            })
+            .collect();
+        Some(Value::Array(ranges))
+    }
+
+    fn handle_folding_range(&self, params: &Value) -> Option<Value> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|td| td.get("uri"))
+            .and_then(Value::as_str)?;
+        let content = self.documents.get(uri)?;
+
+        let mut ranges = Vec::new();
+        let mut open_braces = Vec::new();
+        let mut comment_start = None;
+        let lines: Vec<&str> = content.lines().collect();
+        for (idx, line) in lines.iter().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.starts_with('#') && !trimmed.starts_with("#!") {
+                comment_start.get_or_insert(idx);
+            } else if let Some(start) = comment_start.take()
+                && idx - start > 1
+            {
+                ranges.push(serde_json::json!({
+                    "startLine": start, "endLine": idx - 1, "kind": "comment"
+                }));
+            }
+            if trimmed.ends_with('{') {
+                open_braces.push(idx);
+            }
+            if trimmed.starts_with('}')
+                && let Some(start) = open_braces.pop()
+            {
+                ranges.push(serde_json::json!({
+                    "startLine": start, "endLine": idx, "kind": "region"
+                }));
+            }
+        }
+        if let Some(start) = comment_start
+            && lines.len() - start > 1
+        {
+            ranges.push(serde_json::json!({
+                "startLine": start, "endLine": lines.len() - 1, "kind": "comment"
+            }));
+        }
+        Some(Value::Array(ranges))
+    }
+
+    /// One unresolved lens per function, on its name.
+    fn handle_code_lens(&self, params: &Value) -> Option<Value> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|td| td.get("uri"))
+            .and_then(Value::as_str)?;
+        let content = self.documents.get(uri)?;
+
+        let lenses = extract_symbols(content)
+            .into_iter()
+            .filter(|symbol| symbol["kind"] == 12)
+            .map(|symbol| {
+                serde_json::json!({
+                    "range": symbol["selectionRange"],
+                    "data": { "uri": uri, "name": symbol["name"] }
+                })
+            })
+            .collect();
+        Some(Value::Array(lenses))
+    }
+
+    /// Titles a lens with the number of other occurrences of its function.
+    fn handle_code_lens_resolve(&self, params: &Value) -> Value {
+        let mut lens = params.clone();
+        let uri = params["data"]["uri"].as_str().unwrap_or_default();
+        let name = params["data"]["name"].as_str().unwrap_or_default();
+        let count = self
+            .documents
+            .get(uri)
+            .map_or(0, |content| content.matches(name).count().saturating_sub(1));
+        lens["command"] = serde_json::json!({
+            "title": format!("{count} references"),
+            "command": "mockls.showReferences"
+        });
+        lens
+    }
+
+    /// Range of the word under the cursor, or `null` off a word.
+    fn handle_prepare_rename(&self, params: &Value) -> Value {
+        extract_position(params)
+            .and_then(|(uri, line, col)| {
+                let (start, end) = word_bounds(self.documents.get(uri)?, line, col)?;
+                Some(location_json(uri, line, start, end)["range"].clone())
+            })
+            .unwrap_or(Value::Null)
+    }
+
+    /// Replaces every occurrence of the word under the cursor with
+    /// `newName`, as a `changes` workspace edit.
+    fn handle_rename(&self, params: &Value) -> Option<Value> {
+        let (uri, _, _) = extract_position(params)?;
+        let new_name = params.get("newName").and_then(Value::as_str)?;
+        let edits: Vec<Value> = self
+            .handle_references(params)?
+            .as_array()?
+            .iter()
+            .map(|location| serde_json::json!({ "range": location["range"], "newText": new_name }))
+            .collect();
+        Some(serde_json::json!({ "changes": { uri: edits } }))
+    }
+
+    fn handle_inlay_hint(&self, params: &Value) -> Option<Value> {
+        let uri = params
+            .get("textDocument")
+            .and_then(|td| td.get("uri"))
+            .and_then(Value::as_str)?;
+        let content = self.documents.get(uri)?;
+        let start = params["range"]["start"]["line"]
+            .as_u64()
+            .unwrap_or_default();
+        // Clients end the range at character 0 of the line after the last
+        // one they want, so that line is excluded.
+        let end = params["range"]["end"]["line"].as_u64().unwrap_or(u64::MAX);
+
+        let hints = content
+            .lines()
+            .zip(0u64..)
+            .filter(|(_, line)| (start..end).contains(line))
+            .filter_map(|(text, line)| {
+                let name_end = text.find('=')?;
+                let name = &text[..name_end];
+                if name.is_empty() || !name.bytes().all(is_word_char) {
+                    return None;
+                }
+                Some(serde_json::json!({
+                    "position": { "line": line, "character": name_end },
+                    "label": ": string",
+                    "kind": 1
+                }))
+            })
+            .collect();
+        Some(Value::Array(hints))
+    }
+
     fn handle_workspace_symbols(&self, params: &Value) -> Value {
         let query = params.get("query").and_then(Value::as_str).unwrap_or("");
 
@@ -486,6 +1073,21 @@ impl MockServer {
         Value::Array(all_symbols)
     }
 
+    /// Lines of the document that contain `--error-marker`.
+    fn error_lines(&self, uri: &str) -> Vec<u32> {
+        let (Some(marker), Some(content)) =
+            (self.args.error_marker.as_deref(), self.documents.get(uri))
+        else {
+            return Vec::new();
+        };
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.contains(marker))
+            .filter_map(|(i, _)| u32::try_from(i).ok())
+            .collect()
+    }
+
     fn publish_diagnostics(&self, uri: &str) {
         let delay = self.args.diagnostics_delay;
         let uri_owned = uri.to_string();
@@ -499,14 +1101,58 @@ impl MockServer {
         // Capture line count at publish time so delayed publications
         // reflect the content that triggered them, not later edits.
         let line_count = self.documents.get(uri).map_or(0, |c| c.lines().count());
+        let error_lines = self.error_lines(uri);
+
+        let related_uri = self
+            .args
+            .publish_related
+            .as_deref()
+            .and_then(|name| uri.rsplit_once('/').map(|(dir, _)| format!("{dir}/{name}")));
 
         if delay > 0 {
             std::thread::spawn(move || {
                 std::thread::sleep(Duration::from_millis(delay));
-                send_diagnostics_notification(&writer, &uri_owned, version, line_count);
+                send_diagnostics_notification(
+                    &writer,
+                    &uri_owned,
+                    version,
+                    line_count,
+                    &error_lines,
+                );
+                if let Some(related) = &related_uri {
+                    send_diagnostics_notification(&writer, related, None, 0, &[]);
+                }
             });
         } else {
-            send_diagnostics_notification(&self.writer, &uri_owned, version, line_count);
+            send_diagnostics_notification(
+                &self.writer,
+                &uri_owned,
+                version,
+                line_count,
+                &error_lines,
+            );
+            if let Some(related) = &related_uri {
+                send_diagnostics_notification(&self.writer, related, None, 0, &[]);
+            }
+        }
+
+        if let Some(late) = self.args.late_diagnostics {
+            let writer = self.writer.clone();
+            let uri_owned = uri.to_string();
+            let mut error_lines = self.error_lines(uri);
+            if !error_lines.contains(&0) {
+                error_lines.insert(0, 0);
+            }
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_millis(delay + late));
+                send_diagnostics_notification(
+                    &writer,
+                    &uri_owned,
+                    version,
+                    line_count,
+                    &error_lines,
+                );
+            });
         }
     }
 
@@ -559,6 +1205,45 @@ impl MockServer {
         });
     }
 
+    /// Reports indexing progress for `--index-on-open` milliseconds. The
+    /// begin is sent before returning, right after the file's diagnostics,
+    /// so it precedes the answer to any request that follows.
+    fn start_open_indexing(&mut self) {
+        let duration = Duration::from_millis(self.args.index_on_open);
+        self.indexing_until = Some(Instant::now() + duration);
+        let token = "mockls-open-indexing";
+        send_message(
+            &self.writer,
+            &serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "$/progress",
+                "params": {
+                    "token": token,
+                    "value": { "kind": "begin", "title": "Indexing" }
+                }
+            }),
+        );
+
+        let writer = self.writer.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(duration);
+            send_message(
+                &writer,
+                &serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "method": "$/progress",
+                    "params": { "token": token, "value": { "kind": "end" } }
+                }),
+            );
+        });
+    }
+
+    /// Returns whether the `--index-on-open` indexing is still running.
+    fn is_indexing(&self) -> bool {
+        self.indexing_until
+            .is_some_and(|until| Instant::now() < until)
+    }
+
     fn simulate_progress_around_diagnostics(&self, uri: &str) {
         let uri_owned = uri.to_string();
         let writer = self.writer.clone();
@@ -567,6 +1252,7 @@ impl MockServer {
         let publish_version = self.args.publish_version;
         let diagnostics_delay = self.args.diagnostics_delay;
         let line_count = self.documents.get(uri).map_or(0, |c| c.lines().count());
+        let error_lines = self.error_lines(uri);
         let version = if publish_version {
             Some(self.versions.get(uri).copied().unwrap_or(1))
         } else {
@@ -608,7 +1294,13 @@ impl MockServer {
             }
 
             if !no_diagnostics {
-                send_diagnostics_notification(&writer, &uri_owned, version, line_count);
+                send_diagnostics_notification(
+                    &writer,
+                    &uri_owned,
+                    version,
+                    line_count,
+                    &error_lines,
+                );
             }
 
             std::thread::sleep(Duration::from_millis(50));
@@ -627,6 +1319,20 @@ impl MockServer {
         });
     }
 
+    /// Sends `--show-message` as a `window/showMessage` warning, if set.
+    fn send_show_message(&self) {
+        if let Some(text) = &self.args.show_message {
+            send_message(
+                &self.writer,
+                &serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "method": "window/showMessage",
+                    "params": { "type": 2, "message": text }
+                }),
+            );
+        }
+    }
+
     fn send_configuration_request(&self) {
         let req_id = self.next_request_id.fetch_add(1, Ordering::SeqCst);
         send_message(
@@ -714,18 +1420,31 @@ fn send_diagnostics_notification(
     uri: &str,
     version: Option<i32>,
     line_count: usize,
+    error_lines: &[u32],
 ) {
-    let mut params = serde_json::json!({
-        "uri": uri,
-        "diagnostics": [{
+    let mut diagnostics = vec![serde_json::json!({
+        "range": {
+            "start": { "line": 0, "character": 0 },
+            "end": { "line": 0, "character": 1 }
+        },
+        "severity": 2,
+        "source": "mockls",
+        "message": format!("mockls: mock diagnostic ({line_count} lines)")
+    })];
+    diagnostics.extend(error_lines.iter().map(|line| {
+        serde_json::json!({
             "range": {
-                "start": { "line": 0, "character": 0 },
-                "end": { "line": 0, "character": 1 }
+                "start": { "line": line, "character": 0 },
+                "end": { "line": line, "character": 1 }
             },
-            "severity": 2,
+            "severity": 1,
             "source": "mockls",
-            "message": format!("mockls: mock diagnostic ({line_count} lines)")
-        }]
+            "message": "mockls: marked error"
+        })
+    }));
+    let mut params = serde_json::json!({
+        "uri": uri,
+        "diagnostics": diagnostics
     });
 
     if let Some(v) = version {
@@ -768,8 +1487,46 @@ fn try_parse_message(buffer: &[u8]) -> Option<(String, usize)> {
     Some((body.to_string(), total))
 }
 
+/// Replace the UTF-16 `range` of `content` with `text`.
+fn apply_range_edit(content: &str, range: &Value, text: &str) -> Option<String> {
+    let start = utf16_offset(content, range.get("start")?)?;
+    let end = utf16_offset(content, range.get("end")?)?;
+    if start > end {
+        return None;
+    }
+    Some(format!("{}{text}{}", &content[..start], &content[end..]))
+}
+
+/// Byte offset of an LSP position, counting characters in UTF-16 units.
+fn utf16_offset(content: &str, position: &Value) -> Option<usize> {
+    let line = usize::try_from(position.get("line")?.as_u64()?).ok()?;
+    let character = usize::try_from(position.get("character")?.as_u64()?).ok()?;
+
+    let line_start = if line == 0 {
+        0
+    } else {
+        content.match_indices('\n').nth(line - 1)?.0 + 1
+    };
+    let line_text = content[line_start..].split('\n').next().unwrap_or_default();
+
+    let mut units = 0;
+    for (idx, c) in line_text.char_indices() {
+        if units >= character {
+            return Some(line_start + idx);
+        }
+        units += c.len_utf16();
+    }
+    Some(line_start + line_text.len())
+}
+
 /// Extract the word at a given line and column from content.
 fn extract_word(content: &str, line: usize, col: usize) -> Option<String> {
+    let (start, end) = word_bounds(content, line, col)?;
+    Some(content.lines().nth(line)?[start..end].to_string())
+}
+
+/// Byte range of the word containing `col` on `line`.
+fn word_bounds(content: &str, line: usize, col: usize) -> Option<(usize, usize)> {
     let line_text = content.lines().nth(line)?;
 
     if col >= line_text.len() {
@@ -791,7 +1548,7 @@ fn extract_word(content: &str, line: usize, col: usize) -> Option<String> {
         return None;
     }
 
-    Some(line_text[start..end].to_string())
+    Some((start, end))
 }
 
 const fn is_word_char(b: u8) -> bool {
@@ -852,6 +1609,9 @@ fn extract_symbols(content: &str) -> Vec<Value> {
 
 fn main() {
     let args = Args::parse();
+    if let Some(line) = &args.stderr_line {
+        let _ = writeln!(std::io::stderr(), "{line}");
+    }
     let writer = stdout_writer();
     let mut server = MockServer::new(args, writer);
     let mut stdin = std::io::stdin().lock();
@@ -876,6 +1636,7 @@ mod tests {
             diagnostics_delay: 0,
             no_diagnostics: false,
             diagnostics_on_save: false,
+            diagnostics_on_watched_files: false,
             drop_after: None,
             hang_on: vec![],
             fail_on: vec![],
@@ -883,6 +1644,20 @@ mod tests {
             publish_version: false,
             progress_on_change: false,
             cpu_busy: None,
+            publish_related: None,
+            error_marker: None,
+            inlay_hints: false,
+            folding_ranges: false,
+            prepare_rename: false,
+            code_lens: false,
+            incremental_sync: false,
+            show_message: None,
+            command_fixes: false,
+            organize_imports: false,
+            stderr_line: None,
+            no_definition: false,
+            index_on_open: 0,
+            late_diagnostics: None,
         }
     }
 
@@ -3,9 +3,9 @@
 
 use anyhow::{Result, anyhow};
 use lsp_types::{
-    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
-    TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem, Uri,
-    VersionedTextDocumentIdentifier,
+    DidChangeTextDocumentParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams, Position,
+    PositionEncodingKind, Range, TextDocumentContentChangeEvent, TextDocumentIdentifier,
+    TextDocumentItem, TextDocumentSyncKind, Uri, VersionedTextDocumentIdentifier,
 };
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
@@ -13,10 +13,78 @@ use std::time::{Instant, SystemTime};
 use tokio::fs;
 use tracing::{debug, trace};
 
+use crate::config::LanguageOverrides;
+use crate::lsp::LspClient;
+
+/// Line terminator style of a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// `\n` (Unix).
+    Lf,
+    /// `\r\n` (Windows).
+    CrLf,
+}
+
+impl LineEnding {
+    /// Detects the style from the first line terminator (`Lf` if none).
+    #[must_use]
+    pub fn detect(content: &str) -> Self {
+        match content.find('\n') {
+            Some(i) if content[..i].ends_with('\r') => Self::CrLf,
+            _ => Self::Lf,
+        }
+    }
+
+    /// Rewrites every line terminator in `text` to this style.
+    #[must_use]
+    pub fn apply(self, text: &str) -> String {
+        let lf = text.replace("\r\n", "\n");
+        match self {
+            Self::Lf => lf,
+            Self::CrLf => lf.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// Fails if `len` bytes of `path` exceed `max_file_bytes` (0 = no limit).
+///
+/// # Errors
+///
+/// Returns a "file too large" error when the limit is exceeded.
+pub fn check_file_size(path: &Path, len: u64, max_file_bytes: u64) -> Result<()> {
+    if max_file_bytes > 0 && len > max_file_bytes {
+        return Err(anyhow!(
+            "File too large: {} is {len} bytes (max_file_bytes is {max_file_bytes})",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Returns line `n` of `content`, splitting on `\r\n`, `\n` or `\r` as
+/// LSP positions do, without the terminator.
+#[must_use]
+pub fn lsp_line(content: &str, n: usize) -> Option<&str> {
+    let mut rest = content;
+    for _ in 0..n {
+        let end = rest.find(['\r', '\n'])?;
+        let skip = if rest[end..].starts_with("\r\n") {
+            2
+        } else {
+            1
+        };
+        rest = &rest[end + skip..];
+    }
+    let end = rest.find(['\r', '\n']).unwrap_or(rest.len());
+    Some(&rest[..end])
+}
+
 /// Tracks the state of an open document.
 struct OpenDocument {
     version: i32,
     content: String,
+    /// Line terminator style detected when the document was opened.
+    line_ending: LineEnding,
     mtime: SystemTime,
     last_accessed: Instant,
 }
@@ -28,6 +96,10 @@ struct OpenDocument {
 /// access, tracking their versions, and detecting changes on disk.
 pub struct DocumentManager {
     documents: HashMap<PathBuf, OpenDocument>,
+    /// Files larger than this are refused before being read (0 = no limit).
+    max_file_bytes: u64,
+    /// User `[languages]` globs, consulted before the built-in table.
+    language_overrides: LanguageOverrides,
 }
 
 impl Default for DocumentManager {
@@ -42,9 +114,31 @@ impl DocumentManager {
     pub fn new() -> Self {
         Self {
             documents: HashMap::new(),
+            max_file_bytes: 0,
+            language_overrides: LanguageOverrides::default(),
         }
     }
 
+    /// Refuses to open files larger than `max_file_bytes` (0 = no limit).
+    #[must_use]
+    pub const fn with_max_file_bytes(mut self, max_file_bytes: u64) -> Self {
+        self.max_file_bytes = max_file_bytes;
+        self
+    }
+
+    /// Returns the size limit for opened files (0 = no limit).
+    #[must_use]
+    pub const fn max_file_bytes(&self) -> u64 {
+        self.max_file_bytes
+    }
+
+    /// Detects languages with `overrides` before the built-in table.
+    #[must_use]
+    pub fn with_language_overrides(mut self, overrides: LanguageOverrides) -> Self {
+        self.language_overrides = overrides;
+        self
+    }
+
     /// Ensures a document is open and returns the notification to send if needed.
     ///
     /// If the document is already open but the file has changed on disk,
@@ -55,6 +149,7 @@ impl DocumentManager {
     /// Returns an error if:
     /// - The path cannot be canonicalized.
     /// - File metadata cannot be read.
+    /// - The file is larger than the configured `max_file_bytes`.
     /// - The file cannot be read from disk.
     /// - The path cannot be converted to a valid URI.
     pub async fn ensure_open(&mut self, path: &Path) -> Result<Option<DocumentNotification>> {
@@ -62,31 +157,27 @@ impl DocumentManager {
         let metadata = fs::metadata(&path).await?;
         let mtime = metadata.modified()?;
 
+        check_file_size(&path, metadata.len(), self.max_file_bytes)?;
+
         if let Some(doc) = self.documents.get_mut(&path) {
             // Document already open - check if it changed on disk
             if mtime > doc.mtime {
                 let content = fs::read_to_string(&path).await?;
                 if content != doc.content {
                     doc.version += 1;
-                    doc.content.clone_from(&content);
+                    doc.line_ending = LineEnding::detect(&content);
+                    let previous = std::mem::replace(&mut doc.content, content.clone());
                     doc.mtime = mtime;
                     doc.last_accessed = Instant::now();
 
                     debug!("Document changed on disk: {}", path.display());
 
-                    return Ok(Some(DocumentNotification::Change(
-                        DidChangeTextDocumentParams {
-                            text_document: VersionedTextDocumentIdentifier {
-                                uri: path_to_uri(&path)?,
-                                version: doc.version,
-                            },
-                            content_changes: vec![TextDocumentContentChangeEvent {
-                                range: None,
-                                range_length: None,
-                                text: content,
-                            }],
-                        },
-                    )));
+                    return Ok(Some(DocumentNotification::Change(DocumentChange {
+                        uri: path_to_uri(&path)?,
+                        version: doc.version,
+                        previous,
+                        text: content,
+                    })));
                 }
             }
 
@@ -100,10 +191,11 @@ impl DocumentManager {
         let uri = path_to_uri(&path)?;
 
         // Detect language ID from extension
-        let language_id = detect_language_id(&path);
+        let language_id = self.language_id_for_path(&path).to_string();
 
         let doc = OpenDocument {
             version: 1,
+            line_ending: LineEnding::detect(&content),
             content: content.clone(),
             mtime,
             last_accessed: Instant::now(),
@@ -116,7 +208,7 @@ impl DocumentManager {
             DidOpenTextDocumentParams {
                 text_document: TextDocumentItem {
                     uri,
-                    language_id: language_id.to_string(),
+                    language_id,
                     version: 1,
                     text: content,
                 },
@@ -144,6 +236,40 @@ impl DocumentManager {
         }
     }
 
+    /// Marks every document under one of `removed` as closed and returns the
+    /// notifications to send.
+    ///
+    /// Documents that also lie under one of `kept` stay open, so removing a
+    /// root nested in another does not close files the outer root still owns.
+    pub fn close_under(
+        &mut self,
+        removed: &[PathBuf],
+        kept: &[PathBuf],
+    ) -> Vec<DidCloseTextDocumentParams> {
+        let closing: Vec<PathBuf> = self
+            .documents
+            .keys()
+            .filter(|path| {
+                removed.iter().any(|root| path.starts_with(root))
+                    && !kept.iter().any(|root| path.starts_with(root))
+            })
+            .cloned()
+            .collect();
+
+        let mut closed = Vec::new();
+        for path in closing {
+            self.documents.remove(&path);
+            debug!("Closing document outside workspace: {}", path.display());
+            match path_to_uri(&path) {
+                Ok(uri) => closed.push(DidCloseTextDocumentParams {
+                    text_document: TextDocumentIdentifier { uri },
+                }),
+                Err(e) => debug!("Skipping didClose for {}: {e}", path.display()),
+            }
+        }
+        closed
+    }
+
     /// Returns paths of documents that haven't been accessed within the timeout.
     #[must_use]
     pub fn stale_documents(&self, timeout_secs: u64) -> Vec<PathBuf> {
@@ -161,6 +287,22 @@ impl DocumentManager {
             .collect()
     }
 
+    /// Returns the content Catenary last synced to the server for a document,
+    /// or `None` if it is not open.
+    #[must_use]
+    pub fn content(&self, path: &Path) -> Option<&str> {
+        let path = path.canonicalize().ok()?;
+        self.documents.get(&path).map(|doc| doc.content.as_str())
+    }
+
+    /// Returns line `n` of the content last synced for a document, using
+    /// the same line splitting as LSP positions, or `None` if the document
+    /// is not open or has fewer lines.
+    #[must_use]
+    pub fn line(&self, path: &Path, n: usize) -> Option<&str> {
+        lsp_line(self.content(path)?, n)
+    }
+
     /// Returns the URI for an open document.
     ///
     /// # Errors
@@ -172,8 +314,10 @@ impl DocumentManager {
 
     /// Returns the language ID for a given path.
     #[must_use]
-    pub fn language_id_for_path(&self, path: &Path) -> &'static str {
-        detect_language_id(path)
+    pub fn language_id_for_path(&self, path: &Path) -> &str {
+        self.language_overrides
+            .lookup(path)
+            .unwrap_or_else(|| detect_language_id(path))
     }
 
     /// Checks if there are any open documents for the given language ID.
@@ -181,13 +325,151 @@ impl DocumentManager {
     pub fn has_open_documents(&self, language_id: &str) -> bool {
         self.documents
             .keys()
-            .any(|path| detect_language_id(path) == language_id)
+            .any(|path| self.language_id_for_path(path) == language_id)
+    }
+
+    /// Returns the paths of all open documents, sorted.
+    #[must_use]
+    pub fn open_paths(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = self.documents.keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+
+    /// Returns the paths of all open documents, most recently accessed first.
+    #[must_use]
+    pub fn recent_paths(&self) -> Vec<PathBuf> {
+        let mut docs: Vec<(&PathBuf, &OpenDocument)> = self.documents.iter().collect();
+        docs.sort_by(|(a_path, a), (b_path, b)| {
+            b.last_accessed
+                .cmp(&a.last_accessed)
+                .then_with(|| a_path.cmp(b_path))
+        });
+        docs.into_iter().map(|(path, _)| path.clone()).collect()
+    }
+
+    /// Returns true if any open document of the given language lies under `root`.
+    #[must_use]
+    pub fn has_open_documents_under(&self, language_id: &str, root: &Path) -> bool {
+        self.documents
+            .keys()
+            .any(|path| path.starts_with(root) && self.language_id_for_path(path) == language_id)
+    }
+
+    /// Sends `didOpen` to `client` for every tracked document it routes that
+    /// the server has not been sent, and returns how many were reopened.
+    ///
+    /// A server restarted after a crash starts with no open documents; this
+    /// replays the content last synced to its predecessor.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a notification cannot be sent.
+    pub async fn reopen_on(&self, client: &LspClient) -> Result<usize> {
+        let mut reopened = 0;
+        for (path, doc) in self.routed_to(client.language()) {
+            let uri = path_to_uri(path)?;
+            if client.has_opened(&uri).await {
+                continue;
+            }
+            debug!("Reopening document on restarted server: {}", path.display());
+            client
+                .did_open(DidOpenTextDocumentParams {
+                    text_document: TextDocumentItem {
+                        uri,
+                        language_id: self.language_id_for_path(path).to_string(),
+                        version: doc.version,
+                        text: doc.content.clone(),
+                    },
+                })
+                .await?;
+            reopened += 1;
+        }
+        Ok(reopened)
+    }
+
+    /// Brings a companion server up to date with the open document at
+    /// `path`: `didOpen` if it has never seen it, a full-text `didChange` if
+    /// it is behind. Returns whether anything was sent.
+    ///
+    /// Companions are not the document's primary server, so
+    /// [`Self::ensure_open`] notifications never reach them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the path cannot be converted to a URI or a
+    /// notification cannot be sent.
+    pub async fn sync_companion(&self, path: &Path, client: &LspClient) -> Result<bool> {
+        let path = path.canonicalize()?;
+        let Some(doc) = self.documents.get(&path) else {
+            return Ok(false);
+        };
+        let uri = path_to_uri(&path)?;
+        match client.sent_version(&uri).await {
+            Some(version) if version >= doc.version => Ok(false),
+            Some(_) => {
+                client
+                    .did_change(DidChangeTextDocumentParams {
+                        text_document: VersionedTextDocumentIdentifier {
+                            uri,
+                            version: doc.version,
+                        },
+                        content_changes: vec![TextDocumentContentChangeEvent {
+                            range: None,
+                            range_length: None,
+                            text: doc.content.clone(),
+                        }],
+                    })
+                    .await?;
+                Ok(true)
+            }
+            None => {
+                client
+                    .did_open(DidOpenTextDocumentParams {
+                        text_document: TextDocumentItem {
+                            uri,
+                            language_id: self.language_id_for_path(&path).to_string(),
+                            version: doc.version,
+                            text: doc.content.clone(),
+                        },
+                    })
+                    .await?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Returns the URIs of the open documents routed to the client stored
+    /// under `client_key`, sorted.
+    #[must_use]
+    pub fn open_uris_for(&self, client_key: &str) -> Vec<Uri> {
+        let mut uris: Vec<Uri> = self
+            .routed_to(client_key)
+            .filter_map(|(path, _)| path_to_uri(path).ok())
+            .collect();
+        uris.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        uris
+    }
+
+    /// Open documents whose language and root match `client_key`.
+    fn routed_to<'a>(
+        &'a self,
+        client_key: &'a str,
+    ) -> impl Iterator<Item = (&'a PathBuf, &'a OpenDocument)> {
+        let (language_id, root) = crate::lsp::split_client_key(client_key);
+        self.documents.iter().filter(move |(path, _)| {
+            self.language_id_for_path(path) == language_id
+                && root.is_none_or(|root| path.starts_with(root))
+        })
     }
 
     /// Notifies the manager that a file was written externally (by Catenary itself).
     ///
     /// Updates internal state with the new content and returns the appropriate
-    /// LSP notification to send, without re-reading from disk.
+    /// LSP notification to send, without re-reading from disk. `content` is
+    /// sent exactly as given, line endings included: callers pass what is
+    /// (or is about to be) on disk, so rewriting it would put the server's
+    /// positions out of step with the file.
     ///
     /// # Errors
     ///
@@ -204,29 +486,26 @@ impl DocumentManager {
         if let Some(doc) = self.documents.get_mut(&path) {
             // Already open — send didChange
             doc.version += 1;
-            doc.content = content.to_string();
+            doc.line_ending = LineEnding::detect(content);
+            let previous = std::mem::replace(&mut doc.content, content.to_string());
             doc.mtime = mtime;
             doc.last_accessed = Instant::now();
 
             debug!("External write (change): {}", path.display());
 
-            Ok(DocumentNotification::Change(DidChangeTextDocumentParams {
-                text_document: VersionedTextDocumentIdentifier {
-                    uri,
-                    version: doc.version,
-                },
-                content_changes: vec![TextDocumentContentChangeEvent {
-                    range: None,
-                    range_length: None,
-                    text: content.to_string(),
-                }],
+            Ok(DocumentNotification::Change(DocumentChange {
+                uri,
+                version: doc.version,
+                previous,
+                text: content.to_string(),
             }))
         } else {
             // Not open — send didOpen
-            let language_id = detect_language_id(&path);
+            let language_id = self.language_id_for_path(&path).to_string();
 
             let doc = OpenDocument {
                 version: 1,
+                line_ending: LineEnding::detect(content),
                 content: content.to_string(),
                 mtime,
                 last_accessed: Instant::now(),
@@ -242,7 +521,7 @@ impl DocumentManager {
             Ok(DocumentNotification::Open(DidOpenTextDocumentParams {
                 text_document: TextDocumentItem {
                     uri,
-                    language_id: language_id.to_string(),
+                    language_id,
                     version: 1,
                     text: content.to_string(),
                 },
@@ -256,7 +535,142 @@ pub enum DocumentNotification {
     /// A `textDocument/didOpen` notification.
     Open(DidOpenTextDocumentParams),
     /// A `textDocument/didChange` notification.
-    Change(DidChangeTextDocumentParams),
+    Change(DocumentChange),
+}
+
+impl DocumentNotification {
+    /// Sends the notification, shaping a change to the server's sync kind.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the notification fails.
+    pub async fn send(self, client: &LspClient) -> Result<()> {
+        match self {
+            Self::Open(params) => client.did_open(params).await,
+            Self::Change(change) => {
+                client
+                    .did_change(change.params(client.sync_kind(), &client.encoding()))
+                    .await
+            }
+        }
+    }
+}
+
+/// A new version of an open document, with the content it replaces.
+pub struct DocumentChange {
+    uri: Uri,
+    version: i32,
+    previous: String,
+    text: String,
+}
+
+impl DocumentChange {
+    /// Builds the `didChange` params for a server using `sync_kind`.
+    ///
+    /// `INCREMENTAL` servers get a single edit covering only the changed
+    /// span, with positions in `encoding`; everyone else gets the full text.
+    #[must_use]
+    pub fn params(
+        self,
+        sync_kind: TextDocumentSyncKind,
+        encoding: &PositionEncodingKind,
+    ) -> DidChangeTextDocumentParams {
+        let change = if sync_kind == TextDocumentSyncKind::INCREMENTAL {
+            incremental_change(&self.previous, &self.text, encoding)
+        } else {
+            TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: self.text,
+            }
+        };
+        DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: self.uri,
+                version: self.version,
+            },
+            content_changes: vec![change],
+        }
+    }
+}
+
+/// Computes the smallest single-range edit turning `previous` into `text`.
+///
+/// The unchanged prefix and suffix are trimmed to character boundaries and
+/// never split a `\r\n` pair.
+fn incremental_change(
+    previous: &str,
+    text: &str,
+    encoding: &PositionEncodingKind,
+) -> TextDocumentContentChangeEvent {
+    let mut prefix: usize = previous
+        .chars()
+        .zip(text.chars())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .sum();
+    if previous[..prefix].ends_with('\r') {
+        prefix -= 1;
+    }
+
+    let max_suffix = previous.len().min(text.len()) - prefix;
+    let mut suffix: usize = previous[prefix..]
+        .chars()
+        .rev()
+        .zip(text[prefix..].chars().rev())
+        .take_while(|(a, b)| a == b)
+        .map(|(c, _)| c.len_utf8())
+        .scan(0, |total, len| {
+            *total += len;
+            Some(*total)
+        })
+        .take_while(|total| *total <= max_suffix)
+        .last()
+        .unwrap_or(0);
+    if previous[previous.len() - suffix..].starts_with('\n') {
+        suffix -= 1;
+    }
+
+    let old_end = previous.len() - suffix;
+    TextDocumentContentChangeEvent {
+        range: Some(Range {
+            start: offset_position(previous, prefix, encoding),
+            end: offset_position(previous, old_end, encoding),
+        }),
+        range_length: None,
+        text: text[prefix..text.len() - suffix].to_string(),
+    }
+}
+
+/// Converts a byte offset in `content` to an LSP position in `encoding`.
+fn offset_position(content: &str, offset: usize, encoding: &PositionEncodingKind) -> Position {
+    let before = &content[..offset];
+    let mut line: u32 = 0;
+    let mut line_start = 0;
+    let mut chars = before.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\r' if chars.peek().is_some_and(|(_, next)| *next == '\n') => {}
+            '\r' | '\n' => {
+                line += 1;
+                line_start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    let rest = &before[line_start..];
+    let character = if *encoding == PositionEncodingKind::UTF8 {
+        rest.len()
+    } else if *encoding == PositionEncodingKind::UTF32 {
+        rest.chars().count()
+    } else {
+        rest.encode_utf16().count()
+    };
+    Position {
+        line,
+        character: u32::try_from(character).unwrap_or(u32::MAX),
+    }
 }
 
 fn path_to_uri(path: &Path) -> Result<Uri> {
@@ -324,6 +738,7 @@ fn detect_language_id(path: &Path) -> &'static str {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use anyhow::Context;
     use std::io::Write;
     use tempfile::NamedTempFile;
 
@@ -346,6 +761,24 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_refuses_file_over_max_bytes() -> Result<()> {
+        let mut file = NamedTempFile::with_suffix(".rs")?;
+        writeln!(file, "fn main() {{ println!(\"too long\"); }}")?;
+
+        let mut manager = DocumentManager::new().with_max_file_bytes(16);
+        let err = manager
+            .ensure_open(file.path())
+            .await
+            .err()
+            .context("oversized file should be refused")?;
+        assert!(err.to_string().contains("File too large"), "got: {err}");
+
+        let mut manager = DocumentManager::new().with_max_file_bytes(1024);
+        assert!(manager.ensure_open(file.path()).await?.is_some());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_already_open_no_change() -> Result<()> {
         let mut file = NamedTempFile::with_suffix(".py")?;
@@ -405,6 +838,41 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_close_under_removed_root() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().canonicalize()?;
+        let removed = root.join("removed");
+        let nested = removed.join("nested");
+        let kept = root.join("kept");
+        std::fs::create_dir_all(&nested)?;
+        std::fs::create_dir_all(&kept)?;
+        std::fs::write(removed.join("a.rs"), "fn a() {}")?;
+        std::fs::write(nested.join("b.rs"), "fn b() {}")?;
+        std::fs::write(kept.join("c.rs"), "fn c() {}")?;
+
+        let mut manager = DocumentManager::new();
+        manager.ensure_open(&removed.join("a.rs")).await?;
+        manager.ensure_open(&nested.join("b.rs")).await?;
+        manager.ensure_open(&kept.join("c.rs")).await?;
+
+        // The nested root is still a workspace root, so b.rs stays open.
+        let closed = manager.close_under(
+            std::slice::from_ref(&removed),
+            &[kept.clone(), nested.clone()],
+        );
+        assert_eq!(closed.len(), 1);
+        assert_eq!(
+            closed[0].text_document.uri,
+            path_to_uri(&removed.join("a.rs"))?
+        );
+        assert_eq!(
+            manager.open_paths(),
+            vec![kept.join("c.rs"), nested.join("b.rs")]
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_stale_documents() -> Result<()> {
         let mut file = NamedTempFile::with_suffix(".txt")?;
@@ -426,6 +894,172 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_line_ending_detect_and_apply() {
+        assert_eq!(LineEnding::detect("a\r\nb\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("a\nb\r\n"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("no newline"), LineEnding::Lf);
+
+        assert_eq!(LineEnding::CrLf.apply("a\nb\r\nc"), "a\r\nb\r\nc");
+        assert_eq!(LineEnding::Lf.apply("a\r\nb\nc"), "a\nb\nc");
+    }
+
+    #[test]
+    fn test_lsp_line_splits_like_lsp() {
+        let content = "zero\r\none\ntwo\rthree";
+        assert_eq!(lsp_line(content, 0), Some("zero"));
+        assert_eq!(lsp_line(content, 1), Some("one"));
+        assert_eq!(lsp_line(content, 2), Some("two"));
+        assert_eq!(lsp_line(content, 3), Some("three"));
+        assert_eq!(lsp_line(content, 4), None);
+        assert_eq!(lsp_line("end\r\n", 1), Some(""));
+    }
+
+    #[tokio::test]
+    async fn test_external_write_sends_exact_content() -> Result<()> {
+        let file = NamedTempFile::with_suffix(".rs")?;
+        std::fs::write(file.path(), "fn a() {}\r\nfn b() {}\r\n")?;
+
+        let mut manager = DocumentManager::new();
+        manager.ensure_open(file.path()).await?;
+
+        // LF text written into a CRLF file, and a mixed-EOL file, both reach
+        // the server byte for byte.
+        let mtime = std::fs::metadata(file.path())?.modified()?;
+        for written in [
+            "fn a() {}\nfn c() {}\n",
+            "fn a() {}\r\nfn c() {}\nfn d() {}\r\n",
+        ] {
+            let notification = manager.notify_external_write(file.path(), written, mtime)?;
+            let DocumentNotification::Change(change) = notification else {
+                anyhow::bail!("Expected Change notification");
+            };
+            let params = change.params(TextDocumentSyncKind::FULL, &PositionEncodingKind::UTF16);
+            assert_eq!(params.content_changes[0].text, written);
+            assert_eq!(manager.content(file.path()), Some(written));
+        }
+        assert_eq!(manager.line(file.path(), 1), Some("fn c() {}"));
+        Ok(())
+    }
+
+    /// Applies a ranged change to `content` the way a server would.
+    fn apply_change(content: &str, change: &TextDocumentContentChangeEvent) -> Result<String> {
+        let range = change.range.context("expected a ranged change")?;
+        let mut starts = vec![0];
+        let bytes = content.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                    starts.push(i + 2);
+                    i += 2;
+                    continue;
+                }
+                b'\r' | b'\n' => starts.push(i + 1),
+                _ => {}
+            }
+            i += 1;
+        }
+        let to_offset = |pos: Position| -> Result<usize> {
+            let start = *starts.get(pos.line as usize).context("line out of range")?;
+            let line = &content[start..];
+            let mut units = 0;
+            for (idx, c) in line.char_indices() {
+                if units >= pos.character as usize {
+                    return Ok(start + idx);
+                }
+                units += c.len_utf16();
+            }
+            Ok(content.len())
+        };
+        let (start, end) = (to_offset(range.start)?, to_offset(range.end)?);
+        Ok(format!(
+            "{}{}{}",
+            &content[..start],
+            change.text,
+            &content[end..]
+        ))
+    }
+
+    #[test]
+    fn test_incremental_change_is_minimal_and_round_trips() -> Result<()> {
+        let utf16 = PositionEncodingKind::UTF16;
+        let cases = [
+            ("fn a() {}\nfn b() {}\n", "fn a() {}\nfn c() {}\n"),
+            ("let é = 1;\nlet x = 2;\n", "let é = 1;\nlet xy = 2;\n"),
+            ("a\r\nb\r\n", "a\r\nx\r\nb\r\n"),
+            ("one\r\ntwo", "one\ntwo"),
+            ("same", "same"),
+            ("", "new file\n"),
+            ("aaaa", "aa"),
+        ];
+        for (previous, text) in cases {
+            let change = incremental_change(previous, text, &utf16);
+            assert_eq!(
+                apply_change(previous, &change)?,
+                text,
+                "{previous:?} -> {text:?} via {change:?}"
+            );
+        }
+
+        let change = incremental_change("fn a() {}\nfn b() {}\n", "fn a() {}\nfn c() {}\n", &utf16);
+        assert_eq!(
+            change.range,
+            Some(Range::new(Position::new(1, 3), Position::new(1, 4)))
+        );
+        assert_eq!(change.text, "c");
+        Ok(())
+    }
+
+    #[test]
+    fn test_offset_position_counts_encoding_units() {
+        let content = "é\r\nab😀c";
+        let utf8 = PositionEncodingKind::UTF8;
+        let utf16 = PositionEncodingKind::UTF16;
+        let utf32 = PositionEncodingKind::UTF32;
+        let emoji_end = content.find('c').unwrap_or_default();
+
+        assert_eq!(offset_position(content, 2, &utf16), Position::new(0, 1));
+        assert_eq!(offset_position(content, 4, &utf16), Position::new(1, 0));
+        assert_eq!(
+            offset_position(content, emoji_end, &utf8),
+            Position::new(1, 6)
+        );
+        assert_eq!(
+            offset_position(content, emoji_end, &utf16),
+            Position::new(1, 4)
+        );
+        assert_eq!(
+            offset_position(content, emoji_end, &utf32),
+            Position::new(1, 3)
+        );
+    }
+
+    #[test]
+    fn test_change_params_follow_sync_kind() -> Result<()> {
+        let uri = path_to_uri(Path::new("/tmp/a.rs"))?;
+        let change = || DocumentChange {
+            uri: uri.clone(),
+            version: 2,
+            previous: "let a = 1;\n".to_string(),
+            text: "let a = 10;\n".to_string(),
+        };
+        let utf16 = PositionEncodingKind::UTF16;
+
+        let full = change().params(TextDocumentSyncKind::FULL, &utf16);
+        assert_eq!(full.text_document.version, 2);
+        assert_eq!(full.content_changes[0].range, None);
+        assert_eq!(full.content_changes[0].text, "let a = 10;\n");
+
+        let incremental = change().params(TextDocumentSyncKind::INCREMENTAL, &utf16);
+        assert_eq!(
+            incremental.content_changes[0].range,
+            Some(Range::new(Position::new(0, 9), Position::new(0, 9)))
+        );
+        assert_eq!(incremental.content_changes[0].text, "0");
+        Ok(())
+    }
+
     #[test]
     fn test_language_detection() {
         assert_eq!(detect_language_id(Path::new("test.rs")), "rust");
@@ -458,6 +1092,31 @@ mod tests {
         assert_eq!(detect_language_id(Path::new("noextension")), "plaintext");
     }
 
+    #[test]
+    fn test_language_overrides_take_precedence() -> Result<()> {
+        let overrides = LanguageOverrides::new(&HashMap::from([
+            ("*.php".to_string(), "php".to_string()),
+            ("*.rs".to_string(), "rust-template".to_string()),
+            ("Dockerfile*".to_string(), "dockerfile".to_string()),
+        ]))?;
+        let manager = DocumentManager::new().with_language_overrides(overrides);
+
+        assert_eq!(
+            manager.language_id_for_path(Path::new("/a/x.rs")),
+            "rust-template"
+        );
+        assert_eq!(
+            manager.language_id_for_path(Path::new("/a/Dockerfile.dev")),
+            "dockerfile"
+        );
+        assert_eq!(manager.language_id_for_path(Path::new("/a/x.py")), "python");
+        assert_eq!(
+            DocumentManager::new().language_id_for_path(Path::new("/a/Dockerfile.dev")),
+            "plaintext"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_path_to_uri() -> Result<()> {
         let uri = path_to_uri(Path::new("/home/user/test.rs"))?;
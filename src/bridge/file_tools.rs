@@ -1,15 +1,22 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 // Copyright (C) 2026 Mark Wells <contact@markwells.dev>
 
-//! File I/O tool handlers: `list_directory`.
+//! File I/O tool handlers: `list_directory`, `diff` and `apply_edit`.
 //!
 //! Path operations validate paths against workspace roots before access.
 
 use anyhow::{Result, anyhow};
+use lsp_types::{
+    DocumentChangeOperation, DocumentChanges, OneOf, Position, PositionEncodingKind, TextEdit,
+    WorkspaceEdit,
+};
 use serde::Deserialize;
+use std::collections::{BTreeMap, VecDeque};
 use std::fmt::Write;
+use std::path::PathBuf;
 
-use super::handler::LspBridgeHandler;
+use super::document_manager::check_file_size;
+use super::handler::{LspBridgeHandler, byte_offset, retain_root_edits};
 use crate::mcp::CallToolResult;
 
 /// Input for `list_directory`.
@@ -19,7 +26,203 @@ pub struct ListDirectoryInput {
     pub path: String,
 }
 
+/// Maximum size of the unified diff returned by the `diff` tool.
+const DIFF_MAX_BYTES: usize = 100_000;
+
+/// Input for `diff`.
+#[derive(Debug, Deserialize)]
+pub struct DiffInput {
+    /// Git ref to compare the working tree against (default `HEAD`).
+    #[serde(rename = "ref")]
+    pub git_ref: Option<String>,
+    /// File or directory to limit the diff to (default: every workspace root).
+    pub path: Option<String>,
+}
+
+/// Number of proposed edits remembered for `apply_edit`.
+const PROPOSED_EDITS_MAX: usize = 32;
+
+/// Input for `apply_edit`.
+///
+/// Either `edit_id` or all of `file`, `line`, `character` and `new_name`.
+#[derive(Debug, Deserialize)]
+pub struct ApplyEditInput {
+    /// Id of an edit proposed by `rename` or `organize_imports`.
+    pub edit_id: Option<String>,
+    /// File of the symbol to rename.
+    pub file: Option<String>,
+    /// Line of the symbol to rename (0-indexed).
+    pub line: Option<u32>,
+    /// Character of the symbol to rename (0-indexed).
+    pub character: Option<u32>,
+    /// New name for the symbol.
+    pub new_name: Option<String>,
+    /// Drop edits to files outside the workspace roots (default: false).
+    #[serde(default)]
+    pub roots_only: bool,
+}
+
+/// Recently proposed workspace edits, addressable by `edit-<n>` ids.
+///
+/// Only the last [`PROPOSED_EDITS_MAX`] are kept; an edit is removed once
+/// applied so the same id cannot be written twice.
+#[derive(Debug, Default)]
+pub struct ProposedEdits {
+    next_id: u64,
+    edits: VecDeque<(String, WorkspaceEdit)>,
+}
+
+impl ProposedEdits {
+    /// Remembers `edit` and returns its id, forgetting the oldest edit if full.
+    pub fn insert(&mut self, edit: WorkspaceEdit) -> String {
+        self.next_id += 1;
+        let id = format!("edit-{}", self.next_id);
+        if self.edits.len() >= PROPOSED_EDITS_MAX {
+            self.edits.pop_front();
+        }
+        self.edits.push_back((id.clone(), edit));
+        id
+    }
+
+    /// Removes and returns the edit with `id`.
+    pub fn take(&mut self, id: &str) -> Option<WorkspaceEdit> {
+        let index = self.edits.iter().position(|(edit_id, _)| edit_id == id)?;
+        self.edits.remove(index).map(|(_, edit)| edit)
+    }
+}
+
 impl LspBridgeHandler {
+    /// Records `edit` for `apply_edit` and appends its id to `output`.
+    ///
+    /// Does nothing unless `allow_edits` is enabled.
+    pub(super) fn append_edit_id(&self, output: &mut String, edit: WorkspaceEdit) {
+        if !self.client_manager.config().allow_edits {
+            return;
+        }
+        let Ok(mut proposed) = self.proposed_edits.lock() else {
+            return;
+        };
+        let id = proposed.insert(edit);
+        drop(proposed);
+        let _ = write!(output, "\n\nEdit ID: {id} (apply with apply_edit)");
+    }
+
+    /// Handles the `apply_edit` tool call.
+    ///
+    /// Every target file is validated and every edit computed before the
+    /// first write, so a rejected edit leaves the tree untouched.
+    pub(super) fn handle_apply_edit(
+        &self,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<CallToolResult> {
+        if !self.client_manager.config().allow_edits {
+            return Err(anyhow!(
+                "apply_edit is disabled; set allow_edits = true in the Catenary config to enable it"
+            ));
+        }
+        let input: ApplyEditInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+
+        let mut edit = if let Some(id) = &input.edit_id {
+            self.proposed_edits
+                .lock()
+                .map_err(|_| anyhow!("Proposed edit store is unavailable"))?
+                .take(id)
+                .ok_or_else(|| anyhow!("Unknown or expired edit id: {id}"))?
+        } else {
+            let (Some(file), Some(line), Some(character), Some(new_name)) =
+                (&input.file, input.line, input.character, &input.new_name)
+            else {
+                return Err(anyhow!(
+                    "Pass either edit_id or file, line, character and new_name"
+                ));
+            };
+            let path = self.resolve_input_path(file)?;
+            let position = Position { line, character };
+            let Some(edit) = self.rename_edit(&path, position, new_name.clone())? else {
+                return Ok(CallToolResult::text(
+                    "Rename not supported at this location",
+                ));
+            };
+            edit
+        };
+
+        tracing::debug!("apply_edit: {:?}", input.edit_id);
+
+        // Edit URIs come from the server, so filter them by the roots it was
+        // given; every target is still validated against canonical roots.
+        if input.roots_only {
+            let roots = self.runtime.block_on(self.client_manager.roots());
+            retain_root_edits(&mut edit, &roots);
+        }
+        let files = text_edits_by_file(&edit)?;
+        if files.is_empty() {
+            return Ok(CallToolResult::text("Edit contains no changes"));
+        }
+        let validator = self.runtime.block_on(self.path_validator.read());
+        let targets = files
+            .iter()
+            .map(|(path, edits)| Ok((validator.validate_write(path)?, edits)))
+            .collect::<Result<Vec<_>>>()?;
+        drop(validator);
+
+        let max_file_bytes = self.client_manager.config().max_file_bytes;
+        let mut writes = Vec::with_capacity(targets.len());
+        for (path, edits) in &targets {
+            let len = std::fs::metadata(path)
+                .map_err(|e| anyhow!("Failed to read {}: {e}", path.display()))?
+                .len();
+            check_file_size(path, len, max_file_bytes)?;
+            let content = std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Failed to read {}: {e}", path.display()))?;
+            let encoding = self.runtime.block_on(self.encoding_for(path));
+            let updated = apply_text_edits(&content, edits, &encoding)
+                .map_err(|e| anyhow!("{}: {e}", path.display()))?;
+            writes.push((path, updated));
+        }
+
+        for (path, updated) in writes {
+            std::fs::write(path, &updated)
+                .map_err(|e| anyhow!("Failed to write {}: {e}", path.display()))?;
+            if let Err(e) = self
+                .runtime
+                .block_on(self.sync_written_file(path, &updated))
+            {
+                tracing::warn!("Failed to sync {} with its server: {e}", path.display());
+            }
+        }
+
+        Ok(CallToolResult::text(format!(
+            "Applied {}",
+            format_edit_summary(&files)
+        )))
+    }
+
+    /// Returns the position encoding of the server for `path`, or UTF-16
+    /// when no server handles it.
+    async fn encoding_for(&self, path: &std::path::Path) -> PositionEncodingKind {
+        match self.get_client_for_path(path).await {
+            Ok(client) => client.lock().await.encoding(),
+            Err(_) => PositionEncodingKind::UTF16,
+        }
+    }
+
+    /// Tells the server for `path` about content Catenary just wrote.
+    async fn sync_written_file(&self, path: &std::path::Path, content: &str) -> Result<()> {
+        let mtime = std::fs::metadata(path)?.modified()?;
+        let Ok(client_mutex) = self.get_client_for_path(path).await else {
+            return Ok(());
+        };
+        let notification = self
+            .doc_manager
+            .lock()
+            .await
+            .notify_external_write(path, content, mtime)?;
+        let client = client_mutex.lock().await;
+        notification.send(&client).await
+    }
+
     /// Handles the `list_directory` tool call.
     pub(super) fn handle_list_directory(
         &self,
@@ -104,4 +307,317 @@ impl LspBridgeHandler {
 
         Ok(CallToolResult::text(result))
     }
+
+    /// Handles the `diff` tool call.
+    ///
+    /// Runs `git diff <ref>` read-only in each target directory and returns
+    /// the combined unified diff, truncated to [`DIFF_MAX_BYTES`].
+    pub(super) fn handle_diff(
+        &self,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<CallToolResult> {
+        let input: DiffInput = match arguments {
+            Some(args) => {
+                serde_json::from_value(args).map_err(|e| anyhow!("Invalid arguments: {e}"))?
+            }
+            None => DiffInput {
+                git_ref: None,
+                path: None,
+            },
+        };
+
+        let git_ref = input.git_ref.as_deref().unwrap_or("HEAD");
+        if git_ref.is_empty() || git_ref.starts_with('-') || git_ref.contains('\0') {
+            return Err(anyhow!("Invalid git ref: {git_ref}"));
+        }
+
+        tracing::debug!("diff: {git_ref} {:?}", input.path);
+
+        // (directory to run git in, pathspec)
+        let targets: Vec<(std::path::PathBuf, String)> = if let Some(path) = &input.path {
+            let path = Self::resolve_path(path)?;
+            let canonical = self
+                .runtime
+                .block_on(self.path_validator.read())
+                .validate_read(&path)?;
+            if canonical.is_dir() {
+                vec![(canonical, ".".to_string())]
+            } else {
+                let dir = canonical
+                    .parent()
+                    .ok_or_else(|| anyhow!("Path has no parent: {}", canonical.display()))?
+                    .to_path_buf();
+                let name = canonical
+                    .file_name()
+                    .map(|n| n.to_string_lossy().to_string())
+                    .ok_or_else(|| anyhow!("Path has no file name: {}", canonical.display()))?;
+                vec![(dir, name)]
+            }
+        } else {
+            self.runtime
+                .block_on(self.client_manager.roots())
+                .into_iter()
+                .map(|root| (root, ".".to_string()))
+                .collect()
+        };
+
+        let mut diff = String::new();
+        let mut errors = Vec::new();
+        for (dir, pathspec) in &targets {
+            match run_git_diff(dir, git_ref, pathspec) {
+                Ok(output) => diff.push_str(&output),
+                Err(e) => errors.push(format!("{}: {e}", dir.display())),
+            }
+        }
+
+        if diff.is_empty() {
+            if errors.len() == targets.len() && !errors.is_empty() {
+                return Err(anyhow!("git diff failed:\n{}", errors.join("\n")));
+            }
+            return Ok(CallToolResult::text(format!(
+                "No changes against {git_ref}"
+            )));
+        }
+
+        Ok(CallToolResult::text(truncate_diff(&diff, DIFF_MAX_BYTES)))
+    }
+}
+
+/// Groups the text edits of `edit` by target file.
+///
+/// Fails on file create, rename or delete operations, which `apply_edit`
+/// does not perform.
+fn text_edits_by_file(edit: &WorkspaceEdit) -> Result<BTreeMap<PathBuf, Vec<TextEdit>>> {
+    let mut files: BTreeMap<PathBuf, Vec<TextEdit>> = BTreeMap::new();
+    if let Some(changes) = &edit.changes {
+        for (uri, edits) in changes {
+            files
+                .entry(PathBuf::from(uri.path().as_str()))
+                .or_default()
+                .extend(edits.iter().cloned());
+        }
+    }
+
+    let mut push_document_edit = |doc_edit: &lsp_types::TextDocumentEdit| {
+        files
+            .entry(PathBuf::from(doc_edit.text_document.uri.path().as_str()))
+            .or_default()
+            .extend(doc_edit.edits.iter().map(|e| match e {
+                OneOf::Left(text_edit) => text_edit.clone(),
+                OneOf::Right(annotated) => annotated.text_edit.clone(),
+            }));
+    };
+    match &edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => edits.iter().for_each(&mut push_document_edit),
+        Some(DocumentChanges::Operations(ops)) => {
+            for op in ops {
+                match op {
+                    DocumentChangeOperation::Edit(doc_edit) => push_document_edit(doc_edit),
+                    DocumentChangeOperation::Op(_) => {
+                        return Err(anyhow!(
+                            "apply_edit does not create, rename or delete files; apply this edit manually"
+                        ));
+                    }
+                }
+            }
+        }
+        None => {}
+    }
+    Ok(files)
+}
+
+/// Summarizes what `apply_edit` writes for `edit`, as `N edit(s) to M
+/// file(s):` followed by one indented line per file.
+///
+/// # Errors
+///
+/// Fails when the edit creates, renames or deletes files.
+pub(super) fn edit_summary(edit: &WorkspaceEdit) -> Result<String> {
+    Ok(format_edit_summary(&text_edits_by_file(edit)?))
+}
+
+fn format_edit_summary(files: &BTreeMap<PathBuf, Vec<TextEdit>>) -> String {
+    let total: usize = files.values().map(Vec::len).sum();
+    let mut summary = format!("{total} edit(s) to {} file(s):", files.len());
+    for (path, edits) in files {
+        let _ = write!(summary, "\n  {} ({} edit(s))", path.display(), edits.len());
+    }
+    summary
+}
+
+/// Applies LSP text edits to `content`.
+///
+/// Positions are interpreted in `encoding`. Edits may arrive in any order but
+/// must not overlap; inserts at the same position keep their given order.
+fn apply_text_edits(
+    content: &str,
+    edits: &[TextEdit],
+    encoding: &PositionEncodingKind,
+) -> Result<String> {
+    let lines: Vec<(usize, &str)> = content
+        .split_inclusive('\n')
+        .scan(0, |start, line| {
+            let line_start = *start;
+            *start += line.len();
+            Some((line_start, line.trim_end_matches(['\n', '\r'])))
+        })
+        .collect();
+    let offset = |position: Position| {
+        lines
+            .get(position.line as usize)
+            .map_or(content.len(), |(start, line)| {
+                start + byte_offset(line, position.character, encoding)
+            })
+    };
+
+    let mut ranges: Vec<(usize, usize, &str)> = edits
+        .iter()
+        .map(|e| {
+            (
+                offset(e.range.start),
+                offset(e.range.end),
+                e.new_text.as_str(),
+            )
+        })
+        .collect();
+    ranges.sort_by_key(|&(start, end, _)| (start, end));
+
+    let mut result = String::with_capacity(content.len());
+    let mut cursor = 0;
+    for (start, end, text) in ranges {
+        if start < cursor || end < start {
+            return Err(anyhow!("Edit contains overlapping or inverted ranges"));
+        }
+        result.push_str(&content[cursor..start]);
+        result.push_str(text);
+        cursor = end;
+    }
+    result.push_str(&content[cursor..]);
+    Ok(result)
+}
+
+/// Runs `git diff` in `dir` and returns its stdout.
+///
+/// Optional locks are disabled so the call never writes to the repository.
+fn run_git_diff(dir: &std::path::Path, git_ref: &str, pathspec: &str) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "diff",
+            "--no-color",
+            "--no-ext-diff",
+            "--no-textconv",
+            git_ref,
+            "--",
+            pathspec,
+        ])
+        .env("GIT_OPTIONAL_LOCKS", "0")
+        .stdin(std::process::Stdio::null())
+        .output()
+        .map_err(|e| anyhow!("Failed to run git: {e}"))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("{}", stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Truncates a diff to at most `max_bytes`, cutting at a line boundary.
+fn truncate_diff(diff: &str, max_bytes: usize) -> String {
+    if diff.len() <= max_bytes {
+        return diff.to_string();
+    }
+    let mut end = max_bytes;
+    while !diff.is_char_boundary(end) {
+        end -= 1;
+    }
+    let end = diff[..end].rfind('\n').map_or(end, |i| i + 1);
+    let mut out = diff[..end].to_string();
+    let _ = write!(
+        out,
+        "\n[diff truncated: showing {end} of {} bytes]",
+        diff.len()
+    );
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::Range;
+
+    fn text_edit(start: (u32, u32), end: (u32, u32), new_text: &str) -> TextEdit {
+        TextEdit {
+            range: Range {
+                start: Position::new(start.0, start.1),
+                end: Position::new(end.0, end.1),
+            },
+            new_text: new_text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_apply_text_edits_in_any_order() -> Result<()> {
+        let content = "fn foo() {}\nfn main() { foo(); }\n";
+        let edits = [
+            text_edit((1, 12), (1, 15), "bar"),
+            text_edit((0, 3), (0, 6), "bar"),
+        ];
+        let updated = apply_text_edits(content, &edits, &PositionEncodingKind::UTF16)?;
+        assert_eq!(updated, "fn bar() {}\nfn main() { bar(); }\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_text_edits_uses_encoding() -> Result<()> {
+        // 'é' is one UTF-16 unit but two UTF-8 bytes.
+        let content = "let é = x;\r\nlet y = x;\r\n";
+        let utf16 = apply_text_edits(
+            content,
+            &[text_edit((0, 8), (0, 9), "z")],
+            &PositionEncodingKind::UTF16,
+        )?;
+        assert_eq!(utf16, "let é = z;\r\nlet y = x;\r\n");
+
+        let utf8 = apply_text_edits(
+            content,
+            &[text_edit((0, 9), (0, 10), "z")],
+            &PositionEncodingKind::UTF8,
+        )?;
+        assert_eq!(utf8, utf16);
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_text_edits_rejects_overlap() {
+        let edits = [
+            text_edit((0, 0), (0, 4), "a"),
+            text_edit((0, 2), (0, 6), "b"),
+        ];
+        assert!(apply_text_edits("abcdefgh", &edits, &PositionEncodingKind::UTF16).is_err());
+    }
+
+    #[test]
+    fn test_proposed_edits_are_taken_once() {
+        let mut proposed = ProposedEdits::default();
+        let id = proposed.insert(WorkspaceEdit::default());
+        assert_eq!(id, "edit-1");
+        assert!(proposed.take(&id).is_some());
+        assert!(proposed.take(&id).is_none());
+    }
+
+    #[test]
+    fn test_truncate_diff_cuts_at_line_boundary() {
+        let diff = "line one\nline two\nline three\n";
+        assert_eq!(truncate_diff(diff, 100), diff);
+
+        let truncated = truncate_diff(diff, 12);
+        assert!(truncated.starts_with("line one\n\n["), "got: {truncated}");
+        assert!(
+            truncated.contains("showing 9 of 29 bytes"),
+            "got: {truncated}"
+        );
+    }
 }
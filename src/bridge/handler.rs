@@ -7,14 +7,18 @@ use anyhow::{Result, anyhow};
 use ignore::WalkBuilder;
 use lsp_types::{
     CallHierarchyIncomingCall, CallHierarchyIncomingCallsParams, CallHierarchyOutgoingCall,
-    CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams, CodeActionContext,
-    CodeActionOrCommand, CodeActionParams, Diagnostic, DiagnosticSeverity, DocumentChanges,
-    DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams,
-    GotoDefinitionResponse, Hover, HoverParams, Location, LocationLink, Position, Range,
-    ReferenceContext, ReferenceParams, RenameParams, SymbolInformation, TextDocumentIdentifier,
-    TextDocumentPositionParams, TypeHierarchyItem, TypeHierarchyPrepareParams,
-    TypeHierarchySubtypesParams, TypeHierarchySupertypesParams, WorkspaceEdit,
-    WorkspaceSymbolParams, WorkspaceSymbolResponse,
+    CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams, CodeAction, CodeActionContext,
+    CodeActionKind, CodeActionOrCommand, CodeActionParams, CodeLens, CodeLensParams,
+    CompletionContext, CompletionItem, CompletionItemKind, CompletionParams, CompletionResponse,
+    CompletionTriggerKind, Diagnostic, DiagnosticSeverity, DocumentChangeOperation,
+    DocumentChanges, DocumentHighlight, DocumentHighlightKind, DocumentHighlightParams,
+    DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, FoldingRange, FoldingRangeKind,
+    FoldingRangeParams, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverParams,
+    InlayHint, InlayHintLabel, InlayHintParams, Location, LocationLink, Position,
+    PrepareRenameResponse, Range, ReferenceContext, ReferenceParams, RenameParams,
+    SymbolInformation, TextDocumentIdentifier, TextDocumentPositionParams, TypeHierarchyItem,
+    TypeHierarchyPrepareParams, TypeHierarchySubtypesParams, TypeHierarchySupertypesParams, Uri,
+    WorkspaceEdit, WorkspaceSymbolParams, WorkspaceSymbolResponse,
 };
 use serde::Deserialize;
 use std::path::{Path, PathBuf};
@@ -24,19 +28,57 @@ use tokio::runtime::Handle;
 use tokio::sync::Mutex;
 use tracing::{debug, warn};
 
+use crate::config::DiagnosticsWaitStrategy;
 use crate::lsp::{
     ClientManager, DIAGNOSTICS_TIMEOUT, DiagnosticsWaitResult, LspClient, ServerState,
+    progress_summary,
 };
-use crate::mcp::{CallToolResult, Tool, ToolHandler};
+use crate::mcp::{CallToolResult, Tool, ToolContent, ToolHandler};
 use crate::session::{EventBroadcaster, EventKind};
 
 use super::PathValidator;
 
 /// Tools that do not require LSP server readiness.
 /// Everything else waits by default — new tools are safe automatically.
-const METHODS_SKIP_WAIT: &[&str] = &["status", "list_directory"];
+const METHODS_SKIP_WAIT: &[&str] = &[
+    "status",
+    "health_check",
+    "capabilities",
+    "list_directory",
+    "diff",
+];
+
+/// Per-directory ignore file (`.gitignore` syntax) for paths that should
+/// stay out of `codebase_map` and `search` without touching `.gitignore`.
+const IGNORE_FILENAME: &str = ".catenaryignore";
+
+/// Most files `codebase_map` fetches symbols for at once.
+const MAP_SYMBOL_CONCURRENCY: usize = 8;
+
+/// Most open documents one `search` asks a server for symbols; the others
+/// are searched only when their symbols are already cached.
+const OPEN_SYMBOL_FETCHES: usize = 8;
+
+/// How long `search` waits for one open document's symbols.
+const OPEN_SYMBOL_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Outcome of fetching one file's symbols for `codebase_map`.
+enum MapSymbols {
+    /// Formatted symbols, from the cache or the server.
+    Found(String),
+    /// No server is available for this language.
+    Unavailable(String),
+    /// Plain text, a file that failed to open, or a request that timed out.
+    Skipped,
+}
+
+/// How often the startup gate checks whether a server is still being spawned.
+const INIT_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
-use super::{DocumentManager, DocumentNotification};
+use super::DocumentManager;
+use super::file_tools::{ProposedEdits, edit_summary};
+use super::symbol_cache::SymbolCache;
+use super::symbol_index::{SymbolIndex, matching_document_symbols, symbol_index_dir};
 
 /// Controls how much symbol detail to include in output.
 #[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
@@ -68,17 +110,48 @@ pub struct SymbolOrPositionInput {
     pub character: Option<u32>,
 }
 
-/// Input for tools that need only a file path.
+/// A symbol that matched a name lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SymbolCandidate {
+    name: String,
+    kind: lsp_types::SymbolKind,
+    container: Option<String>,
+    path: PathBuf,
+    position: Position,
+}
+
+/// Result of resolving a [`SymbolOrPositionInput`] when a name may match
+/// more than one symbol.
+enum SymbolResolution {
+    /// Exactly one position to query.
+    Unique(PathBuf, Position),
+    /// Several symbols share the name; the caller should list them.
+    Ambiguous(String, Vec<SymbolCandidate>),
+}
+
+/// Input for diagnostics.
 #[derive(Debug, Deserialize)]
-pub struct FileInput {
+pub struct DiagnosticsInput {
     /// Path to the file.
     pub file: String,
+    /// Close and re-open the document so the server re-analyzes it from
+    /// disk, discarding cached diagnostics (default: false).
+    #[serde(default)]
+    pub force_refresh: bool,
+    /// Also report what the file's server has published for every other
+    /// file, including ones that were never opened (default: false).
+    #[serde(default)]
+    pub workspace: bool,
 }
 
 const fn default_true() -> bool {
     true
 }
 
+const fn default_max_references() -> usize {
+    100
+}
+
 /// Input for `find_references` - accepts either symbol name OR position.
 #[derive(Debug, Deserialize)]
 pub struct FindReferencesInput {
@@ -92,6 +165,43 @@ pub struct FindReferencesInput {
     pub character: Option<u32>,
     #[serde(default = "default_true")]
     pub include_declaration: bool,
+    /// Most references returned, not counting the definition; 0 for all
+    /// (default: 100).
+    #[serde(default = "default_max_references")]
+    pub max_results: usize,
+    /// Number of references to skip, for paging through the rest.
+    #[serde(default)]
+    pub offset: usize,
+    /// Group the output by file, printing each path once (default: false).
+    #[serde(default)]
+    pub grouped: bool,
+}
+
+/// Input for `investigate`.
+#[derive(Debug, Deserialize)]
+pub struct InvestigateInput {
+    /// Symbol name to look up (uses workspace symbols)
+    pub symbol: String,
+    /// File to search first when the name is common
+    pub file: Option<String>,
+}
+
+/// Input for document symbols.
+#[derive(Debug, Deserialize)]
+pub struct DocumentSymbolsInput {
+    /// Path to the file.
+    pub file: String,
+    /// Emit one fully-qualified path per symbol (`Outer::Inner::item`)
+    /// instead of an indented tree (default: false).
+    #[serde(default)]
+    pub qualified: bool,
+    /// Symbol detail level: outline, signatures, or full (default: full)
+    #[serde(default = "default_document_detail_level")]
+    pub detail_level: DetailLevel,
+}
+
+const fn default_document_detail_level() -> DetailLevel {
+    DetailLevel::Full
 }
 
 /// Input for unified search.
@@ -99,16 +209,137 @@ pub struct FindReferencesInput {
 pub struct SearchInput {
     /// One or more search queries.
     pub queries: Vec<String>,
+    /// How symbol and text results are combined (default: `heatmap`).
+    #[serde(default)]
+    pub mode: SearchMode,
+}
+
+/// How `search` combines LSP symbols with text matches.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Workspace symbols followed by a heatmap of every text match.
+    #[default]
+    Heatmap,
+    /// Query LSP and grep concurrently; text matches on lines already
+    /// reported as symbols are dropped.
+    Merged,
+}
+
+/// Input for completion.
+#[derive(Debug, Deserialize)]
+pub struct CompletionInput {
+    pub file: String,
+    pub line: u32,
+    pub character: u32,
+    /// Only include items of these kinds (e.g., "method", "field").
+    pub kinds: Option<Vec<String>>,
+    /// Group items under per-kind headers (default: false).
+    #[serde(default)]
+    pub group: bool,
+    /// Resolve the first N listed items and show their documentation
+    /// (default: 0, capped at [`COMPLETION_MAX_RESOLVE`]).
+    #[serde(default)]
+    pub resolve_top: usize,
 }
 
 /// Input for code actions.
+///
+/// Either the full range (`start_*`/`end_*`) or a single `line`/`character`
+/// cursor must be given. A cursor is widened to a range automatically.
 #[derive(Debug, Deserialize)]
 pub struct CodeActionInput {
+    pub file: String,
+    pub start_line: Option<u32>,
+    pub start_character: Option<u32>,
+    pub end_line: Option<u32>,
+    pub end_character: Option<u32>,
+    pub line: Option<u32>,
+    pub character: Option<u32>,
+}
+
+/// Where a `code_actions` request should look.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CodeActionTarget {
+    /// An explicit range from the caller.
+    Range(Range),
+    /// A cursor position to widen into a range.
+    Cursor(Position),
+}
+
+impl CodeActionInput {
+    fn target(&self) -> Result<CodeActionTarget> {
+        match (
+            self.start_line,
+            self.start_character,
+            self.end_line,
+            self.end_character,
+            self.line,
+            self.character,
+        ) {
+            (Some(sl), Some(sc), Some(el), Some(ec), None, None) => {
+                Ok(CodeActionTarget::Range(Range {
+                    start: Position::new(sl, sc),
+                    end: Position::new(el, ec),
+                }))
+            }
+            (None, None, None, None, Some(line), Some(character)) => {
+                Ok(CodeActionTarget::Cursor(Position::new(line, character)))
+            }
+            _ => Err(anyhow!(
+                "Provide either start_line, start_character, end_line and end_character, \
+                 or just line and character"
+            )),
+        }
+    }
+}
+
+/// Input for diagnostic fixes and explanations.
+#[derive(Debug, Deserialize)]
+pub struct DiagnosticFixesInput {
+    pub file: String,
+    pub line: u32,
+    pub character: u32,
+}
+
+/// Input for folding ranges.
+#[derive(Debug, Deserialize)]
+pub struct FoldingRangesInput {
+    /// Path to the file.
+    pub file: String,
+}
+
+/// Input for tools that take a single cursor position.
+#[derive(Debug, Deserialize)]
+pub struct PositionInput {
+    /// Path to the file.
+    pub file: String,
+    /// 0-indexed line number.
+    pub line: u32,
+    /// 0-indexed character position.
+    pub character: u32,
+}
+
+/// Input for code lenses.
+#[derive(Debug, Deserialize)]
+pub struct CodeLensInput {
+    /// Path to the file.
+    pub file: String,
+}
+
+/// Input for organize imports.
+#[derive(Debug, Deserialize)]
+pub struct OrganizeImportsInput {
+    /// Path to the file.
+    pub file: String,
+}
+
+/// Input for inlay hints over a line range (both ends inclusive).
+#[derive(Debug, Deserialize)]
+pub struct InlayHintsInput {
     pub file: String,
     pub start_line: u32,
-    pub start_character: u32,
     pub end_line: u32,
-    pub end_character: u32,
 }
 
 /// Input for rename.
@@ -118,6 +349,13 @@ pub struct RenameInput {
     pub line: u32,
     pub character: u32,
     pub new_name: String,
+    /// Preview only (default). `false` writes the edits through `apply_edit`,
+    /// which requires `allow_edits`.
+    #[serde(default = "default_true")]
+    pub dry_run: bool,
+    /// Drop edits to files outside the workspace roots (default: false).
+    #[serde(default)]
+    pub roots_only: bool,
 }
 
 /// Input for call hierarchy.
@@ -169,6 +407,45 @@ pub struct CodebaseMapInput {
     pub detail_level: DetailLevel,
 }
 
+/// Which diagnostics `workspace_diagnostics` counts.
+#[derive(Debug, Clone, Copy, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SeverityFilter {
+    /// Errors only.
+    Error,
+    /// Warnings only.
+    Warning,
+    /// Errors, warnings, and informational diagnostics and hints.
+    #[default]
+    All,
+}
+
+/// Input for workspace diagnostics.
+#[derive(Debug, Deserialize)]
+pub struct WorkspaceDiagnosticsInput {
+    /// Which severities to report (default: all)
+    #[serde(default)]
+    pub severity: SeverityFilter,
+    /// Max lines of output before truncation (default: 2000)
+    #[serde(default = "default_budget")]
+    pub budget: usize,
+}
+
+/// Input for `execute_command`.
+#[derive(Debug, Deserialize)]
+pub struct ExecuteCommandInput {
+    /// Language whose server runs the command (e.g. "rust").
+    pub language: String,
+    /// File or root directory the command concerns. Picks the server of the
+    /// root containing it when the language sets `isolate_roots`.
+    pub file: Option<String>,
+    /// Command id, which must be listed in `allowed_server_commands`.
+    pub command: String,
+    /// Arguments passed to the command as-is.
+    #[serde(default)]
+    pub arguments: Vec<serde_json::Value>,
+}
+
 const fn default_depth() -> usize {
     5
 }
@@ -185,25 +462,51 @@ pub struct LspBridgeHandler {
     pub(super) runtime: Handle,
     pub(super) broadcaster: EventBroadcaster,
     pub(super) path_validator: Arc<tokio::sync::RwLock<PathValidator>>,
+    /// When the bridge was created, for `health_check` uptime.
+    started_at: std::time::Instant,
+    /// On-disk symbol index, when `symbol_index` is enabled.
+    symbol_index: Option<Arc<std::sync::Mutex<SymbolIndex>>>,
+    /// Document symbols `codebase_map` can reuse for unchanged files.
+    symbol_cache: std::sync::Mutex<SymbolCache>,
+    /// Edits offered by `rename` and `organize_imports` for `apply_edit`.
+    pub(super) proposed_edits: std::sync::Mutex<ProposedEdits>,
 }
 
 impl LspBridgeHandler {
     /// Creates a new `LspBridgeHandler`.
-    pub const fn new(
+    pub fn new(
         client_manager: Arc<ClientManager>,
         doc_manager: Arc<Mutex<DocumentManager>>,
         runtime: Handle,
         broadcaster: EventBroadcaster,
         path_validator: Arc<tokio::sync::RwLock<PathValidator>>,
     ) -> Self {
+        let symbol_index = client_manager
+            .config()
+            .symbol_index
+            .then(|| Arc::new(std::sync::Mutex::new(SymbolIndex::new(symbol_index_dir()))));
+        let symbol_cache =
+            std::sync::Mutex::new(SymbolCache::new(client_manager.config().symbol_cache_size));
         Self {
             client_manager,
             doc_manager,
             runtime,
             broadcaster,
             path_validator,
+            started_at: std::time::Instant::now(),
+            symbol_index,
+            symbol_cache,
+            proposed_edits: std::sync::Mutex::new(ProposedEdits::default()),
         }
     }
+    /// Returns the on-disk symbol index, when `symbol_index` is enabled.
+    ///
+    /// Recorded symbols stay in memory until the caller flushes the index.
+    #[must_use]
+    pub fn symbol_index(&self) -> Option<Arc<std::sync::Mutex<SymbolIndex>>> {
+        self.symbol_index.clone()
+    }
+
     /// Gets the appropriate LSP client for the given file path.
     pub(super) async fn get_client_for_path(&self, path: &Path) -> Result<Arc<Mutex<LspClient>>> {
         let lang_id = {
@@ -211,7 +514,9 @@ impl LspBridgeHandler {
             doc_manager.language_id_for_path(path).to_string()
         };
 
-        self.client_manager.get_client(&lang_id).await
+        self.client_manager
+            .get_client_for_file(&lang_id, path)
+            .await
     }
 
     /// Waits for the server handling the given path to be ready.
@@ -257,6 +562,45 @@ impl LspBridgeHandler {
         Ok(())
     }
 
+    /// Waits, during the first `init_timeout` seconds of the session, until
+    /// the startup spawn of the detected servers has finished.
+    ///
+    /// With `init_timeout` set, servers are spawned in the background so the
+    /// MCP loop starts at once. Unlike the ready-wait this does not talk to
+    /// any server: it only keeps early calls from racing that spawn. Calls
+    /// for languages without a configured server never wait, and the wait
+    /// gives up silently at the end of the startup window.
+    async fn wait_for_server_spawn(&self, path: Option<&Path>) {
+        let config = self.client_manager.config();
+        let Some(remaining) = init_wait_remaining(config.init_timeout, self.started_at.elapsed())
+        else {
+            return;
+        };
+
+        let relevant = match path {
+            Some(path) => {
+                let lang_id = {
+                    let doc_manager = self.doc_manager.lock().await;
+                    doc_manager.language_id_for_path(path).to_string()
+                };
+                self.client_manager.handles_file(&lang_id, path).await
+            }
+            None => !config.server.is_empty(),
+        };
+        if !relevant {
+            return;
+        }
+
+        let deadline = tokio::time::Instant::now() + remaining;
+        while self.client_manager.is_spawning() {
+            if tokio::time::Instant::now() >= deadline {
+                debug!("init_timeout elapsed while servers were still spawning");
+                return;
+            }
+            tokio::time::sleep(INIT_POLL_INTERVAL).await;
+        }
+    }
+
     /// Extract file path from arguments if present.
     fn extract_file_path(arguments: Option<&serde_json::Value>) -> Option<PathBuf> {
         arguments
@@ -275,7 +619,10 @@ impl LspBridgeHandler {
             return CallToolResult::text("No LSP servers running");
         }
 
-        let mut output = vec![format!("Catenary {}", env!("CATENARY_VERSION"))];
+        let mut output = vec![
+            format!("Catenary {}", env!("CATENARY_VERSION")),
+            progress_summary(&statuses),
+        ];
         for status in statuses {
             let state_str = match status.state {
                 ServerState::Initializing => "Initializing",
@@ -284,10 +631,17 @@ impl LspBridgeHandler {
                 ServerState::Dead => "Dead",
             };
 
-            let mut line = format!(
-                "{}: {} (uptime: {}s)",
-                status.language, state_str, status.uptime_secs
-            );
+            let mut line = if status.restarts > 0 {
+                format!(
+                    "{}: {} (uptime: {}s, restarts: {})",
+                    status.language, state_str, status.uptime_secs, status.restarts
+                )
+            } else {
+                format!(
+                    "{}: {} (uptime: {}s)",
+                    status.language, state_str, status.uptime_secs
+                )
+            };
 
             if let Some(title) = &status.progress_title {
                 use std::fmt::Write;
@@ -306,6 +660,105 @@ impl LspBridgeHandler {
         CallToolResult::text(output.join("\n"))
     }
 
+    /// Handles the `capabilities` tool: the tools each active server's
+    /// advertised capabilities support, one `<lang>: <tools>` line per server.
+    fn handle_capabilities(&self) -> CallToolResult {
+        let mut lines = self.runtime.block_on(async {
+            self.wait_for_server_spawn(None).await;
+            let mut lines = Vec::new();
+            for (key, client_mutex) in self.client_manager.active_clients().await {
+                let tools = client_mutex.lock().await.supported_tools().join(" ");
+                lines.push(format!(
+                    "{key}: {}",
+                    if tools.is_empty() { "none" } else { &tools }
+                ));
+            }
+            lines
+        });
+
+        if lines.is_empty() {
+            return CallToolResult::text("No LSP servers running");
+        }
+        lines.sort();
+        CallToolResult::text(lines.join("\n"))
+    }
+
+    /// Sends `workspace/executeCommand` to the server for a language and
+    /// returns its raw result. Only ids in `allowed_server_commands` are
+    /// sent, so an agent cannot run arbitrary server commands.
+    fn handle_execute_command(
+        &self,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<CallToolResult> {
+        let input: ExecuteCommandInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+
+        let allowed = &self.client_manager.config().allowed_server_commands;
+        if !allowed.contains(&input.command) {
+            return Err(anyhow!(
+                "Command '{}' is not allowed; add it to allowed_server_commands in the Catenary config",
+                input.command
+            ));
+        }
+
+        let path = input
+            .file
+            .as_deref()
+            .map(|file| self.resolve_input_path(file))
+            .transpose()?;
+        let isolated = self
+            .client_manager
+            .config()
+            .server
+            .get(&input.language)
+            .is_some_and(|s| s.isolate_roots);
+        if isolated && path.is_none() {
+            return Err(anyhow!(
+                "[{}] runs one server per root; pass file to pick the root whose server runs the command",
+                input.language
+            ));
+        }
+
+        let outcome = self.runtime.block_on(async {
+            let client_mutex = match &path {
+                Some(path) => {
+                    self.client_manager
+                        .get_client_for_file(&input.language, path)
+                        .await?
+                }
+                None => self.client_manager.get_client(&input.language).await?,
+            };
+            let client = client_mutex.lock().await;
+            client
+                .execute_command(&input.command, input.arguments.clone())
+                .await
+        })?;
+
+        let result = outcome.result.unwrap_or(serde_json::Value::Null);
+        let mut output = serde_json::to_string_pretty(&result)?;
+        if !outcome.edits.is_empty() {
+            use std::fmt::Write;
+
+            let _ = write!(
+                output,
+                "\n\n[server proposed {} edit(s) via workspace/applyEdit; not applied]",
+                outcome.edits.len()
+            );
+        }
+        Ok(CallToolResult::text(output))
+    }
+
+    /// Reports bridge liveness from in-memory state only: no LSP requests
+    /// are sent and no lock is waited on.
+    fn handle_health_check(&self) -> CallToolResult {
+        CallToolResult::text(format_health(
+            env!("CATENARY_VERSION"),
+            self.started_at.elapsed().as_secs(),
+            self.client_manager.try_server_states().as_deref(),
+        ))
+    }
+
     /// How long to wait for initial analysis after opening a document.
     ///
     /// We only need the first `publishDiagnostics` for the URI — not the
@@ -317,6 +770,19 @@ impl LspBridgeHandler {
     async fn ensure_document_open(
         &self,
         path: &Path,
+    ) -> Result<(lsp_types::Uri, Arc<Mutex<LspClient>>)> {
+        self.open_document(path, true).await
+    }
+
+    /// Opens and syncs a document, optionally waiting for the server's
+    /// first analysis of it.
+    ///
+    /// The wait holds the client lock, so callers that don't need analysis
+    /// (document symbols are syntactic) skip it to avoid serializing on it.
+    async fn open_document(
+        &self,
+        path: &Path,
+        wait_for_analysis: bool,
     ) -> Result<(lsp_types::Uri, Arc<Mutex<LspClient>>)> {
         let client_mutex = self.get_client_for_path(path).await?;
         let mut doc_manager = self.doc_manager.lock().await;
@@ -332,18 +798,15 @@ impl LspBridgeHandler {
 
         let uri = doc_manager.uri_for_path(path)?;
 
-        if let Some(notification) = doc_manager.ensure_open(path).await? {
-            // Snapshot generation *before* sending the notification so
-            // we can wait for the server to publish fresh diagnostics.
-            let snapshot = client.diagnostics_generation(&uri).await;
+        // Snapshot generation *before* sending any notification so we can
+        // wait for the server to publish fresh diagnostics.
+        let snapshot = client.diagnostics_generation(&uri).await;
+        let reopened = doc_manager.reopen_on(&client).await? > 0;
+        let notification = doc_manager.ensure_open(path).await?;
 
-            match notification {
-                DocumentNotification::Open(params) => {
-                    client.did_open(params).await?;
-                }
-                DocumentNotification::Change(params) => {
-                    client.did_change(params).await?;
-                }
+        if reopened || notification.is_some() {
+            if let Some(notification) = notification {
+                notification.send(&client).await?;
             }
 
             drop(doc_manager);
@@ -351,9 +814,11 @@ impl LspBridgeHandler {
             // Wait for the server to analyze the file before returning.
             // Ignore the result — timeout/inactive is not fatal, the
             // subsequent query may still succeed with partial analysis.
-            let _ = client
-                .wait_for_diagnostics_update(&uri, snapshot, Self::OPEN_ANALYSIS_TIMEOUT)
-                .await;
+            if wait_for_analysis {
+                let _ = client
+                    .wait_for_diagnostics_update(&uri, snapshot, Self::OPEN_ANALYSIS_TIMEOUT)
+                    .await;
+            }
 
             drop(client);
             return Ok((uri, client_mutex.clone()));
@@ -364,6 +829,55 @@ impl LspBridgeHandler {
         Ok((uri, client_mutex.clone()))
     }
 
+    /// Sends a position request, retrying it up to `warmup_retries` times
+    /// while the answer is empty and the server is indexing.
+    ///
+    /// Servers that are still loading the project answer with nothing
+    /// rather than an error, which an agent would otherwise take at face
+    /// value.
+    async fn request_during_warmup<T>(
+        &self,
+        client_mutex: &Mutex<LspClient>,
+        request: impl AsyncFn(&LspClient) -> Result<Option<T>>,
+        is_empty: impl Fn(&T) -> bool,
+    ) -> Result<Option<T>> {
+        let config = self.client_manager.config();
+        let delay = Duration::from_millis(config.warmup_retry_ms);
+        let mut attempt = 0;
+        loop {
+            let client = client_mutex.lock().await;
+            let result = request(&client).await?;
+            let indexing = client.server_state() == ServerState::Indexing;
+            let language = client.language().to_string();
+            drop(client);
+
+            let empty = result.as_ref().is_none_or(&is_empty);
+            if !empty || !indexing || attempt >= config.warmup_retries {
+                return Ok(result);
+            }
+            attempt += 1;
+            debug!(
+                "[{language}] empty answer while indexing, retrying ({attempt}/{})",
+                config.warmup_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Resolves a tool's input path.
+    ///
+    /// With `strict_roots`, paths outside the workspace roots are rejected
+    /// with the same error the file I/O tools return.
+    pub(super) fn resolve_input_path(&self, file: &str) -> Result<PathBuf> {
+        let path = Self::resolve_path(file)?;
+        if self.client_manager.config().strict_roots {
+            self.runtime
+                .block_on(self.path_validator.read())
+                .validate_read(&path)?;
+        }
+        Ok(path)
+    }
+
     /// Resolves a file path, converting relative paths to absolute using the current working directory.
     pub(super) fn resolve_path(file: &str) -> Result<PathBuf> {
         let path = PathBuf::from(file);
@@ -396,65 +910,129 @@ impl LspBridgeHandler {
             let character = input
                 .character
                 .ok_or_else(|| anyhow!("'character' is required when using position"))?;
-            let path = Self::resolve_path(file)?;
+            let path = self.resolve_input_path(file)?;
             Ok((path, Position { line, character }))
         }
     }
 
-    fn handle_hover(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
-        let input: SymbolOrPositionInput =
-            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
-                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
-        let (path, position) = self.resolve_symbol_or_position(&input)?;
+    /// Like [`resolve_symbol_or_position`], but reports every match when a
+    /// symbol name is ambiguous instead of silently picking the first.
+    ///
+    /// Exact name matches are preferred; partial matches are only considered
+    /// when there is no exact match. Candidates in `file` (when given) take
+    /// precedence over workspace-wide results.
+    fn resolve_symbol_or_position_disambiguated(
+        &self,
+        input: &SymbolOrPositionInput,
+    ) -> Result<SymbolResolution> {
+        let Some(symbol) = &input.symbol else {
+            let (path, position) = self.resolve_symbol_or_position(input)?;
+            return Ok(SymbolResolution::Unique(path, position));
+        };
 
-        debug!("Hover request: {}:{}", path.display(), position.line);
+        let mut candidates = if let Some(file) = &input.file {
+            let path = self.resolve_input_path(file)?;
+            self.document_symbol_candidates(symbol, &path)?
+        } else {
+            Vec::new()
+        };
+        if candidates.is_empty() {
+            candidates = self.workspace_symbol_candidates(symbol);
+        }
 
-        let result = self.runtime.block_on(async {
-            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
-            let params = HoverParams {
-                text_document_position_params: TextDocumentPositionParams {
-                    text_document: TextDocumentIdentifier { uri },
-                    position,
-                },
+        match candidates.len() {
+            0 => Err(anyhow!("Symbol '{symbol}' not found in workspace")),
+            1 => {
+                let c = candidates.remove(0);
+                Ok(SymbolResolution::Unique(c.path, c.position))
+            }
+            _ => Ok(SymbolResolution::Ambiguous(symbol.clone(), candidates)),
+        }
+    }
+
+    /// Collects symbols in `path` matching `symbol`.
+    fn document_symbol_candidates(
+        &self,
+        symbol: &str,
+        path: &Path,
+    ) -> Result<Vec<SymbolCandidate>> {
+        let (uri, response) = self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(path).await?;
+            let params = DocumentSymbolParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
                 work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
             };
-            client_mutex.lock().await.hover(params).await
+            let response = client_mutex.lock().await.document_symbols(params).await?;
+            Ok::<_, anyhow::Error>((uri, response))
         })?;
 
-        result.map_or_else(
-            || Ok(CallToolResult::text("No hover information available")),
-            |hover| Ok(CallToolResult::text(format_hover(&hover))),
-        )
+        let max_depth = self.client_manager.config().max_symbol_depth;
+        Ok(response.map_or_else(Vec::new, |response| {
+            document_symbol_candidates(&response, symbol, &uri, max_depth)
+        }))
     }
 
-    fn handle_definition(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
+    /// Collects symbols matching `symbol` from every active server.
+    fn workspace_symbol_candidates(&self, symbol: &str) -> Vec<SymbolCandidate> {
+        self.runtime.block_on(async {
+            let params = WorkspaceSymbolParams {
+                query: symbol.to_string(),
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
+            };
+
+            let mut candidates = Vec::new();
+            for client_mutex in self.client_manager.active_clients().await.values() {
+                if let Ok(Some(response)) = client_mutex
+                    .lock()
+                    .await
+                    .workspace_symbols(params.clone())
+                    .await
+                {
+                    for candidate in workspace_symbol_candidates(&response, symbol) {
+                        if !candidates.contains(&candidate) {
+                            candidates.push(candidate);
+                        }
+                    }
+                }
+            }
+            candidates
+        })
+    }
+
+    fn handle_hover(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
         let input: SymbolOrPositionInput =
             serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
                 .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
         let (path, position) = self.resolve_symbol_or_position(&input)?;
 
-        debug!("Definition request: {}:{}", path.display(), position.line);
+        debug!("Hover request: {}:{}", path.display(), position.line);
 
         let result = self.runtime.block_on(async {
             let (uri, client_mutex) = self.ensure_document_open(&path).await?;
-            let params = GotoDefinitionParams {
+            let params = HoverParams {
                 text_document_position_params: TextDocumentPositionParams {
                     text_document: TextDocumentIdentifier { uri },
                     position,
                 },
                 work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
-                partial_result_params: lsp_types::PartialResultParams::default(),
             };
-            client_mutex.lock().await.definition(params).await
+            self.request_during_warmup(
+                &client_mutex,
+                async |client| client.hover(params.clone()).await,
+                |_| false,
+            )
+            .await
         })?;
 
         result.map_or_else(
-            || Ok(CallToolResult::text("No definition found")),
-            |response| Ok(CallToolResult::text(format_definition_response(&response))),
+            || Ok(CallToolResult::text("No hover information available")),
+            |hover| Ok(CallToolResult::text(format_hover(&hover))),
         )
     }
 
-    fn handle_type_definition(
+    fn handle_document_highlight(
         &self,
         arguments: Option<serde_json::Value>,
     ) -> Result<CallToolResult> {
@@ -464,14 +1042,14 @@ impl LspBridgeHandler {
         let (path, position) = self.resolve_symbol_or_position(&input)?;
 
         debug!(
-            "Type definition request: {}:{}",
+            "Document highlight request: {}:{}",
             path.display(),
             position.line
         );
 
         let result = self.runtime.block_on(async {
             let (uri, client_mutex) = self.ensure_document_open(&path).await?;
-            let params = GotoDefinitionParams {
+            let params = DocumentHighlightParams {
                 text_document_position_params: TextDocumentPositionParams {
                     text_document: TextDocumentIdentifier { uri },
                     position,
@@ -479,12 +1057,114 @@ impl LspBridgeHandler {
                 work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
                 partial_result_params: lsp_types::PartialResultParams::default(),
             };
-            client_mutex.lock().await.type_definition(params).await
+            client_mutex.lock().await.document_highlight(params).await
         })?;
 
+        result.map_or_else(
+            || Ok(CallToolResult::text("No highlights found")),
+            |highlights| {
+                Ok(CallToolResult::text(format_document_highlights(
+                    &highlights,
+                )))
+            },
+        )
+    }
+
+    fn handle_definition(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
+        let input: SymbolOrPositionInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+        let (path, position) = self.resolve_symbol_or_position(&input)?;
+
+        debug!("Definition request: {}:{}", path.display(), position.line);
+
+        let result = self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
+            let params = GotoDefinitionParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri },
+                    position,
+                },
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
+            };
+            self.request_during_warmup(
+                &client_mutex,
+                async |client| client.definition(params.clone()).await,
+                is_empty_definition_response,
+            )
+            .await
+        })?;
+        let roots = self.runtime.block_on(self.client_manager.roots());
+
+        match result {
+            Some(response) if !is_empty_definition_response(&response) => Ok(CallToolResult::text(
+                format_definition_response(&response, &roots),
+            )),
+            _ => {
+                // Some servers cannot resolve a position the workspace index
+                // still knows by name
+                let fallback = input
+                    .symbol
+                    .as_deref()
+                    .and_then(|symbol| self.find_symbol_in_workspace(symbol).ok());
+                Ok(CallToolResult::text(fallback.map_or_else(
+                    || "No definition found".to_string(),
+                    |(path, position)| format_approximate_definition(&path, position, &roots),
+                )))
+            }
+        }
+    }
+
+    fn handle_type_definition(
+        &self,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<CallToolResult> {
+        let input: SymbolOrPositionInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+        let (path, position) = match self.resolve_symbol_or_position_disambiguated(&input)? {
+            SymbolResolution::Unique(path, position) => (path, position),
+            SymbolResolution::Ambiguous(name, candidates) => {
+                return Ok(CallToolResult::text(format_symbol_candidates(
+                    &name,
+                    &candidates,
+                )));
+            }
+        };
+
+        debug!(
+            "Type definition request: {}:{}",
+            path.display(),
+            position.line
+        );
+
+        let result = self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
+            let params = GotoDefinitionParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri },
+                    position,
+                },
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
+            };
+            self.request_during_warmup(
+                &client_mutex,
+                async |client| client.type_definition(params.clone()).await,
+                is_empty_definition_response,
+            )
+            .await
+        })?;
+        let roots = self.runtime.block_on(self.client_manager.roots());
+
         result.map_or_else(
             || Ok(CallToolResult::text("No type definition found")),
-            |response| Ok(CallToolResult::text(format_definition_response(&response))),
+            |response| {
+                Ok(CallToolResult::text(format_definition_response(
+                    &response, &roots,
+                )))
+            },
         )
     }
 
@@ -513,12 +1193,22 @@ impl LspBridgeHandler {
                 work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
                 partial_result_params: lsp_types::PartialResultParams::default(),
             };
-            client_mutex.lock().await.implementation(params).await
+            self.request_during_warmup(
+                &client_mutex,
+                async |client| client.implementation(params.clone()).await,
+                is_empty_definition_response,
+            )
+            .await
         })?;
+        let roots = self.runtime.block_on(self.client_manager.roots());
 
         result.map_or_else(
             || Ok(CallToolResult::text("No implementations found")),
-            |response| Ok(CallToolResult::text(format_definition_response(&response))),
+            |response| {
+                Ok(CallToolResult::text(format_definition_response(
+                    &response, &roots,
+                )))
+            },
         )
     }
 
@@ -538,6 +1228,10 @@ impl LspBridgeHandler {
             character: input.character,
         };
         let (target_path, target_position) = self.resolve_symbol_or_position(&sym_input)?;
+        let page = ReferencePage {
+            offset: input.offset,
+            max_results: input.max_results,
+        };
 
         let (references, definition) = self.runtime.block_on(async {
             let (uri, client_mutex) = self.ensure_document_open(&target_path).await?;
@@ -563,23 +1257,171 @@ impl LspBridgeHandler {
                 partial_result_params: lsp_types::PartialResultParams::default(),
             };
 
-            let client = client_mutex.lock().await;
-            let refs = client.references(ref_params).await?;
-            let def = client.definition(def_params).await?;
-            drop(client);
+            let refs = self
+                .request_during_warmup(
+                    &client_mutex,
+                    async |client| client.references(ref_params.clone()).await,
+                    Vec::is_empty,
+                )
+                .await?;
+            let def = client_mutex.lock().await.definition(def_params).await?;
             Ok::<_, anyhow::Error>((refs, def))
         })?;
 
         match references {
             Some(locations) if !locations.is_empty() => {
                 let def_loc = definition.as_ref().and_then(extract_definition_location);
+                let roots = self.runtime.block_on(self.client_manager.roots());
                 Ok(CallToolResult::text(format_locations_with_definition(
                     &locations,
                     def_loc.as_ref(),
+                    &roots,
+                    page,
+                    input.grouped,
+                ))
+                .with_json(references_json(
+                    &locations,
+                    def_loc.as_ref(),
+                    &roots,
+                    page,
                 )))
             }
-            _ => Ok(CallToolResult::text("No references found")),
+            _ => Ok(
+                CallToolResult::text("No references found").with_json(references_json(
+                    &[],
+                    None,
+                    &[],
+                    page,
+                )),
+            ),
+        }
+    }
+
+    /// Reports a symbol's definition, enclosing symbols, a source preview and
+    /// per-file reference counts in one call.
+    fn handle_investigate(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
+        let input: InvestigateInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+        let sym_input = SymbolOrPositionInput {
+            symbol: Some(input.symbol.clone()),
+            file: input.file,
+            line: None,
+            character: None,
+        };
+        let (path, position) = match self.resolve_symbol_or_position_disambiguated(&sym_input)? {
+            SymbolResolution::Unique(path, position) => (path, position),
+            SymbolResolution::Ambiguous(name, candidates) => {
+                return Ok(CallToolResult::text(format_symbol_candidates(
+                    &name,
+                    &candidates,
+                )));
+            }
+        };
+
+        debug!(
+            "Investigate request: {} at {}:{}",
+            input.symbol,
+            path.display(),
+            position.line
+        );
+
+        let (uri, definition, references) = self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
+            let text_document_position = TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position,
+            };
+            let def_params = GotoDefinitionParams {
+                text_document_position_params: text_document_position.clone(),
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
+            };
+            let ref_params = ReferenceParams {
+                text_document_position,
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
+                context: ReferenceContext {
+                    include_declaration: false,
+                },
+            };
+
+            let client = client_mutex.lock().await;
+            let definition = client.definition(def_params).await?;
+            let references = client.references(ref_params).await?;
+            drop(client);
+            Ok::<_, anyhow::Error>((uri, definition, references))
+        })?;
+
+        // Without a definition answer, the symbol's own location stands in.
+        let definition = definition
+            .as_ref()
+            .and_then(extract_definition_location)
+            .unwrap_or(Location {
+                uri,
+                range: Range::new(position, position),
+            });
+        let (context, preview) = self.runtime.block_on(self.definition_context(&definition));
+        let roots = self.runtime.block_on(self.client_manager.roots());
+
+        Ok(CallToolResult::text(format_investigation(
+            &Investigation {
+                symbol: &input.symbol,
+                definition: &definition,
+                context: &context,
+                preview: &preview,
+                references: references.as_deref().unwrap_or_default(),
+            },
+            &roots,
+        )))
+    }
+
+    /// Returns the symbols enclosing a definition and its first source lines.
+    ///
+    /// Both are empty when the definition's file cannot be opened (no server,
+    /// or outside the roots with `strict_roots`).
+    async fn definition_context(&self, definition: &Location) -> (Vec<String>, Vec<(u32, String)>) {
+        let path = PathBuf::from(definition.uri.path().as_str());
+        if self.client_manager.config().strict_roots
+            && self
+                .path_validator
+                .read()
+                .await
+                .validate_read(&path)
+                .is_err()
+        {
+            return (Vec::new(), Vec::new());
         }
+        let (uri, client_mutex) = match self.ensure_document_open(&path).await {
+            Ok(opened) => opened,
+            Err(e) => {
+                debug!("Cannot open definition {}: {e}", path.display());
+                return (Vec::new(), Vec::new());
+            }
+        };
+
+        let params = DocumentSymbolParams {
+            text_document: TextDocumentIdentifier { uri },
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            partial_result_params: lsp_types::PartialResultParams::default(),
+        };
+        let symbols = client_mutex.lock().await.document_symbols(params).await;
+        let context = match symbols {
+            Ok(Some(response)) => enclosing_symbol_names(&response, definition.range.start),
+            Ok(None) => Vec::new(),
+            Err(e) => {
+                debug!("Document symbols failed for {}: {e}", path.display());
+                Vec::new()
+            }
+        };
+
+        let start = definition.range.start.line;
+        let doc_manager = self.doc_manager.lock().await;
+        let preview = (start..start.saturating_add(INVESTIGATE_PREVIEW_LINES))
+            .map_while(|line| Some((line, doc_manager.line(&path, line as usize)?.to_string())))
+            .collect();
+        drop(doc_manager);
+        (context, preview)
     }
 
     /// Resolve a symbol name to a file path and position.
@@ -591,7 +1433,7 @@ impl LspBridgeHandler {
     ) -> Result<(std::path::PathBuf, Position)> {
         // If a file is provided, try document symbols first for efficiency
         if let Some(file) = scope_file {
-            let path = Self::resolve_path(file)?;
+            let path = self.resolve_input_path(file)?;
             if let Some(result) = self.find_symbol_in_document(symbol, &path)? {
                 return Ok(result);
             }
@@ -618,8 +1460,9 @@ impl LspBridgeHandler {
         })?;
 
         let (uri, response) = result;
+        let max_depth = self.client_manager.config().max_symbol_depth;
         if let Some(response) = response
-            && let Some(position) = find_symbol_in_document_response(&response, symbol)
+            && let Some(position) = find_symbol_in_document_response(&response, symbol, max_depth)
         {
             let file_path = std::path::PathBuf::from(uri.path().as_str());
             return Ok(Some((file_path, position)));
@@ -661,11 +1504,11 @@ impl LspBridgeHandler {
         &self,
         arguments: Option<serde_json::Value>,
     ) -> Result<CallToolResult> {
-        let input: FileInput =
+        let input: DocumentSymbolsInput =
             serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
                 .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
 
-        let path = Self::resolve_path(&input.file)?;
+        let path = self.resolve_input_path(&input.file)?;
 
         debug!("Document symbols request: {}", input.file);
 
@@ -681,12 +1524,24 @@ impl LspBridgeHandler {
                 work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
                 partial_result_params: lsp_types::PartialResultParams::default(),
             };
-            client_mutex.lock().await.document_symbols(params).await
+            let response = client_mutex.lock().await.document_symbols(params).await?;
+            if let Some(response) = &response {
+                self.index_symbols(&path, response).await;
+            }
+            Ok::<_, anyhow::Error>(response)
         })?;
 
         result.map_or_else(
             || Ok(CallToolResult::text("No symbols found")),
-            |response| Ok(CallToolResult::text(format_document_symbols(&response))),
+            |response| {
+                let max_depth = self.client_manager.config().max_symbol_depth;
+                Ok(CallToolResult::text(format_document_symbols(
+                    &response,
+                    input.detail_level,
+                    max_depth,
+                    input.qualified,
+                )))
+            },
         )
     }
 
@@ -703,228 +1558,329 @@ impl LspBridgeHandler {
         let mut sections = Vec::new();
 
         for query in &input.queries {
-            sections.push(self.search_single(query));
+            sections.push(self.search_single(query, input.mode));
         }
 
         Ok(CallToolResult::text(sections.join("\n")))
     }
 
     /// Executes a single search query: LSP workspace symbols + ripgrep file heatmap.
-    fn search_single(&self, query: &str) -> String {
-        debug!("Search request: query={query}");
+    fn search_single(&self, query: &str, mode: SearchMode) -> String {
+        debug!("Search request: query={query} mode={mode:?}");
 
-        // 1. Workspace symbols from all active LSP servers
-        let symbol_lines = self.runtime.block_on(async {
-            let params = WorkspaceSymbolParams {
-                query: query.to_string(),
-                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
-                partial_result_params: lsp_types::PartialResultParams::default(),
-            };
-
-            let clients = self.client_manager.active_clients().await;
-            let mut lines = Vec::new();
+        let roots = self.runtime.block_on(self.client_manager.roots());
 
-            for client_mutex in clients.values() {
-                if let Ok(Some(response)) = client_mutex
-                    .lock()
-                    .await
-                    .workspace_symbols(params.clone())
-                    .await
-                    && let Some(formatted) = format_workspace_symbols(&response)
-                {
-                    lines.push(formatted);
-                }
-            }
+        // Symbols of documents the agent already has open come first: they
+        // are cheap to ask for and likely what it is working on. Indexed
+        // symbols of files that are not open follow.
+        let mut open_symbols = self.open_document_symbols(query);
+        open_symbols.extend(self.indexed_symbols(query, &roots));
+
+        if mode == SearchMode::Merged {
+            // Grep runs on its own thread while the LSP servers answer
+            let (responses, rg_output) = std::thread::scope(|scope| {
+                let rg = scope.spawn(|| Self::run_ripgrep(query, &roots));
+                let responses = self.workspace_symbol_responses(query);
+                (responses, rg.join().unwrap_or_default())
+            });
+            let responses = prepend_open_symbols(open_symbols, responses);
+            let symbol_lines: Vec<String> = responses
+                .iter()
+                .filter_map(format_workspace_symbols)
+                .collect();
+            let covered = symbol_definition_lines(&responses);
+            let heatmap = format_heatmap(&rg_output, &roots, &covered);
+            return combine_search_sections(&symbol_lines, &heatmap, "Other text matches");
+        }
 
-            lines
-        });
+        // 1. Open-document symbols, then workspace symbols from all active
+        //    LSP servers
+        let symbol_lines: Vec<String> =
+            prepend_open_symbols(open_symbols, self.workspace_symbol_responses(query))
+                .iter()
+                .filter_map(format_workspace_symbols)
+                .collect();
 
         // 2. Ripgrep file heatmap (always, covers all non-ignored files)
-        let roots = self.runtime.block_on(self.client_manager.roots());
-        let heatmap = Self::ripgrep_heatmap(query, &roots);
+        let heatmap = format_heatmap(
+            &Self::run_ripgrep(query, &roots),
+            &roots,
+            &std::collections::HashSet::new(),
+        );
 
         // 3. Combine
-        let has_symbols = !symbol_lines.is_empty();
-        let has_heatmap = !heatmap.is_empty();
-
-        if !has_symbols && !has_heatmap {
-            return "No results found".to_string();
-        }
-
-        let mut output = String::new();
+        combine_search_sections(&symbol_lines, &heatmap, "File matches")
+    }
 
-        if has_symbols {
-            output.push_str("## Symbols\n");
-            output.push_str(&symbol_lines.join("\n"));
-        }
+    /// Collects the symbols of open documents whose name contains `query`
+    /// (case-insensitively).
+    ///
+    /// Symbols come from the in-memory symbol cache. Only the
+    /// [`OPEN_SYMBOL_FETCHES`] most recently used open documents missing
+    /// from it are asked for, each bounded by [`OPEN_SYMBOL_TIMEOUT`], and
+    /// their answers are cached for the next search.
+    fn open_document_symbols(&self, query: &str) -> Vec<SymbolInformation> {
+        let max_depth = self.client_manager.config().max_symbol_depth;
+        self.runtime.block_on(async {
+            let paths = self.doc_manager.lock().await.recent_paths();
+            let mut matches = Vec::new();
+            let mut fetches = 0;
+            for path in paths {
+                let Ok(uri) = self.doc_manager.lock().await.uri_for_path(&path) else {
+                    continue;
+                };
+                let Ok(mtime) = std::fs::metadata(&path).and_then(|m| m.modified()) else {
+                    continue;
+                };
+                let cached = self.symbol_cache.lock().ok().and_then(|mut cache| {
+                    cache
+                        .get(&path, mtime)
+                        .map(|response| matching_document_symbols(response, query, &uri, max_depth))
+                });
+                if let Some(cached) = cached {
+                    matches.extend(cached);
+                    continue;
+                }
 
-        if has_heatmap {
-            if has_symbols {
-                output.push_str("\n\n");
+                if fetches == OPEN_SYMBOL_FETCHES {
+                    continue;
+                }
+                fetches += 1;
+                let Ok(client_mutex) = self.get_client_for_path(&path).await else {
+                    continue;
+                };
+                let params = DocumentSymbolParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                    partial_result_params: lsp_types::PartialResultParams::default(),
+                };
+                let client = client_mutex.lock().await;
+                let result =
+                    tokio::time::timeout(OPEN_SYMBOL_TIMEOUT, client.document_symbols(params))
+                        .await;
+                drop(client);
+                match result {
+                    Ok(Ok(Some(response))) => {
+                        matches
+                            .extend(matching_document_symbols(&response, query, &uri, max_depth));
+                        self.index_symbols(&path, &response).await;
+                        if let Ok(mut cache) = self.symbol_cache.lock() {
+                            cache.insert(path, mtime, response);
+                        }
+                    }
+                    Ok(Ok(None)) => {}
+                    Ok(Err(e)) => debug!("documentSymbol failed for {}: {e}", path.display()),
+                    Err(_) => debug!("documentSymbol timed out for {}", path.display()),
+                }
             }
-            output.push_str("## File matches\n");
-            output.push_str(&heatmap);
-        }
+            matches
+        })
+    }
 
-        output
+    /// Searches the symbol index for files that are not open.
+    fn indexed_symbols(&self, query: &str, roots: &[PathBuf]) -> Vec<SymbolInformation> {
+        let Some(index) = &self.symbol_index else {
+            return Vec::new();
+        };
+        let open_paths = self
+            .runtime
+            .block_on(async { self.doc_manager.lock().await.open_paths() });
+        index.lock().map_or_else(
+            |_| Vec::new(),
+            |mut index| index.search(roots, query, &open_paths),
+        )
     }
 
-    /// Runs ripgrep and returns a file-level heatmap: file path, match count, line range.
+    /// Records a document symbol response in the symbol index, if enabled.
     ///
-    /// Searches all non-ignored files (no `--type` filter) so config files,
-    /// docs, and other non-code files are included.
-    fn ripgrep_heatmap(query: &str, roots: &[PathBuf]) -> String {
-        use std::collections::BTreeMap;
-        use std::fmt::Write;
-        use std::process::Command;
+    /// Files outside the workspace roots are not indexed.
+    async fn index_symbols(&self, path: &Path, response: &DocumentSymbolResponse) {
+        let Some(index) = &self.symbol_index else {
+            return;
+        };
+        let roots = self.client_manager.roots().await;
+        let Some(root) = roots
+            .iter()
+            .filter(|root| path.starts_with(root))
+            .max_by_key(|root| root.as_os_str().len())
+        else {
+            return;
+        };
+        let max_depth = self.client_manager.config().max_symbol_depth;
+        if let Ok(mut index) = index.lock() {
+            index.record(root, path, response, max_depth);
+        }
+    }
 
-        let mut cmd = Command::new("rg");
-        cmd.args(["--line-number", "--no-heading", "--ignore-case", query]);
+    /// Fetches and formats the symbols of one `codebase_map` file, reusing
+    /// the cache when the file is unchanged.
+    async fn fetch_map_symbols(&self, path: &Path, detail_level: DetailLevel) -> MapSymbols {
+        // Simple extension check to avoid wasted LSP calls
+        let lang_id = {
+            let doc_manager = self.doc_manager.lock().await;
+            doc_manager.language_id_for_path(path).to_string()
+        };
+        if lang_id == "plaintext" {
+            return MapSymbols::Skipped;
+        }
 
-        for root in roots {
-            cmd.arg(root);
+        // Reuse symbols from an earlier map if the file is unchanged
+        let mtime = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        if let Some(mtime) = mtime
+            && let Some(symbols) = self.cached_symbols(path, mtime, detail_level)
+        {
+            return MapSymbols::Found(symbols);
         }
 
-        let Ok(rg_output) = cmd.output() else {
-            return String::new();
+        let Ok(client_mutex) = self.get_client_for_path(path).await else {
+            return MapSymbols::Unavailable(lang_id);
+        };
+        // Attempt to open and get symbols with a short timeout
+        let Ok((uri, _)) = self.open_document(path, false).await else {
+            return MapSymbols::Skipped;
         };
+        let params = DocumentSymbolParams {
+            text_document: TextDocumentIdentifier { uri },
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            partial_result_params: lsp_types::PartialResultParams::default(),
+        };
+
+        let client = client_mutex.lock().await;
+        // 1s timeout per file to keep map generation snappy but reliable
+        let timeout_result = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            client.document_symbols(params),
+        )
+        .await;
+        drop(client);
 
-        if !rg_output.status.success() && rg_output.stdout.is_empty() {
-            return String::new();
+        let Ok(Ok(Some(response))) = timeout_result else {
+            return MapSymbols::Skipped;
+        };
+        let symbols = format_compact_symbols(&response, detail_level);
+        self.index_symbols(path, &response).await;
+        if let Some(mtime) = mtime
+            && let Ok(mut cache) = self.symbol_cache.lock()
+        {
+            cache.insert(path.to_path_buf(), mtime, response);
         }
+        MapSymbols::Found(symbols)
+    }
 
-        // Parse output lines: "file:line:content" — group by file
-        // Use BTreeMap for deterministic (sorted) file order
-        let mut file_stats: BTreeMap<String, (usize, u32, u32)> = BTreeMap::new();
-        let stdout = String::from_utf8_lossy(&rg_output.stdout);
+    /// Formats the cached symbols of `path`, if they were recorded at `mtime`.
+    fn cached_symbols(
+        &self,
+        path: &Path,
+        mtime: std::time::SystemTime,
+        detail_level: DetailLevel,
+    ) -> Option<String> {
+        let mut cache = self.symbol_cache.lock().ok()?;
+        cache
+            .get(path, mtime)
+            .map(|response| format_compact_symbols(response, detail_level))
+    }
 
-        for line in stdout.lines() {
-            // Format: file:line_number:content
-            // Find second colon (first colon may be in Windows path, but we're on Linux)
-            let Some((file, rest)) = line.split_once(':') else {
-                continue;
-            };
-            let Some((line_str, _content)) = rest.split_once(':') else {
-                continue;
-            };
-            let Ok(line_num) = line_str.parse::<u32>() else {
-                continue;
+    /// Queries every active LSP server for workspace symbols matching `query`.
+    ///
+    /// With the symbol index enabled, servers that are still starting or
+    /// indexing are skipped rather than waited for.
+    fn workspace_symbol_responses(&self, query: &str) -> Vec<WorkspaceSymbolResponse> {
+        self.runtime.block_on(async {
+            let params = WorkspaceSymbolParams {
+                query: query.to_string(),
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
             };
 
-            let entry = file_stats
-                .entry(file.to_string())
-                .or_insert((0, u32::MAX, 0));
-            entry.0 += 1; // count
-            entry.1 = entry.1.min(line_num); // min line
-            entry.2 = entry.2.max(line_num); // max line
-        }
+            let clients = self.client_manager.active_clients().await;
+            let mut responses = Vec::new();
 
-        if file_stats.is_empty() {
-            return String::new();
-        }
+            for client_mutex in clients.values() {
+                let client = client_mutex.lock().await;
+                if self.symbol_index.is_some() && !client.is_ready() {
+                    continue;
+                }
+                if let Ok(Some(response)) = client.workspace_symbols_cached(params.clone()).await {
+                    responses.push(response);
+                }
+            }
+
+            responses
+        })
+    }
 
-        // Sort by match count descending, then by file path
-        let mut sorted: Vec<_> = file_stats.into_iter().collect();
-        sorted.sort_by(|a, b| b.1.0.cmp(&a.1.0).then_with(|| a.0.cmp(&b.0)));
+    /// Runs ripgrep over the roots and returns its raw `file:line:content` output.
+    ///
+    /// Searches all non-ignored files (no `--type` filter) so config files,
+    /// docs, and other non-code files are included. Each root runs
+    /// separately so its `.catenaryignore` matches relative to that root.
+    fn run_ripgrep(query: &str, roots: &[PathBuf]) -> String {
+        use std::process::Command;
 
-        // Show all files — the model has no way to retrieve omitted results
         let mut output = String::new();
-        for (file, (count, min_line, max_line)) in sorted {
-            let display_path = roots
-                .iter()
-                .find_map(|root| {
-                    let root_str = root.to_string_lossy();
-                    file.strip_prefix(root_str.as_ref())
-                        .map(|rest| rest.strip_prefix('/').unwrap_or(rest).to_string())
-                })
-                .unwrap_or_else(|| file.clone());
+        for root in roots {
+            let mut cmd = Command::new("rg");
+            cmd.args(["--line-number", "--no-heading", "--ignore-case"]);
+            if root.join(IGNORE_FILENAME).is_file() {
+                cmd.current_dir(root)
+                    .args(["--ignore-file", IGNORE_FILENAME]);
+            }
+            cmd.arg("--").arg(query).arg(root);
 
-            let line_range = if min_line == max_line {
-                format!("line {min_line}")
-            } else {
-                format!("lines {min_line}-{max_line}")
+            let Ok(rg_output) = cmd.output() else {
+                return String::new();
             };
 
-            let match_word = if count == 1 { "match" } else { "matches" };
-            let _ = writeln!(
-                output,
-                "{display_path}: {count} {match_word} ({line_range})"
-            );
-        }
+            if !rg_output.status.success() && rg_output.stdout.is_empty() {
+                continue;
+            }
 
-        // Remove trailing newline
-        output.truncate(output.trim_end().len());
+            output.push_str(&String::from_utf8_lossy(&rg_output.stdout));
+        }
         output
     }
 
-    fn handle_code_actions(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
-        let input: CodeActionInput =
+    fn handle_completion(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
+        let input: CompletionInput =
             serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
                 .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
 
-        let path = Self::resolve_path(&input.file)?;
+        let path = self.resolve_input_path(&input.file)?;
 
         debug!(
-            "Code actions request: {} [{},{}]-[{},{}]",
-            input.file,
-            input.start_line,
-            input.start_character,
-            input.end_line,
-            input.end_character
+            "Completion request: {}:{}:{}",
+            input.file, input.line, input.character
         );
 
-        let result = self.runtime.block_on(async {
+        let (result, resolved, cursor) = self.runtime.block_on(async {
             let (uri, client_mutex) = self.ensure_document_open(&path).await?;
 
-            // Get diagnostics for the range to include in context
-            let diagnostics = client_mutex.lock().await.get_diagnostics(&uri).await;
-
-            let params = CodeActionParams {
-                text_document: TextDocumentIdentifier { uri },
-                range: Range {
-                    start: Position {
-                        line: input.start_line,
-                        character: input.start_character,
-                    },
-                    end: Position {
-                        line: input.end_line,
-                        character: input.end_character,
-                    },
+            // The buffer Catenary synced is what the server sees, so read the
+            // cursor context from it rather than from disk.
+            let line_text = self
+                .doc_manager
+                .lock()
+                .await
+                .line(&path, input.line as usize)
+                .unwrap_or_default()
+                .to_string();
+            let client = client_mutex.lock().await;
+            let cursor = CompletionCursor::new(&line_text, input.character, &client.encoding());
+
+            let trigger_character = cursor
+                .preceding
+                .map(String::from)
+                .filter(|c| client.completion_trigger_characters().contains(c));
+            let context = CompletionContext {
+                trigger_kind: if trigger_character.is_some() {
+                    CompletionTriggerKind::TRIGGER_CHARACTER
+                } else {
+                    CompletionTriggerKind::INVOKED
                 },
-                context: CodeActionContext {
-                    diagnostics,
-                    only: None,
-                    trigger_kind: None,
-                },
-                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
-                partial_result_params: lsp_types::PartialResultParams::default(),
+                trigger_character,
             };
-            client_mutex.lock().await.code_actions(params).await
-        })?;
-
-        match result {
-            Some(actions) if !actions.is_empty() => {
-                Ok(CallToolResult::text(format_code_actions(&actions)))
-            }
-            _ => Ok(CallToolResult::text("No code actions available")),
-        }
-    }
-
-    fn handle_rename(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
-        let input: RenameInput =
-            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
-                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
 
-        let path = Self::resolve_path(&input.file)?;
-
-        debug!(
-            "Rename request: {}:{}:{} -> {}",
-            input.file, input.line, input.character, input.new_name
-        );
-
-        let result = self.runtime.block_on(async {
-            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
-            let params = RenameParams {
+            let params = CompletionParams {
                 text_document_position: TextDocumentPositionParams {
                     text_document: TextDocumentIdentifier { uri },
                     position: Position {
@@ -932,251 +1888,1149 @@ impl LspBridgeHandler {
                         character: input.character,
                     },
                 },
-                new_name: input.new_name,
                 work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
+                context: Some(context),
             };
-            client_mutex.lock().await.rename(params).await
+            let result = client.completion(params).await?;
+
+            let top: Vec<CompletionItem> = result.as_ref().map_or_else(Vec::new, |response| {
+                filter_completion_items(response, input.kinds.as_deref())
+                    .into_iter()
+                    .take(input.resolve_top.min(COMPLETION_MAX_RESOLVE))
+                    .cloned()
+                    .collect()
+            });
+            let mut resolved = Vec::with_capacity(top.len());
+            for item in top {
+                if client.supports_completion_resolve() {
+                    match client.resolve_completion_item(item.clone()).await {
+                        Ok(full) => resolved.push(full),
+                        Err(e) => {
+                            debug!("completionItem/resolve failed for {}: {e}", item.label);
+                            resolved.push(item);
+                        }
+                    }
+                } else {
+                    resolved.push(item);
+                }
+            }
+            drop(client);
+            Ok::<_, anyhow::Error>((result, resolved, cursor))
         })?;
 
-        Ok(result.map_or_else(
-            || CallToolResult::text("Rename not supported at this location"),
-            |edit| CallToolResult::text(format_workspace_edit(&edit)),
-        ))
+        let mut body = result.map_or_else(
+            || "No completions available".to_string(),
+            |response| format_completion(&response, input.kinds.as_deref(), input.group),
+        );
+        if !resolved.is_empty() {
+            body.push_str("\n\n");
+            body.push_str(&format_completion_details(&resolved));
+        }
+        Ok(CallToolResult::text(format!(
+            "{}\n\n{body}",
+            cursor.header()
+        )))
     }
 
-    fn handle_diagnostics(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
-        let input: FileInput =
+    fn handle_code_actions(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
+        let input: CodeActionInput =
             serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
                 .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
 
-        let path = Self::resolve_path(&input.file)?;
+        let path = self.resolve_input_path(&input.file)?;
 
-        debug!("Diagnostics request: {}", input.file);
+        let target = input.target()?;
+        debug!("Code actions request: {} {target:?}", input.file);
 
-        let diagnostics = self.runtime.block_on(async {
-            let client_mutex = self.get_client_for_path(&path).await?;
-            let mut doc_manager = self.doc_manager.lock().await;
+        let (range, derived_from, result) = self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
             let client = client_mutex.lock().await;
 
-            if !client.is_alive() {
-                return Err(anyhow!(
-                    "[{}] server is no longer running",
-                    client.language()
-                ));
-            }
-
-            let uri = doc_manager.uri_for_path(&path)?;
-
-            if let Some(notification) = doc_manager.ensure_open(&path).await? {
-                // Snapshot generation *before* sending the change
-                let snapshot = client.diagnostics_generation(&uri).await;
-
-                match notification {
-                    super::DocumentNotification::Open(params) => {
-                        client.did_open(params).await?;
-                    }
-                    super::DocumentNotification::Change(params) => {
-                        client.did_change(params).await?;
-                    }
+            let (range, derived_from) = match target {
+                CodeActionTarget::Range(range) => (range, None),
+                CodeActionTarget::Cursor(position) => {
+                    let (range, source) = smart_range(&client, &uri, position).await;
+                    (range, Some(source))
                 }
+            };
 
-                // Trigger flycheck on servers that only run diagnostics on save
-                client.did_save(uri.clone()).await?;
+            // Get diagnostics for the range to include in context
+            let diagnostics = client.get_diagnostics(&uri).await;
 
-                drop(doc_manager);
+            let params = CodeActionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                range,
+                context: CodeActionContext {
+                    diagnostics,
+                    only: None,
+                    trigger_kind: None,
+                },
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
+            };
+            let result = client.code_actions(params).await;
+            drop(client);
+            let mut result = result?;
 
-                match client
-                    .wait_for_diagnostics_update(&uri, snapshot, DIAGNOSTICS_TIMEOUT)
-                    .await
-                {
-                    DiagnosticsWaitResult::Updated => {}
-                    DiagnosticsWaitResult::ServerDied => {
-                        return Err(anyhow!(
-                            "[{}] server died during analysis",
-                            client.language()
-                        ));
-                    }
-                }
-            } else {
-                drop(doc_manager);
+            let companion = self.companion_code_actions(&path, &uri, range).await;
+            if !companion.is_empty() {
+                result.get_or_insert_with(Vec::new).extend(companion);
             }
-
-            Ok::<_, anyhow::Error>(client.get_diagnostics(&uri).await)
+            Ok::<_, anyhow::Error>((range, derived_from, result))
         })?;
 
-        if diagnostics.is_empty() {
-            Ok(CallToolResult::text("No diagnostics"))
-        } else {
-            Ok(CallToolResult::text(format_diagnostics(&diagnostics)))
-        }
+        let body = match result {
+            Some(actions) if !actions.is_empty() => format_code_actions(&actions),
+            _ => "No code actions available".to_string(),
+        };
+        let text = match derived_from {
+            Some(source) => format!(
+                "Range: [{},{}]-[{},{}] ({source})\n\n{body}",
+                range.start.line, range.start.character, range.end.line, range.end.character
+            ),
+            None => body,
+        };
+        Ok(CallToolResult::text(text))
     }
 
-    fn handle_call_hierarchy(
+    fn handle_diagnostic_fixes(
         &self,
         arguments: Option<serde_json::Value>,
     ) -> Result<CallToolResult> {
-        let input: CallHierarchyInput =
+        let input: DiagnosticFixesInput =
             serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
                 .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
 
-        let sym_input = SymbolOrPositionInput {
-            symbol: input.symbol,
-            file: input.file,
+        let path = self.resolve_input_path(&input.file)?;
+        let position = Position {
             line: input.line,
             character: input.character,
         };
-        let (path, position) = self.resolve_symbol_or_position(&sym_input)?;
 
         debug!(
-            "Call hierarchy request: {}:{} direction={}",
-            path.display(),
-            position.line,
-            input.direction
+            "Diagnostic fixes request: {} [{},{}]",
+            input.file, input.line, input.character
         );
 
-        let result = self.runtime.block_on(async {
+        let (diagnostics, actions) = self.runtime.block_on(async {
             let (uri, client_mutex) = self.ensure_document_open(&path).await?;
 
-            // First, prepare the call hierarchy
-            let prepare_params = CallHierarchyPrepareParams {
-                text_document_position_params: TextDocumentPositionParams {
-                    text_document: TextDocumentIdentifier { uri },
-                    position,
+            let diagnostics: Vec<Diagnostic> = client_mutex
+                .lock()
+                .await
+                .get_diagnostics(&uri)
+                .await
+                .into_iter()
+                .filter(|d| d.range.start <= position && position <= d.range.end)
+                .collect();
+            let actions = diagnostic_fix_actions(&client_mutex, uri, &diagnostics).await?;
+
+            Ok::<_, anyhow::Error>((diagnostics, actions))
+        })?;
+
+        if diagnostics.is_empty() {
+            return Ok(CallToolResult::text(format!(
+                "No diagnostic at {}:{}",
+                input.line + 1,
+                input.character + 1
+            )));
+        }
+
+        Ok(CallToolResult::text(format_diagnostic_fixes(
+            &diagnostics,
+            &actions,
+        )))
+    }
+
+    fn handle_organize_imports(
+        &self,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<CallToolResult> {
+        let input: OrganizeImportsInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+        let path = self.resolve_input_path(&input.file)?;
+
+        debug!("Organize imports request: {}", input.file);
+
+        let action = self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
+
+            let doc_manager = self.doc_manager.lock().await;
+            let line_count = doc_manager.content(&path).map_or(0, |c| c.lines().count());
+            drop(doc_manager);
+
+            let params = CodeActionParams {
+                text_document: TextDocumentIdentifier { uri },
+                range: Range {
+                    start: Position::default(),
+                    end: Position {
+                        line: u32::try_from(line_count).unwrap_or(u32::MAX),
+                        character: 0,
+                    },
+                },
+                context: CodeActionContext {
+                    diagnostics: Vec::new(),
+                    only: Some(vec![CodeActionKind::SOURCE_ORGANIZE_IMPORTS]),
+                    trigger_kind: None,
                 },
                 work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
             };
 
             let client = client_mutex.lock().await;
-            let items = client.prepare_call_hierarchy(prepare_params).await?;
-
-            let Some(items) = items else {
+            let actions = client.code_actions(params).await?.unwrap_or_default();
+            // Servers may ignore `only`, so check the kind ourselves.
+            let Some(mut action) = actions.into_iter().find_map(|action| match action {
+                CodeActionOrCommand::CodeAction(ca)
+                    if ca.kind.as_ref().is_some_and(|k| {
+                        k.as_str()
+                            .starts_with(CodeActionKind::SOURCE_ORGANIZE_IMPORTS.as_str())
+                    }) =>
+                {
+                    Some(ca)
+                }
+                _ => None,
+            }) else {
                 return Ok::<_, anyhow::Error>(None);
             };
 
-            if items.is_empty() {
-                return Ok(None);
-            }
-
-            // Get calls for the first item (safe: we checked is_empty above)
-            let Some(item) = items.into_iter().next() else {
-                return Ok(None);
-            };
-
-            match input.direction.as_str() {
-                "incoming" => {
-                    let params = CallHierarchyIncomingCallsParams {
-                        item,
-                        work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
-                        partial_result_params: lsp_types::PartialResultParams::default(),
-                    };
-                    let calls = client.incoming_calls(params).await?;
-                    drop(client);
-                    Ok(calls.map(|c| format_incoming_calls(&c)))
-                }
-                "outgoing" => {
-                    let params = CallHierarchyOutgoingCallsParams {
-                        item,
-                        work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
-                        partial_result_params: lsp_types::PartialResultParams::default(),
-                    };
-                    let calls = client.outgoing_calls(params).await?;
-                    drop(client);
-                    Ok(calls.map(|c| format_outgoing_calls(&c)))
+            if action.edit.is_none() {
+                match client.resolve_code_action(action.clone()).await {
+                    Ok(resolved) => action = resolved,
+                    Err(e) => debug!("Failed to resolve '{}': {e}", action.title),
                 }
-                _ => Err(anyhow!("direction must be 'incoming' or 'outgoing'")),
             }
+            drop(client);
+            Ok(Some(action))
         })?;
 
-        match result {
-            Some(text) if !text.is_empty() => Ok(CallToolResult::text(text)),
-            _ => Ok(CallToolResult::text("No call hierarchy found")),
+        match action {
+            Some(CodeAction {
+                title,
+                edit: Some(edit),
+                ..
+            }) => {
+                let mut output = format!("{title}\n\n{}", format_workspace_edit(&edit));
+                self.append_edit_id(&mut output, edit);
+                Ok(CallToolResult::text(output))
+            }
+            _ => Ok(CallToolResult::text("No import organization available")),
         }
     }
 
-    fn handle_type_hierarchy(
+    fn handle_explain_diagnostic(
         &self,
         arguments: Option<serde_json::Value>,
     ) -> Result<CallToolResult> {
-        let input: TypeHierarchyInput =
+        let input: DiagnosticFixesInput =
             serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
                 .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
 
-        let sym_input = SymbolOrPositionInput {
-            symbol: input.symbol,
-            file: input.file,
+        let path = self.resolve_input_path(&input.file)?;
+        let position = Position {
             line: input.line,
             character: input.character,
         };
-        let (path, position) = self.resolve_symbol_or_position(&sym_input)?;
 
         debug!(
-            "Type hierarchy request: {}:{} direction={}",
-            path.display(),
-            position.line,
-            input.direction
+            "Explain diagnostic request: {} [{},{}]",
+            input.file, input.line, input.character
         );
 
-        let result = self.runtime.block_on(async {
+        let explanation = self.runtime.block_on(async {
             let (uri, client_mutex) = self.ensure_document_open(&path).await?;
 
-            // First, prepare the type hierarchy
-            let prepare_params = TypeHierarchyPrepareParams {
-                text_document_position_params: TextDocumentPositionParams {
-                    text_document: TextDocumentIdentifier { uri },
-                    position,
-                },
-                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            let diagnostics: Vec<Diagnostic> = client_mutex
+                .lock()
+                .await
+                .get_diagnostics(&uri)
+                .await
+                .into_iter()
+                .filter(|d| d.range.start <= position && position <= d.range.end)
+                .collect();
+            if diagnostics.is_empty() {
+                return Ok::<_, anyhow::Error>(None);
+            }
+
+            let position_params = TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position,
             };
 
-            let client = client_mutex.lock().await;
-            let items = client.prepare_type_hierarchy(prepare_params).await?;
+            // Context is best-effort: a server that cannot answer one request
+            // should not hide what the others found.
+            let hover = client_mutex
+                .lock()
+                .await
+                .hover(HoverParams {
+                    text_document_position_params: position_params.clone(),
+                    work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                })
+                .await
+                .unwrap_or_else(|e| {
+                    debug!("Hover failed while explaining diagnostic: {e}");
+                    None
+                });
 
-            let Some(items) = items else {
-                return Ok::<_, anyhow::Error>(None);
+            let definition_params = GotoDefinitionParams {
+                text_document_position_params: position_params,
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
+            };
+            let type_definition = client_mutex
+                .lock()
+                .await
+                .type_definition(definition_params.clone())
+                .await
+                .unwrap_or_else(|e| {
+                    debug!("Type definition failed while explaining diagnostic: {e}");
+                    None
+                });
+            let definition = match type_definition {
+                Some(response) => Some(("Type definition", response)),
+                None => client_mutex
+                    .lock()
+                    .await
+                    .definition(definition_params)
+                    .await
+                    .unwrap_or_else(|e| {
+                        debug!("Definition failed while explaining diagnostic: {e}");
+                        None
+                    })
+                    .map(|response| ("Definition", response)),
             };
 
-            if items.is_empty() {
-                return Ok(None);
-            }
+            let actions = diagnostic_fix_actions(&client_mutex, uri, &diagnostics).await?;
 
-            // Get hierarchy for the first item (safe: we checked is_empty above)
-            let Some(item) = items.into_iter().next() else {
-                return Ok(None);
-            };
+            Ok(Some((diagnostics, hover, definition, actions)))
+        })?;
 
-            match input.direction.as_str() {
-                "supertypes" => {
-                    let params = TypeHierarchySupertypesParams {
-                        item,
-                        work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
-                        partial_result_params: lsp_types::PartialResultParams::default(),
-                    };
-                    let types = client.supertypes(params).await?;
-                    drop(client);
-                    Ok(types.map(|t| format_type_hierarchy_items(&t)))
-                }
-                "subtypes" => {
-                    let params = TypeHierarchySubtypesParams {
-                        item,
-                        work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
-                        partial_result_params: lsp_types::PartialResultParams::default(),
-                    };
-                    let types = client.subtypes(params).await?;
-                    drop(client);
-                    Ok(types.map(|t| format_type_hierarchy_items(&t)))
-                }
-                _ => Err(anyhow!("direction must be 'supertypes' or 'subtypes'")),
+        let Some((diagnostics, hover, definition, actions)) = explanation else {
+            return Ok(CallToolResult::text(format!(
+                "No diagnostic at {}:{}",
+                input.line + 1,
+                input.character + 1
+            )));
+        };
+
+        let roots = self.runtime.block_on(self.client_manager.roots());
+        let definition = definition
+            .map(|(label, response)| (label, format_definition_response(&response, &roots)));
+
+        Ok(CallToolResult::text(format_diagnostic_explanation(
+            &diagnostics,
+            hover.as_ref(),
+            definition
+                .as_ref()
+                .map(|(label, text)| (*label, text.as_str())),
+            &actions,
+        )))
+    }
+
+    fn handle_folding_ranges(
+        &self,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<CallToolResult> {
+        let input: FoldingRangesInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+        let path = self.resolve_input_path(&input.file)?;
+
+        debug!("Folding ranges request: {}", input.file);
+
+        let ranges = self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
+            let client = client_mutex.lock().await;
+            if !client.supports_folding_ranges() {
+                return Ok::<_, anyhow::Error>(None);
             }
+            let params = FoldingRangeParams {
+                text_document: TextDocumentIdentifier { uri },
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
+            };
+            let ranges = client.folding_ranges(params).await?;
+            drop(client);
+            Ok(Some(ranges.unwrap_or_default()))
         })?;
 
-        match result {
-            Some(text) if !text.is_empty() => Ok(CallToolResult::text(text)),
-            _ => Ok(CallToolResult::text("No type hierarchy found")),
+        let Some(ranges) = ranges else {
+            return Ok(CallToolResult::text("Folding ranges not supported"));
+        };
+        if ranges.is_empty() {
+            return Ok(CallToolResult::text("No folding ranges"));
         }
+        Ok(CallToolResult::text(format_folding_ranges(&ranges)))
     }
-    #[allow(
-        clippy::too_many_lines,
-        reason = "Complexity of codebase map generation requires many lines"
+
+    fn handle_code_lens(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
+        let input: CodeLensInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+
+        let path = self.resolve_input_path(&input.file)?;
+
+        debug!("Code lens request: {}", input.file);
+
+        let lenses = self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
+            let client = client_mutex.lock().await;
+            if !client.supports_code_lens() {
+                return Ok::<_, anyhow::Error>(None);
+            }
+            let params = CodeLensParams {
+                text_document: TextDocumentIdentifier { uri },
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
+            };
+            let lenses = client.code_lens(params).await?.unwrap_or_default();
+
+            // Servers often defer the title (e.g. reference counts) to resolve.
+            let mut resolved = Vec::with_capacity(lenses.len());
+            for lens in lenses {
+                if lens.command.is_some() || !client.supports_code_lens_resolve() {
+                    resolved.push(lens);
+                    continue;
+                }
+                match client.resolve_code_lens(lens).await {
+                    Ok(lens) => resolved.push(lens),
+                    Err(e) => debug!("codeLens/resolve failed: {e}"),
+                }
+            }
+            drop(client);
+            Ok(Some(resolved))
+        })?;
+
+        let Some(lenses) = lenses else {
+            return Ok(CallToolResult::text("Code lens not supported"));
+        };
+        let text = format_code_lenses(&lenses);
+        if text.is_empty() {
+            return Ok(CallToolResult::text("No code lenses"));
+        }
+        Ok(CallToolResult::text(text))
+    }
+
+    fn handle_inlay_hints(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
+        let input: InlayHintsInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+        if input.end_line < input.start_line {
+            return Err(anyhow!(
+                "end_line ({}) is before start_line ({})",
+                input.end_line,
+                input.start_line
+            ));
+        }
+
+        let path = self.resolve_input_path(&input.file)?;
+
+        debug!(
+            "Inlay hints request: {} [{}-{}]",
+            input.file, input.start_line, input.end_line
+        );
+
+        let hints = self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
+            let client = client_mutex.lock().await;
+            if !client.supports_inlay_hints() {
+                return Ok::<_, anyhow::Error>(None);
+            }
+            let params = InlayHintParams {
+                text_document: TextDocumentIdentifier { uri },
+                range: Range {
+                    start: Position {
+                        line: input.start_line,
+                        character: 0,
+                    },
+                    end: Position {
+                        line: input.end_line.saturating_add(1),
+                        character: 0,
+                    },
+                },
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            };
+            let hints = client.inlay_hint(params).await?;
+            drop(client);
+            Ok(Some(hints.unwrap_or_default()))
+        })?;
+
+        let Some(mut hints) = hints else {
+            return Ok(CallToolResult::text("Inlay hints not supported"));
+        };
+        hints.retain(|h| h.position.line <= input.end_line);
+        if hints.is_empty() {
+            return Ok(CallToolResult::text("No inlay hints"));
+        }
+        Ok(CallToolResult::text(format_inlay_hints(&hints)))
+    }
+
+    fn handle_selection_range(
+        &self,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<CallToolResult> {
+        let input: PositionInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+
+        let path = self.resolve_input_path(&input.file)?;
+        let position = Position {
+            line: input.line,
+            character: input.character,
+        };
+
+        debug!(
+            "Selection range request: {}:{}:{}",
+            input.file, input.line, input.character
+        );
+
+        let ranges = self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
+            let client = client_mutex.lock().await;
+            if !client.supports_selection_range() {
+                return Ok::<_, anyhow::Error>(None);
+            }
+            let params = lsp_types::SelectionRangeParams {
+                text_document: TextDocumentIdentifier { uri },
+                positions: vec![position],
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
+            };
+            let ranges = client.selection_range(params).await?;
+            drop(client);
+            Ok(Some(ranges.unwrap_or_default()))
+        })?;
+
+        let Some(ranges) = ranges else {
+            return Ok(CallToolResult::text("Selection ranges not supported"));
+        };
+        let Some(selection) = ranges.first() else {
+            return Ok(CallToolResult::text("No selection ranges"));
+        };
+        Ok(CallToolResult::text(format_selection_range(selection)))
+    }
+
+    fn handle_prepare_rename(
+        &self,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<CallToolResult> {
+        let input: PositionInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+
+        let path = self.resolve_input_path(&input.file)?;
+        let position = Position {
+            line: input.line,
+            character: input.character,
+        };
+
+        debug!(
+            "Prepare rename request: {}:{}:{}",
+            input.file, input.line, input.character
+        );
+
+        let prepared = self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
+            let client = client_mutex.lock().await;
+            if !client.supports_prepare_rename() {
+                return Ok::<_, anyhow::Error>(None);
+            }
+            let params = TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri },
+                position,
+            };
+            let response = client.prepare_rename(params).await?;
+            let encoding = client.encoding();
+            drop(client);
+
+            // Quote the target text when the server only sends a range.
+            let text = match &response {
+                Some(PrepareRenameResponse::Range(range)) => {
+                    let doc_manager = self.doc_manager.lock().await;
+                    doc_manager
+                        .line(&path, range.start.line as usize)
+                        .and_then(|line| range_text(line, range, &encoding))
+                        .map(str::to_string)
+                }
+                _ => None,
+            };
+            Ok(Some((response, text)))
+        })?;
+
+        let Some((response, text)) = prepared else {
+            return Ok(CallToolResult::text("Prepare rename not supported"));
+        };
+        let Some(response) = response else {
+            return Ok(CallToolResult::text("Cannot rename at this location"));
+        };
+        Ok(CallToolResult::text(format_prepare_rename(
+            &response,
+            position,
+            text.as_deref(),
+        )))
+    }
+
+    fn handle_rename(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
+        let input: RenameInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+
+        // Writes share apply_edit's opt-in, root validation and server sync;
+        // refuse rather than silently preview when edits are disabled.
+        if !input.dry_run {
+            if !self.client_manager.config().allow_edits {
+                return Err(anyhow!(
+                    "rename with dry_run: false requires allow_edits = true in the Catenary config; \
+                     otherwise apply the listed changes with your editing tools"
+                ));
+            }
+            return self.handle_apply_edit(Some(serde_json::json!({
+                "file": input.file,
+                "line": input.line,
+                "character": input.character,
+                "new_name": input.new_name,
+                "roots_only": input.roots_only,
+            })));
+        }
+
+        let path = self.resolve_input_path(&input.file)?;
+
+        debug!(
+            "Rename request: {}:{}:{} -> {}",
+            input.file, input.line, input.character, input.new_name
+        );
+
+        let position = Position {
+            line: input.line,
+            character: input.character,
+        };
+        let Some(mut edit) = self.rename_edit(&path, position, input.new_name)? else {
+            return Ok(CallToolResult::text(
+                "Rename not supported at this location",
+            ));
+        };
+
+        // Edit URIs come from the server, so compare them against the roots
+        // it was given rather than the validator's canonical ones.
+        let roots = self.runtime.block_on(self.client_manager.roots());
+        let outside = outside_root_paths(&edit, &roots);
+        if input.roots_only {
+            retain_root_edits(&mut edit, &roots);
+        }
+        // Same summary a real apply prints, so the preview reads alike
+        let mut output = match edit_summary(&edit) {
+            Ok(summary) => format!("Would apply {summary}\n"),
+            Err(e) => format!("Cannot apply: {e}\n"),
+        };
+        output.push_str("Dry run \u{2014} no files were modified.\n");
+        if !outside.is_empty() {
+            use std::fmt::Write;
+            if input.roots_only {
+                let _ = writeln!(
+                    output,
+                    "Dropped edits to {} file(s) outside the workspace roots (roots_only):",
+                    outside.len()
+                );
+            } else {
+                let _ = writeln!(
+                    output,
+                    "\u{26a0} WARNING: this rename edits {} file(s) outside the workspace roots; \
+                     pass roots_only: true to drop them:",
+                    outside.len()
+                );
+            }
+            for path in &outside {
+                let _ = writeln!(output, "  {path}");
+            }
+        }
+        output.push_str(&format_workspace_edit(&edit));
+        self.append_edit_id(&mut output, edit);
+        Ok(CallToolResult::text(output))
+    }
+
+    /// Asks the server for the edit that renames the symbol at `position`.
+    pub(super) fn rename_edit(
+        &self,
+        path: &Path,
+        position: Position,
+        new_name: String,
+    ) -> Result<Option<WorkspaceEdit>> {
+        self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(path).await?;
+            let params = RenameParams {
+                text_document_position: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri },
+                    position,
+                },
+                new_name,
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            };
+            client_mutex.lock().await.rename(params).await
+        })
+    }
+
+    fn handle_diagnostics(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
+        use std::fmt::Write;
+
+        let input: DiagnosticsInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+
+        let path = self.resolve_input_path(&input.file)?;
+
+        debug!(
+            "Diagnostics request: {} (force_refresh={}, workspace={})",
+            input.file, input.force_refresh, input.workspace
+        );
+
+        let (diagnostics, others) = self.runtime.block_on(async {
+            let client_mutex = self.get_client_for_path(&path).await?;
+            let mut doc_manager = self.doc_manager.lock().await;
+            let client = client_mutex.lock().await;
+
+            if !client.is_alive() {
+                return Err(anyhow!(
+                    "[{}] server is no longer running",
+                    client.language()
+                ));
+            }
+
+            let uri = doc_manager.uri_for_path(&path)?;
+
+            // Escape hatch for servers stuck on resolved errors: a fresh
+            // didOpen makes them re-read the file instead of trusting state.
+            if input.force_refresh {
+                if let Some(params) = doc_manager.close(&path)? {
+                    client.did_close(params).await?;
+                }
+                client.clear_diagnostics(&uri).await;
+            }
+
+            // Snapshot generation *before* sending the change
+            let snapshot = client.diagnostics_generation(&uri).await;
+            let reopened = doc_manager.reopen_on(&client).await? > 0;
+            let notification = doc_manager.ensure_open(&path).await?;
+
+            if reopened || notification.is_some() {
+                if let Some(notification) = notification {
+                    notification.send(&client).await?;
+                }
+
+                // Trigger flycheck on servers that only run diagnostics on save
+                client.did_save(uri.clone()).await?;
+
+                drop(doc_manager);
+
+                self.wait_for_fresh_diagnostics(&client, &uri, snapshot)
+                    .await?;
+            } else {
+                drop(doc_manager);
+            }
+
+            let mut diagnostics = client.get_diagnostics(&uri).await;
+            let others: Vec<(PathBuf, Vec<Diagnostic>)> = if input.workspace {
+                client
+                    .all_diagnostics()
+                    .await
+                    .into_iter()
+                    .filter(|(other, _)| *other != uri)
+                    .map(|(other, diags)| (PathBuf::from(other.path().to_string()), diags))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            drop(client);
+            diagnostics.extend(self.companion_diagnostics(&path, &uri).await);
+            Ok::<_, anyhow::Error>((diagnostics, others))
+        })?;
+
+        let mut text = if diagnostics.is_empty() {
+            "No diagnostics".to_string()
+        } else {
+            format_diagnostics(&diagnostics)
+        };
+        let mut json = diagnostics_json(&path, &diagnostics);
+        if input.workspace {
+            for (other, diags) in &others {
+                let _ = write!(text, "\n\n{}:", other.display());
+                for line in format_diagnostics(diags).lines() {
+                    let _ = write!(text, "\n  {line}");
+                }
+            }
+            json["workspace"] = others
+                .iter()
+                .map(|(other, diags)| diagnostics_json(other, diags))
+                .collect();
+        }
+        Ok(CallToolResult::text(text).with_json(json))
+    }
+
+    /// Waits, according to `diagnostics_wait_strategy`, for `client` to
+    /// publish diagnostics for `uri` newer than `snapshot`.
+    async fn wait_for_fresh_diagnostics(
+        &self,
+        client: &LspClient,
+        uri: &Uri,
+        snapshot: u64,
+    ) -> Result<()> {
+        let config = self.client_manager.config();
+        let wait_result = match config.diagnostics_wait_strategy {
+            DiagnosticsWaitStrategy::Progress => {
+                client
+                    .wait_for_diagnostics_update(uri, snapshot, DIAGNOSTICS_TIMEOUT)
+                    .await
+            }
+            DiagnosticsWaitStrategy::Settle => {
+                client
+                    .wait_for_diagnostics_settle(
+                        uri,
+                        Duration::from_millis(config.diagnostics_settle_ms),
+                        DIAGNOSTICS_TIMEOUT,
+                    )
+                    .await
+            }
+            DiagnosticsWaitStrategy::Immediate => DiagnosticsWaitResult::Updated,
+        };
+
+        match wait_result {
+            DiagnosticsWaitResult::Updated => Ok(()),
+            DiagnosticsWaitResult::ServerDied => Err(anyhow!(
+                "[{}] server died during analysis",
+                client.language()
+            )),
+        }
+    }
+
+    /// Syncs `path` to the companion servers of its language and collects
+    /// their diagnostics. A companion that fails is logged and skipped.
+    async fn companion_diagnostics(&self, path: &Path, uri: &Uri) -> Vec<Diagnostic> {
+        let lang_id = self
+            .doc_manager
+            .lock()
+            .await
+            .language_id_for_path(path)
+            .to_string();
+        let mut diagnostics = Vec::new();
+
+        for (key, client_mutex) in self
+            .client_manager
+            .companion_clients_for_file(&lang_id, path)
+            .await
+        {
+            let doc_manager = self.doc_manager.lock().await;
+            let client = client_mutex.lock().await;
+            let snapshot = client.diagnostics_generation(uri).await;
+            let synced = doc_manager.sync_companion(path, &client).await;
+            drop(doc_manager);
+
+            let fresh = match synced {
+                Ok(false) => Ok(()),
+                Ok(true) => match client.did_save(uri.clone()).await {
+                    Ok(()) => {
+                        self.wait_for_fresh_diagnostics(&client, uri, snapshot)
+                            .await
+                    }
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+            if let Err(e) = fresh {
+                warn!("[{key}] companion diagnostics unavailable: {e}");
+                continue;
+            }
+            diagnostics.extend(client.get_diagnostics(uri).await);
+            drop(client);
+        }
+
+        diagnostics
+    }
+
+    /// Asks the companion servers of `path`'s language for code actions on
+    /// `range`. A companion that fails is logged and skipped.
+    async fn companion_code_actions(
+        &self,
+        path: &Path,
+        uri: &Uri,
+        range: Range,
+    ) -> Vec<lsp_types::CodeActionOrCommand> {
+        let lang_id = self
+            .doc_manager
+            .lock()
+            .await
+            .language_id_for_path(path)
+            .to_string();
+        let mut actions = Vec::new();
+
+        for (key, client_mutex) in self
+            .client_manager
+            .companion_clients_for_file(&lang_id, path)
+            .await
+        {
+            let doc_manager = self.doc_manager.lock().await;
+            let client = client_mutex.lock().await;
+            let synced = doc_manager.sync_companion(path, &client).await;
+            drop(doc_manager);
+            if let Err(e) = synced {
+                warn!("[{key}] companion code actions unavailable: {e}");
+                continue;
+            }
+
+            let params = CodeActionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                range,
+                context: CodeActionContext {
+                    diagnostics: client.get_diagnostics(uri).await,
+                    only: None,
+                    trigger_kind: None,
+                },
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                partial_result_params: lsp_types::PartialResultParams::default(),
+            };
+            match client.code_actions(params).await {
+                Ok(Some(response)) => actions.extend(response),
+                Ok(None) => {}
+                Err(e) => warn!("[{key}] companion code actions failed: {e}"),
+            }
+        }
+
+        actions
+    }
+
+    fn handle_call_hierarchy(
+        &self,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<CallToolResult> {
+        let input: CallHierarchyInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+
+        let sym_input = SymbolOrPositionInput {
+            symbol: input.symbol,
+            file: input.file,
+            line: input.line,
+            character: input.character,
+        };
+        let (path, position) = match self.resolve_symbol_or_position_disambiguated(&sym_input)? {
+            SymbolResolution::Unique(path, position) => (path, position),
+            SymbolResolution::Ambiguous(name, candidates) => {
+                return Ok(CallToolResult::text(format_symbol_candidates(
+                    &name,
+                    &candidates,
+                )));
+            }
+        };
+
+        debug!(
+            "Call hierarchy request: {}:{} direction={}",
+            path.display(),
+            position.line,
+            input.direction
+        );
+
+        let result = self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
+            let roots = self.client_manager.roots().await;
+
+            // First, prepare the call hierarchy
+            let prepare_params = CallHierarchyPrepareParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri },
+                    position,
+                },
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            };
+
+            let client = client_mutex.lock().await;
+            let items = client.prepare_call_hierarchy(prepare_params).await?;
+
+            let Some(items) = items else {
+                return Ok::<_, anyhow::Error>(None);
+            };
+
+            if items.is_empty() {
+                return Ok(None);
+            }
+
+            // Get calls for the first item (safe: we checked is_empty above)
+            let Some(item) = items.into_iter().next() else {
+                return Ok(None);
+            };
+
+            match input.direction.as_str() {
+                "incoming" => {
+                    let params = CallHierarchyIncomingCallsParams {
+                        item,
+                        work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                        partial_result_params: lsp_types::PartialResultParams::default(),
+                    };
+                    let calls = client.incoming_calls(params).await?;
+                    drop(client);
+                    Ok(calls.map(|c| format_incoming_calls(&c, &roots)))
+                }
+                "outgoing" => {
+                    let params = CallHierarchyOutgoingCallsParams {
+                        item,
+                        work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                        partial_result_params: lsp_types::PartialResultParams::default(),
+                    };
+                    let calls = client.outgoing_calls(params).await?;
+                    drop(client);
+                    Ok(calls.map(|c| format_outgoing_calls(&c, &roots)))
+                }
+                _ => Err(anyhow!("direction must be 'incoming' or 'outgoing'")),
+            }
+        })?;
+
+        match result {
+            Some(text) if !text.is_empty() => Ok(CallToolResult::text(text)),
+            _ => Ok(CallToolResult::text("No call hierarchy found")),
+        }
+    }
+
+    fn handle_type_hierarchy(
+        &self,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<CallToolResult> {
+        let input: TypeHierarchyInput =
+            serde_json::from_value(arguments.ok_or_else(|| anyhow!("Missing arguments"))?)
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+
+        let sym_input = SymbolOrPositionInput {
+            symbol: input.symbol,
+            file: input.file,
+            line: input.line,
+            character: input.character,
+        };
+        let (path, position) = self.resolve_symbol_or_position(&sym_input)?;
+
+        debug!(
+            "Type hierarchy request: {}:{} direction={}",
+            path.display(),
+            position.line,
+            input.direction
+        );
+
+        let result = self.runtime.block_on(async {
+            let (uri, client_mutex) = self.ensure_document_open(&path).await?;
+            let roots = self.client_manager.roots().await;
+
+            // First, prepare the type hierarchy
+            let prepare_params = TypeHierarchyPrepareParams {
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri },
+                    position,
+                },
+                work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            };
+
+            let client = client_mutex.lock().await;
+            let items = client.prepare_type_hierarchy(prepare_params).await?;
+
+            let Some(items) = items else {
+                return Ok::<_, anyhow::Error>(None);
+            };
+
+            if items.is_empty() {
+                return Ok(None);
+            }
+
+            // Get hierarchy for the first item (safe: we checked is_empty above)
+            let Some(item) = items.into_iter().next() else {
+                return Ok(None);
+            };
+
+            match input.direction.as_str() {
+                "supertypes" => {
+                    let params = TypeHierarchySupertypesParams {
+                        item,
+                        work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                        partial_result_params: lsp_types::PartialResultParams::default(),
+                    };
+                    let types = client.supertypes(params).await?;
+                    drop(client);
+                    Ok(types.map(|t| format_type_hierarchy_items(&t, &roots)))
+                }
+                "subtypes" => {
+                    let params = TypeHierarchySubtypesParams {
+                        item,
+                        work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+                        partial_result_params: lsp_types::PartialResultParams::default(),
+                    };
+                    let types = client.subtypes(params).await?;
+                    drop(client);
+                    Ok(types.map(|t| format_type_hierarchy_items(&t, &roots)))
+                }
+                _ => Err(anyhow!("direction must be 'supertypes' or 'subtypes'")),
+            }
+        })?;
+
+        match result {
+            Some(text) if !text.is_empty() => Ok(CallToolResult::text(text)),
+            _ => Ok(CallToolResult::text("No type hierarchy found")),
+        }
+    }
+
+    fn handle_workspace_diagnostics(
+        &self,
+        arguments: Option<serde_json::Value>,
+    ) -> Result<CallToolResult> {
+        let input: WorkspaceDiagnosticsInput =
+            serde_json::from_value(arguments.unwrap_or_else(|| serde_json::json!({})))
+                .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
+
+        debug!(
+            "Workspace diagnostics request: severity={:?} budget={}",
+            input.severity, input.budget
+        );
+
+        let groups = self.runtime.block_on(async {
+            let open_uris: Vec<Uri> = {
+                let doc_manager = self.doc_manager.lock().await;
+                doc_manager
+                    .open_paths()
+                    .iter()
+                    .filter_map(|path| doc_manager.uri_for_path(path).ok())
+                    .collect()
+            };
+
+            let mut clients: Vec<_> = self
+                .client_manager
+                .active_clients()
+                .await
+                .into_iter()
+                .collect();
+            clients.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+            let mut groups = Vec::new();
+            for (key, client_mutex) in clients {
+                let client = client_mutex.lock().await;
+                let mut files = Vec::new();
+                for uri in &open_uris {
+                    let diags = client.get_diagnostics(uri).await;
+                    if !diags.is_empty() {
+                        files.push((uri.path().to_string(), diags));
+                    }
+                }
+                drop(client);
+                if !files.is_empty() {
+                    groups.push((key, files));
+                }
+            }
+            groups
+        });
+
+        Ok(CallToolResult::text(format_workspace_diagnostics(
+            &groups,
+            input.severity,
+            input.budget,
+        )))
+    }
+
+    #[allow(
+        clippy::too_many_lines,
+        reason = "Complexity of codebase map generation requires many lines"
     )]
     fn handle_codebase_map(&self, arguments: Option<serde_json::Value>) -> Result<CallToolResult> {
         use std::fmt::Write;
@@ -1192,7 +3046,7 @@ impl LspBridgeHandler {
                 .map_err(|e| anyhow!("Invalid arguments: {e}"))?;
 
         let root_paths: Vec<PathBuf> = if let Some(p) = &input.path {
-            vec![Self::resolve_path(p)?]
+            vec![self.resolve_input_path(p)?]
         } else {
             let roots = self.runtime.block_on(self.client_manager.roots());
             if roots.is_empty() {
@@ -1225,6 +3079,7 @@ impl LspBridgeHandler {
                 .max_depth(Some(input.max_depth))
                 .git_ignore(true)
                 .hidden(true)
+                .add_custom_ignore_filename(IGNORE_FILENAME)
                 .build();
 
             // Add a virtual root entry for multi-root display
@@ -1269,64 +3124,56 @@ impl LspBridgeHandler {
         // Pick the first root for relative path display in single-root mode
         let primary_root = root_paths.first().cloned().unwrap_or_default();
 
-        // 2. Fetch Symbols (Async Phase)
+        // 2. Fetch Symbols (concurrently, see MAP_SYMBOL_CONCURRENCY)
         let unavailable_langs = if input.include_symbols {
-            let entries_len = entries.len();
             let detail_level = input.detail_level;
-            debug!("Fetching symbols for {} files", entries_len);
-
-            self.runtime.block_on(async {
-                let mut unavailable: Vec<String> = Vec::new();
-
-                for entry in &mut entries {
-                    if entry.is_dir {
-                        continue;
-                    }
-
-                    // Simple extension check to avoid wasted LSP calls
-                    let lang_id = {
-                        let doc_manager = self.doc_manager.lock().await;
-                        doc_manager.language_id_for_path(&entry.path).to_string()
-                    };
-
-                    if lang_id == "plaintext" {
-                        continue;
-                    }
-
-                    if let Ok(client_mutex) = self.client_manager.get_client(&lang_id).await {
-                        // Attempt to open and get symbols with a short timeout
-                        if let Ok((uri, _)) = self.ensure_document_open(&entry.path).await {
-                            let params = DocumentSymbolParams {
-                                text_document: TextDocumentIdentifier { uri },
-                                work_done_progress_params:
-                                    lsp_types::WorkDoneProgressParams::default(),
-                                partial_result_params: lsp_types::PartialResultParams::default(),
-                            };
-
-                            let client = client_mutex.lock().await;
-                            let symbols_future = client.document_symbols(params);
-
-                            // 1s timeout per file to keep map generation snappy but reliable
-                            let timeout_result = tokio::time::timeout(
-                                std::time::Duration::from_secs(1),
-                                symbols_future,
-                            )
-                            .await;
-                            drop(client);
-
-                            if let Ok(Ok(Some(response))) = timeout_result {
-                                entry.symbols =
-                                    Some(format_compact_symbols(&response, detail_level));
+            let files: Vec<usize> = entries
+                .iter()
+                .enumerate()
+                .filter(|(_, entry)| !entry.is_dir)
+                .map(|(index, _)| index)
+                .collect();
+            debug!("Fetching symbols for {} files", files.len());
+
+            // Workers pull the next file from a shared counter. Results are
+            // keyed by entry index so the output doesn't depend on timing.
+            let next = std::sync::atomic::AtomicUsize::new(0);
+            let mut results: Vec<(usize, MapSymbols)> = std::thread::scope(|scope| {
+                let workers: Vec<_> = (0..MAP_SYMBOL_CONCURRENCY.min(files.len()))
+                    .map(|_| {
+                        scope.spawn(|| {
+                            let mut fetched = Vec::new();
+                            while let Some(&index) =
+                                files.get(next.fetch_add(1, std::sync::atomic::Ordering::Relaxed))
+                            {
+                                let symbols = self.runtime.block_on(
+                                    self.fetch_map_symbols(&entries[index].path, detail_level),
+                                );
+                                fetched.push((index, symbols));
                             }
-                        }
-                    } else if !unavailable.contains(&lang_id) {
+                            fetched
+                        })
+                    })
+                    .collect();
+                workers
+                    .into_iter()
+                    .flat_map(|worker| worker.join().unwrap_or_default())
+                    .collect()
+            });
+            results.sort_by_key(|(index, _)| *index);
+
+            let mut unavailable: Vec<String> = Vec::new();
+            for (index, symbols) in results {
+                match symbols {
+                    MapSymbols::Found(symbols) => entries[index].symbols = Some(symbols),
+                    MapSymbols::Unavailable(lang_id) if !unavailable.contains(&lang_id) => {
                         warn!("[{lang_id}] unavailable during codebase map symbol fetch");
                         unavailable.push(lang_id);
                     }
+                    MapSymbols::Unavailable(_) | MapSymbols::Skipped => {}
                 }
-
-                unavailable
-            })
+            }
+            unavailable
         } else {
             Vec::new()
         };
@@ -1399,7 +3246,7 @@ impl LspBridgeHandler {
 impl ToolHandler for LspBridgeHandler {
     #[allow(clippy::too_many_lines, reason = "Naturally long list of tools")]
     fn list_tools(&self) -> Vec<Tool> {
-        let tools = vec![
+        let mut tools = vec![
             Tool {
                 name: "hover".to_string(),
                 description: Some("Get hover information (documentation, type info) for a symbol. Accepts a symbol name or file/line/character position.".to_string()),
@@ -1407,12 +3254,17 @@ impl ToolHandler for LspBridgeHandler {
             },
             Tool {
                 name: "definition".to_string(),
-                description: Some("Go to the definition of a symbol. Accepts a symbol name or file/line/character position.".to_string()),
+                description: Some("Go to the definition of a symbol. Accepts a symbol name or file/line/character position. Locations outside the workspace roots are marked with [dep]. When the server finds nothing for a symbol name, falls back to a workspace symbol match marked approximate.".to_string()),
+                input_schema: symbol_or_position_schema(),
+            },
+            Tool {
+                name: "document_highlight".to_string(),
+                description: Some("List every occurrence of a symbol within its own file, marked Read, Write or Text. File-local and cheaper than find_references. Accepts a symbol name or file/line/character position.".to_string()),
                 input_schema: symbol_or_position_schema(),
             },
             Tool {
                 name: "type_definition".to_string(),
-                description: Some("Go to the type definition of a symbol (e.g., for a variable, go to its type's definition). Accepts a symbol name or file/line/character position.".to_string()),
+                description: Some("Go to the type definition of a symbol (e.g., for a variable, go to its type's definition). Accepts a symbol name or file/line/character position. If a name matches several symbols, lists them with their positions instead of guessing.".to_string()),
                 input_schema: symbol_or_position_schema(),
             },
             Tool {
@@ -1422,7 +3274,7 @@ impl ToolHandler for LspBridgeHandler {
             },
             Tool {
                 name: "find_references".to_string(),
-                description: Some("Find all references to a symbol. Accepts either a symbol name (searched across workspace) or a file/line/character position. The definition is marked with [def] in results.".to_string()),
+                description: Some("Find all references to a symbol. Accepts either a symbol name (searched across workspace) or a file/line/character position. The definition is marked with [def] and locations outside the workspace roots with [dep]. Long results are paged: a trailing note gives the total and the offset for the next page.".to_string()),
                 input_schema: serde_json::json!({
                     "type": "object",
                     "properties": {
@@ -1430,18 +3282,45 @@ impl ToolHandler for LspBridgeHandler {
                         "file": { "type": "string", "description": "Absolute or relative path to the file. Required if using line/character position; optional with symbol to narrow search scope." },
                         "line": { "type": "integer", "description": "Line number (0-indexed). Required if not using symbol." },
                         "character": { "type": "integer", "description": "Character position (0-indexed). Required if not using symbol." },
-                        "include_declaration": { "type": "boolean", "description": "Include the declaration in results (default: true)" }
+                        "include_declaration": { "type": "boolean", "description": "Include the declaration in results (default: true)" },
+                        "max_results": { "type": "integer", "description": "Most references to return, not counting the definition, which is always listed (default: 100, 0 for all)" },
+                        "offset": { "type": "integer", "description": "Number of references to skip, to page through the rest (default: 0)" },
+                        "grouped": { "type": "boolean", "description": "Group references by file: each path once with its reference count, then indented L{line}:{col} lines (default: false)" }
                     }
                 }),
             },
+            Tool {
+                name: "investigate".to_string(),
+                description: Some("Tell me about a symbol in one call: its definition with a short code preview, the enclosing symbols (class/module) it is defined in, and how many references each file has. Use instead of chaining search, definition and find_references.".to_string()),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "symbol": { "type": "string", "description": "Symbol name to investigate (e.g., 'MyClass', 'handleRequest')" },
+                        "file": { "type": "string", "description": "Optional file to search first when the name is common" }
+                    },
+                    "required": ["symbol"]
+                }),
+            },
             Tool {
                 name: "document_symbols".to_string(),
                 description: Some("Get the symbol outline of a file (functions, classes, variables, etc.).".to_string()),
-                input_schema: file_schema(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "file": { "type": "string", "description": "Absolute path to the file" },
+                        "qualified": { "type": "boolean", "description": "List each symbol by its full containment path (e.g., 'MyClass::Inner::method') instead of an indented tree (default: false)" },
+                        "detail_level": {
+                            "type": "string",
+                            "enum": ["outline", "signatures", "full"],
+                            "description": "Symbol detail: outline (classes/structs only), signatures (+functions/methods), full (everything). Default: full"
+                        }
+                    },
+                    "required": ["file"]
+                }),
             },
             Tool {
                 name: "search".to_string(),
-                description: Some("Search for a symbol or pattern across the workspace. Returns LSP workspace symbols (semantic) plus a file heatmap showing which files contain the query and where (match count + line range).".to_string()),
+                description: Some("Search for a symbol or pattern across the workspace. Returns LSP workspace symbols (semantic) plus a file heatmap showing which files contain the query and where (match count + line range). Use mode \"merged\" to drop text matches that are the symbol definitions themselves.".to_string()),
                 input_schema: serde_json::json!({
                     "type": "object",
                     "properties": {
@@ -1449,14 +3328,39 @@ impl ToolHandler for LspBridgeHandler {
                             "type": "array",
                             "items": { "type": "string" },
                             "description": "Symbol names or text patterns to search for"
+                        },
+                        "mode": {
+                            "type": "string",
+                            "enum": ["heatmap", "merged"],
+                            "description": "heatmap: symbols plus every text match (default). merged: query LSP and grep concurrently and list only text matches not already reported as symbols"
                         }
                     },
                     "required": ["queries"]
                 }),
             },
+            Tool {
+                name: "completion".to_string(),
+                description: Some("Get completion suggestions at a position (e.g., the methods and fields available after `obj.`). Output starts with the cursor line and the partial token being completed. Optionally filter by item kind, group results under per-kind headers, and show documentation for the top few items.".to_string()),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "file": { "type": "string", "description": "Absolute path to the file" },
+                        "line": { "type": "integer", "description": "Line number (0-indexed)" },
+                        "character": { "type": "integer", "description": "Character position (0-indexed)" },
+                        "kinds": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Only include these completion kinds (e.g., 'method', 'field', 'keyword', 'function', 'variable')"
+                        },
+                        "group": { "type": "boolean", "description": "Group items under per-kind headers (default: false)" },
+                        "resolve_top": { "type": "integer", "description": "Resolve the first N listed items and include their documentation (default: 0, max: 10)" }
+                    },
+                    "required": ["file", "line", "character"]
+                }),
+            },
             Tool {
                 name: "code_actions".to_string(),
-                description: Some("Get available code actions (quick fixes, refactorings) for a range.".to_string()),
+                description: Some("Get available code actions (quick fixes, refactorings) for a range. Pass either the full range, or just line/character to let Catenary pick the range at that cursor.".to_string()),
                 input_schema: serde_json::json!({
                     "type": "object",
                     "properties": {
@@ -1464,21 +3368,88 @@ impl ToolHandler for LspBridgeHandler {
                         "start_line": { "type": "integer", "description": "Start line (0-indexed)" },
                         "start_character": { "type": "integer", "description": "Start character (0-indexed)" },
                         "end_line": { "type": "integer", "description": "End line (0-indexed)" },
-                        "end_character": { "type": "integer", "description": "End character (0-indexed)" }
+                        "end_character": { "type": "integer", "description": "End character (0-indexed)" },
+                        "line": { "type": "integer", "description": "Cursor line (0-indexed), instead of a range" },
+                        "character": { "type": "integer", "description": "Cursor character (0-indexed), instead of a range" }
+                    },
+                    "required": ["file"]
+                }),
+            },
+            Tool {
+                name: "diagnostic_fixes".to_string(),
+                description: Some("List the fixes available for the diagnostic at a position: titles, kinds, and how many edits each would make. Does not apply anything.".to_string()),
+                input_schema: position_schema(),
+            },
+            Tool {
+                name: "organize_imports".to_string(),
+                description: Some("Compute the server's \"organize imports\" edit for a whole file (sort, dedupe, remove unused). Does not modify files.".to_string()),
+                input_schema: file_schema(),
+            },
+            Tool {
+                name: "explain_diagnostic".to_string(),
+                description: Some("Explain the diagnostic at a position in one call: the diagnostic, hover on the token, its type definition (or definition) and the available fixes. Does not apply anything.".to_string()),
+                input_schema: position_schema(),
+            },
+            Tool {
+                name: "workspace_diagnostics".to_string(),
+                description: Some("Summarize cached diagnostics for every open document, grouped by server, as `path: N errors, M warnings`. Does not open files or wait for analysis.".to_string()),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "severity": {
+                            "type": "string",
+                            "enum": ["error", "warning", "all"],
+                            "description": "Only count errors, only warnings, or everything (default: all)"
+                        },
+                        "budget": { "type": "integer", "description": "Max lines of output before truncation (default: 2000)" }
+                    }
+                }),
+            },
+            Tool {
+                name: "code_lens".to_string(),
+                description: Some("List the server's code lenses for a file (e.g. \"Run test\", \"3 references\") as `L<line>: <title>`.".to_string()),
+                input_schema: file_schema(),
+            },
+            Tool {
+                name: "folding_ranges".to_string(),
+                description: Some("List a file's collapsible regions (blocks, comments, imports) as `L<start>-L<end> <kind>`, to navigate a large file without reading all of it.".to_string()),
+                input_schema: file_schema(),
+            },
+            Tool {
+                name: "inlay_hints".to_string(),
+                description: Some("Get the server's inlay hints (inferred types, parameter names) for a range of lines, one per line as `L<line>:<col> <label>`.".to_string()),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "file": { "type": "string", "description": "Absolute path to the file" },
+                        "start_line": { "type": "integer", "description": "First line (0-indexed)" },
+                        "end_line": { "type": "integer", "description": "Last line, inclusive (0-indexed)" }
                     },
-                    "required": ["file", "start_line", "start_character", "end_line", "end_character"]
+                    "required": ["file", "start_line", "end_line"]
                 }),
             },
+            Tool {
+                name: "selection_range".to_string(),
+                description: Some("List the nested syntactic ranges around a position (e.g. word, expression, statement, block), innermost first, as `L<line>:<col>-L<line>:<col>`. Use to snap an edit to a syntactic boundary.".to_string()),
+                input_schema: position_schema(),
+            },
+            Tool {
+                name: "prepare_rename".to_string(),
+                description: Some("Check that the symbol at a position can be renamed, and report the range and text a `rename` would replace. Use before `rename` when unsure the cursor is on a renameable token.".to_string()),
+                input_schema: position_schema(),
+            },
             Tool {
                 name: "rename".to_string(),
-                description: Some("Compute the edits needed to rename a symbol across the codebase. Returns proposed changes — does not modify files.".to_string()),
+                description: Some("Compute the edits needed to rename a symbol across the codebase. Returns proposed changes — does not modify files unless dry_run is false and edits are enabled.".to_string()),
                 input_schema: serde_json::json!({
                     "type": "object",
                     "properties": {
                         "file": { "type": "string", "description": "Absolute path to the file" },
                         "line": { "type": "integer", "description": "Line number (0-indexed)" },
                         "character": { "type": "integer", "description": "Character position (0-indexed)" },
-                        "new_name": { "type": "string", "description": "New name for the symbol" }
+                        "new_name": { "type": "string", "description": "New name for the symbol" },
+                        "dry_run": { "type": "boolean", "description": "Preview the edits without writing files (default: true). false applies them, which requires allow_edits" },
+                        "roots_only": { "type": "boolean", "description": "Drop edits to files outside the workspace roots (default: false; such files are flagged either way)" }
                     },
                     "required": ["file", "line", "character", "new_name"]
                 }),
@@ -1486,11 +3457,19 @@ impl ToolHandler for LspBridgeHandler {
             Tool {
                 name: "diagnostics".to_string(),
                 description: Some("Get diagnostics (errors, warnings, hints) for a file.".to_string()),
-                input_schema: file_schema(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "file": { "type": "string", "description": "Absolute path to the file" },
+                        "force_refresh": { "type": "boolean", "description": "Close and re-open the file so the server re-analyzes it from disk. Use when diagnostics look stale (default: false)" },
+                        "workspace": { "type": "boolean", "description": "Also list what the file's server reported for every other file, including files never opened (default: false)" }
+                    },
+                    "required": ["file"]
+                }),
             },
             Tool {
                 name: "call_hierarchy".to_string(),
-                description: Some("Get incoming or outgoing calls for a function/method. Accepts a symbol name or file/line/character position.".to_string()),
+                description: Some("Get incoming or outgoing calls for a function/method. Accepts a symbol name or file/line/character position. If a name matches several symbols, lists them with their positions instead of guessing.".to_string()),
                 input_schema: serde_json::json!({
                     "type": "object",
                     "properties": {
@@ -1520,7 +3499,25 @@ impl ToolHandler for LspBridgeHandler {
             },
             Tool {
                 name: "status".to_string(),
-                description: Some("Report the status of all LSP servers (state, progress, uptime).".to_string()),
+                description: Some("Report the status of all LSP servers (state, progress, uptime). The first line after the version summarizes readiness, e.g. `2/3 servers ready; rust indexing 40%`; wait and poll again before querying a server that is still indexing.".to_string()),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "required": []
+                }),
+            },
+            Tool {
+                name: "health_check".to_string(),
+                description: Some("Cheap liveness probe: bridge version, uptime and each server's state, without contacting any language server.".to_string()),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {},
+                    "required": []
+                }),
+            },
+            Tool {
+                name: "capabilities".to_string(),
+                description: Some("List, per running language server, the tools its advertised capabilities support (e.g. 'rust: hover definition references ...'). Check this before calling a tool a server may not implement.".to_string()),
                 input_schema: serde_json::json!({
                     "type": "object",
                     "properties": {},
@@ -1545,19 +3542,75 @@ impl ToolHandler for LspBridgeHandler {
                     },
                     "required": []
                 }),
-            },
-            Tool {
-                name: "list_directory".to_string(),
-                description: Some("List the contents of a directory. Shows directories, files with sizes, and symlinks with targets. Symlinks are not followed. Path must be within workspace roots.".to_string()),
+            },
+            Tool {
+                name: "list_directory".to_string(),
+                description: Some("List the contents of a directory. Shows directories, files with sizes, and symlinks with targets. Symlinks are not followed. Path must be within workspace roots.".to_string()),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "path": { "type": "string", "description": "Absolute or relative path to the directory" }
+                    },
+                    "required": ["path"]
+                }),
+            },
+            Tool {
+                name: "diff".to_string(),
+                description: Some("Show the unified git diff between the working tree and a ref. Read-only; output is capped at 100 KB. Paths must be within workspace roots.".to_string()),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "ref": { "type": "string", "description": "Git ref to compare against (default: HEAD)" },
+                        "path": { "type": "string", "description": "File or directory to limit the diff to (default: all workspace roots)" }
+                    },
+                    "required": []
+                }),
+            },
+        ];
+
+        if !self
+            .client_manager
+            .config()
+            .allowed_server_commands
+            .is_empty()
+        {
+            tools.push(Tool {
+                name: "execute_command".to_string(),
+                description: Some(format!(
+                    "Run a server-specific command through workspace/executeCommand and return its raw JSON result. Allowed commands: {}.",
+                    self.client_manager.config().allowed_server_commands.join(", ")
+                )),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "language": { "type": "string", "description": "Language whose server runs the command (e.g. 'rust')" },
+                        "file": { "type": "string", "description": "File or root directory the command concerns. Required when the language runs one server per root (isolate_roots), to pick that root's server" },
+                        "command": { "type": "string", "description": "Command id (e.g. 'rust-analyzer.reloadWorkspace')" },
+                        "arguments": { "type": "array", "description": "Arguments passed to the command as-is (default: none)" }
+                    },
+                    "required": ["language", "command"]
+                }),
+            });
+        }
+
+        if self.client_manager.config().allow_edits {
+            tools.push(Tool {
+                name: "apply_edit".to_string(),
+                description: Some("Write a proposed edit to disk. Pass the edit_id printed by `rename` or `organize_imports`, or rename inputs to compute and apply a rename in one step. Every file is checked against the workspace roots before anything is written.".to_string()),
                 input_schema: serde_json::json!({
                     "type": "object",
                     "properties": {
-                        "path": { "type": "string", "description": "Absolute or relative path to the directory" }
+                        "edit_id": { "type": "string", "description": "Id of a proposed edit (e.g. 'edit-3')" },
+                        "file": { "type": "string", "description": "File of the symbol to rename (instead of edit_id)" },
+                        "line": { "type": "integer", "description": "Line number (0-indexed)" },
+                        "character": { "type": "integer", "description": "Character position (0-indexed)" },
+                        "new_name": { "type": "string", "description": "New name for the symbol" },
+                        "roots_only": { "type": "boolean", "description": "Drop edits to files outside the workspace roots instead of refusing the edit (default: false)" }
                     },
-                    "required": ["path"]
+                    "required": []
                 }),
-            },
-        ];
+            });
+        }
 
         tools
     }
@@ -1596,7 +3649,15 @@ impl ToolHandler for LspBridgeHandler {
         // Wait for LSP readiness on all tools that touch language servers.
         // File-scoped calls wait for the specific server; symbol-only calls
         // wait for all active servers since we don't know which will handle it.
-        if !METHODS_SKIP_WAIT.contains(&name) {
+        // With the symbol index enabled, search answers from the index and
+        // whichever servers are ready instead of waiting for all of them.
+        let skip_wait =
+            METHODS_SKIP_WAIT.contains(&name) || (name == "search" && self.symbol_index.is_some());
+        if !skip_wait {
+            self.runtime.block_on(
+                self.wait_for_server_spawn(Self::extract_file_path(arguments.as_ref()).as_deref()),
+            );
+
             let wait_result = Self::extract_file_path(arguments.as_ref())
                 .as_ref()
                 .map_or_else(
@@ -1612,19 +3673,36 @@ impl ToolHandler for LspBridgeHandler {
 
         let result = match name {
             "hover" => self.handle_hover(arguments),
+            "document_highlight" => self.handle_document_highlight(arguments),
             "definition" => self.handle_definition(arguments),
             "type_definition" => self.handle_type_definition(arguments),
             "implementation" => self.handle_implementation(arguments),
             "find_references" => self.handle_find_references(arguments),
+            "investigate" => self.handle_investigate(arguments),
             "document_symbols" => self.handle_document_symbols(arguments),
             "search" => self.handle_search(arguments),
+            "completion" => self.handle_completion(arguments),
             "code_actions" => self.handle_code_actions(arguments),
+            "diagnostic_fixes" => self.handle_diagnostic_fixes(arguments),
+            "explain_diagnostic" => self.handle_explain_diagnostic(arguments),
+            "code_lens" => self.handle_code_lens(arguments),
+            "selection_range" => self.handle_selection_range(arguments),
+            "organize_imports" => self.handle_organize_imports(arguments),
+            "folding_ranges" => self.handle_folding_ranges(arguments),
+            "workspace_diagnostics" => self.handle_workspace_diagnostics(arguments),
+            "inlay_hints" => self.handle_inlay_hints(arguments),
+            "prepare_rename" => self.handle_prepare_rename(arguments),
             "rename" => self.handle_rename(arguments),
             "diagnostics" => self.handle_diagnostics(arguments),
             "call_hierarchy" => self.handle_call_hierarchy(arguments),
             "type_hierarchy" => self.handle_type_hierarchy(arguments),
             "codebase_map" => self.handle_codebase_map(arguments),
             "list_directory" => self.handle_list_directory(arguments),
+            "diff" => self.handle_diff(arguments),
+            "apply_edit" => self.handle_apply_edit(arguments),
+            "health_check" => Ok(self.handle_health_check()),
+            "capabilities" => Ok(self.handle_capabilities()),
+            "execute_command" => self.handle_execute_command(arguments),
             _ => Err(anyhow!("Unknown tool: {name}")),
         };
 
@@ -1633,8 +3711,104 @@ impl ToolHandler for LspBridgeHandler {
             Err(_) => broadcast_result(false),
         }
 
-        result
+        let max_bytes = self.client_manager.config().max_result_bytes;
+        result.map(|res| limit_result_size(res, max_bytes))
+    }
+}
+
+/// Formats the `health_check` response. `states` is `None` while a server
+/// is spawning; a `None` state means that server is busy with a request.
+fn format_health(
+    version: &str,
+    uptime_secs: u64,
+    states: Option<&[(String, Option<ServerState>)]>,
+) -> String {
+    let servers = match states {
+        None => "spawning".to_string(),
+        Some([]) => "none".to_string(),
+        Some(states) => states
+            .iter()
+            .map(|(lang, state)| {
+                let state = match state {
+                    Some(ServerState::Initializing) => "Initializing",
+                    Some(ServerState::Indexing) => "Indexing",
+                    Some(ServerState::Ready) => "Ready",
+                    Some(ServerState::Dead) => "Dead",
+                    None => "Busy",
+                };
+                format!("{lang} {state}")
+            })
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    format!("Catenary {version}\nUptime: {uptime_secs}s\nServers: {servers}")
+}
+
+/// Caps every text block of a tool result at `max_bytes` (0 = no limit).
+///
+/// Structured content cannot be cut without breaking it, so it is dropped
+/// when its serialized form is over the limit.
+fn limit_result_size(mut result: CallToolResult, max_bytes: usize) -> CallToolResult {
+    if max_bytes == 0 {
+        return result;
+    }
+    for content in &mut result.content {
+        let ToolContent::Text { text } = content;
+        if text.len() > max_bytes {
+            *text = truncate_middle(text, max_bytes);
+        }
+    }
+    if result
+        .structured_content
+        .as_ref()
+        .is_some_and(|value| value.to_string().len() > max_bytes)
+    {
+        result.structured_content = None;
+    }
+    result
+}
+
+/// Shortens `text` to roughly `max_bytes`, keeping its head and tail.
+///
+/// Multi-line output is cut at line boundaries so list-like results keep
+/// whole entries from both ends; a single long line is cut at a character
+/// boundary. A marker in the middle says how much was dropped.
+fn truncate_middle(text: &str, max_bytes: usize) -> String {
+    let half = max_bytes / 2;
+    let floor = |mut i: usize| {
+        while !text.is_char_boundary(i) {
+            i -= 1;
+        }
+        i
+    };
+    let ceil = |mut i: usize| {
+        while !text.is_char_boundary(i) {
+            i += 1;
+        }
+        i
+    };
+
+    let mut head = floor(half);
+    let mut tail = ceil(text.len() - half);
+    if text.contains('\n') {
+        head = text[..head].rfind('\n').map_or(head, |i| i + 1);
+        tail = text[tail..].find('\n').map_or(tail, |i| tail + i + 1);
     }
+
+    let omitted = &text[head..tail];
+    let lines = omitted.matches('\n').count();
+    let marker = if lines > 0 {
+        format!(
+            "[... {lines} lines ({} bytes) omitted: result exceeded max_result_bytes ({max_bytes}) ...]\n",
+            omitted.len()
+        )
+    } else {
+        format!(
+            "[... {} bytes omitted: result exceeded max_result_bytes ({max_bytes}) ...]",
+            omitted.len()
+        )
+    };
+    format!("{}{marker}{}", &text[..head], &text[tail..])
 }
 
 // ... (existing schema helpers)
@@ -1660,6 +3834,14 @@ fn format_compact_symbols(response: &DocumentSymbolResponse, level: DetailLevel)
     result.join("\n")
 }
 
+/// Returns how much of the `init_timeout` startup window is left after
+/// `elapsed`, or `None` once it has passed (or the gate is disabled).
+fn init_wait_remaining(init_timeout: u64, elapsed: Duration) -> Option<Duration> {
+    Duration::from_secs(init_timeout)
+        .checked_sub(elapsed)
+        .filter(|remaining| !remaining.is_zero())
+}
+
 const fn matches_detail_level(kind: lsp_types::SymbolKind, level: DetailLevel) -> bool {
     use lsp_types::SymbolKind;
 
@@ -1718,6 +3900,18 @@ fn file_schema() -> serde_json::Value {
     })
 }
 
+fn position_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "properties": {
+            "file": { "type": "string", "description": "Absolute path to the file" },
+            "line": { "type": "integer", "description": "Line number (0-indexed)" },
+            "character": { "type": "integer", "description": "Character position (0-indexed)" }
+        },
+        "required": ["file", "line", "character"]
+    })
+}
+
 // Formatting helpers
 fn format_hover(hover: &Hover) -> String {
     use lsp_types::HoverContents;
@@ -1741,16 +3935,18 @@ fn format_marked_string(marked: &lsp_types::MarkedString) -> String {
     }
 }
 
-fn format_definition_response(response: &GotoDefinitionResponse) -> String {
+fn format_definition_response(response: &GotoDefinitionResponse, roots: &[PathBuf]) -> String {
     match response {
-        GotoDefinitionResponse::Scalar(location) => format_location(location),
+        GotoDefinitionResponse::Scalar(location) => {
+            mark_dependency(format_location(location), &location.uri, roots)
+        }
         GotoDefinitionResponse::Array(locations) => {
             if locations.is_empty() {
                 "No results".to_string()
             } else {
                 locations
                     .iter()
-                    .map(format_location)
+                    .map(|loc| mark_dependency(format_location(loc), &loc.uri, roots))
                     .collect::<Vec<_>>()
                     .join("\n")
             }
@@ -1761,7 +3957,9 @@ fn format_definition_response(response: &GotoDefinitionResponse) -> String {
             } else {
                 links
                     .iter()
-                    .map(format_location_link)
+                    .map(|link| {
+                        mark_dependency(format_location_link(link), &link.target_uri, roots)
+                    })
                     .collect::<Vec<_>>()
                     .join("\n")
             }
@@ -1769,10 +3967,40 @@ fn format_definition_response(response: &GotoDefinitionResponse) -> String {
     }
 }
 
+/// Whether a definition response holds no locations.
+const fn is_empty_definition_response(response: &GotoDefinitionResponse) -> bool {
+    match response {
+        GotoDefinitionResponse::Scalar(_) => false,
+        GotoDefinitionResponse::Array(locations) => locations.is_empty(),
+        GotoDefinitionResponse::Link(links) => links.is_empty(),
+    }
+}
+
+/// Format a definition found by workspace symbol lookup rather than by the
+/// server's `textDocument/definition`.
+fn format_approximate_definition(path: &Path, position: Position, roots: &[PathBuf]) -> String {
+    let mut formatted = format!(
+        "{}:{}:{}",
+        path.display(),
+        position.line + 1,
+        position.character + 1
+    );
+    if is_dependency_path(path, roots) {
+        formatted.push_str(" [dep]");
+    }
+    formatted.push_str(
+        "\n(approximate: server returned no definition; matched by workspace symbol name)",
+    );
+    formatted
+}
+
 /// Find a symbol by name in a document symbol response, returning its position.
+///
+/// Nested trees are searched no deeper than `max_depth` levels.
 fn find_symbol_in_document_response(
     response: &DocumentSymbolResponse,
     name: &str,
+    max_depth: usize,
 ) -> Option<Position> {
     match response {
         DocumentSymbolResponse::Flat(symbols) => {
@@ -1783,18 +4011,28 @@ fn find_symbol_in_document_response(
                 .or_else(|| symbols.iter().find(|s| s.name.contains(name)))
                 .map(|s| s.location.range.start)
         }
-        DocumentSymbolResponse::Nested(symbols) => find_in_nested_symbols(symbols, name),
+        DocumentSymbolResponse::Nested(symbols) => find_in_nested_symbols(symbols, name, max_depth),
     }
 }
 
 /// Recursively search nested document symbols.
-fn find_in_nested_symbols(symbols: &[DocumentSymbol], name: &str) -> Option<Position> {
+///
+/// `depth_remaining` bounds the recursion so a malformed or hostile server
+/// response cannot overflow the stack; children below the limit are skipped.
+fn find_in_nested_symbols(
+    symbols: &[DocumentSymbol],
+    name: &str,
+    depth_remaining: usize,
+) -> Option<Position> {
+    if depth_remaining == 0 {
+        return None;
+    }
     for symbol in symbols {
         if symbol.name == name {
             return Some(symbol.selection_range.start);
         }
         if let Some(children) = &symbol.children
-            && let Some(pos) = find_in_nested_symbols(children, name)
+            && let Some(pos) = find_in_nested_symbols(children, name, depth_remaining - 1)
         {
             return Some(pos);
         }
@@ -1805,7 +4043,7 @@ fn find_in_nested_symbols(symbols: &[DocumentSymbol], name: &str) -> Option<Posi
             return Some(symbol.selection_range.start);
         }
         if let Some(children) = &symbol.children
-            && let Some(pos) = find_in_nested_symbols(children, name)
+            && let Some(pos) = find_in_nested_symbols(children, name, depth_remaining - 1)
         {
             return Some(pos);
         }
@@ -1844,6 +4082,121 @@ fn find_symbol_in_workspace_response(
     }
 }
 
+/// Returns the exact matches in `items`, or the partial matches if there
+/// are none.
+fn exact_or_partial<T>(items: Vec<T>, name: &str, item_name: impl Fn(&T) -> &str) -> Vec<T> {
+    let (exact, partial): (Vec<T>, Vec<T>) = items
+        .into_iter()
+        .filter(|item| item_name(item).contains(name))
+        .partition(|item| item_name(item) == name);
+    if exact.is_empty() { partial } else { exact }
+}
+
+/// Collects the document symbols matching `name` as disambiguation candidates.
+fn document_symbol_candidates(
+    response: &DocumentSymbolResponse,
+    name: &str,
+    uri: &Uri,
+    max_depth: usize,
+) -> Vec<SymbolCandidate> {
+    fn walk(
+        symbols: &[DocumentSymbol],
+        container: Option<&str>,
+        path: &Path,
+        depth_remaining: usize,
+        out: &mut Vec<SymbolCandidate>,
+    ) {
+        if depth_remaining == 0 {
+            return;
+        }
+        for sym in symbols {
+            out.push(SymbolCandidate {
+                name: sym.name.clone(),
+                kind: sym.kind,
+                container: container.map(str::to_string),
+                path: path.to_path_buf(),
+                position: sym.selection_range.start,
+            });
+            if let Some(children) = &sym.children {
+                walk(children, Some(&sym.name), path, depth_remaining - 1, out);
+            }
+        }
+    }
+
+    let mut all = Vec::new();
+    match response {
+        DocumentSymbolResponse::Flat(symbols) => {
+            all.extend(symbols.iter().map(symbol_information_candidate));
+        }
+        DocumentSymbolResponse::Nested(symbols) => {
+            let path = PathBuf::from(uri.path().as_str());
+            walk(symbols, None, &path, max_depth, &mut all);
+        }
+    }
+    exact_or_partial(all, name, |c| &c.name)
+}
+
+/// Collects the workspace symbols matching `name` as disambiguation candidates.
+fn workspace_symbol_candidates(
+    response: &WorkspaceSymbolResponse,
+    name: &str,
+) -> Vec<SymbolCandidate> {
+    let all = match response {
+        WorkspaceSymbolResponse::Flat(symbols) => {
+            symbols.iter().map(symbol_information_candidate).collect()
+        }
+        WorkspaceSymbolResponse::Nested(symbols) => symbols
+            .iter()
+            .filter_map(|s| match &s.location {
+                lsp_types::OneOf::Left(location) => Some(SymbolCandidate {
+                    name: s.name.clone(),
+                    kind: s.kind,
+                    container: s.container_name.clone(),
+                    path: PathBuf::from(location.uri.path().as_str()),
+                    position: location.range.start,
+                }),
+                lsp_types::OneOf::Right(_) => None, // URI-only location, can't get position
+            })
+            .collect(),
+    };
+    exact_or_partial(all, name, |c| &c.name)
+}
+
+fn symbol_information_candidate(sym: &SymbolInformation) -> SymbolCandidate {
+    SymbolCandidate {
+        name: sym.name.clone(),
+        kind: sym.kind,
+        container: sym.container_name.clone(),
+        path: PathBuf::from(sym.location.uri.path().as_str()),
+        position: sym.location.range.start,
+    }
+}
+
+/// Lists the symbols an ambiguous name matched, with the 0-indexed
+/// positions to pass back as `file`/`line`/`character`.
+fn format_symbol_candidates(name: &str, candidates: &[SymbolCandidate]) -> String {
+    let mut lines = vec![format!(
+        "'{name}' matches {} symbols. Re-run with the file, line and character of the one you mean:",
+        candidates.len()
+    )];
+    for c in candidates {
+        let container = c
+            .container
+            .as_ref()
+            .map(|container| format!(" in {container}"))
+            .unwrap_or_default();
+        lines.push(format!(
+            "  {} [{:?}]{container} \u{2014} {} line {} character {}",
+            c.name,
+            c.kind,
+            c.path.display(),
+            c.position.line,
+            c.position.character
+        ));
+    }
+    lines.join("\n")
+}
+
 fn format_location(location: &Location) -> String {
     let path = location.uri.path();
     let line = location.range.start.line + 1;
@@ -1858,21 +4211,40 @@ fn format_location_link(loc_link: &LocationLink) -> String {
     format!("{path}:{line}:{col}")
 }
 
-/// Format locations with the definition marked and listed first.
-fn format_locations_with_definition(
-    locations: &[Location],
-    definition: Option<&Location>,
-) -> String {
-    // Check if a location matches the definition
-    let is_definition = |loc: &Location| -> bool {
-        definition.is_some_and(|def| loc.uri == def.uri && loc.range.start == def.range.start)
-    };
+/// Append ` [dep]` to a formatted location that lies outside every workspace root.
+///
+/// Dependency sources (registry crates, `node_modules`, toolchain headers) are
+/// read-only from the agent's point of view.
+fn mark_dependency(formatted: String, uri: &Uri, roots: &[PathBuf]) -> String {
+    if is_dependency_path(Path::new(uri.path().as_str()), roots) {
+        format!("{formatted} [dep]")
+    } else {
+        formatted
+    }
+}
+
+/// Returns `true` if `path` is not under any of `roots`.
+///
+/// With no roots configured nothing is considered a dependency.
+fn is_dependency_path(path: &Path, roots: &[PathBuf]) -> bool {
+    !roots.is_empty() && !roots.iter().any(|root| path.starts_with(root))
+}
+
+/// Whether `loc` starts where `definition` does.
+fn is_definition_location(loc: &Location, definition: Option<&Location>) -> bool {
+    definition.is_some_and(|def| loc.uri == def.uri && loc.range.start == def.range.start)
+}
 
-    // Sort: definition first, then by file path and line
+/// Order references with the definition first, then by file path and
+/// position.
+fn sort_references<'a>(
+    locations: &'a [Location],
+    definition: Option<&Location>,
+) -> Vec<&'a Location> {
     let mut sorted: Vec<_> = locations.iter().collect();
     sorted.sort_by(|a, b| {
-        let a_is_def = is_definition(a);
-        let b_is_def = is_definition(b);
+        let a_is_def = is_definition_location(a, definition);
+        let b_is_def = is_definition_location(b, definition);
         match (a_is_def, b_is_def) {
             (true, false) => std::cmp::Ordering::Less,
             (false, true) => std::cmp::Ordering::Greater,
@@ -1890,18 +4262,160 @@ fn format_locations_with_definition(
             }
         }
     });
-
     sorted
+}
+
+/// The slice of references `find_references` returns.
+#[derive(Debug, Clone, Copy)]
+struct ReferencePage {
+    /// References skipped before the page starts.
+    offset: usize,
+    /// Most references on the page; 0 for no limit.
+    max_results: usize,
+}
+
+impl ReferencePage {
+    /// Every reference on one page.
+    #[cfg(test)]
+    const ALL: Self = Self {
+        offset: 0,
+        max_results: 0,
+    };
+}
+
+/// Sorts references and cuts out `page`. The definition is always kept,
+/// ahead of the page, and does not count towards `offset` or `max_results`.
+///
+/// Returns the kept locations and the number of non-definition references.
+fn page_references<'a>(
+    locations: &'a [Location],
+    definition: Option<&Location>,
+    page: ReferencePage,
+) -> (Vec<&'a Location>, usize) {
+    let (mut kept, others): (Vec<_>, Vec<_>) = sort_references(locations, definition)
+        .into_iter()
+        .partition(|loc| is_definition_location(loc, definition));
+    let total = others.len();
+    let limit = if page.max_results == 0 {
+        usize::MAX
+    } else {
+        page.max_results
+    };
+    kept.extend(others.into_iter().skip(page.offset).take(limit));
+    (kept, total)
+}
+
+/// Format locations with the definition marked and listed first.
+///
+/// Locations outside `roots` are marked `[dep]`. With `grouped`, each file
+/// is printed once as a header with its reference count, followed by
+/// indented `L{line}:{col}` lines; the definition's file comes first. When
+/// `page` leaves references out, a trailing note says how many are shown
+/// and how to get the rest.
+fn format_locations_with_definition(
+    locations: &[Location],
+    definition: Option<&Location>,
+    roots: &[PathBuf],
+    page: ReferencePage,
+    grouped: bool,
+) -> String {
+    let (kept, total) = page_references(locations, definition, page);
+    let shown = kept
         .iter()
-        .map(|loc| {
-            if is_definition(loc) {
-                format!("{} [def]", format_location(loc))
+        .filter(|loc| !is_definition_location(loc, definition))
+        .count();
+    let mut lines: Vec<String> = if grouped {
+        format_reference_groups(&kept, definition, roots)
+    } else {
+        kept.iter()
+            .map(|loc| {
+                let formatted = if is_definition_location(loc, definition) {
+                    format!("{} [def]", format_location(loc))
+                } else {
+                    format_location(loc)
+                };
+                mark_dependency(formatted, &loc.uri, roots)
+            })
+            .collect()
+    };
+
+    if shown < total {
+        use std::fmt::Write;
+
+        let next = page.offset + shown;
+        let mut note = format!("[showing {shown} of {total} references");
+        if page.offset > 0 {
+            let _ = write!(note, " from offset {}", page.offset);
+        }
+        if next < total {
+            let _ = write!(note, "; pass offset={next} for more");
+        }
+        note.push(']');
+        lines.push(note);
+    }
+    lines.join("\n")
+}
+
+/// Group sorted references by file, in order of first appearance, so the
+/// definition's file (sorted first) leads.
+fn format_reference_groups(
+    locations: &[&Location],
+    definition: Option<&Location>,
+    roots: &[PathBuf],
+) -> Vec<String> {
+    let mut groups: Vec<(&Uri, Vec<&Location>)> = Vec::new();
+    for loc in locations {
+        match groups.iter_mut().find(|(uri, _)| **uri == loc.uri) {
+            Some((_, group)) => group.push(loc),
+            None => groups.push((&loc.uri, vec![loc])),
+        }
+    }
+
+    let mut lines = Vec::new();
+    for (uri, group) in groups {
+        let noun = if group.len() == 1 {
+            "reference"
+        } else {
+            "references"
+        };
+        let header = format!("{} ({} {noun})", uri.path(), group.len());
+        lines.push(mark_dependency(header, uri, roots));
+        for loc in group {
+            let line = loc.range.start.line + 1;
+            let col = loc.range.start.character + 1;
+            if is_definition_location(loc, definition) {
+                lines.push(format!("  L{line}:{col} [def]"));
             } else {
-                format_location(loc)
+                lines.push(format!("  L{line}:{col}"));
             }
+        }
+    }
+    lines
+}
+
+/// Structured form of [`format_locations_with_definition`]: one object per
+/// reference, in the same order, with 1-indexed `line`/`character`, and the
+/// `total` number of references (not counting the definition) across pages.
+fn references_json(
+    locations: &[Location],
+    definition: Option<&Location>,
+    roots: &[PathBuf],
+    page: ReferencePage,
+) -> serde_json::Value {
+    let (kept, total) = page_references(locations, definition, page);
+    let references: Vec<_> = kept
+        .iter()
+        .map(|loc| {
+            serde_json::json!({
+                "file": loc.uri.path().as_str(),
+                "line": loc.range.start.line + 1,
+                "character": loc.range.start.character + 1,
+                "definition": is_definition_location(loc, definition),
+                "dependency": is_dependency_path(Path::new(loc.uri.path().as_str()), roots),
+            })
         })
-        .collect::<Vec<_>>()
-        .join("\n")
+        .collect();
+    serde_json::json!({ "references": references, "total": total })
 }
 
 /// Extract the first location from a `GotoDefinitionResponse`.
@@ -1916,14 +4430,372 @@ fn extract_definition_location(response: &GotoDefinitionResponse) -> Option<Loca
     }
 }
 
-fn format_document_symbols(response: &DocumentSymbolResponse) -> String {
-    match response {
-        DocumentSymbolResponse::Flat(symbols) => symbols
-            .iter()
-            .map(format_symbol_info)
-            .collect::<Vec<_>>()
-            .join("\n"),
-        DocumentSymbolResponse::Nested(symbols) => format_nested_symbols(symbols, 0),
+/// Source lines shown from the definition in `investigate` output.
+const INVESTIGATE_PREVIEW_LINES: u32 = 5;
+
+/// What `investigate` gathered about a symbol.
+struct Investigation<'a> {
+    symbol: &'a str,
+    definition: &'a Location,
+    /// Enclosing symbols, outermost first.
+    context: &'a [String],
+    /// Zero-based line numbers and text starting at the definition.
+    preview: &'a [(u32, String)],
+    references: &'a [Location],
+}
+
+/// Formats an `investigate` report: definition, context, a numbered source
+/// preview and reference counts per root-relative file.
+///
+/// References inside the definition's range (the declaration itself) are not
+/// counted.
+fn format_investigation(report: &Investigation<'_>, roots: &[PathBuf]) -> String {
+    let def = report.definition;
+    let mut lines = vec![format!("Symbol: {}", report.symbol)];
+
+    let def_file = root_relative_path(def.uri.path().as_str(), roots);
+    lines.push(mark_dependency(
+        format!(
+            "Definition: {def_file}:{}:{}",
+            def.range.start.line + 1,
+            def.range.start.character + 1
+        ),
+        &def.uri,
+        roots,
+    ));
+    if !report.context.is_empty() {
+        lines.push(format!("Context: {}", report.context.join(" > ")));
+    }
+
+    let width = report
+        .preview
+        .last()
+        .map_or(0, |(line, _)| (line + 1).to_string().len());
+    for (line, text) in report.preview {
+        lines.push(format!("  {:>width$} | {text}", line + 1));
+    }
+
+    let mut by_file = std::collections::BTreeMap::<String, usize>::new();
+    for loc in report.references {
+        if loc.uri == def.uri && range_contains(&def.range, loc.range.start) {
+            continue;
+        }
+        *by_file
+            .entry(root_relative_path(loc.uri.path().as_str(), roots))
+            .or_default() += 1;
+    }
+    let total: usize = by_file.values().sum();
+    if total == 0 {
+        lines.push("References: none".to_string());
+    } else {
+        let files = if by_file.len() == 1 { "file" } else { "files" };
+        lines.push(format!("References: {total} in {} {files}", by_file.len()));
+        for (file, count) in &by_file {
+            lines.push(format!("  {file}: {count}"));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Maximum number of completion items included in tool output.
+const COMPLETION_MAX_ITEMS: usize = 50;
+
+/// Maximum number of completion items resolved for `resolve_top`.
+const COMPLETION_MAX_RESOLVE: usize = 10;
+
+/// Maximum documentation lines shown per resolved completion item.
+const COMPLETION_DOC_MAX_LINES: usize = 8;
+
+/// Converts `character` (in the negotiated position `encoding`) to a byte
+/// offset within `line`, clamped to the line's length.
+pub(super) fn byte_offset(
+    line: &str,
+    character: u32,
+    encoding: &lsp_types::PositionEncodingKind,
+) -> usize {
+    let unit_len = |c: char| -> usize {
+        if *encoding == lsp_types::PositionEncodingKind::UTF8 {
+            c.len_utf8()
+        } else if *encoding == lsp_types::PositionEncodingKind::UTF32 {
+            1
+        } else {
+            c.len_utf16()
+        }
+    };
+
+    let mut units = 0;
+    for (idx, c) in line.char_indices() {
+        if units >= character as usize {
+            return idx;
+        }
+        units += unit_len(c);
+    }
+    line.len()
+}
+
+/// The text around a completion request's cursor.
+#[derive(Debug, PartialEq, Eq)]
+struct CompletionCursor {
+    /// The full line the cursor is on.
+    line: String,
+    /// Identifier characters immediately before the cursor.
+    prefix: String,
+    /// The character immediately before the cursor, if any.
+    preceding: Option<char>,
+}
+
+impl CompletionCursor {
+    /// Locates `character` (in the negotiated position `encoding`) within
+    /// `line` and extracts the partial token before it.
+    fn new(line: &str, character: u32, encoding: &lsp_types::PositionEncodingKind) -> Self {
+        let end = byte_offset(line, character, encoding);
+        let before = &line[..end];
+        let prefix_start = before
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+            .last()
+            .map_or(end, |(idx, _)| idx);
+
+        Self {
+            line: line.to_string(),
+            prefix: before[prefix_start..].to_string(),
+            preceding: before.chars().next_back(),
+        }
+    }
+
+    /// Renders the cursor context shown above the completion list.
+    fn header(&self) -> String {
+        if self.prefix.is_empty() {
+            format!("Line: {}", self.line)
+        } else {
+            format!("Line: {}\nPrefix: {}", self.line, self.prefix)
+        }
+    }
+}
+
+/// Returns the items of a completion response whose kind is in `kinds`
+/// (case-insensitive, ignoring `_`/`-`, so `enum_member` matches
+/// `EnumMember`), or all items without a filter.
+fn filter_completion_items<'a>(
+    response: &'a CompletionResponse,
+    kinds: Option<&[String]>,
+) -> Vec<&'a CompletionItem> {
+    let items = match response {
+        CompletionResponse::Array(items) => items.as_slice(),
+        CompletionResponse::List(list) => list.items.as_slice(),
+    };
+
+    let wanted: Option<Vec<String>> =
+        kinds.map(|kinds| kinds.iter().map(|k| normalize_kind_name(k)).collect());
+    items
+        .iter()
+        .filter(|item| {
+            wanted.as_ref().is_none_or(|wanted| {
+                let name = normalize_kind_name(&completion_kind_name(item.kind));
+                wanted.contains(&name)
+            })
+        })
+        .collect()
+}
+
+/// Format a completion response, one `label [Kind] detail` line per item.
+///
+/// `kinds` filters items as in [`filter_completion_items`]. With `group`,
+/// items are listed under a header per kind in order of first appearance.
+/// Output is capped at [`COMPLETION_MAX_ITEMS`] after filtering.
+fn format_completion(
+    response: &CompletionResponse,
+    kinds: Option<&[String]>,
+    group: bool,
+) -> String {
+    let filtered = filter_completion_items(response, kinds);
+
+    if filtered.is_empty() {
+        return "No completions available".to_string();
+    }
+
+    let total = filtered.len();
+    let shown = &filtered[..total.min(COMPLETION_MAX_ITEMS)];
+
+    let mut lines = Vec::new();
+    if group {
+        let mut groups: Vec<(String, Vec<&CompletionItem>)> = Vec::new();
+        for item in shown {
+            let kind = completion_kind_name(item.kind);
+            if let Some((_, members)) = groups.iter_mut().find(|(k, _)| *k == kind) {
+                members.push(item);
+            } else {
+                groups.push((kind, vec![item]));
+            }
+        }
+        for (kind, members) in groups {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(format!("## {kind} ({})", members.len()));
+            lines.extend(
+                members
+                    .iter()
+                    .map(|item| format_completion_item(item, false)),
+            );
+        }
+    } else {
+        lines.extend(shown.iter().map(|item| format_completion_item(item, true)));
+    }
+
+    if total > COMPLETION_MAX_ITEMS {
+        lines.push(format!("... and {} more", total - COMPLETION_MAX_ITEMS));
+    }
+
+    lines.join("\n")
+}
+
+/// Format resolved completion items with their documentation, each capped
+/// at [`COMPLETION_DOC_MAX_LINES`] lines.
+fn format_completion_details(items: &[CompletionItem]) -> String {
+    let mut lines = vec!["## Details".to_string()];
+    for item in items {
+        lines.push(format_completion_item(item, true));
+        let docs = match &item.documentation {
+            Some(lsp_types::Documentation::String(s)) => s.as_str(),
+            Some(lsp_types::Documentation::MarkupContent(m)) => m.value.as_str(),
+            None => "",
+        };
+        let doc_lines: Vec<&str> = docs.trim().lines().collect();
+        lines.extend(
+            doc_lines
+                .iter()
+                .take(COMPLETION_DOC_MAX_LINES)
+                .map(|l| format!("  {l}").trim_end().to_string()),
+        );
+        if doc_lines.len() > COMPLETION_DOC_MAX_LINES {
+            lines.push("  ...".to_string());
+        }
+    }
+    lines.join("\n")
+}
+
+/// Format a single completion item. The kind tag is omitted under group headers.
+fn format_completion_item(item: &CompletionItem, with_kind: bool) -> String {
+    let mut line = if with_kind {
+        format!("{} [{}]", item.label, completion_kind_name(item.kind))
+    } else {
+        item.label.clone()
+    };
+    if let Some(detail) = item.detail.as_deref().filter(|d| !d.is_empty()) {
+        line.push(' ');
+        line.push_str(detail);
+    }
+    line
+}
+
+fn completion_kind_name(kind: Option<CompletionItemKind>) -> String {
+    kind.map_or_else(|| "Other".to_string(), |k| format!("{k:?}"))
+}
+
+fn normalize_kind_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Format a document symbol response as an indented tree, or with
+/// `qualified` as one `Outer::Inner::item` path per line.
+///
+/// Only symbols matching `level` are listed; a non-matching container is
+/// kept when one of its descendants matches, so the hierarchy stays intact.
+fn format_document_symbols(
+    response: &DocumentSymbolResponse,
+    level: DetailLevel,
+    max_depth: usize,
+    qualified: bool,
+) -> String {
+    match response {
+        DocumentSymbolResponse::Flat(symbols) => {
+            let format = if qualified {
+                format_qualified_symbol_info
+            } else {
+                format_symbol_info
+            };
+            symbols
+                .iter()
+                .filter(|s| matches_detail_level(s.kind, level))
+                .map(format)
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        DocumentSymbolResponse::Nested(symbols) if qualified => {
+            let mut lines = Vec::new();
+            format_qualified_symbols(symbols, "", 0, level, max_depth, &mut lines);
+            lines.join("\n")
+        }
+        DocumentSymbolResponse::Nested(symbols) => {
+            format_nested_symbols(symbols, 0, level, max_depth)
+        }
+    }
+}
+
+/// Returns `true` if `sym` or any descendant within `depth_remaining`
+/// levels matches `level`.
+fn has_visible_symbol(sym: &DocumentSymbol, level: DetailLevel, depth_remaining: usize) -> bool {
+    if matches_detail_level(sym.kind, level) {
+        return true;
+    }
+    depth_remaining > 1
+        && sym.children.as_ref().is_some_and(|children| {
+            children
+                .iter()
+                .any(|c| has_visible_symbol(c, level, depth_remaining - 1))
+        })
+}
+
+/// Format a flat symbol using its `container_name` as the qualifying prefix.
+fn format_qualified_symbol_info(sym: &SymbolInformation) -> String {
+    let kind = format!("{:?}", sym.kind);
+    let loc = format_location(&sym.location);
+    let name = sym
+        .container_name
+        .as_deref()
+        .filter(|c| !c.is_empty())
+        .map_or_else(|| sym.name.clone(), |c| format!("{c}::{}", sym.name));
+    format!("{name} [{kind}] {loc}")
+}
+
+/// Append one `parent::name [Kind] line N` entry per symbol, depth-first.
+///
+/// Recursion stops at `max_depth`, mirroring [`format_nested_symbols`].
+fn format_qualified_symbols(
+    symbols: &[DocumentSymbol],
+    parent: &str,
+    depth: usize,
+    level: DetailLevel,
+    max_depth: usize,
+    lines: &mut Vec<String>,
+) {
+    for sym in symbols {
+        if !has_visible_symbol(sym, level, max_depth - depth) {
+            continue;
+        }
+        let path = if parent.is_empty() {
+            sym.name.clone()
+        } else {
+            format!("{parent}::{}", sym.name)
+        };
+        let line = sym.range.start.line + 1;
+        lines.push(format!("{path} [{:?}] line {line}", sym.kind));
+        if let Some(children) = &sym.children
+            && !children.is_empty()
+        {
+            if depth + 1 >= max_depth {
+                lines.push(format!(
+                    "{path}::... (nesting deeper than {max_depth} levels omitted)"
+                ));
+            } else {
+                format_qualified_symbols(children, &path, depth + 1, level, max_depth, lines);
+            }
+        }
     }
 }
 
@@ -1933,20 +4805,235 @@ fn format_symbol_info(sym: &SymbolInformation) -> String {
     format!("{} [{}] {}", sym.name, kind, loc)
 }
 
-fn format_nested_symbols(symbols: &[DocumentSymbol], indent: usize) -> String {
+/// Format a nested symbol tree, one symbol per line indented by depth.
+///
+/// Children deeper than `max_depth` are replaced by a single truncation
+/// marker instead of being recursed into.
+fn format_nested_symbols(
+    symbols: &[DocumentSymbol],
+    indent: usize,
+    level: DetailLevel,
+    max_depth: usize,
+) -> String {
     let mut result = Vec::new();
     for sym in symbols {
+        if !has_visible_symbol(sym, level, max_depth - indent) {
+            continue;
+        }
         let kind = format!("{:?}", sym.kind);
         let prefix = "  ".repeat(indent);
         let line = sym.range.start.line + 1;
         result.push(format!("{}{} [{}] line {}", prefix, sym.name, kind, line));
-        if let Some(children) = &sym.children {
-            result.push(format_nested_symbols(children, indent + 1));
+        if let Some(children) = &sym.children
+            && !children.is_empty()
+        {
+            if indent + 1 >= max_depth {
+                result.push(format!(
+                    "{prefix}  ... (nesting deeper than {max_depth} levels omitted)"
+                ));
+            } else {
+                let nested = format_nested_symbols(children, indent + 1, level, max_depth);
+                if !nested.is_empty() {
+                    result.push(nested);
+                }
+            }
         }
     }
     result.join("\n")
 }
 
+/// Joins symbol results and a text-match heatmap into `search` output.
+fn combine_search_sections(symbol_lines: &[String], heatmap: &str, heatmap_title: &str) -> String {
+    let has_symbols = !symbol_lines.is_empty();
+    let has_heatmap = !heatmap.is_empty();
+
+    if !has_symbols && !has_heatmap {
+        return "No results found".to_string();
+    }
+
+    let mut output = String::new();
+
+    if has_symbols {
+        output.push_str("## Symbols\n");
+        output.push_str(&symbol_lines.join("\n"));
+    }
+
+    if has_heatmap {
+        if has_symbols {
+            output.push_str("\n\n");
+        }
+        output.push_str("## ");
+        output.push_str(heatmap_title);
+        output.push('\n');
+        output.push_str(heatmap);
+    }
+
+    output
+}
+
+/// Puts the open-document symbols ahead of the workspace symbol responses,
+/// dropping workspace symbols already listed.
+///
+/// Open-document symbols point at their name, while a workspace symbol
+/// usually spans its whole declaration, which can start on an attribute or
+/// doc comment. A workspace symbol is therefore a duplicate when an open
+/// symbol of the same name in the same file lies within its range.
+fn prepend_open_symbols(
+    open: Vec<SymbolInformation>,
+    responses: Vec<WorkspaceSymbolResponse>,
+) -> Vec<WorkspaceSymbolResponse> {
+    let mut open_lines: std::collections::HashMap<(&str, &str), Vec<u32>> =
+        std::collections::HashMap::new();
+    for sym in &open {
+        open_lines
+            .entry((sym.location.uri.path().as_str(), sym.name.as_str()))
+            .or_default()
+            .push(sym.location.range.start.line);
+    }
+    let is_new = |name: &str, uri: &Uri, range: &Range| {
+        !open_lines
+            .get(&(uri.path().as_str(), name))
+            .is_some_and(|lines| {
+                lines
+                    .iter()
+                    .any(|line| (range.start.line..=range.end.line).contains(line))
+            })
+    };
+
+    let responses: Vec<WorkspaceSymbolResponse> = responses
+        .into_iter()
+        .map(|response| match response {
+            WorkspaceSymbolResponse::Flat(mut symbols) => {
+                symbols.retain(|s| is_new(&s.name, &s.location.uri, &s.location.range));
+                WorkspaceSymbolResponse::Flat(symbols)
+            }
+            WorkspaceSymbolResponse::Nested(mut symbols) => {
+                symbols.retain(|s| match &s.location {
+                    lsp_types::OneOf::Left(loc) => is_new(&s.name, &loc.uri, &loc.range),
+                    lsp_types::OneOf::Right(_) => true,
+                });
+                WorkspaceSymbolResponse::Nested(symbols)
+            }
+        })
+        .collect();
+
+    let mut all = vec![WorkspaceSymbolResponse::Flat(open)];
+    all.extend(responses);
+    all
+}
+
+/// Returns the `(path, 1-based line)` of every symbol with a known location,
+/// used by merged search to skip text matches that are symbol definitions.
+fn symbol_definition_lines(
+    responses: &[WorkspaceSymbolResponse],
+) -> std::collections::HashSet<(String, u32)> {
+    let mut lines = std::collections::HashSet::new();
+    for response in responses {
+        match response {
+            WorkspaceSymbolResponse::Flat(symbols) => {
+                for sym in symbols {
+                    lines.insert((
+                        sym.location.uri.path().to_string(),
+                        sym.location.range.start.line + 1,
+                    ));
+                }
+            }
+            WorkspaceSymbolResponse::Nested(symbols) => {
+                for sym in symbols {
+                    if let lsp_types::OneOf::Left(loc) = &sym.location {
+                        lines.insert((loc.uri.path().to_string(), loc.range.start.line + 1));
+                    }
+                }
+            }
+        }
+    }
+    lines
+}
+
+/// Returns `file` relative to the first root containing it, or unchanged if
+/// it lies outside every root.
+fn root_relative_path(file: &str, roots: &[PathBuf]) -> String {
+    // Compare whole components so `/ws/app` does not claim `/ws/app2/...`.
+    roots
+        .iter()
+        .find_map(|root| {
+            Path::new(file)
+                .strip_prefix(root)
+                .ok()
+                .map(|rest| rest.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| file.to_string())
+}
+
+/// Builds a file-level heatmap (file path, match count, line range) from
+/// ripgrep `file:line:content` output, skipping lines in `exclude`.
+fn format_heatmap(
+    rg_output: &str,
+    roots: &[PathBuf],
+    exclude: &std::collections::HashSet<(String, u32)>,
+) -> String {
+    use std::collections::BTreeMap;
+    use std::fmt::Write;
+
+    // Parse output lines: "file:line:content" — group by file
+    // Use BTreeMap for deterministic (sorted) file order
+    let mut file_stats: BTreeMap<String, (usize, u32, u32)> = BTreeMap::new();
+
+    for line in rg_output.lines() {
+        // Format: file:line_number:content
+        // Find second colon (first colon may be in Windows path, but we're on Linux)
+        let Some((file, rest)) = line.split_once(':') else {
+            continue;
+        };
+        let Some((line_str, _content)) = rest.split_once(':') else {
+            continue;
+        };
+        let Ok(line_num) = line_str.parse::<u32>() else {
+            continue;
+        };
+        if exclude.contains(&(file.to_string(), line_num)) {
+            continue;
+        }
+
+        let entry = file_stats
+            .entry(file.to_string())
+            .or_insert((0, u32::MAX, 0));
+        entry.0 += 1; // count
+        entry.1 = entry.1.min(line_num); // min line
+        entry.2 = entry.2.max(line_num); // max line
+    }
+
+    if file_stats.is_empty() {
+        return String::new();
+    }
+
+    // Sort by match count descending, then by file path
+    let mut sorted: Vec<_> = file_stats.into_iter().collect();
+    sorted.sort_by(|a, b| b.1.0.cmp(&a.1.0).then_with(|| a.0.cmp(&b.0)));
+
+    // Show all files — the model has no way to retrieve omitted results
+    let mut output = String::new();
+    for (file, (count, min_line, max_line)) in sorted {
+        let display_path = root_relative_path(&file, roots);
+
+        let line_range = if min_line == max_line {
+            format!("line {min_line}")
+        } else {
+            format!("lines {min_line}-{max_line}")
+        };
+
+        let match_word = if count == 1 { "match" } else { "matches" };
+        let _ = writeln!(
+            output,
+            "{display_path}: {count} {match_word} ({line_range})"
+        );
+    }
+
+    // Remove trailing newline
+    output.truncate(output.trim_end().len());
+    output
+}
+
 fn format_workspace_symbols(response: &WorkspaceSymbolResponse) -> Option<String> {
     match response {
         WorkspaceSymbolResponse::Flat(symbols) => {
@@ -1987,23 +5074,690 @@ fn format_workspace_symbols(response: &WorkspaceSymbolResponse) -> Option<String
     }
 }
 
-fn format_code_actions(actions: &[CodeActionOrCommand]) -> String {
-    actions
-        .iter()
-        .enumerate()
-        .map(|(i, action)| match action {
-            CodeActionOrCommand::Command(cmd) => format!("{}. [Command] {}", i + 1, cmd.title),
+/// Widens a cursor position into a range for code actions.
+///
+/// Prefers the innermost non-empty `selectionRange` (usually the token or
+/// expression under the cursor), then the smallest document symbol that
+/// contains the position, and finally the empty range at the cursor.
+async fn smart_range(client: &LspClient, uri: &Uri, position: Position) -> (Range, &'static str) {
+    if client.supports_selection_range() {
+        let params = lsp_types::SelectionRangeParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            positions: vec![position],
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+            partial_result_params: lsp_types::PartialResultParams::default(),
+        };
+        match client.selection_range(params).await {
+            Ok(Some(ranges)) => {
+                if let Some(range) = ranges.first().and_then(innermost_selection) {
+                    return (range, "selection range");
+                }
+            }
+            Ok(None) => {}
+            Err(e) => debug!("selectionRange failed, falling back to symbols: {e}"),
+        }
+    }
+
+    let params = DocumentSymbolParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+        partial_result_params: lsp_types::PartialResultParams::default(),
+    };
+    if let Ok(Some(symbols)) = client.document_symbols(params).await
+        && let Some(range) = enclosing_symbol_range(&symbols, position)
+    {
+        return (range, "enclosing symbol");
+    }
+
+    (Range::new(position, position), "cursor")
+}
+
+/// Returns the first non-empty range walking outwards from the innermost
+/// selection.
+fn innermost_selection(selection: &lsp_types::SelectionRange) -> Option<Range> {
+    let mut current = Some(selection);
+    while let Some(sel) = current {
+        if sel.range.start != sel.range.end {
+            return Some(sel.range);
+        }
+        current = sel.parent.as_deref();
+    }
+    None
+}
+
+/// Returns the range of the smallest symbol containing `position`.
+fn enclosing_symbol_range(symbols: &DocumentSymbolResponse, position: Position) -> Option<Range> {
+    fn smallest(symbols: &[DocumentSymbol], position: Position) -> Option<Range> {
+        symbols
+            .iter()
+            .filter(|s| range_contains(&s.range, position))
+            .map(|s| {
+                s.children
+                    .as_deref()
+                    .and_then(|children| smallest(children, position))
+                    .unwrap_or(s.range)
+            })
+            .next()
+    }
+
+    match symbols {
+        DocumentSymbolResponse::Nested(symbols) => smallest(symbols, position),
+        DocumentSymbolResponse::Flat(symbols) => symbols
+            .iter()
+            .map(|s| s.location.range)
+            .filter(|r| range_contains(r, position))
+            .min_by_key(|r| {
+                (
+                    r.end.line - r.start.line,
+                    r.end.character.abs_diff(r.start.character),
+                )
+            }),
+    }
+}
+
+/// Names the symbols enclosing `position` as `name [Kind]`, outermost first.
+///
+/// The symbol defined at `position` is itself left out.
+fn enclosing_symbol_names(symbols: &DocumentSymbolResponse, position: Position) -> Vec<String> {
+    match symbols {
+        DocumentSymbolResponse::Nested(symbols) => {
+            let mut names = Vec::new();
+            let mut level = symbols.as_slice();
+            while let Some(sym) = level.iter().find(|s| range_contains(&s.range, position)) {
+                if sym.range.start == position || range_contains(&sym.selection_range, position) {
+                    break;
+                }
+                names.push(format!("{} [{:?}]", sym.name, sym.kind));
+                level = sym.children.as_deref().unwrap_or_default();
+            }
+            names
+        }
+        // Flat symbols only carry their container's name.
+        DocumentSymbolResponse::Flat(symbols) => symbols
+            .iter()
+            .filter(|s| range_contains(&s.location.range, position))
+            .min_by_key(|s| {
+                let r = s.location.range;
+                (
+                    r.end.line - r.start.line,
+                    r.end.character.abs_diff(r.start.character),
+                )
+            })
+            .and_then(|s| s.container_name.clone())
+            .filter(|c| !c.is_empty())
+            .into_iter()
+            .collect(),
+    }
+}
+
+fn range_contains(range: &Range, position: Position) -> bool {
+    range.start <= position && position <= range.end
+}
+
+fn format_code_actions(actions: &[CodeActionOrCommand]) -> String {
+    actions
+        .iter()
+        .enumerate()
+        .map(|(i, action)| match action {
+            CodeActionOrCommand::Command(cmd) => format!("{}. [Command] {}", i + 1, cmd.title),
+            CodeActionOrCommand::CodeAction(ca) => {
+                let kind = ca
+                    .kind
+                    .as_ref()
+                    .map(|k| format!(" ({})", k.as_str()))
+                    .unwrap_or_default();
+                format!("{}. {}{}", i + 1, ca.title, kind)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats the diagnostics at a position and the fixes offered for them.
+fn format_diagnostic_fixes(diagnostics: &[Diagnostic], actions: &[CodeActionOrCommand]) -> String {
+    let mut lines = vec![format!("Diagnostics ({}):", diagnostics.len())];
+    lines.extend(
+        format_diagnostics(diagnostics)
+            .lines()
+            .map(|l| format!("  {l}")),
+    );
+    lines.extend(format_fix_lines(actions));
+    lines.join("\n")
+}
+
+/// Formats document highlights as `L<line>:<col> [<kind>]` lines (1-indexed),
+/// in document order. A highlight without a kind is `Text`, as in the spec.
+fn format_document_highlights(highlights: &[DocumentHighlight]) -> String {
+    if highlights.is_empty() {
+        return "No highlights found".to_string();
+    }
+    let mut sorted: Vec<&DocumentHighlight> = highlights.iter().collect();
+    sorted.sort_by_key(|h| (h.range.start.line, h.range.start.character));
+    sorted
+        .iter()
+        .map(|highlight| {
+            let kind = match highlight.kind {
+                Some(DocumentHighlightKind::READ) => "Read",
+                Some(DocumentHighlightKind::WRITE) => "Write",
+                _ => "Text",
+            };
+            format!(
+                "L{}:{} [{kind}]",
+                highlight.range.start.line + 1,
+                highlight.range.start.character + 1
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Diagnostics of one server, grouped by file path.
+type ServerDiagnostics = (String, Vec<(String, Vec<Diagnostic>)>);
+
+/// Formats per-server diagnostic counts as a `[server]` header followed by
+/// `path: N errors, M warnings` lines, stopping after `budget` lines.
+///
+/// Files with nothing left after applying `filter` are omitted. With
+/// [`SeverityFilter::All`], informational diagnostics and hints are counted
+/// as `other`.
+fn format_workspace_diagnostics(
+    groups: &[ServerDiagnostics],
+    filter: SeverityFilter,
+    budget: usize,
+) -> String {
+    use std::fmt::Write;
+
+    let mut lines = Vec::new();
+    let (mut total_errors, mut total_warnings, mut total_other) = (0, 0, 0);
+    for (server, files) in groups {
+        let mut file_lines = Vec::new();
+        for (path, diags) in files {
+            let errors = diags
+                .iter()
+                .filter(|d| d.severity == Some(DiagnosticSeverity::ERROR))
+                .count();
+            let warnings = diags
+                .iter()
+                .filter(|d| d.severity == Some(DiagnosticSeverity::WARNING))
+                .count();
+            let other = diags.len() - errors - warnings;
+            let (errors, warnings, other) = match filter {
+                SeverityFilter::Error => (errors, 0, 0),
+                SeverityFilter::Warning => (0, warnings, 0),
+                SeverityFilter::All => (errors, warnings, other),
+            };
+            if errors + warnings + other == 0 {
+                continue;
+            }
+            total_errors += errors;
+            total_warnings += warnings;
+            total_other += other;
+            let mut line = format!("{path}: {errors} errors, {warnings} warnings");
+            if other > 0 {
+                let _ = write!(line, ", {other} other");
+            }
+            file_lines.push(line);
+        }
+        if !file_lines.is_empty() {
+            lines.push(format!("[{server}]"));
+            lines.extend(file_lines);
+        }
+    }
+
+    if lines.is_empty() {
+        return "No diagnostics in open documents".to_string();
+    }
+
+    let mut output = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        if i >= budget {
+            output.push_str("... (truncated)\n");
+            break;
+        }
+        let _ = writeln!(output, "{line}");
+    }
+    let _ = write!(
+        output,
+        "Total: {total_errors} errors, {total_warnings} warnings"
+    );
+    if total_other > 0 {
+        let _ = write!(output, ", {total_other} other");
+    }
+    output
+}
+
+/// Returns the text `range` covers within `line`, or `None` if the range
+/// spans several lines.
+fn range_text<'a>(
+    line: &'a str,
+    range: &Range,
+    encoding: &lsp_types::PositionEncodingKind,
+) -> Option<&'a str> {
+    if range.start.line != range.end.line {
+        return None;
+    }
+    let start = byte_offset(line, range.start.character, encoding);
+    let end = byte_offset(line, range.end.character, encoding);
+    line.get(start..end).filter(|text| !text.is_empty())
+}
+
+/// Formats a `prepareRename` answer as the target a rename would replace.
+///
+/// `text` is the document text under a bare range answer; positions are
+/// shown 1-indexed.
+fn format_prepare_rename(
+    response: &PrepareRenameResponse,
+    position: Position,
+    text: Option<&str>,
+) -> String {
+    let span = |range: &Range| {
+        format!(
+            "L{}:{}-L{}:{}",
+            range.start.line + 1,
+            range.start.character + 1,
+            range.end.line + 1,
+            range.end.character + 1
+        )
+    };
+    match response {
+        PrepareRenameResponse::Range(range) => text.map_or_else(
+            || format!("Can rename {}", span(range)),
+            |text| format!("Can rename `{text}` at {}", span(range)),
+        ),
+        PrepareRenameResponse::RangeWithPlaceholder { range, placeholder } => {
+            format!("Can rename `{placeholder}` at {}", span(range))
+        }
+        PrepareRenameResponse::DefaultBehavior { .. } => format!(
+            "Can rename the identifier at L{}:{}",
+            position.line + 1,
+            position.character + 1
+        ),
+    }
+}
+
+/// Formats code lenses as `L<line>: <title>` lines (1-indexed), in document
+/// order. Lenses still lacking a command or title after resolve are skipped.
+fn format_code_lenses(lenses: &[CodeLens]) -> String {
+    let mut lines: Vec<(Position, &str)> = lenses
+        .iter()
+        .filter_map(|lens| {
+            let title = lens.command.as_ref()?.title.trim();
+            (!title.is_empty()).then_some((lens.range.start, title))
+        })
+        .collect();
+    lines.sort_by_key(|(pos, _)| (pos.line, pos.character));
+    lines
+        .iter()
+        .map(|(pos, title)| format!("L{}: {title}", pos.line + 1))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats folding ranges as `L<start>-L<end> <kind>` lines (1-indexed),
+/// sorted by start line. Ranges without a kind show only their lines.
+fn format_folding_ranges(ranges: &[FoldingRange]) -> String {
+    let mut sorted: Vec<&FoldingRange> = ranges.iter().collect();
+    sorted.sort_by_key(|r| (r.start_line, r.end_line));
+    sorted
+        .iter()
+        .map(|range| {
+            let lines = format!("L{}-L{}", range.start_line + 1, range.end_line + 1);
+            match &range.kind {
+                Some(FoldingRangeKind::Comment) => format!("{lines} comment"),
+                Some(FoldingRangeKind::Imports) => format!("{lines} imports"),
+                Some(FoldingRangeKind::Region) => format!("{lines} region"),
+                None => lines,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats inlay hints as `L<line>:<col> <label>` lines (1-indexed), in
+/// document order.
+fn format_inlay_hints(hints: &[InlayHint]) -> String {
+    let mut sorted: Vec<&InlayHint> = hints.iter().collect();
+    sorted.sort_by_key(|h| (h.position.line, h.position.character));
+    sorted
+        .iter()
+        .map(|hint| {
+            let label = match &hint.label {
+                InlayHintLabel::String(s) => s.clone(),
+                InlayHintLabel::LabelParts(parts) => {
+                    parts.iter().map(|p| p.value.as_str()).collect::<String>()
+                }
+            };
+            format!(
+                "L{}:{} {}",
+                hint.position.line + 1,
+                hint.position.character + 1,
+                label.trim()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Formats a selection range chain as `L<line>:<col>-L<line>:<col>` lines
+/// (1-indexed), innermost first. Repeated ranges are listed once.
+fn format_selection_range(selection: &lsp_types::SelectionRange) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut previous: Option<Range> = None;
+    let mut current = Some(selection);
+    while let Some(sel) = current {
+        if previous != Some(sel.range) {
+            lines.push(format!(
+                "L{}:{}-L{}:{}",
+                sel.range.start.line + 1,
+                sel.range.start.character + 1,
+                sel.range.end.line + 1,
+                sel.range.end.character + 1
+            ));
+            previous = Some(sel.range);
+        }
+        current = sel.parent.as_deref();
+    }
+    lines.join("\n")
+}
+
+/// Requests the code actions that fix `diagnostics`, resolving (but never
+/// applying) any whose edits the server deferred.
+async fn diagnostic_fix_actions(
+    client_mutex: &Arc<Mutex<LspClient>>,
+    uri: Uri,
+    diagnostics: &[Diagnostic],
+) -> Result<Vec<CodeActionOrCommand>> {
+    let Some(first) = diagnostics.first() else {
+        return Ok(Vec::new());
+    };
+
+    let params = CodeActionParams {
+        text_document: TextDocumentIdentifier { uri },
+        range: first.range,
+        context: CodeActionContext {
+            diagnostics: diagnostics.to_vec(),
+            only: None,
+            trigger_kind: None,
+        },
+        work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+        partial_result_params: lsp_types::PartialResultParams::default(),
+    };
+    let mut actions = client_mutex
+        .lock()
+        .await
+        .code_actions(params)
+        .await?
+        .unwrap_or_default();
+
+    // Servers may defer computing edits until resolve; resolve (but
+    // never apply) so the listing reflects what each fix would do.
+    for action in &mut actions {
+        if let CodeActionOrCommand::CodeAction(ca) = action
+            && ca.edit.is_none()
+            && ca.command.is_none()
+        {
+            match client_mutex
+                .lock()
+                .await
+                .resolve_code_action(ca.clone())
+                .await
+            {
+                Ok(resolved) => *ca = resolved,
+                Err(e) => debug!("Failed to resolve code action '{}': {e}", ca.title),
+            }
+        }
+    }
+
+    // Some servers implement quickfixes as commands that send their edit
+    // back through `workspace/applyEdit`. The client records that edit
+    // without applying it, so run the command to learn what it would do.
+    for action in &mut actions {
+        if let CodeActionOrCommand::CodeAction(ca) = action
+            && ca.edit.is_none()
+            && ca
+                .kind
+                .as_ref()
+                .is_some_and(|k| k.as_str().starts_with(CodeActionKind::QUICKFIX.as_str()))
+            && let Some(command) = &ca.command
+        {
+            let outcome = client_mutex
+                .lock()
+                .await
+                .execute_command(
+                    &command.command,
+                    command.arguments.clone().unwrap_or_default(),
+                )
+                .await;
+            match outcome {
+                Ok(outcome) if !outcome.edits.is_empty() => {
+                    ca.edit = Some(merge_workspace_edits(outcome.edits));
+                }
+                Ok(_) => {}
+                Err(e) => debug!("Failed to run command for '{}': {e}", ca.title),
+            }
+        }
+    }
+
+    Ok(actions)
+}
+
+/// Combines the edits a command proposed into one workspace edit.
+#[allow(
+    clippy::mutable_key_type,
+    reason = "WorkspaceEdit keys its changes by Uri, whose hash never changes"
+)]
+fn merge_workspace_edits(mut edits: Vec<WorkspaceEdit>) -> WorkspaceEdit {
+    if edits.len() == 1 {
+        return edits.remove(0);
+    }
+
+    let mut merged = WorkspaceEdit::default();
+    let mut operations = Vec::new();
+    for edit in edits {
+        if let Some(changes) = edit.changes {
+            let merged_changes = merged
+                .changes
+                .get_or_insert_with(std::collections::HashMap::new);
+            for (uri, text_edits) in changes {
+                merged_changes.entry(uri).or_default().extend(text_edits);
+            }
+        }
+        match edit.document_changes {
+            Some(DocumentChanges::Edits(edits)) => {
+                operations.extend(edits.into_iter().map(DocumentChangeOperation::Edit));
+            }
+            Some(DocumentChanges::Operations(ops)) => operations.extend(ops),
+            None => {}
+        }
+        if let Some(annotations) = edit.change_annotations {
+            merged
+                .change_annotations
+                .get_or_insert_with(std::collections::HashMap::new)
+                .extend(annotations);
+        }
+    }
+    if !operations.is_empty() {
+        merged.document_changes = Some(DocumentChanges::Operations(operations));
+    }
+    merged
+}
+
+/// Formats the diagnostics at a position together with the hover, definition
+/// and fixes gathered to explain them.
+fn format_diagnostic_explanation(
+    diagnostics: &[Diagnostic],
+    hover: Option<&Hover>,
+    definition: Option<(&str, &str)>,
+    actions: &[CodeActionOrCommand],
+) -> String {
+    let mut lines = vec![format!("Diagnostics ({}):", diagnostics.len())];
+    lines.extend(
+        format_diagnostics(diagnostics)
+            .lines()
+            .map(|l| format!("  {l}")),
+    );
+
+    match hover {
+        Some(hover) => {
+            lines.push("Hover:".to_string());
+            lines.extend(format_hover(hover).lines().map(|l| format!("  {l}")));
+        }
+        None => lines.push("Hover: none".to_string()),
+    }
+
+    match definition {
+        Some((label, text)) => {
+            lines.push(format!("{label}:"));
+            lines.extend(text.lines().map(|l| format!("  {l}")));
+        }
+        None => lines.push("Definition: none".to_string()),
+    }
+
+    lines.extend(format_fix_lines(actions));
+    lines.join("\n")
+}
+
+/// Formats code actions as a numbered "Fixes" list.
+fn format_fix_lines(actions: &[CodeActionOrCommand]) -> Vec<String> {
+    if actions.is_empty() {
+        return vec!["No fixes available".to_string()];
+    }
+
+    let mut lines = vec![format!("Fixes ({}):", actions.len())];
+    for (i, action) in actions.iter().enumerate() {
+        let line = match action {
+            CodeActionOrCommand::Command(cmd) => {
+                format!(
+                    "{}. [Command] {} \u{2014} runs {}",
+                    i + 1,
+                    cmd.title,
+                    cmd.command
+                )
+            }
             CodeActionOrCommand::CodeAction(ca) => {
                 let kind = ca
                     .kind
                     .as_ref()
                     .map(|k| format!(" ({})", k.as_str()))
                     .unwrap_or_default();
-                format!("{}. {}{}", i + 1, ca.title, kind)
+                let preferred = if ca.is_preferred == Some(true) {
+                    " [preferred]"
+                } else {
+                    ""
+                };
+                let effect = match (&ca.edit, &ca.command) {
+                    (Some(edit), _) => {
+                        let (edits, files) = count_workspace_edits(edit);
+                        format!("{edits} edit(s) in {files} file(s)")
+                    }
+                    (None, Some(cmd)) => format!("runs {}", cmd.command),
+                    (None, None) => "no edits".to_string(),
+                };
+                format!("{}. {}{kind}{preferred} \u{2014} {effect}", i + 1, ca.title)
             }
-        })
-        .collect::<Vec<_>>()
-        .join("\n")
+        };
+        lines.push(line);
+    }
+    lines
+}
+
+/// Counts text edits and distinct files touched by a workspace edit.
+fn count_workspace_edits(edit: &WorkspaceEdit) -> (usize, usize) {
+    let mut edits = 0;
+    let mut files = std::collections::HashSet::new();
+
+    if let Some(changes) = &edit.changes {
+        for (uri, text_edits) in changes {
+            edits += text_edits.len();
+            files.insert(uri.as_str().to_string());
+        }
+    }
+
+    match &edit.document_changes {
+        Some(DocumentChanges::Edits(doc_edits)) => {
+            for doc_edit in doc_edits {
+                edits += doc_edit.edits.len();
+                files.insert(doc_edit.text_document.uri.as_str().to_string());
+            }
+        }
+        Some(DocumentChanges::Operations(ops)) => {
+            for op in ops {
+                match op {
+                    lsp_types::DocumentChangeOperation::Edit(doc_edit) => {
+                        edits += doc_edit.edits.len();
+                        files.insert(doc_edit.text_document.uri.as_str().to_string());
+                    }
+                    lsp_types::DocumentChangeOperation::Op(_) => edits += 1,
+                }
+            }
+        }
+        None => {}
+    }
+
+    (edits, files.len())
+}
+
+/// Returns the URIs a resource operation touches.
+fn resource_op_uris(op: &lsp_types::ResourceOp) -> Vec<&Uri> {
+    match op {
+        lsp_types::ResourceOp::Create(c) => vec![&c.uri],
+        lsp_types::ResourceOp::Rename(r) => vec![&r.old_uri, &r.new_uri],
+        lsp_types::ResourceOp::Delete(d) => vec![&d.uri],
+    }
+}
+
+/// Returns the sorted, de-duplicated paths a workspace edit touches that
+/// are not under any of `roots`.
+fn outside_root_paths(edit: &WorkspaceEdit, roots: &[PathBuf]) -> Vec<String> {
+    let mut uris: Vec<&Uri> = Vec::new();
+    if let Some(changes) = &edit.changes {
+        uris.extend(changes.keys());
+    }
+    match &edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => {
+            uris.extend(edits.iter().map(|e| &e.text_document.uri));
+        }
+        Some(DocumentChanges::Operations(ops)) => {
+            for op in ops {
+                match op {
+                    lsp_types::DocumentChangeOperation::Op(resource_op) => {
+                        uris.extend(resource_op_uris(resource_op));
+                    }
+                    lsp_types::DocumentChangeOperation::Edit(e) => {
+                        uris.push(&e.text_document.uri);
+                    }
+                }
+            }
+        }
+        None => {}
+    }
+
+    let mut paths: Vec<String> = uris
+        .into_iter()
+        .map(|uri| uri.path().to_string())
+        .filter(|path| is_dependency_path(Path::new(path), roots))
+        .collect();
+    paths.sort_unstable();
+    paths.dedup();
+    paths
+}
+
+/// Removes every change to a file outside `roots` from a workspace edit.
+pub(super) fn retain_root_edits(edit: &mut WorkspaceEdit, roots: &[PathBuf]) {
+    let inside = |uri: &Uri| !is_dependency_path(Path::new(uri.path().as_str()), roots);
+    if let Some(changes) = &mut edit.changes {
+        changes.retain(|uri, _| inside(uri));
+    }
+    match &mut edit.document_changes {
+        Some(DocumentChanges::Edits(edits)) => edits.retain(|e| inside(&e.text_document.uri)),
+        Some(DocumentChanges::Operations(ops)) => ops.retain(|op| match op {
+            lsp_types::DocumentChangeOperation::Op(resource_op) => {
+                resource_op_uris(resource_op).into_iter().all(inside)
+            }
+            lsp_types::DocumentChangeOperation::Edit(e) => inside(&e.text_document.uri),
+        }),
+        None => {}
+    }
 }
 
 fn format_workspace_edit(edit: &WorkspaceEdit) -> String {
@@ -2102,129 +5856,836 @@ fn format_workspace_edit(edit: &WorkspaceEdit) -> String {
     }
 }
 
+/// Display name of a diagnostic severity.
+const fn diagnostic_severity_name(severity: Option<DiagnosticSeverity>) -> &'static str {
+    match severity {
+        Some(DiagnosticSeverity::ERROR) => "error",
+        Some(DiagnosticSeverity::WARNING) => "warning",
+        Some(DiagnosticSeverity::INFORMATION) => "info",
+        Some(DiagnosticSeverity::HINT) => "hint",
+        _ => "unknown",
+    }
+}
+
+/// A diagnostic's code as a string, if it has one.
+fn diagnostic_code(diagnostic: &Diagnostic) -> Option<String> {
+    diagnostic.code.as_ref().map(|c| match c {
+        lsp_types::NumberOrString::Number(n) => n.to_string(),
+        lsp_types::NumberOrString::String(s) => s.clone(),
+    })
+}
+
+/// Structured form of [`format_diagnostics`], with 1-indexed
+/// `line`/`character`.
+fn diagnostics_json(file: &Path, diagnostics: &[Diagnostic]) -> serde_json::Value {
+    let diagnostics: Vec<_> = diagnostics
+        .iter()
+        .map(|d| {
+            serde_json::json!({
+                "line": d.range.start.line + 1,
+                "character": d.range.start.character + 1,
+                "severity": diagnostic_severity_name(d.severity),
+                "source": d.source,
+                "code": diagnostic_code(d),
+                "message": d.message,
+            })
+        })
+        .collect();
+    serde_json::json!({ "file": file, "diagnostics": diagnostics })
+}
+
 fn format_diagnostics(diagnostics: &[Diagnostic]) -> String {
     diagnostics
         .iter()
         .map(|d| {
-            let severity = match d.severity {
-                Some(DiagnosticSeverity::ERROR) => "error",
-                Some(DiagnosticSeverity::WARNING) => "warning",
-                Some(DiagnosticSeverity::INFORMATION) => "info",
-                Some(DiagnosticSeverity::HINT) => "hint",
-                _ => "unknown",
-            };
+            let severity = diagnostic_severity_name(d.severity);
             let line = d.range.start.line + 1;
             let col = d.range.start.character + 1;
             let source = d.source.as_deref().unwrap_or("");
-            let code = d
-                .code
-                .as_ref()
-                .map(|c| match c {
-                    lsp_types::NumberOrString::Number(n) => n.to_string(),
-                    lsp_types::NumberOrString::String(s) => s.clone(),
-                })
-                .unwrap_or_default();
+            let code = diagnostic_code(d).unwrap_or_default();
+
+            if code.is_empty() {
+                format!("{}:{}: [{}] {}: {}", line, col, severity, source, d.message)
+            } else {
+                format!(
+                    "{}:{}: [{}] {}({}): {}",
+                    line, col, severity, source, code, d.message
+                )
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// One entry of a call or type hierarchy: its file, 1-indexed line, and the
+/// label shown under the file heading.
+struct HierarchyEntry<'a> {
+    path: &'a str,
+    line: u32,
+    label: String,
+}
+
+/// Groups hierarchy entries by file, showing paths relative to `roots`,
+/// with each file's entries indented underneath as `L<line> <label>`.
+///
+/// Files are sorted by path and entries by line.
+fn format_hierarchy_entries(mut entries: Vec<HierarchyEntry<'_>>, roots: &[PathBuf]) -> String {
+    use std::fmt::Write;
+
+    entries.sort_by(|a, b| a.path.cmp(b.path).then(a.line.cmp(&b.line)));
+    let mut output = String::new();
+    let mut current: Option<&str> = None;
+    for entry in &entries {
+        if current != Some(entry.path) {
+            let _ = writeln!(output, "{}", root_relative_path(entry.path, roots));
+            current = Some(entry.path);
+        }
+        let _ = writeln!(output, "  L{} {}", entry.line, entry.label);
+    }
+    output.truncate(output.trim_end().len());
+    output
+}
+
+fn format_incoming_calls(calls: &[CallHierarchyIncomingCall], roots: &[PathBuf]) -> String {
+    if calls.is_empty() {
+        return "No incoming calls".to_string();
+    }
+
+    let entries = calls
+        .iter()
+        .map(|call| {
+            // A caller that calls several times is listed once.
+            let sites = match call.from_ranges.len() {
+                0 | 1 => String::new(),
+                n => format!(" ({n} call sites)"),
+            };
+            HierarchyEntry {
+                path: call.from.uri.path().as_str(),
+                line: call.from.range.start.line + 1,
+                label: format!("{} [{:?}]{sites}", call.from.name, call.from.kind),
+            }
+        })
+        .collect();
+    format_hierarchy_entries(entries, roots)
+}
+
+fn format_outgoing_calls(calls: &[CallHierarchyOutgoingCall], roots: &[PathBuf]) -> String {
+    if calls.is_empty() {
+        return "No outgoing calls".to_string();
+    }
+
+    let entries = calls
+        .iter()
+        .map(|call| HierarchyEntry {
+            path: call.to.uri.path().as_str(),
+            line: call.to.range.start.line + 1,
+            label: format!("{} [{:?}]", call.to.name, call.to.kind),
+        })
+        .collect();
+    format_hierarchy_entries(entries, roots)
+}
+
+fn format_type_hierarchy_items(items: &[TypeHierarchyItem], roots: &[PathBuf]) -> String {
+    if items.is_empty() {
+        return "No types found".to_string();
+    }
+
+    let entries = items
+        .iter()
+        .map(|item| HierarchyEntry {
+            path: item.uri.path().as_str(),
+            line: item.range.start.line + 1,
+            label: format!("{} [{:?}]", item.name, item.kind),
+        })
+        .collect();
+    format_hierarchy_entries(entries, roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+    use lsp_types::{
+        DocumentSymbol, Range, SymbolInformation, SymbolKind, WorkspaceSymbolResponse,
+    };
+
+    fn make_position(line: u32, character: u32) -> Position {
+        Position { line, character }
+    }
+
+    fn make_range(start_line: u32, start_char: u32, end_line: u32, end_char: u32) -> Range {
+        Range {
+            start: make_position(start_line, start_char),
+            end: make_position(end_line, end_char),
+        }
+    }
+
+    fn make_document_symbol(name: &str, kind: SymbolKind, range: Range) -> DocumentSymbol {
+        #[allow(
+            deprecated,
+            reason = "LSP spec uses deprecated fields in some versions"
+        )]
+        DocumentSymbol {
+            name: name.to_string(),
+            detail: None,
+            kind,
+            tags: None,
+            deprecated: None,
+            range,
+            selection_range: range,
+            children: None,
+        }
+    }
+
+    #[test]
+    fn test_code_action_target_requires_range_or_cursor() -> Result<()> {
+        let input: CodeActionInput = serde_json::from_value(
+            serde_json::json!({ "file": "a.rs", "line": 3, "character": 7 }),
+        )?;
+        assert_eq!(
+            input.target()?,
+            CodeActionTarget::Cursor(make_position(3, 7))
+        );
+
+        let input: CodeActionInput = serde_json::from_value(serde_json::json!({
+            "file": "a.rs", "start_line": 1, "start_character": 0, "end_line": 2, "end_character": 4
+        }))?;
+        assert_eq!(
+            input.target()?,
+            CodeActionTarget::Range(make_range(1, 0, 2, 4))
+        );
+
+        let partial: CodeActionInput =
+            serde_json::from_value(serde_json::json!({ "file": "a.rs", "start_line": 1 }))?;
+        assert!(partial.target().is_err());
+        let mixed: CodeActionInput = serde_json::from_value(serde_json::json!({
+            "file": "a.rs", "start_line": 1, "start_character": 0, "end_line": 2,
+            "end_character": 4, "line": 1, "character": 0
+        }))?;
+        assert!(mixed.target().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_innermost_selection_skips_empty_ranges() {
+        let selection = lsp_types::SelectionRange {
+            range: make_range(2, 4, 2, 4),
+            parent: Some(Box::new(lsp_types::SelectionRange {
+                range: make_range(2, 0, 2, 12),
+                parent: None,
+            })),
+        };
+        assert_eq!(
+            innermost_selection(&selection),
+            Some(make_range(2, 0, 2, 12))
+        );
+    }
+
+    #[test]
+    fn test_enclosing_symbol_range_picks_smallest() {
+        let mut outer = make_document_symbol("Outer", SymbolKind::STRUCT, make_range(0, 0, 10, 1));
+        outer.children = Some(vec![
+            make_document_symbol("first", SymbolKind::METHOD, make_range(1, 4, 3, 5)),
+            make_document_symbol("second", SymbolKind::METHOD, make_range(5, 4, 8, 5)),
+        ]);
+        let nested = DocumentSymbolResponse::Nested(vec![outer]);
+
+        assert_eq!(
+            enclosing_symbol_range(&nested, make_position(6, 8)),
+            Some(make_range(5, 4, 8, 5))
+        );
+        assert_eq!(
+            enclosing_symbol_range(&nested, make_position(4, 0)),
+            Some(make_range(0, 0, 10, 1))
+        );
+        assert_eq!(enclosing_symbol_range(&nested, make_position(11, 0)), None);
+    }
+
+    #[test]
+    fn test_truncate_middle_keeps_head_and_tail_lines() {
+        let text = (0..100)
+            .map(|i| format!("entry {i:03}"))
+            .collect::<Vec<_>>()
+            .join("\n")
+            + "\n";
+        let out = truncate_middle(&text, 100);
+        assert!(out.starts_with("entry 000\n"), "got: {out}");
+        assert!(out.ends_with("entry 099\n"), "got: {out}");
+        assert!(out.contains("lines ("), "got: {out}");
+        assert!(out.contains("max_result_bytes (100)"), "got: {out}");
+        assert!(
+            out.lines()
+                .all(|l| l.starts_with("entry") || l.starts_with("[..."))
+        );
+
+        let long = "é".repeat(100);
+        let out = truncate_middle(&long, 51);
+        assert!(out.starts_with("é"), "got: {out}");
+        assert!(out.contains("bytes omitted"), "got: {out}");
+    }
+
+    #[test]
+    fn test_limit_result_size() {
+        let text = "x".repeat(10);
+        let kept = limit_result_size(CallToolResult::text(text.clone()), 0);
+        let ToolContent::Text { text: unlimited } = &kept.content[0];
+        assert_eq!(unlimited, &text);
+
+        let capped = limit_result_size(CallToolResult::error(text), 4);
+        assert_eq!(capped.is_error, Some(true));
+        let ToolContent::Text { text: short } = &capped.content[0];
+        assert!(short.starts_with("xx[... 6 bytes omitted"), "got: {short}");
+
+        let small = serde_json::json!({ "n": 1 });
+        let kept = limit_result_size(CallToolResult::text("ok").with_json(small.clone()), 16);
+        assert_eq!(kept.structured_content, Some(small));
+        let big = serde_json::json!({ "items": ["x".repeat(32)] });
+        let dropped = limit_result_size(CallToolResult::text("ok").with_json(big), 16);
+        assert_eq!(dropped.structured_content, None);
+    }
+
+    #[test]
+    fn test_format_completion_details_caps_docs() {
+        let long_doc = (1..=10)
+            .map(|i| format!("line {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let items = vec![
+            CompletionItem {
+                label: "push".to_string(),
+                kind: Some(CompletionItemKind::METHOD),
+                detail: Some("fn(&mut self, T)".to_string()),
+                documentation: Some(lsp_types::Documentation::String(long_doc)),
+                ..CompletionItem::default()
+            },
+            CompletionItem {
+                label: "len".to_string(),
+                kind: Some(CompletionItemKind::METHOD),
+                ..CompletionItem::default()
+            },
+        ];
+
+        let output = format_completion_details(&items);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "## Details");
+        assert_eq!(lines[1], "push [Method] fn(&mut self, T)");
+        assert_eq!(lines[2], "  line 1");
+        assert_eq!(lines[9], "  line 8");
+        assert_eq!(lines[10], "  ...");
+        assert_eq!(lines[11], "len [Method]");
+        assert_eq!(lines.len(), 12);
+    }
+
+    #[test]
+    fn test_rename_edits_outside_roots() -> Result<()> {
+        let edit_at = |text: &str| lsp_types::TextEdit {
+            range: make_range(0, 0, 0, 3),
+            new_text: text.to_string(),
+        };
+        let inside: Uri = "file:///ws/src/lib.rs".parse()?;
+        let sibling: Uri = "file:///other/src/main.rs".parse()?;
+        let mut edit = WorkspaceEdit {
+            changes: Some(
+                [
+                    (inside.clone(), vec![edit_at("new")]),
+                    (sibling, vec![edit_at("new")]),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ..WorkspaceEdit::default()
+        };
+        let roots = [PathBuf::from("/ws")];
+
+        assert_eq!(
+            outside_root_paths(&edit, &roots),
+            vec!["/other/src/main.rs"]
+        );
+        assert!(outside_root_paths(&edit, &[]).is_empty());
+
+        retain_root_edits(&mut edit, &roots);
+        let kept: Vec<String> = edit
+            .changes
+            .iter()
+            .flat_map(|changes| changes.keys().map(|uri| uri.path().to_string()))
+            .collect();
+        assert_eq!(kept, vec!["/ws/src/lib.rs"]);
+        assert!(outside_root_paths(&edit, &roots).is_empty());
+
+        let ops = WorkspaceEdit {
+            document_changes: Some(DocumentChanges::Operations(vec![
+                lsp_types::DocumentChangeOperation::Op(lsp_types::ResourceOp::Rename(
+                    lsp_types::RenameFile {
+                        old_uri: inside,
+                        new_uri: "file:///elsewhere/lib.rs".parse()?,
+                        options: None,
+                        annotation_id: None,
+                    },
+                )),
+            ])),
+            ..WorkspaceEdit::default()
+        };
+        assert_eq!(outside_root_paths(&ops, &roots), vec!["/elsewhere/lib.rs"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_health() {
+        let states = vec![
+            ("python".to_string(), None),
+            ("rust".to_string(), Some(ServerState::Ready)),
+        ];
+        assert_eq!(
+            format_health("1.0.0", 42, Some(&states)),
+            "Catenary 1.0.0\nUptime: 42s\nServers: python Busy, rust Ready"
+        );
+        assert!(format_health("1.0.0", 0, Some(&[])).ends_with("Servers: none"));
+        assert!(format_health("1.0.0", 0, None).ends_with("Servers: spawning"));
+    }
+
+    #[test]
+    fn test_init_wait_remaining() {
+        assert_eq!(init_wait_remaining(0, Duration::ZERO), None);
+        assert_eq!(
+            init_wait_remaining(5, Duration::from_secs(2)),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(init_wait_remaining(5, Duration::from_secs(5)), None);
+        assert_eq!(init_wait_remaining(5, Duration::from_secs(9)), None);
+    }
+
+    #[test]
+    fn test_format_diagnostic_explanation() {
+        let diagnostic = Diagnostic {
+            range: Range::new(make_position(3, 8), make_position(3, 14)),
+            severity: Some(DiagnosticSeverity::ERROR),
+            message: "no method named `frob` found".to_string(),
+            ..Diagnostic::default()
+        };
+        let hover = Hover {
+            contents: lsp_types::HoverContents::Scalar(lsp_types::MarkedString::String(
+                "struct Widget".to_string(),
+            )),
+            range: None,
+        };
+
+        let output = format_diagnostic_explanation(
+            std::slice::from_ref(&diagnostic),
+            Some(&hover),
+            Some(("Type definition", "src/widget.rs:10:1")),
+            &[],
+        );
+        assert!(output.starts_with("Diagnostics (1):\n  "), "got: {output}");
+        assert!(output.contains("no method named `frob` found"));
+        assert!(
+            output.contains("\nHover:\n  struct Widget"),
+            "got: {output}"
+        );
+        assert!(
+            output.contains("\nType definition:\n  src/widget.rs:10:1"),
+            "got: {output}"
+        );
+        assert!(output.ends_with("\nNo fixes available"), "got: {output}");
+
+        let bare = format_diagnostic_explanation(&[diagnostic], None, None, &[]);
+        assert!(
+            bare.contains("\nHover: none\nDefinition: none\n"),
+            "got: {bare}"
+        );
+    }
+
+    #[test]
+    fn test_format_document_highlights() {
+        let highlight = |line, character, kind| DocumentHighlight {
+            range: Range::new(
+                make_position(line, character),
+                make_position(line, character + 3),
+            ),
+            kind,
+        };
+        let highlights = vec![
+            highlight(4, 2, Some(DocumentHighlightKind::READ)),
+            highlight(1, 0, Some(DocumentHighlightKind::WRITE)),
+            highlight(6, 9, None),
+        ];
+
+        assert_eq!(
+            format_document_highlights(&highlights),
+            "L2:1 [Write]\nL5:3 [Read]\nL7:10 [Text]"
+        );
+        assert_eq!(format_document_highlights(&[]), "No highlights found");
+    }
+
+    #[test]
+    fn test_format_code_lenses_skips_untitled() {
+        let lens = |line, title: Option<&str>| CodeLens {
+            range: make_range(line, 0, line, 4),
+            command: title.map(|title| lsp_types::Command {
+                title: title.to_string(),
+                command: "run".to_string(),
+                arguments: None,
+            }),
+            data: None,
+        };
+        let lenses = vec![
+            lens(9, Some("Run test")),
+            lens(2, Some("3 references")),
+            lens(5, None),
+            lens(7, Some("  ")),
+        ];
+
+        assert_eq!(
+            format_code_lenses(&lenses),
+            "L3: 3 references\nL10: Run test"
+        );
+        assert_eq!(format_code_lenses(&[lens(1, None)]), "");
+    }
+
+    #[test]
+    fn test_format_prepare_rename() {
+        let range = make_range(4, 8, 4, 13);
+        let position = make_position(4, 10);
+
+        assert_eq!(
+            format_prepare_rename(
+                &PrepareRenameResponse::Range(range),
+                position,
+                Some("total")
+            ),
+            "Can rename `total` at L5:9-L5:14"
+        );
+        assert_eq!(
+            format_prepare_rename(&PrepareRenameResponse::Range(range), position, None),
+            "Can rename L5:9-L5:14"
+        );
+        assert_eq!(
+            format_prepare_rename(
+                &PrepareRenameResponse::RangeWithPlaceholder {
+                    range,
+                    placeholder: "total".to_string(),
+                },
+                position,
+                None,
+            ),
+            "Can rename `total` at L5:9-L5:14"
+        );
+        assert_eq!(
+            format_prepare_rename(
+                &PrepareRenameResponse::DefaultBehavior {
+                    default_behavior: true,
+                },
+                position,
+                None,
+            ),
+            "Can rename the identifier at L5:11"
+        );
+    }
+
+    #[test]
+    fn test_range_text_respects_encoding() {
+        let utf16 = lsp_types::PositionEncodingKind::UTF16;
+        let utf8 = lsp_types::PositionEncodingKind::UTF8;
+        let line = "let é = naïve;";
+
+        assert_eq!(
+            range_text(line, &make_range(0, 8, 0, 13), &utf16),
+            Some("naïve")
+        );
+        assert_eq!(
+            range_text(line, &make_range(0, 9, 0, 15), &utf8),
+            Some("naïve")
+        );
+        assert_eq!(range_text(line, &make_range(0, 4, 1, 0), &utf16), None);
+        assert_eq!(range_text(line, &make_range(0, 3, 0, 3), &utf16), None);
+    }
+
+    #[test]
+    fn test_format_workspace_diagnostics() {
+        let diag = |severity| Diagnostic {
+            range: make_range(0, 0, 0, 1),
+            severity: Some(severity),
+            message: "problem".to_string(),
+            ..Diagnostic::default()
+        };
+        let groups = vec![
+            (
+                "python".to_string(),
+                vec![(
+                    "/src/app.py".to_string(),
+                    vec![diag(DiagnosticSeverity::HINT)],
+                )],
+            ),
+            (
+                "rust".to_string(),
+                vec![
+                    (
+                        "/src/lib.rs".to_string(),
+                        vec![
+                            diag(DiagnosticSeverity::ERROR),
+                            diag(DiagnosticSeverity::ERROR),
+                            diag(DiagnosticSeverity::WARNING),
+                        ],
+                    ),
+                    (
+                        "/src/main.rs".to_string(),
+                        vec![diag(DiagnosticSeverity::WARNING)],
+                    ),
+                ],
+            ),
+        ];
+
+        assert_eq!(
+            format_workspace_diagnostics(&groups, SeverityFilter::All, 2000),
+            "[python]\n/src/app.py: 0 errors, 0 warnings, 1 other\n\
+             [rust]\n/src/lib.rs: 2 errors, 1 warnings\n/src/main.rs: 0 errors, 1 warnings\n\
+             Total: 2 errors, 2 warnings, 1 other"
+        );
+        assert_eq!(
+            format_workspace_diagnostics(&groups, SeverityFilter::Error, 2000),
+            "[rust]\n/src/lib.rs: 2 errors, 0 warnings\nTotal: 2 errors, 0 warnings"
+        );
+        assert_eq!(
+            format_workspace_diagnostics(&groups, SeverityFilter::Warning, 2),
+            "[rust]\n/src/lib.rs: 0 errors, 1 warnings\n... (truncated)\n\
+             Total: 0 errors, 2 warnings"
+        );
+        assert_eq!(
+            format_workspace_diagnostics(&[], SeverityFilter::All, 2000),
+            "No diagnostics in open documents"
+        );
+    }
 
-            if code.is_empty() {
-                format!("{}:{}: [{}] {}: {}", line, col, severity, source, d.message)
-            } else {
-                format!(
-                    "{}:{}: [{}] {}({}): {}",
-                    line, col, severity, source, code, d.message
-                )
-            }
-        })
-        .collect::<Vec<_>>()
-        .join("\n")
-}
+    #[test]
+    fn test_format_folding_ranges_sorted_by_start() {
+        let range = |start_line, end_line, kind| FoldingRange {
+            start_line,
+            start_character: None,
+            end_line,
+            end_character: None,
+            kind,
+            collapsed_text: None,
+        };
+        let ranges = vec![
+            range(9, 41, Some(FoldingRangeKind::Region)),
+            range(0, 2, Some(FoldingRangeKind::Imports)),
+            range(4, 7, Some(FoldingRangeKind::Comment)),
+            range(12, 20, None),
+        ];
 
-fn format_incoming_calls(calls: &[CallHierarchyIncomingCall]) -> String {
-    if calls.is_empty() {
-        return "No incoming calls".to_string();
+        assert_eq!(
+            format_folding_ranges(&ranges),
+            "L1-L3 imports\nL5-L8 comment\nL10-L42 region\nL13-L21"
+        );
     }
 
-    calls
-        .iter()
-        .map(|call| {
-            let path = call.from.uri.path();
-            let line = call.from.range.start.line + 1;
-            let name = &call.from.name;
-            let kind = format!("{:?}", call.from.kind);
-            format!("{name} [{kind}] {path}:{line}")
-        })
-        .collect::<Vec<_>>()
-        .join("\n")
-}
+    #[test]
+    fn test_format_inlay_hints_sorts_and_joins_parts() {
+        let hints = vec![
+            InlayHint {
+                position: make_position(11, 7),
+                label: InlayHintLabel::String(": Vec<String>".to_string()),
+                kind: Some(lsp_types::InlayHintKind::TYPE),
+                text_edits: None,
+                tooltip: None,
+                padding_left: None,
+                padding_right: None,
+                data: None,
+            },
+            InlayHint {
+                position: make_position(3, 12),
+                label: InlayHintLabel::LabelParts(vec![
+                    lsp_types::InlayHintLabelPart {
+                        value: "name".to_string(),
+                        ..lsp_types::InlayHintLabelPart::default()
+                    },
+                    lsp_types::InlayHintLabelPart {
+                        value: ": ".to_string(),
+                        ..lsp_types::InlayHintLabelPart::default()
+                    },
+                ]),
+                kind: Some(lsp_types::InlayHintKind::PARAMETER),
+                text_edits: None,
+                tooltip: None,
+                padding_left: None,
+                padding_right: Some(true),
+                data: None,
+            },
+        ];
 
-fn format_outgoing_calls(calls: &[CallHierarchyOutgoingCall]) -> String {
-    if calls.is_empty() {
-        return "No outgoing calls".to_string();
+        assert_eq!(
+            format_inlay_hints(&hints),
+            "L4:13 name:\nL12:8 : Vec<String>"
+        );
     }
 
-    calls
-        .iter()
-        .map(|call| {
-            let path = call.to.uri.path();
-            let line = call.to.range.start.line + 1;
-            let name = &call.to.name;
-            let kind = format!("{:?}", call.to.kind);
-            format!("{name} [{kind}] {path}:{line}")
+    #[test]
+    fn test_format_selection_range_innermost_first() {
+        let level = |range, parent: Option<lsp_types::SelectionRange>| lsp_types::SelectionRange {
+            range,
+            parent: parent.map(Box::new),
+        };
+        let block = level(make_range(2, 0, 6, 1), None);
+        let statement = level(make_range(4, 4, 4, 20), Some(block));
+        let duplicate = level(make_range(4, 4, 4, 20), Some(statement));
+        let word = level(make_range(4, 8, 4, 13), Some(duplicate));
+
+        assert_eq!(
+            format_selection_range(&word),
+            "L5:9-L5:14\nL5:5-L5:21\nL3:1-L7:2"
+        );
+    }
+
+    fn make_call_item(name: &str, uri: &str, line: u32) -> Result<lsp_types::CallHierarchyItem> {
+        Ok(lsp_types::CallHierarchyItem {
+            name: name.to_string(),
+            kind: SymbolKind::FUNCTION,
+            tags: None,
+            detail: None,
+            uri: uri.parse()?,
+            range: make_range(line, 0, line + 2, 1),
+            selection_range: make_range(line, 3, line, 3),
+            data: None,
         })
-        .collect::<Vec<_>>()
-        .join("\n")
-}
+    }
 
-fn format_type_hierarchy_items(items: &[TypeHierarchyItem]) -> String {
-    if items.is_empty() {
-        return "No types found".to_string();
+    #[test]
+    fn test_format_incoming_calls_groups_by_file() -> Result<()> {
+        let roots = vec![PathBuf::from("/work/app")];
+        let calls = vec![
+            CallHierarchyIncomingCall {
+                from: make_call_item("run", "file:///work/app/src/main.rs", 40)?,
+                from_ranges: vec![make_range(42, 4, 42, 9)],
+            },
+            CallHierarchyIncomingCall {
+                from: make_call_item("setup", "file:///work/app/src/lib.rs", 9)?,
+                from_ranges: vec![make_range(10, 4, 10, 9), make_range(14, 4, 14, 9)],
+            },
+            CallHierarchyIncomingCall {
+                from: make_call_item("main", "file:///work/app/src/main.rs", 2)?,
+                from_ranges: vec![make_range(3, 4, 3, 9)],
+            },
+            CallHierarchyIncomingCall {
+                from: make_call_item("vendored", "file:///opt/dep/lib.rs", 0)?,
+                from_ranges: vec![make_range(1, 4, 1, 9)],
+            },
+        ];
+
+        assert_eq!(
+            format_incoming_calls(&calls, &roots),
+            "/opt/dep/lib.rs\n  L1 vendored [Function]\n\
+             src/lib.rs\n  L10 setup [Function] (2 call sites)\n\
+             src/main.rs\n  L3 main [Function]\n  L41 run [Function]"
+        );
+        assert_eq!(format_incoming_calls(&[], &roots), "No incoming calls");
+        Ok(())
     }
 
-    items
-        .iter()
-        .map(|item| {
-            let path = item.uri.path();
-            let line = item.range.start.line + 1;
-            let kind = format!("{:?}", item.kind);
-            format!("{} [{}] {}:{}", item.name, kind, path, line)
-        })
-        .collect::<Vec<_>>()
-        .join("\n")
-}
+    #[test]
+    fn test_format_investigation() -> Result<()> {
+        let roots = vec![PathBuf::from("/work/app")];
+        let location = |uri: &str, range: Range| -> Result<Location> {
+            Ok(Location {
+                uri: uri.parse()?,
+                range,
+            })
+        };
+        let definition = location("file:///work/app/src/lib.rs", make_range(9, 4, 9, 14))?;
+        let references = vec![
+            location("file:///work/app/src/lib.rs", make_range(9, 7, 9, 12))?,
+            location("file:///work/app/src/main.rs", make_range(3, 4, 3, 9))?,
+            location("file:///work/app/src/lib.rs", make_range(20, 8, 20, 13))?,
+            location("file:///work/app/src/main.rs", make_range(7, 4, 7, 9))?,
+        ];
+        let context = vec!["app [Module]".to_string(), "Greeter [Class]".to_string()];
+        let preview = vec![
+            (9, "    fn greet(&self) {".to_string()),
+            (10, "        println!(\"hi\");".to_string()),
+            (11, "    }".to_string()),
+        ];
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use anyhow::Context;
-    use lsp_types::{
-        DocumentSymbol, Range, SymbolInformation, SymbolKind, WorkspaceSymbolResponse,
-    };
+        let report = Investigation {
+            symbol: "greet",
+            definition: &definition,
+            context: &context,
+            preview: &preview,
+            references: &references,
+        };
+        assert_eq!(
+            format_investigation(&report, &roots),
+            "Symbol: greet\n\
+             Definition: src/lib.rs:10:5\n\
+             Context: app [Module] > Greeter [Class]\n\
+             \x20 10 |     fn greet(&self) {\n\
+             \x20 11 |         println!(\"hi\");\n\
+             \x20 12 |     }\n\
+             References: 3 in 2 files\n\
+             \x20 src/lib.rs: 1\n\
+             \x20 src/main.rs: 2"
+        );
 
-    fn make_position(line: u32, character: u32) -> Position {
-        Position { line, character }
+        let dep = location("file:///opt/dep/greet.rs", make_range(0, 0, 0, 5))?;
+        let report = Investigation {
+            symbol: "greet",
+            definition: &dep,
+            context: &[],
+            preview: &[],
+            references: &[],
+        };
+        assert_eq!(
+            format_investigation(&report, &roots),
+            "Symbol: greet\nDefinition: /opt/dep/greet.rs:1:1 [dep]\nReferences: none"
+        );
+        Ok(())
     }
 
-    fn make_range(start_line: u32, start_char: u32, end_line: u32, end_char: u32) -> Range {
-        Range {
-            start: make_position(start_line, start_char),
-            end: make_position(end_line, end_char),
-        }
+    #[test]
+    fn test_enclosing_symbol_names_stops_at_symbol() {
+        let mut method = make_document_symbol("greet", SymbolKind::METHOD, make_range(4, 4, 6, 5));
+        method.selection_range = make_range(4, 7, 4, 12);
+        let mut class = make_document_symbol("Greeter", SymbolKind::CLASS, make_range(2, 0, 7, 1));
+        class.selection_range = make_range(2, 6, 2, 13);
+        class.children = Some(vec![method]);
+        let mut module = make_document_symbol("app", SymbolKind::MODULE, make_range(0, 0, 9, 0));
+        module.selection_range = make_range(0, 4, 0, 7);
+        module.children = Some(vec![class]);
+        let symbols = DocumentSymbolResponse::Nested(vec![module]);
+
+        assert_eq!(
+            enclosing_symbol_names(&symbols, make_position(4, 8)),
+            vec!["app [Module]", "Greeter [Class]"]
+        );
+        assert_eq!(
+            enclosing_symbol_names(&symbols, make_position(2, 0)),
+            vec!["app [Module]"]
+        );
+        assert!(enclosing_symbol_names(&symbols, make_position(12, 0)).is_empty());
     }
 
-    fn make_document_symbol(name: &str, kind: SymbolKind, range: Range) -> DocumentSymbol {
-        #[allow(
-            deprecated,
-            reason = "LSP spec uses deprecated fields in some versions"
-        )]
-        DocumentSymbol {
-            name: name.to_string(),
-            detail: None,
-            kind,
-            tags: None,
-            deprecated: None,
-            range,
-            selection_range: range,
-            children: None,
-        }
+    #[test]
+    fn test_format_type_hierarchy_items_groups_by_file() -> Result<()> {
+        let roots = vec![PathBuf::from("/work")];
+        let item = |name: &str, uri: &str, line| -> Result<TypeHierarchyItem> {
+            Ok(TypeHierarchyItem {
+                name: name.to_string(),
+                kind: SymbolKind::CLASS,
+                tags: None,
+                detail: None,
+                uri: uri.parse()?,
+                range: make_range(line, 0, line + 5, 1),
+                selection_range: make_range(line, 6, line, 6),
+                data: None,
+            })
+        };
+        let items = vec![
+            item("Square", "file:///work/shapes.py", 20)?,
+            item("Circle", "file:///work/shapes.py", 4)?,
+        ];
+
+        assert_eq!(
+            format_type_hierarchy_items(&items, &roots),
+            "shapes.py\n  L5 Circle [Class]\n  L21 Square [Class]"
+        );
+        Ok(())
     }
 
     fn make_symbol_info(
@@ -2250,6 +6711,124 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_merged_search_drops_symbol_lines() -> Result<()> {
+        let symbol = lsp_types::WorkspaceSymbol {
+            name: "Widget".to_string(),
+            kind: lsp_types::SymbolKind::STRUCT,
+            tags: None,
+            container_name: None,
+            location: lsp_types::OneOf::Left(Location {
+                uri: "file:///ws/src/lib.rs".parse()?,
+                range: Range::default(),
+            }),
+            data: None,
+        };
+        let covered = symbol_definition_lines(&[WorkspaceSymbolResponse::Nested(vec![symbol])]);
+        assert!(covered.contains(&("/ws/src/lib.rs".to_string(), 1)));
+
+        let rg_output = "/ws/src/lib.rs:1:pub struct Widget;\n\
+                         /ws/src/lib.rs:9:// build a Widget\n\
+                         /ws/README.md:3:The Widget type\n";
+        let roots = [PathBuf::from("/ws")];
+        let heatmap = format_heatmap(rg_output, &roots, &covered);
+        assert_eq!(
+            heatmap,
+            "README.md: 1 match (line 3)\nsrc/lib.rs: 1 match (line 9)"
+        );
+
+        let all = format_heatmap(rg_output, &roots, &std::collections::HashSet::new());
+        assert!(
+            all.contains("src/lib.rs: 2 matches (lines 1-9)"),
+            "got: {all}"
+        );
+
+        let output = combine_search_sections(
+            &["Widget [Struct] /ws/src/lib.rs:1:1".to_string()],
+            &heatmap,
+            "Other text matches",
+        );
+        assert!(output.starts_with("## Symbols\nWidget"), "got: {output}");
+        assert!(
+            output.contains("\n\n## Other text matches\nREADME.md"),
+            "got: {output}"
+        );
+        assert_eq!(
+            combine_search_sections(&[], "", "File matches"),
+            "No results found"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_document_symbols_lead_search_results() -> Result<()> {
+        let uri: Uri = "file:///ws/src/lib.rs".parse()?;
+        let mut widget = make_document_symbol("Widget", SymbolKind::STRUCT, make_range(4, 0, 9, 1));
+        widget.children = Some(vec![
+            make_document_symbol("new_widget", SymbolKind::METHOD, make_range(6, 4, 8, 5)),
+            make_document_symbol("size", SymbolKind::FIELD, make_range(5, 4, 5, 14)),
+        ]);
+        let response = DocumentSymbolResponse::Nested(vec![widget]);
+
+        let open = matching_document_symbols(&response, "WIDGET", &uri, 64);
+        let names: Vec<(&str, Option<&str>)> = open
+            .iter()
+            .map(|s| (s.name.as_str(), s.container_name.as_deref()))
+            .collect();
+        assert_eq!(names, [("Widget", None), ("new_widget", Some("Widget"))]);
+        assert_eq!(
+            matching_document_symbols(&response, "widget", &uri, 1).len(),
+            1
+        );
+
+        // The server reports the declaration's full range, starting on an
+        // attribute line above the name the open symbol points at.
+        let mut declared =
+            make_symbol_info("Widget", SymbolKind::STRUCT, "file:///ws/src/lib.rs", 3)?;
+        declared.location.range = make_range(3, 0, 9, 1);
+        let workspace = WorkspaceSymbolResponse::Flat(vec![
+            declared,
+            make_symbol_info("WidgetKind", SymbolKind::ENUM, "file:///ws/src/kind.rs", 0)?,
+        ]);
+        let lines: Vec<String> = prepend_open_symbols(open, vec![workspace])
+            .iter()
+            .filter_map(format_workspace_symbols)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "Widget [Struct] /ws/src/lib.rs:5:1\nnew_widget [Method] /ws/src/lib.rs:7:5",
+                "WidgetKind [Enum] /ws/src/kind.rs:1:1",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_completion_cursor_extracts_prefix() {
+        let utf16 = lsp_types::PositionEncodingKind::UTF16;
+        let cursor = CompletionCursor::new("    let x = foo_ba", 18, &utf16);
+        assert_eq!(cursor.prefix, "foo_ba");
+        assert_eq!(cursor.preceding, Some('a'));
+        assert_eq!(cursor.header(), "Line:     let x = foo_ba\nPrefix: foo_ba");
+
+        let cursor = CompletionCursor::new("value.", 6, &utf16);
+        assert_eq!(cursor.prefix, "");
+        assert_eq!(cursor.preceding, Some('.'));
+        assert_eq!(cursor.header(), "Line: value.");
+
+        // "é" is one UTF-16 unit but two UTF-8 bytes
+        let cursor = CompletionCursor::new("é ab cd", 4, &utf16);
+        assert_eq!(cursor.prefix, "ab");
+        let utf8 = lsp_types::PositionEncodingKind::UTF8;
+        let cursor = CompletionCursor::new("é ab cd", 4, &utf8);
+        assert_eq!(cursor.prefix, "a");
+
+        // Positions past the end clamp to the line end
+        let cursor = CompletionCursor::new("abc", 99, &utf16);
+        assert_eq!(cursor.prefix, "abc");
+    }
+
     #[test]
     fn test_find_symbol_exact_match_flat() -> Result<()> {
         let symbols = vec![
@@ -2260,7 +6839,7 @@ mod tests {
         let response = DocumentSymbolResponse::Flat(symbols);
 
         let result =
-            find_symbol_in_document_response(&response, "bar").context("symbol not found")?;
+            find_symbol_in_document_response(&response, "bar", 64).context("symbol not found")?;
         assert_eq!(result.line, 10);
         Ok(())
     }
@@ -2274,8 +6853,8 @@ mod tests {
         let response = DocumentSymbolResponse::Flat(symbols);
 
         // Partial match "request"
-        let result =
-            find_symbol_in_document_response(&response, "request").context("symbol not found")?;
+        let result = find_symbol_in_document_response(&response, "request", 64)
+            .context("symbol not found")?;
         assert_eq!(result.line, 5);
         Ok(())
     }
@@ -2290,7 +6869,7 @@ mod tests {
 
         // Exact match "foo" should be preferred over partial match "foobar"
         let result =
-            find_symbol_in_document_response(&response, "foo").context("symbol not found")?;
+            find_symbol_in_document_response(&response, "foo", 64).context("symbol not found")?;
         assert_eq!(result.line, 10);
         Ok(())
     }
@@ -2305,7 +6884,7 @@ mod tests {
 
         let response = DocumentSymbolResponse::Nested(vec![outer_symbol]);
 
-        let result = find_symbol_in_document_response(&response, "inner_method")
+        let result = find_symbol_in_document_response(&response, "inner_method", 64)
             .context("symbol not found")?;
         assert_eq!(result.line, 5);
         Ok(())
@@ -2325,12 +6904,61 @@ mod tests {
         let response = DocumentSymbolResponse::Nested(vec![outer_symbol]);
 
         // Partial match should find inner_method
-        let result =
-            find_symbol_in_document_response(&response, "request").context("symbol not found")?;
+        let result = find_symbol_in_document_response(&response, "request", 64)
+            .context("symbol not found")?;
         assert_eq!(result.line, 15);
         Ok(())
     }
 
+    #[test]
+    fn test_symbol_candidates_list_all_exact_matches() -> Result<()> {
+        let uri: Uri = "file:///test.rs".parse()?;
+        let mut first = make_document_symbol("parse", SymbolKind::FUNCTION, make_range(0, 0, 5, 0));
+        first.children = Some(vec![make_document_symbol(
+            "count",
+            SymbolKind::VARIABLE,
+            make_range(2, 8, 2, 13),
+        )]);
+        let mut second =
+            make_document_symbol("render", SymbolKind::FUNCTION, make_range(7, 0, 12, 0));
+        second.children = Some(vec![make_document_symbol(
+            "count",
+            SymbolKind::VARIABLE,
+            make_range(9, 8, 9, 13),
+        )]);
+        let counter = make_document_symbol("counter", SymbolKind::STRUCT, make_range(14, 0, 16, 0));
+        let response = DocumentSymbolResponse::Nested(vec![first, second, counter]);
+
+        let candidates = document_symbol_candidates(&response, "count", &uri, 64);
+        assert_eq!(candidates.len(), 2, "partial match 'counter' is ignored");
+        assert_eq!(candidates[0].container.as_deref(), Some("parse"));
+        assert_eq!(candidates[1].position, make_position(9, 8));
+
+        let output = format_symbol_candidates("count", &candidates);
+        assert!(output.starts_with("'count' matches 2 symbols."));
+        assert!(output.contains("count [Variable] in render \u{2014} /test.rs line 9 character 8"));
+
+        // Only partial matches: all of them are candidates
+        let partial = document_symbol_candidates(&response, "coun", &uri, 64);
+        assert_eq!(partial.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_workspace_symbol_candidates() -> Result<()> {
+        let response = WorkspaceSymbolResponse::Flat(vec![
+            make_symbol_info("Config", SymbolKind::STRUCT, "file:///a.rs", 3)?,
+            make_symbol_info("Config", SymbolKind::STRUCT, "file:///b.rs", 8)?,
+            make_symbol_info("load", SymbolKind::FUNCTION, "file:///a.rs", 10)?,
+        ]);
+
+        let candidates = workspace_symbol_candidates(&response, "Config");
+        let paths: Vec<_> = candidates.iter().map(|c| c.path.clone()).collect();
+        assert_eq!(paths, vec![PathBuf::from("/a.rs"), PathBuf::from("/b.rs")]);
+        assert_eq!(workspace_symbol_candidates(&response, "load").len(), 1);
+        Ok(())
+    }
+
     #[test]
     fn test_find_symbol_not_found() -> Result<()> {
         let symbols = vec![make_symbol_info(
@@ -2341,7 +6969,7 @@ mod tests {
         )?];
         let response = DocumentSymbolResponse::Flat(symbols);
 
-        let result = find_symbol_in_document_response(&response, "nonexistent");
+        let result = find_symbol_in_document_response(&response, "nonexistent", 64);
         assert!(result.is_none());
         Ok(())
     }
@@ -2380,6 +7008,403 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_format_document_symbols_qualified() {
+        let method = make_document_symbol("method", SymbolKind::METHOD, make_range(3, 4, 5, 5));
+        let mut inner = make_document_symbol("Inner", SymbolKind::CLASS, make_range(2, 2, 6, 3));
+        inner.children = Some(vec![method]);
+        let mut outer = make_document_symbol("MyClass", SymbolKind::CLASS, make_range(0, 0, 8, 1));
+        outer.children = Some(vec![inner]);
+        let other = make_document_symbol("helper", SymbolKind::FUNCTION, make_range(10, 0, 12, 1));
+        let response = DocumentSymbolResponse::Nested(vec![outer, other]);
+
+        assert_eq!(
+            format_document_symbols(&response, DetailLevel::Full, 64, true),
+            "MyClass [Class] line 1\n\
+             MyClass::Inner [Class] line 3\n\
+             MyClass::Inner::method [Method] line 4\n\
+             helper [Function] line 11"
+        );
+        // Default output is still the indented tree
+        assert!(
+            format_document_symbols(&response, DetailLevel::Full, 64, false)
+                .contains("    method [Method]")
+        );
+    }
+
+    #[test]
+    fn test_format_document_symbols_detail_level() {
+        let field = make_document_symbol("count", SymbolKind::FIELD, make_range(1, 4, 1, 14));
+        let method = make_document_symbol("run", SymbolKind::METHOD, make_range(2, 4, 4, 5));
+        let mut class = make_document_symbol("Worker", SymbolKind::CLASS, make_range(0, 0, 5, 1));
+        class.children = Some(vec![field, method]);
+        // An `impl`-style container that is not itself structural
+        let inner = make_document_symbol("helper", SymbolKind::FUNCTION, make_range(8, 4, 9, 5));
+        let mut object =
+            make_document_symbol("impl Worker", SymbolKind::OBJECT, make_range(7, 0, 10, 1));
+        object.children = Some(vec![inner]);
+        let constant =
+            make_document_symbol("LIMIT", SymbolKind::CONSTANT, make_range(12, 0, 12, 20));
+        let response = DocumentSymbolResponse::Nested(vec![class, object, constant]);
+
+        assert_eq!(
+            format_document_symbols(&response, DetailLevel::Outline, 64, false),
+            "Worker [Class] line 1"
+        );
+        assert_eq!(
+            format_document_symbols(&response, DetailLevel::Signatures, 64, true),
+            "Worker [Class] line 1\n\
+             Worker::run [Method] line 3\n\
+             impl Worker [Object] line 8\n\
+             impl Worker::helper [Function] line 9"
+        );
+        let full = format_document_symbols(&response, DetailLevel::Full, 64, false);
+        assert!(full.contains("count [Field]"));
+        assert!(full.contains("LIMIT [Constant]"));
+    }
+
+    fn make_completion_item(label: &str, kind: CompletionItemKind) -> CompletionItem {
+        CompletionItem {
+            label: label.to_string(),
+            kind: Some(kind),
+            ..CompletionItem::default()
+        }
+    }
+
+    #[test]
+    fn test_format_completion_filter_and_group() {
+        let response = CompletionResponse::Array(vec![
+            make_completion_item("len", CompletionItemKind::METHOD),
+            make_completion_item("capacity", CompletionItemKind::FIELD),
+            make_completion_item("push", CompletionItemKind::METHOD),
+            make_completion_item("match", CompletionItemKind::KEYWORD),
+        ]);
+
+        let flat = format_completion(&response, None, false);
+        assert_eq!(
+            flat,
+            "len [Method]\ncapacity [Field]\npush [Method]\nmatch [Keyword]"
+        );
+
+        let kinds = vec!["method".to_string(), "FIELD".to_string()];
+        let grouped = format_completion(&response, Some(&kinds), true);
+        assert_eq!(
+            grouped,
+            "## Method (2)\nlen\npush\n\n## Field (1)\ncapacity"
+        );
+
+        let none = format_completion(&response, Some(&["struct".to_string()]), false);
+        assert_eq!(none, "No completions available");
+    }
+
+    #[test]
+    fn test_format_completion_caps_items() {
+        let items = (0..COMPLETION_MAX_ITEMS + 5)
+            .map(|i| make_completion_item(&format!("item{i}"), CompletionItemKind::VARIABLE))
+            .collect();
+        let output = format_completion(&CompletionResponse::Array(items), None, false);
+        assert_eq!(output.lines().count(), COMPLETION_MAX_ITEMS + 1);
+        assert!(output.ends_with("... and 5 more"));
+    }
+
+    #[test]
+    fn test_format_diagnostic_fixes() -> Result<()> {
+        let uri: Uri = "file:///src/main.rs".parse()?;
+        let range = Range::new(make_position(2, 4), make_position(2, 9));
+        let diagnostic = Diagnostic {
+            range,
+            severity: Some(DiagnosticSeverity::ERROR),
+            source: Some("rustc".to_string()),
+            message: "unused variable".to_string(),
+            ..Diagnostic::default()
+        };
+        let text_edit = lsp_types::TextEdit::new(range, "_x".to_string());
+        let fix = lsp_types::CodeAction {
+            title: "Prefix with underscore".to_string(),
+            kind: Some(lsp_types::CodeActionKind::QUICKFIX),
+            is_preferred: Some(true),
+            edit: Some(WorkspaceEdit::new(std::collections::HashMap::from([(
+                uri,
+                vec![text_edit.clone(), text_edit],
+            )]))),
+            ..lsp_types::CodeAction::default()
+        };
+        let unresolved = lsp_types::CodeAction {
+            title: "Remove binding".to_string(),
+            ..lsp_types::CodeAction::default()
+        };
+        let actions = vec![
+            CodeActionOrCommand::CodeAction(fix),
+            CodeActionOrCommand::CodeAction(unresolved),
+        ];
+
+        let output = format_diagnostic_fixes(std::slice::from_ref(&diagnostic), &actions);
+        assert_eq!(
+            output,
+            "Diagnostics (1):\n  3:5: [error] rustc: unused variable\nFixes (2):\n\
+             1. Prefix with underscore (quickfix) [preferred] \u{2014} 2 edit(s) in 1 file(s)\n\
+             2. Remove binding \u{2014} no edits"
+        );
+
+        let none = format_diagnostic_fixes(&[diagnostic], &[]);
+        assert!(none.ends_with("No fixes available"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagnostics_json() {
+        let diagnostic = Diagnostic {
+            range: make_range(2, 4, 2, 9),
+            severity: Some(DiagnosticSeverity::WARNING),
+            code: Some(lsp_types::NumberOrString::Number(42)),
+            message: "unused variable".to_string(),
+            ..Diagnostic::default()
+        };
+        assert_eq!(
+            diagnostics_json(Path::new("/src/main.rs"), &[diagnostic]),
+            serde_json::json!({
+                "file": "/src/main.rs",
+                "diagnostics": [{
+                    "line": 3,
+                    "character": 5,
+                    "severity": "warning",
+                    "source": null,
+                    "code": "42",
+                    "message": "unused variable",
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_merge_workspace_edits() -> Result<()> {
+        let main: Uri = "file:///src/main.rs".parse()?;
+        let lib: Uri = "file:///src/lib.rs".parse()?;
+        let edit = |uri: &Uri, line: u32| {
+            let range = Range::new(make_position(line, 0), make_position(line, 1));
+            WorkspaceEdit::new(std::collections::HashMap::from([(
+                uri.clone(),
+                vec![lsp_types::TextEdit::new(range, String::new())],
+            )]))
+        };
+
+        let single = merge_workspace_edits(vec![edit(&main, 1)]);
+        assert_eq!(count_workspace_edits(&single), (1, 1));
+
+        let merged = merge_workspace_edits(vec![edit(&main, 1), edit(&main, 2), edit(&lib, 3)]);
+        assert_eq!(count_workspace_edits(&merged), (3, 2));
+        Ok(())
+    }
+
+    /// Build a single chain of nested symbols `level0 > level1 > ...`.
+    fn make_symbol_chain(depth: usize) -> Vec<DocumentSymbol> {
+        let mut current: Option<DocumentSymbol> = None;
+        for level in (0..depth).rev() {
+            let line = u32::try_from(level).unwrap_or(u32::MAX);
+            let mut sym = make_document_symbol(
+                &format!("level{level}"),
+                SymbolKind::MODULE,
+                make_range(line, 0, line, 10),
+            );
+            sym.children = current.take().map(|child| vec![child]);
+            current = Some(sym);
+        }
+        current.into_iter().collect()
+    }
+
+    #[test]
+    fn test_nested_symbols_depth_limit() {
+        let symbols = make_symbol_chain(10);
+        let response = DocumentSymbolResponse::Nested(symbols.clone());
+
+        // Within the limit the deepest symbol is reachable
+        assert!(find_symbol_in_document_response(&response, "level9", 10).is_some());
+        // Beyond the limit the search stops descending
+        assert!(find_symbol_in_document_response(&response, "level9", 3).is_none());
+        assert!(find_symbol_in_document_response(&response, "level2", 3).is_some());
+
+        let output = format_nested_symbols(&symbols, 0, DetailLevel::Full, 3);
+        assert!(output.contains("level2 [Module]"));
+        assert!(!output.contains("level3"));
+        assert!(output.contains("nesting deeper than 3 levels omitted"));
+
+        let full = format_nested_symbols(&symbols, 0, DetailLevel::Full, 64);
+        assert!(full.contains("level9"));
+        assert!(!full.contains("omitted"));
+    }
+
+    #[test]
+    fn test_is_dependency_path() {
+        let roots = vec![PathBuf::from("/work/project")];
+        assert!(!is_dependency_path(
+            Path::new("/work/project/src/main.rs"),
+            &roots
+        ));
+        assert!(is_dependency_path(
+            Path::new("/home/user/.cargo/registry/src/serde/lib.rs"),
+            &roots
+        ));
+        // Sibling directory sharing a string prefix is still outside the root
+        assert!(is_dependency_path(
+            Path::new("/work/project-old/src/main.rs"),
+            &roots
+        ));
+        assert!(!is_dependency_path(Path::new("/anywhere.rs"), &[]));
+    }
+
+    #[test]
+    fn test_root_relative_path() {
+        let roots = vec![PathBuf::from("/work/project")];
+        assert_eq!(
+            root_relative_path("/work/project/src/main.rs", &roots),
+            "src/main.rs"
+        );
+        // Sibling directory sharing a string prefix is left absolute
+        assert_eq!(
+            root_relative_path("/work/project-old/src/main.rs", &roots),
+            "/work/project-old/src/main.rs"
+        );
+    }
+
+    #[test]
+    fn test_format_locations_marks_dependencies() -> Result<()> {
+        let local = Location {
+            uri: "file:///work/project/src/lib.rs".parse()?,
+            range: make_range(4, 0, 4, 5),
+        };
+        let dep = Location {
+            uri: "file:///usr/lib/node_modules/pkg/index.js".parse()?,
+            range: make_range(9, 2, 9, 8),
+        };
+        let roots = vec![PathBuf::from("/work/project")];
+
+        let output = format_locations_with_definition(
+            &[dep.clone(), local.clone()],
+            Some(&dep),
+            &roots,
+            ReferencePage::ALL,
+            false,
+        );
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "/usr/lib/node_modules/pkg/index.js:10:3 [def] [dep]",
+                "/work/project/src/lib.rs:5:1",
+            ]
+        );
+
+        let json = references_json(
+            &[dep.clone(), local.clone()],
+            Some(&dep),
+            &roots,
+            ReferencePage::ALL,
+        );
+        assert_eq!(
+            json,
+            serde_json::json!({ "references": [
+                { "file": "/usr/lib/node_modules/pkg/index.js", "line": 10, "character": 3,
+                  "definition": true, "dependency": true },
+                { "file": "/work/project/src/lib.rs", "line": 5, "character": 1,
+                  "definition": false, "dependency": false },
+            ], "total": 1 })
+        );
+
+        let response = GotoDefinitionResponse::Array(vec![local, dep]);
+        assert_eq!(
+            format_definition_response(&response, &roots),
+            "/work/project/src/lib.rs:5:1\n/usr/lib/node_modules/pkg/index.js:10:3 [dep]"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_locations_paginates_after_definition() -> Result<()> {
+        let uri: Uri = "file:///work/project/src/lib.rs".parse()?;
+        let locations: Vec<Location> = (0..5)
+            .map(|line| Location {
+                uri: uri.clone(),
+                range: make_range(line, 0, line, 3),
+            })
+            .collect();
+        let definition = locations[0].clone();
+        let roots = vec![PathBuf::from("/work/project")];
+        let page = ReferencePage {
+            offset: 2,
+            max_results: 1,
+        };
+
+        let output =
+            format_locations_with_definition(&locations, Some(&definition), &roots, page, false);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "/work/project/src/lib.rs:1:1 [def]",
+                "/work/project/src/lib.rs:4:1",
+                "[showing 1 of 4 references from offset 2; pass offset=3 for more]",
+            ]
+        );
+
+        let json = references_json(&locations, Some(&definition), &roots, page);
+        assert_eq!(json["total"], 4);
+        assert_eq!(json["references"].as_array().map(Vec::len), Some(2));
+
+        let last = ReferencePage {
+            offset: 3,
+            max_results: 10,
+        };
+        let output =
+            format_locations_with_definition(&locations, Some(&definition), &roots, last, false);
+        assert_eq!(
+            output.lines().last(),
+            Some("[showing 1 of 4 references from offset 3]")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_locations_grouped_by_file() -> Result<()> {
+        let at = |uri: &str, line: u32, character: u32| -> Result<Location> {
+            Ok(Location {
+                uri: uri.parse()?,
+                range: make_range(line, character, line, character + 3),
+            })
+        };
+        let definition = at("file:///work/project/src/z.rs", 9, 4)?;
+        let locations = vec![
+            at("file:///work/project/src/a.rs", 2, 0)?,
+            at("file:///work/project/src/z.rs", 20, 8)?,
+            definition.clone(),
+            at("file:///work/project/src/a.rs", 7, 12)?,
+            at("file:///usr/lib/dep/lib.rs", 0, 0)?,
+        ];
+        let roots = vec![PathBuf::from("/work/project")];
+
+        let output = format_locations_with_definition(
+            &locations,
+            Some(&definition),
+            &roots,
+            ReferencePage::ALL,
+            true,
+        );
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "/work/project/src/z.rs (2 references)",
+                "  L10:5 [def]",
+                "  L21:9",
+                "/usr/lib/dep/lib.rs (1 reference) [dep]",
+                "  L1:1",
+                "/work/project/src/a.rs (2 references)",
+                "  L3:1",
+                "  L8:13",
+            ]
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_find_references_input_validation() -> Result<()> {
         // Test that FindReferencesInput can be deserialized with symbol
@@ -2392,6 +7417,9 @@ mod tests {
         assert!(input.line.is_none());
         assert!(input.character.is_none());
         assert!(input.include_declaration); // default true
+        assert_eq!(input.max_results, 100);
+        assert_eq!(input.offset, 0);
+        assert!(!input.grouped);
 
         // Test with position
         let json = serde_json::json!({
@@ -9,7 +9,14 @@ mod file_tools;
 mod handler;
 /// Path validation and security for file I/O tools.
 pub mod path_security;
+/// In-memory symbol cache for repeated `codebase_map` calls.
+mod symbol_cache;
+/// Persistent symbol index for cross-session `search`.
+mod symbol_index;
 
-pub use document_manager::{DocumentManager, DocumentNotification};
+pub use document_manager::{
+    DocumentChange, DocumentManager, DocumentNotification, LineEnding, check_file_size, lsp_line,
+};
 pub use handler::LspBridgeHandler;
 pub use path_security::PathValidator;
+pub use symbol_index::SymbolIndex;
@@ -7,7 +7,7 @@
 //! and that Catenary's own configuration files cannot be modified by agents.
 
 use anyhow::{Result, anyhow};
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use tracing::debug;
 
 /// Validates that file paths are within workspace roots and protects
@@ -22,10 +22,14 @@ pub struct PathValidator {
 impl PathValidator {
     /// Creates a new `PathValidator` from workspace roots.
     ///
+    /// Roots are canonicalized so that a root reached through a symlink
+    /// still contains the canonical paths it is compared against.
+    ///
     /// Automatically discovers Catenary config file paths to protect:
     /// - `~/.config/catenary/config.toml` (user config)
     /// - `.catenary.toml` files found by searching upward from each root
     pub fn new(roots: Vec<PathBuf>) -> Self {
+        let roots = Self::canonicalize_roots(roots);
         let protected_configs = Self::discover_config_paths(&roots);
         debug!(
             "PathValidator initialized with {} root(s), {} protected config(s)",
@@ -46,6 +50,7 @@ impl PathValidator {
 
     /// Updates the workspace roots and re-discovers protected config paths.
     pub fn update_roots(&mut self, roots: Vec<PathBuf>) {
+        let roots = Self::canonicalize_roots(roots);
         self.protected_configs = Self::discover_config_paths(&roots);
         debug!(
             "PathValidator updated: {} root(s), {} protected config(s)",
@@ -58,7 +63,8 @@ impl PathValidator {
     /// Validates a path for read access.
     ///
     /// Canonicalizes the path (resolving symlinks) and checks that the
-    /// canonical path is within at least one workspace root.
+    /// canonical path is within at least one workspace root, so a symlink
+    /// inside a root cannot reach files outside it.
     ///
     /// # Errors
     ///
@@ -71,10 +77,7 @@ impl PathValidator {
             .map_err(|e| anyhow!("Path does not exist: {}: {e}", path.display()))?;
 
         if !self.is_within_roots(&canonical) {
-            return Err(anyhow!(
-                "Path is outside workspace roots: {}",
-                path.display()
-            ));
+            return Err(self.outside_roots_error(path));
         }
 
         Ok(canonical)
@@ -87,12 +90,16 @@ impl PathValidator {
     ///   `~/.config/catenary/config.toml`).
     ///
     /// For new files that don't exist yet, validates the parent directory
-    /// is within workspace roots instead.
+    /// is within workspace roots instead. The part of a new path below its
+    /// existing ancestor may not contain `..`, and a dangling symlink is
+    /// rejected, since writing through it would create its target wherever
+    /// it points.
     ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - The path (or its parent for new files) is outside workspace roots.
+    /// - The path is a dangling symlink, or a new path containing `..`.
     /// - The path resolves to a Catenary configuration file.
     pub fn validate_write(&self, path: &Path) -> Result<PathBuf> {
         // For new files, the path itself won't exist yet. Check parent instead.
@@ -102,13 +109,15 @@ impl PathValidator {
                 .map_err(|e| anyhow!("Cannot resolve path: {}: {e}", path.display()))?;
 
             if !self.is_within_roots(&canonical) {
-                return Err(anyhow!(
-                    "Path is outside workspace roots: {}",
-                    path.display()
-                ));
+                return Err(self.outside_roots_error(path));
             }
 
             canonical
+        } else if path.symlink_metadata().is_ok() {
+            return Err(anyhow!(
+                "Cannot write through a dangling symlink: {}",
+                path.display()
+            ));
         } else {
             // New file: validate parent directory exists and is within roots
             let parent = path
@@ -138,6 +147,18 @@ impl PathValidator {
                 // If strip_prefix fails, use just the filename
                 path.file_name().map_or_else(|| Path::new(""), Path::new)
             });
+
+            // `..` below the ancestor would climb back out once the missing
+            // directories are created.
+            if remaining
+                .components()
+                .any(|c| matches!(c, Component::ParentDir))
+            {
+                return Err(anyhow!(
+                    "Path is outside workspace roots: {}",
+                    path.display()
+                ));
+            }
             canonical_ancestor.join(remaining)
         };
 
@@ -156,6 +177,41 @@ impl PathValidator {
         self.roots.iter().any(|root| canonical.starts_with(root))
     }
 
+    /// Builds the error for a path whose canonical form is outside every
+    /// root, naming the symlink escape when the path as given was inside.
+    /// The resolved target is not included, so the error does not reveal
+    /// anything about the filesystem outside the roots.
+    fn outside_roots_error(&self, path: &Path) -> anyhow::Error {
+        // `.` and `..` resolved textually, without following symlinks.
+        let mut lexical = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    lexical.pop();
+                }
+                other => lexical.push(other),
+            }
+        }
+        if self.is_within_roots(&lexical) {
+            anyhow!(
+                "Path is a symlink that resolves outside workspace roots: {}",
+                path.display()
+            )
+        } else {
+            anyhow!("Path is outside workspace roots: {}", path.display())
+        }
+    }
+
+    /// Canonicalizes each root, keeping roots that cannot be resolved as
+    /// given.
+    fn canonicalize_roots(roots: Vec<PathBuf>) -> Vec<PathBuf> {
+        roots
+            .into_iter()
+            .map(|root| root.canonicalize().unwrap_or(root))
+            .collect()
+    }
+
     /// Checks if a canonical path matches any protected config file.
     fn is_config_file(&self, canonical: &Path) -> bool {
         self.protected_configs
@@ -168,11 +224,10 @@ impl PathValidator {
         let mut paths = Vec::new();
 
         // User config: ~/.config/catenary/config.toml
-        if let Some(config_dir) = dirs::config_dir() {
-            let user_config = config_dir.join("catenary").join("config.toml");
-            if let Ok(canonical) = user_config.canonicalize() {
-                paths.push(canonical);
-            }
+        if let Some(user_config) = crate::config::user_config_path()
+            && let Ok(canonical) = user_config.canonicalize()
+        {
+            paths.push(canonical);
         }
 
         // Project-local config: .catenary.toml (search upward from each root)
@@ -432,6 +487,87 @@ mod tests {
         // canonicalize() will resolve the symlink to the outside path
         let result = validator.validate_read(&link_path);
         assert!(result.is_err());
+        let err = result
+            .err()
+            .ok_or_else(|| anyhow!("Expected error"))?
+            .to_string();
+        assert!(
+            err.contains("symlink that resolves outside"),
+            "Error should name the symlink escape: {err}"
+        );
+        assert!(validator.validate_write(&link_path).is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_dir_outside_root_fails() -> Result<()> {
+        use std::os::unix::fs as unix_fs;
+
+        let (dir, validator) = setup_workspace()?;
+        let outside_dir = TempDir::new()?;
+        fs::write(outside_dir.path().join("secret.txt"), "secret")?;
+        unix_fs::symlink(outside_dir.path(), dir.path().join("vendor"))?;
+
+        let secret = dir.path().join("vendor/secret.txt");
+        assert!(validator.validate_read(&secret).is_err());
+        assert!(validator.validate_write(&secret).is_err());
+        assert!(
+            validator
+                .validate_write(&dir.path().join("vendor/new/file.rs"))
+                .is_err()
+        );
+        assert!(!outside_dir.path().join("new").exists());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_through_dangling_symlink_fails() -> Result<()> {
+        use std::os::unix::fs as unix_fs;
+
+        let (dir, validator) = setup_workspace()?;
+        let outside_dir = TempDir::new()?;
+        let link_path = dir.path().join("planted.rs");
+        unix_fs::symlink(outside_dir.path().join("created.rs"), &link_path)?;
+
+        let result = validator.validate_write(&link_path);
+        assert!(result.is_err());
+        let err = result
+            .err()
+            .ok_or_else(|| anyhow!("Expected error"))?
+            .to_string();
+        assert!(
+            err.contains("dangling symlink"),
+            "Error should mention the dangling symlink: {err}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_new_path_climbing_out_fails() -> Result<()> {
+        let (dir, validator) = setup_workspace()?;
+        let result = validator.validate_write(&dir.path().join("new_dir/../../escape.rs"));
+        assert!(result.is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_root_is_canonicalized() -> Result<()> {
+        use std::os::unix::fs as unix_fs;
+
+        let dir = TempDir::new()?;
+        let real = dir.path().join("real");
+        fs::create_dir_all(&real)?;
+        fs::write(real.join("a.rs"), "// a")?;
+        let alias = dir.path().join("alias");
+        unix_fs::symlink(&real, &alias)?;
+
+        let validator = PathValidator::new(vec![alias.clone()]);
+        assert_eq!(validator.roots(), &[real.canonicalize()?]);
+        assert!(validator.validate_read(&alias.join("a.rs")).is_ok());
+        assert!(validator.validate_write(&alias.join("b.rs")).is_ok());
         Ok(())
     }
 }
@@ -0,0 +1,144 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (C) 2026 Mark Wells <contact@markwells.dev>
+
+//! In-memory cache of document symbol responses for `codebase_map` and the
+//! open-document symbols `search` lists first.
+//!
+//! Entries are keyed by path and remembered with the file's modification
+//! time, so a repeated map over unchanged files skips the language server.
+//! The cache holds at most `capacity` files and evicts the least recently
+//! used one when full.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use lsp_types::DocumentSymbolResponse;
+
+/// One file's cached symbols.
+#[derive(Debug)]
+struct CachedSymbols {
+    mtime: SystemTime,
+    response: DocumentSymbolResponse,
+    /// Value of the cache's clock when this entry was last read or written.
+    last_used: u64,
+}
+
+/// Bounded, mtime-invalidated LRU cache of document symbols.
+#[derive(Debug)]
+pub struct SymbolCache {
+    capacity: usize,
+    entries: HashMap<PathBuf, CachedSymbols>,
+    clock: u64,
+}
+
+impl SymbolCache {
+    /// Creates a cache holding at most `capacity` files; 0 disables caching.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::new(),
+            clock: 0,
+        }
+    }
+
+    /// Returns the symbols cached for `path` if they were recorded at `mtime`.
+    ///
+    /// An entry recorded at a different modification time is stale and is
+    /// dropped.
+    pub fn get(&mut self, path: &Path, mtime: SystemTime) -> Option<&DocumentSymbolResponse> {
+        if self.entries.get(path)?.mtime != mtime {
+            self.entries.remove(path);
+            return None;
+        }
+        self.clock += 1;
+        let entry = self.entries.get_mut(path)?;
+        entry.last_used = self.clock;
+        Some(&entry.response)
+    }
+
+    /// Caches `response` as the symbols of `path` at `mtime`, evicting the
+    /// least recently used file if the cache is full.
+    pub fn insert(&mut self, path: PathBuf, mtime: SystemTime, response: DocumentSymbolResponse) {
+        if self.capacity == 0 {
+            return;
+        }
+        if !self.entries.contains_key(&path)
+            && self.entries.len() >= self.capacity
+            && let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(path, _)| path.clone())
+        {
+            self.entries.remove(&oldest);
+        }
+
+        self.clock += 1;
+        self.entries.insert(
+            path,
+            CachedSymbols {
+                mtime,
+                response,
+                last_used: self.clock,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn response() -> DocumentSymbolResponse {
+        DocumentSymbolResponse::Nested(Vec::new())
+    }
+
+    #[test]
+    fn test_get_requires_matching_mtime() {
+        let mut cache = SymbolCache::new(4);
+        let path = PathBuf::from("/src/lib.rs");
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(100);
+        cache.insert(path.clone(), mtime, response());
+
+        assert!(cache.get(&path, mtime).is_some());
+        assert!(
+            cache.get(&path, mtime + Duration::from_secs(1)).is_none(),
+            "a changed mtime should miss"
+        );
+        assert!(
+            cache.get(&path, mtime).is_none(),
+            "the stale entry should have been dropped"
+        );
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = SymbolCache::new(2);
+        let mtime = SystemTime::UNIX_EPOCH;
+        let (a, b, c) = (
+            PathBuf::from("/a.rs"),
+            PathBuf::from("/b.rs"),
+            PathBuf::from("/c.rs"),
+        );
+        cache.insert(a.clone(), mtime, response());
+        cache.insert(b.clone(), mtime, response());
+
+        // Touch `a` so `b` becomes the least recently used.
+        assert!(cache.get(&a, mtime).is_some());
+        cache.insert(c.clone(), mtime, response());
+
+        assert!(cache.get(&a, mtime).is_some());
+        assert!(cache.get(&b, mtime).is_none());
+        assert!(cache.get(&c, mtime).is_some());
+    }
+
+    #[test]
+    fn test_zero_capacity_disables() {
+        let mut cache = SymbolCache::new(0);
+        let path = PathBuf::from("/a.rs");
+        cache.insert(path.clone(), SystemTime::UNIX_EPOCH, response());
+        assert!(cache.get(&path, SystemTime::UNIX_EPOCH).is_none());
+    }
+}
@@ -0,0 +1,453 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (C) 2026 Mark Wells <contact@markwells.dev>
+
+//! On-disk symbol index shared across sessions.
+//!
+//! Every `textDocument/documentSymbol` response the bridge receives is
+//! flattened and recorded against the file's modification time. The index is
+//! stored as one JSON shard per workspace root, so a new session can answer
+//! `search` for files no server has looked at yet. Entries whose file has
+//! changed or disappeared since they were recorded are dropped when queried.
+//! Changes are kept in memory until [`SymbolIndex::flush`]; the server
+//! flushes periodically and once more at shutdown.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{Result, anyhow};
+use lsp_types::{
+    DocumentSymbol, DocumentSymbolResponse, Location, Range, SymbolInformation, SymbolKind, Uri,
+};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// Bumped whenever the shard format changes; older shards are discarded.
+const INDEX_VERSION: u32 = 1;
+
+/// Returns the base directory for symbol index shards.
+///
+/// `<config-home>/symbols` when `--config-home` is set, otherwise
+/// `$XDG_STATE_HOME/catenary/symbols/` with fallback to `$XDG_DATA_HOME` or `/tmp`.
+pub fn symbol_index_dir() -> PathBuf {
+    if let Some(home) = crate::config::config_home() {
+        return home.join("symbols");
+    }
+    let state_dir = dirs::state_dir()
+        .or_else(dirs::data_local_dir)
+        .unwrap_or_else(|| PathBuf::from("/tmp"));
+    state_dir.join("catenary").join("symbols")
+}
+
+/// A symbol as stored in the index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedSymbol {
+    name: String,
+    kind: SymbolKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    container: Option<String>,
+    range: Range,
+}
+
+/// The symbols of one file at a given modification time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexedFile {
+    mtime_ms: u64,
+    symbols: Vec<IndexedSymbol>,
+}
+
+/// The on-disk format of one root's shard.
+#[derive(Debug, Default, Deserialize)]
+struct ShardFile {
+    version: u32,
+    files: HashMap<String, IndexedFile>,
+}
+
+/// Borrowed form of [`ShardFile`] used when writing.
+#[derive(Serialize)]
+struct ShardFileRef<'a> {
+    version: u32,
+    files: &'a HashMap<String, IndexedFile>,
+}
+
+/// A root's shard, loaded into memory.
+#[derive(Debug, Default)]
+struct Shard {
+    files: HashMap<String, IndexedFile>,
+    dirty: bool,
+}
+
+/// Persistent, mtime-invalidated index of document symbols.
+#[derive(Debug)]
+pub struct SymbolIndex {
+    dir: PathBuf,
+    shards: HashMap<PathBuf, Shard>,
+}
+
+impl SymbolIndex {
+    /// Creates an index that stores its shards in `dir`.
+    ///
+    /// Shards are loaded lazily, the first time a root is recorded or searched.
+    #[must_use]
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            shards: HashMap::new(),
+        }
+    }
+
+    /// Records the symbols of `path`, which lies under `root`.
+    ///
+    /// Does nothing if the file's modification time cannot be read.
+    pub fn record(
+        &mut self,
+        root: &Path,
+        path: &Path,
+        response: &DocumentSymbolResponse,
+        max_depth: usize,
+    ) {
+        let Some(mtime_ms) = file_mtime_ms(path) else {
+            return;
+        };
+        let symbols = flatten(response, max_depth);
+        let shard = self.shard(root);
+        shard.files.insert(
+            path.to_string_lossy().into_owned(),
+            IndexedFile { mtime_ms, symbols },
+        );
+        shard.dirty = true;
+    }
+
+    /// Returns indexed symbols under `roots` whose name contains `query`
+    /// (case-insensitively), skipping files in `exclude`.
+    ///
+    /// Entries for files that changed or were removed since they were
+    /// recorded are dropped.
+    pub fn search(
+        &mut self,
+        roots: &[PathBuf],
+        query: &str,
+        exclude: &[PathBuf],
+    ) -> Vec<SymbolInformation> {
+        let query = query.to_lowercase();
+        let mut matches = Vec::new();
+
+        for root in roots {
+            let shard = self.shard(root);
+            let before = shard.files.len();
+            shard
+                .files
+                .retain(|path, file| file_mtime_ms(Path::new(path)) == Some(file.mtime_ms));
+            if shard.files.len() != before {
+                shard.dirty = true;
+            }
+
+            let mut paths: Vec<&String> = shard.files.keys().collect();
+            paths.sort();
+            for path in paths {
+                if exclude.iter().any(|p| p.as_os_str() == path.as_str()) {
+                    continue;
+                }
+                let Ok(uri) = path_to_uri(Path::new(path)) else {
+                    continue;
+                };
+                let file = &shard.files[path];
+                matches.extend(
+                    file.symbols
+                        .iter()
+                        .filter(|sym| sym.name.to_lowercase().contains(&query))
+                        .map(|sym| to_symbol_information(sym, &uri)),
+                );
+            }
+        }
+
+        matches
+    }
+
+    /// Writes every shard that changed since it was loaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a shard cannot be written.
+    pub fn flush(&mut self) -> Result<()> {
+        let dirty: Vec<PathBuf> = self
+            .shards
+            .iter()
+            .filter(|(_, shard)| shard.dirty)
+            .map(|(root, _)| root.clone())
+            .collect();
+        if dirty.is_empty() {
+            return Ok(());
+        }
+
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| anyhow!("Failed to create {}: {e}", self.dir.display()))?;
+
+        for root in dirty {
+            let path = self.shard_path(&root);
+            let Some(shard) = self.shards.get_mut(&root) else {
+                continue;
+            };
+            let bytes = serde_json::to_vec(&ShardFileRef {
+                version: INDEX_VERSION,
+                files: &shard.files,
+            })?;
+            write_atomic(&path, &bytes)?;
+            shard.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Returns the shard for `root`, loading it from disk on first use.
+    fn shard(&mut self, root: &Path) -> &mut Shard {
+        let path = self.shard_path(root);
+        self.shards
+            .entry(root.to_path_buf())
+            .or_insert_with(|| load_shard(&path))
+    }
+
+    fn shard_path(&self, root: &Path) -> PathBuf {
+        self.dir
+            .join(format!("{}.json", fnv1a_hash(&root.to_string_lossy())))
+    }
+}
+
+/// Reads a shard, starting empty if it is missing, unreadable, or from
+/// another index version.
+fn load_shard(path: &Path) -> Shard {
+    let Ok(data) = std::fs::read(path) else {
+        return Shard::default();
+    };
+    match serde_json::from_slice::<ShardFile>(&data) {
+        Ok(file) if file.version == INDEX_VERSION => Shard {
+            files: file.files,
+            dirty: false,
+        },
+        Ok(_) => Shard::default(),
+        Err(e) => {
+            debug!("Discarding unreadable symbol index {}: {e}", path.display());
+            Shard::default()
+        }
+    }
+}
+
+/// Flattens a document symbol response into index entries, following at
+/// most `max_depth` levels of nesting.
+fn flatten(response: &DocumentSymbolResponse, max_depth: usize) -> Vec<IndexedSymbol> {
+    fn walk(
+        symbols: &[DocumentSymbol],
+        container: Option<&str>,
+        depth_remaining: usize,
+        out: &mut Vec<IndexedSymbol>,
+    ) {
+        if depth_remaining == 0 {
+            return;
+        }
+        for sym in symbols {
+            out.push(IndexedSymbol {
+                name: sym.name.clone(),
+                kind: sym.kind,
+                container: container.map(str::to_string),
+                range: sym.selection_range,
+            });
+            if let Some(children) = &sym.children {
+                walk(children, Some(&sym.name), depth_remaining - 1, out);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    match response {
+        DocumentSymbolResponse::Flat(symbols) => {
+            out.extend(symbols.iter().map(|sym| IndexedSymbol {
+                name: sym.name.clone(),
+                kind: sym.kind,
+                container: sym.container_name.clone(),
+                range: sym.location.range,
+            }));
+        }
+        DocumentSymbolResponse::Nested(symbols) => walk(symbols, None, max_depth, &mut out),
+    }
+    out
+}
+
+/// Flattens a document's symbols like [`SymbolIndex::record`] does,
+/// keeping those whose name contains `query` (case-insensitively).
+///
+/// Nested symbols are followed at most `max_depth` levels deep and point at
+/// their selection range.
+pub fn matching_document_symbols(
+    response: &DocumentSymbolResponse,
+    query: &str,
+    uri: &Uri,
+    max_depth: usize,
+) -> Vec<SymbolInformation> {
+    let query = query.to_lowercase();
+    flatten(response, max_depth)
+        .iter()
+        .filter(|sym| sym.name.to_lowercase().contains(&query))
+        .map(|sym| to_symbol_information(sym, uri))
+        .collect()
+}
+
+#[allow(
+    deprecated,
+    reason = "SymbolInformation has a deprecated field that must be initialized"
+)]
+fn to_symbol_information(sym: &IndexedSymbol, uri: &Uri) -> SymbolInformation {
+    SymbolInformation {
+        name: sym.name.clone(),
+        kind: sym.kind,
+        tags: None,
+        deprecated: None,
+        location: Location::new(uri.clone(), sym.range),
+        container_name: sym.container.clone(),
+    }
+}
+
+fn path_to_uri(path: &Path) -> Result<Uri> {
+    let url = url::Url::from_file_path(path)
+        .map_err(|()| anyhow!("Invalid file path: {}", path.display()))?;
+    url.as_str()
+        .parse()
+        .map_err(|e| anyhow!("Invalid URI for {}: {e}", path.display()))
+}
+
+fn file_mtime_ms(path: &Path) -> Option<u64> {
+    let duration = std::fs::metadata(path)
+        .ok()?
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?;
+    Some(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+}
+
+/// Writes `data` to `path` via a temp file and rename, so concurrent
+/// sessions never read a half-written shard.
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let temp_path = path.with_extension(format!("tmp.{}", std::process::id()));
+    std::fs::write(&temp_path, data)
+        .map_err(|e| anyhow!("Failed to write {}: {e}", temp_path.display()))?;
+    std::fs::rename(&temp_path, path).map_err(|e| {
+        let _ = std::fs::remove_file(&temp_path);
+        anyhow!(
+            "Failed to rename {} -> {}: {e}",
+            temp_path.display(),
+            path.display()
+        )
+    })
+}
+
+/// FNV-1a 64-bit hash as 16 hex characters, used to name shards.
+fn fnv1a_hash(input: &str) -> String {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+    let mut hash: u64 = FNV_OFFSET;
+    for byte in input.as_bytes() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    format!("{hash:016x}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::Position;
+
+    #[allow(deprecated, reason = "DocumentSymbol has a deprecated field")]
+    fn symbol(
+        name: &str,
+        kind: SymbolKind,
+        line: u32,
+        children: Vec<DocumentSymbol>,
+    ) -> DocumentSymbol {
+        let range = Range::new(Position::new(line, 0), Position::new(line, 10));
+        DocumentSymbol {
+            name: name.to_string(),
+            detail: None,
+            kind,
+            tags: None,
+            deprecated: None,
+            range,
+            selection_range: range,
+            children: Some(children),
+        }
+    }
+
+    #[test]
+    fn test_symbol_index_persists_and_invalidates() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("project");
+        std::fs::create_dir(&root)?;
+        let file = root.join("lib.sh");
+        std::fs::write(&file, "parse_args() { :; }\n")?;
+
+        let response = DocumentSymbolResponse::Nested(vec![symbol(
+            "Config",
+            SymbolKind::CLASS,
+            0,
+            vec![symbol("parse_args", SymbolKind::METHOD, 1, vec![])],
+        )]);
+
+        let index_dir = dir.path().join("index");
+        let mut index = SymbolIndex::new(index_dir.clone());
+        index.record(&root, &file, &response, 64);
+        index.flush()?;
+
+        // A fresh index (a later session) reads the shard back
+        let mut index = SymbolIndex::new(index_dir.clone());
+        let roots = vec![root.clone()];
+        let found = index.search(&roots, "PARSE", &[]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "parse_args");
+        assert_eq!(found[0].container_name.as_deref(), Some("Config"));
+        assert_eq!(found[0].location.range.start.line, 1);
+
+        // Excluded files (open documents) are left to the live server
+        assert!(
+            index
+                .search(&roots, "parse", std::slice::from_ref(&file))
+                .is_empty()
+        );
+
+        // Depth limit applies to what is recorded
+        let mut shallow = SymbolIndex::new(dir.path().join("shallow"));
+        shallow.record(&root, &file, &response, 1);
+        assert!(shallow.search(&roots, "parse", &[]).is_empty());
+
+        // A changed file invalidates its entry
+        let mtime = std::fs::metadata(&file)?.modified()?;
+        std::fs::File::options()
+            .write(true)
+            .open(&file)?
+            .set_modified(mtime + std::time::Duration::from_secs(5))?;
+        assert!(index.search(&roots, "parse", &[]).is_empty());
+        index.flush()?;
+        assert!(
+            SymbolIndex::new(index_dir)
+                .search(&roots, "config", &[])
+                .is_empty(),
+            "Invalidation is persisted"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_symbol_index_ignores_other_versions() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().to_path_buf();
+        let index = SymbolIndex::new(dir.path().to_path_buf());
+        std::fs::write(
+            index.shard_path(&root),
+            r#"{"version": 0, "files": {"/x": {"mtime_ms": 1, "symbols": []}}}"#,
+        )?;
+        assert!(load_shard(&index.shard_path(&root)).files.is_empty());
+
+        std::fs::write(index.shard_path(&root), "not json")?;
+        assert!(load_shard(&index.shard_path(&root)).files.is_empty());
+        Ok(())
+    }
+}
@@ -6,6 +6,18 @@
 use crossterm::tty::IsTty;
 use std::io::stdout;
 
+/// When to colorize terminal output, as selected by `--color`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorChoice {
+    /// Colorize only when stdout is a terminal.
+    #[default]
+    Auto,
+    /// Always emit ANSI color codes, even when piped.
+    Always,
+    /// Never emit ANSI color codes.
+    Never,
+}
+
 /// Configuration for color output.
 #[derive(Debug, Clone)]
 pub struct ColorConfig {
@@ -14,12 +26,16 @@ pub struct ColorConfig {
 }
 
 impl ColorConfig {
-    /// Create a new `ColorConfig`, auto-detecting TTY unless `nocolor` is true.
+    /// Create a new `ColorConfig` for the given choice. `Auto` enables color
+    /// only when stdout is a TTY.
     #[must_use]
-    pub fn new(nocolor: bool) -> Self {
-        Self {
-            enabled: !nocolor && stdout().is_tty(),
-        }
+    pub fn new(choice: ColorChoice) -> Self {
+        let enabled = match choice {
+            ColorChoice::Auto => stdout().is_tty(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        };
+        Self { enabled }
     }
 
     /// ANSI escape code for green (incoming/request).
@@ -96,9 +112,20 @@ impl ColorConfig {
 /// Get the terminal width, defaulting to 80 if unable to detect.
 #[must_use]
 pub fn terminal_width() -> usize {
-    crossterm::terminal::size()
-        .map(|(w, _)| w as usize)
-        .unwrap_or(80)
+    crossterm::terminal::size().map_or(80, |(w, _)| w as usize)
+}
+
+/// Clear the terminal and move the cursor to the top-left corner.
+///
+/// # Errors
+///
+/// Returns an error if writing to stdout fails.
+pub fn clear_screen() -> std::io::Result<()> {
+    crossterm::execute!(
+        stdout(),
+        crossterm::terminal::Clear(crossterm::terminal::ClearType::All),
+        crossterm::cursor::MoveTo(0, 0)
+    )
 }
 
 /// Truncate a string to `max_len` characters, adding "..." if truncated.
@@ -123,8 +150,12 @@ pub struct ColumnWidths {
     pub row_num: usize,
     /// Width of the ID column.
     pub id: usize,
+    /// Width of the session name column.
+    pub name: usize,
     /// Width of the PID column.
     pub pid: usize,
+    /// Width of the server health column.
+    pub health: usize,
     /// Width of the client column.
     pub client: usize,
     /// Width of the workspace column.
@@ -135,30 +166,34 @@ pub struct ColumnWidths {
 
 impl ColumnWidths {
     /// Calculate column widths based on terminal width.
-    /// Columns: # | ID | PID | CLIENT | WORKSPACE | STARTED
+    /// Columns: # | ID | NAME | PID | HEALTH | CLIENT | WORKSPACE | STARTED
     #[must_use]
     pub const fn calculate(term_width: usize) -> Self {
         // Fixed minimum widths
         let row_num = 3; // "#"
         let pid = 8; // "PID"
+        let health = 10; // "2✓ 1… 1✗"
         let started = 12; // "STARTED"
 
         // Calculate flexible widths
-        // Reserve space for separators (5 spaces between 6 columns)
-        let fixed_space = row_num + pid + started + 5;
+        // Reserve space for separators (7 spaces between 8 columns)
+        let fixed_space = row_num + pid + health + started + 7;
         let flexible_space = term_width.saturating_sub(fixed_space);
 
         let min_id = 12;
+        let min_name = 16;
         let min_client = 20;
         let min_workspace = 20;
 
-        let total_min_flex = min_id + min_client + min_workspace;
+        let total_min_flex = min_id + min_name + min_client + min_workspace;
 
         if flexible_space <= total_min_flex {
             Self {
                 row_num,
                 id: min_id,
+                name: min_name,
                 pid,
+                health,
                 client: min_client,
                 workspace: min_workspace,
                 started,
@@ -169,7 +204,9 @@ impl ColumnWidths {
             Self {
                 row_num,
                 id: min_id,
+                name: min_name,
                 pid,
+                health,
                 client: min_client,
                 workspace: min_workspace + extra,
                 started,
@@ -204,7 +241,7 @@ mod tests {
 
     #[test]
     fn test_color_config_disabled() {
-        let config = ColorConfig::new(true);
+        let config = ColorConfig::new(ColorChoice::Never);
         assert!(!config.enabled);
         assert_eq!(config.green("test"), "test");
         assert_eq!(config.blue("test"), "test");
@@ -212,11 +249,19 @@ mod tests {
         assert_eq!(config.cyan("test"), "test");
     }
 
+    #[test]
+    fn test_color_config_always() {
+        let config = ColorConfig::new(ColorChoice::Always);
+        assert!(config.enabled);
+        assert_eq!(config.red("x"), "\x1b[31mx\x1b[0m");
+    }
+
     #[test]
     fn test_calculate_column_widths() {
         let widths = ColumnWidths::calculate(120);
         assert_eq!(widths.row_num, 3);
         assert_eq!(widths.pid, 8);
+        assert_eq!(widths.health, 10);
         assert_eq!(widths.started, 12);
         // Flexible columns should have reasonable widths
         assert!(widths.id >= 12);
@@ -229,6 +274,7 @@ mod tests {
         let widths = ColumnWidths::calculate(60);
         // Should use minimum widths for narrow terminals
         assert_eq!(widths.id, 12);
+        assert_eq!(widths.name, 16);
         assert_eq!(widths.workspace, 20);
         assert_eq!(widths.client, 20);
     }
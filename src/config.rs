@@ -2,26 +2,296 @@
 // Copyright (C) 2026 Mark Wells <contact@markwells.dev>
 
 use anyhow::{Context, Result};
-use serde::Deserialize;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use globset::{Glob, GlobMatcher, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Process-wide override for Catenary's home directory (`--config-home`).
+static CONFIG_HOME: OnceLock<PathBuf> = OnceLock::new();
+
+/// Overrides the directory Catenary uses for its user config and on-disk
+/// state. Must be called before any config is loaded or session created.
+///
+/// With an override, the user config is `<dir>/config.toml`, sessions live
+/// under `<dir>/sessions` and locks under `<dir>/locks`.
+///
+/// # Errors
+///
+/// Returns an error if the override was already set.
+pub fn set_config_home(dir: PathBuf) -> Result<()> {
+    CONFIG_HOME
+        .set(dir)
+        .map_err(|dir| anyhow::anyhow!("config home already set (to {})", dir.display()))
+}
+
+/// Returns the `--config-home` override, if one was set.
+pub fn config_home() -> Option<&'static Path> {
+    CONFIG_HOME.get().map(PathBuf::as_path)
+}
+
+/// Returns the path of the user-level config file.
+///
+/// `<config-home>/config.toml` when overridden, otherwise
+/// `~/.config/catenary/config.toml`.
+#[must_use]
+pub fn user_config_path() -> Option<PathBuf> {
+    config_home().map_or_else(
+        || dirs::config_dir().map(|dir| dir.join("catenary").join("config.toml")),
+        |home| Some(home.join("config.toml")),
+    )
+}
 
 /// Overall configuration for Catenary.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[allow(
+    clippy::struct_excessive_bools,
+    reason = "Config flags are independent user settings"
+)]
 pub struct Config {
     /// Global idle timeout in seconds (default: 300).
     #[serde(default = "default_idle_timeout")]
     pub idle_timeout: u64,
 
-    /// Server definitions keyed by language ID (e.g., "rust", "python").
+    /// Seconds a server may have no open documents before it is shut down
+    /// (default: 900, 0 to keep idle servers running).
+    #[serde(default = "default_server_idle_timeout")]
+    pub server_idle_timeout: u64,
+
+    /// How the `diagnostics` tool waits for fresh results after a change
+    /// (default: `progress`).
+    #[serde(default)]
+    pub diagnostics_wait_strategy: DiagnosticsWaitStrategy,
+
+    /// Quiet period in milliseconds used by the `settle` wait strategy
+    /// (default: 1000).
+    #[serde(default = "default_diagnostics_settle_ms")]
+    pub diagnostics_settle_ms: u64,
+
+    /// Maximum nesting depth followed when formatting or searching document
+    /// symbol trees (default: 64).
+    #[serde(default = "default_max_symbol_depth")]
+    pub max_symbol_depth: usize,
+
+    /// Only report diagnostics that were not already reported for the file
+    /// by the previous edit notification (default: false).
+    #[serde(default)]
+    pub notify_new_diagnostics_only: bool,
+
+    /// After an edit, also report new errors in the other open documents
+    /// served by the same language server (default: false).
+    #[serde(default)]
+    pub notify_dependents: bool,
+
+    /// Keep an on-disk index of document symbols so `search` can find
+    /// symbols in files no server has been asked about yet (default: false).
+    #[serde(default)]
+    pub symbol_index: bool,
+
+    /// Most files whose document symbols `codebase_map` and `search` keep in
+    /// memory between calls; 0 disables the cache (default: 1024).
+    #[serde(default = "default_symbol_cache_size")]
+    pub symbol_cache_size: usize,
+
+    /// Deny edits in the pre-tool hook when applying them would introduce
+    /// new errors (default: false).
+    #[serde(default)]
+    pub block_on_errors: bool,
+
+    /// Number of new errors an edit must introduce before `block_on_errors`
+    /// denies it (default: 1).
+    #[serde(default = "default_block_error_threshold")]
+    pub block_error_threshold: usize,
+
+    /// Reject every path-taking tool input outside the workspace roots, not
+    /// just file I/O (default: false).
+    #[serde(default)]
+    pub strict_roots: bool,
+
+    /// Offer the `apply_edit` tool, which writes rename and quickfix edits
+    /// to disk instead of only previewing them (default: false).
+    #[serde(default)]
+    pub allow_edits: bool,
+
+    /// Command ids the `execute_command` tool may send to a server through
+    /// `workspace/executeCommand` (e.g. `rust-analyzer.reloadWorkspace`).
+    /// The tool is only offered when this is non-empty (default: empty).
+    #[serde(default)]
+    pub allowed_server_commands: Vec<String>,
+
+    /// Seconds to wait for each server to acknowledge `shutdown`/`exit`
+    /// before force-killing it (default: 3).
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout: u64,
+
+    /// Largest workspace file, in bytes, that tools will open in a language
+    /// server or edit; 0 disables the limit (default: 10 MiB).
+    #[serde(default = "default_max_file_bytes")]
+    pub max_file_bytes: u64,
+
+    /// Most language servers running at once. When a session opens, the
+    /// languages with the most files are started first; a request needing
+    /// another server fails until one shuts down. 0 means no limit
+    /// (default: 0).
+    #[serde(default)]
+    pub max_concurrent_servers: usize,
+
+    /// Largest tool result, in bytes, returned to the client. Longer output
+    /// keeps its head and tail around a truncation marker; 0 disables the
+    /// limit (default: 256 KiB).
+    #[serde(default = "default_max_result_bytes")]
+    pub max_result_bytes: usize,
+
+    /// Resolve server commands with `sh -lc 'command -v <server>'` in the
+    /// workspace root, so version-manager shims (mise, asdf) are found
+    /// (default: false).
     #[serde(default)]
+    pub resolve_in_login_shell: bool,
+
+    /// Seconds after startup during which tool calls wait for their server
+    /// to be spawned before dispatching. When set, servers start in the
+    /// background instead of before the MCP loop; 0 spawns them up front
+    /// (default: 0).
+    #[serde(default)]
+    pub init_timeout: u64,
+
+    /// Times a position request (hover, definition, ...) that comes back
+    /// empty is retried while its server is indexing; 0
+    /// disables retries (default: 3).
+    #[serde(default = "default_warmup_retries")]
+    pub warmup_retries: u32,
+
+    /// Delay in milliseconds between those retries (default: 500).
+    #[serde(default = "default_warmup_retry_ms")]
+    pub warmup_retry_ms: u64,
+
+    /// Files or directories marking a project root. Without `--root`, the
+    /// nearest directory at or above the current one that contains any of
+    /// them becomes the workspace root; empty keeps the current directory.
+    #[serde(default = "default_root_markers")]
+    pub root_markers: Vec<String>,
+
+    /// LSP request timeouts in seconds keyed by method (e.g.
+    /// `workspace_symbol = 3`); other methods wait 30 seconds.
+    #[serde(default, serialize_with = "sorted_map")]
+    pub timeouts: HashMap<String, u64>,
+
+    /// Language ID overrides keyed by glob (e.g., `"*.php" = "php"`),
+    /// consulted before the built-in extension table.
+    #[serde(default, serialize_with = "sorted_map")]
+    pub languages: HashMap<String, String>,
+
+    /// Server definitions keyed by language ID (e.g., "rust", "python").
+    #[serde(default, serialize_with = "sorted_map")]
     pub server: HashMap<String, ServerConfig>,
 }
 
+/// Serializes a map with its keys in order, so printed configs are stable.
+fn sorted_map<S: Serializer, V: Serialize>(
+    map: &HashMap<String, V>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
+
+/// Compiled `[languages]` table.
+///
+/// A pattern without a `/` is matched against the file name. Any other is
+/// matched against the whole path, anchored at any directory unless it is
+/// absolute. When several patterns match, the longest wins.
+#[derive(Debug, Clone, Default)]
+pub struct LanguageOverrides {
+    rules: Vec<LanguageRule>,
+}
+
+#[derive(Debug, Clone)]
+struct LanguageRule {
+    matcher: GlobMatcher,
+    whole_path: bool,
+    language_id: String,
+}
+
+impl LanguageOverrides {
+    /// Compiles `(glob, language id)` pairs.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any glob is invalid.
+    pub fn new<'a>(patterns: impl IntoIterator<Item = (&'a String, &'a String)>) -> Result<Self> {
+        let mut patterns: Vec<_> = patterns.into_iter().collect();
+        patterns.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+        let rules = patterns
+            .into_iter()
+            .map(|(pattern, language_id)| {
+                let whole_path = pattern.contains('/');
+                let anchored = if whole_path && !pattern.starts_with(['/', '*']) {
+                    format!("**/{pattern}")
+                } else {
+                    pattern.clone()
+                };
+                let glob = Glob::new(&anchored)
+                    .with_context(|| format!("Invalid [languages] glob '{pattern}'"))?;
+                Ok(LanguageRule {
+                    matcher: glob.compile_matcher(),
+                    whole_path,
+                    language_id: language_id.clone(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Returns the language ID the overrides assign to `path`, if any.
+    #[must_use]
+    pub fn lookup(&self, path: &Path) -> Option<&str> {
+        let file_name = path.file_name().map(Path::new);
+        self.rules
+            .iter()
+            .find(|rule| {
+                if rule.whole_path {
+                    rule.matcher.is_match(path)
+                } else {
+                    file_name.is_some_and(|name| rule.matcher.is_match(name))
+                }
+            })
+            .map(|rule| rule.language_id.as_str())
+    }
+}
+
+/// How the `diagnostics` tool waits for the server after a file change.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticsWaitStrategy {
+    /// Follow the server's own signals (document versions, `$/progress`
+    /// tokens, or CPU activity) until it reports idle.
+    #[default]
+    Progress,
+    /// Wait until published diagnostics stop changing for
+    /// `diagnostics_settle_ms`.
+    Settle,
+    /// Return the cached diagnostics without waiting.
+    Immediate,
+}
+
+/// How Catenary talks to a language server.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// Spawn `command` and speak LSP over its stdin/stdout.
+    #[default]
+    Stdio,
+    /// Connect to an already running server at `host`:`port`.
+    Tcp,
+}
+
 /// Configuration for a specific LSP server.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ServerConfig {
-    /// The command to execute (e.g., "rust-analyzer").
+    /// The command to execute (e.g., "rust-analyzer"). Required for the
+    /// `stdio` transport.
+    #[serde(default)]
     pub command: String,
 
     /// Arguments to pass to the command.
@@ -31,61 +301,246 @@ pub struct ServerConfig {
     /// Initialization options to pass to the LSP server.
     #[serde(default)]
     pub initialization_options: Option<serde_json::Value>,
-}
 
-const fn default_idle_timeout() -> u64 {
-    300
+    /// Extra environment variables set when spawning the server.
+    #[serde(default, serialize_with = "sorted_map")]
+    pub env: HashMap<String, String>,
+
+    /// Working directory for the server process. Relative paths are resolved
+    /// against the server's first workspace root. Unset inherits Catenary's.
+    #[serde(default)]
+    pub cwd: Option<PathBuf>,
+
+    /// Spawn a separate server for each workspace root instead of one
+    /// server spanning all roots.
+    #[serde(default)]
+    pub isolate_roots: bool,
+
+    /// Globs a file must match (relative to its workspace root) to be sent
+    /// to this server. Empty means every file of the language.
+    #[serde(default)]
+    pub document_selector: Vec<String>,
+
+    /// Languages whose files this server also receives, alongside the
+    /// server configured for each of them (e.g. a linter). Its diagnostics
+    /// and code actions are merged with the primary server's.
+    #[serde(default)]
+    pub languages: Vec<String>,
+
+    /// How to reach the server (default: stdio).
+    #[serde(default)]
+    pub transport: Transport,
+
+    /// Host to connect to with the `tcp` transport (default: 127.0.0.1).
+    #[serde(default)]
+    pub host: Option<String>,
+
+    /// Port to connect to with the `tcp` transport.
+    #[serde(default)]
+    pub port: Option<u16>,
 }
 
-impl Config {
-    /// Load configuration from standard paths or a specific file.
+impl ServerConfig {
+    /// Returns the working directory to spawn the server in, resolving a
+    /// relative `cwd` against `root`.
+    #[must_use]
+    pub fn resolved_cwd(&self, root: Option<&Path>) -> Option<PathBuf> {
+        let cwd = self.cwd.as_ref()?;
+        Some(match root {
+            Some(root) if cwd.is_relative() => root.join(cwd),
+            _ => cwd.clone(),
+        })
+    }
+
+    /// Returns the `(host, port)` to connect to, or `None` for the `stdio`
+    /// transport.
     ///
     /// # Errors
     ///
-    /// Returns an error if:
-    /// - Default values cannot be set.
-    /// - The configuration file exists but cannot be read or parsed.
-    /// - The configuration cannot be deserialized into the `Config` struct.
-    pub fn load(explicit_file: Option<PathBuf>) -> Result<Self> {
-        let mut builder = config::Config::builder();
+    /// Returns an error if the `tcp` transport has no `port`.
+    pub fn tcp_address(&self) -> Result<Option<(String, u16)>> {
+        match self.transport {
+            Transport::Stdio => Ok(None),
+            Transport::Tcp => {
+                let port = self.port.context("transport = \"tcp\" requires a `port`")?;
+                let host = self.host.clone().unwrap_or_else(|| "127.0.0.1".to_string());
+                Ok(Some((host, port)))
+            }
+        }
+    }
 
-        // 1. Start with defaults
-        builder = builder.set_default("idle_timeout", 300)?;
+    /// Checks that the settings for the chosen transport are present and
+    /// that `document_selector` compiles.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first problem found.
+    pub fn validate(&self) -> Result<()> {
+        if self.tcp_address()?.is_none() && self.command.is_empty() {
+            anyhow::bail!("missing `command`");
+        }
+        self.document_selector_set()?;
+        Ok(())
+    }
 
-        // 2. Load from user config directory (~/.config/catenary/config.toml)
-        if let Some(config_dir) = dirs::config_dir() {
-            let config_path = config_dir.join("catenary").join("config.toml");
-            if config_path.exists() {
-                builder = builder.add_source(config::File::from(config_path));
-            }
+    /// Compiles `document_selector`, or returns `None` if it is empty.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any glob is invalid.
+    pub fn document_selector_set(&self) -> Result<Option<GlobSet>> {
+        if self.document_selector.is_empty() {
+            return Ok(None);
+        }
+        let mut builder = GlobSetBuilder::new();
+        for pattern in &self.document_selector {
+            builder.add(
+                Glob::new(pattern)
+                    .with_context(|| format!("Invalid document_selector glob '{pattern}'"))?,
+            );
         }
+        Ok(Some(builder.build()?))
+    }
+}
+
+const fn default_idle_timeout() -> u64 {
+    300
+}
+
+const fn default_server_idle_timeout() -> u64 {
+    900
+}
+
+const fn default_diagnostics_settle_ms() -> u64 {
+    1000
+}
 
-        // 3. Load from project-local config (.catenary.toml) searching upwards
+const fn default_max_symbol_depth() -> usize {
+    64
+}
+
+const fn default_symbol_cache_size() -> usize {
+    1024
+}
+
+const fn default_block_error_threshold() -> usize {
+    1
+}
+
+const fn default_shutdown_timeout() -> u64 {
+    3
+}
+
+const fn default_max_file_bytes() -> u64 {
+    10 * 1024 * 1024
+}
+
+const fn default_max_result_bytes() -> usize {
+    256 * 1024
+}
+
+const fn default_warmup_retries() -> u32 {
+    3
+}
+
+const fn default_warmup_retry_ms() -> u64 {
+    500
+}
+
+fn default_root_markers() -> Vec<String> {
+    [
+        ".git",
+        "Cargo.toml",
+        "package.json",
+        "go.mod",
+        "pyproject.toml",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+impl Config {
+    /// Returns the config files that exist, in the order they are merged:
+    /// the user config, the nearest `.catenary.toml` above the current
+    /// directory, then `explicit_file`. Later files override earlier ones.
+    #[must_use]
+    pub fn files(explicit_file: Option<PathBuf>) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+
+        // User config directory (~/.config/catenary/config.toml)
+        if let Some(config_path) = user_config_path()
+            && config_path.exists()
+        {
+            files.push(config_path);
+        }
+
+        // Project-local config (.catenary.toml) searching upwards
         if let Ok(cwd) = std::env::current_dir() {
             let mut current = Some(cwd.as_path());
             while let Some(path) = current {
                 let config_path = path.join(".catenary.toml");
                 if config_path.exists() {
-                    builder = builder.add_source(config::File::from(config_path));
+                    files.push(config_path);
                     break;
                 }
                 current = path.parent();
             }
         }
 
-        // 4. Load from explicit file if provided
+        // Explicit file, if provided
         if let Some(path) = explicit_file {
+            files.push(path);
+        }
+
+        files
+    }
+
+    /// Load configuration from standard paths or a specific file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - Default values cannot be set.
+    /// - The configuration file exists but cannot be read or parsed.
+    /// - The configuration cannot be deserialized into the `Config` struct.
+    pub fn load(explicit_file: Option<PathBuf>) -> Result<Self> {
+        let mut builder = config::Config::builder();
+
+        // 1. Start with defaults
+        builder = builder.set_default("idle_timeout", 300)?;
+
+        // 2-4. User config, project-local `.catenary.toml`, explicit file
+        for path in Self::files(explicit_file) {
             builder = builder.add_source(config::File::from(path));
         }
 
-        // 4. Load from environment variables (CATENARY_IDLE_TIMEOUT, etc.)
+        // 5. Load from environment variables (CATENARY_IDLE_TIMEOUT, etc.)
         builder = builder.add_source(config::Environment::with_prefix("CATENARY"));
 
         let config = builder.build().context("Failed to build configuration")?;
 
-        config
+        let config: Self = config
             .try_deserialize()
-            .context("Failed to deserialize configuration")
+            .context("Failed to deserialize configuration")?;
+
+        for (lang, server) in &config.server {
+            server
+                .validate()
+                .with_context(|| format!("Invalid configuration for server '{lang}'"))?;
+        }
+
+        config.language_overrides()?;
+
+        Ok(config)
+    }
+
+    /// Compiles the `[languages]` table.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any glob is invalid.
+    pub fn language_overrides(&self) -> Result<LanguageOverrides> {
+        LanguageOverrides::new(&self.languages)
     }
 }
 
@@ -130,6 +585,41 @@ mod tests {
         std::env::set_current_dir(original_dir)?;
 
         assert_eq!(config.idle_timeout, 42);
+        assert_eq!(config.server_idle_timeout, 900);
+        assert_eq!(
+            config.diagnostics_wait_strategy,
+            DiagnosticsWaitStrategy::Progress
+        );
+        assert_eq!(config.diagnostics_settle_ms, 1000);
+        assert_eq!(config.max_symbol_depth, 64);
+        assert!(!config.notify_new_diagnostics_only);
+        assert!(!config.notify_dependents);
+        assert!(!config.symbol_index);
+        assert_eq!(config.symbol_cache_size, 1024);
+        assert!(!config.block_on_errors);
+        assert_eq!(config.block_error_threshold, 1);
+        assert!(!config.strict_roots);
+        assert!(!config.allow_edits);
+        assert!(config.allowed_server_commands.is_empty());
+        assert_eq!(config.shutdown_timeout, 3);
+        assert_eq!(config.max_file_bytes, 10 * 1024 * 1024);
+        assert_eq!(config.max_concurrent_servers, 0);
+        assert_eq!(config.max_result_bytes, 256 * 1024);
+        assert!(!config.resolve_in_login_shell);
+        assert_eq!(config.init_timeout, 0);
+        assert_eq!(config.warmup_retries, 3);
+        assert_eq!(config.warmup_retry_ms, 500);
+        assert_eq!(
+            config.root_markers,
+            [
+                ".git",
+                "Cargo.toml",
+                "package.json",
+                "go.mod",
+                "pyproject.toml"
+            ]
+        );
+        assert!(config.timeouts.is_empty());
 
         assert_eq!(
             config
@@ -142,4 +632,209 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_server_env_deserialize() -> Result<()> {
+        let config: Config = toml::from_str(
+            r#"
+            [server.rust]
+            command = "rust-analyzer"
+
+            [server.rust.env]
+            RA_LOG = "info"
+            "#,
+        )?;
+        let rust = config.server.get("rust").context("missing rust server")?;
+        assert_eq!(rust.env.get("RA_LOG").map(String::as_str), Some("info"));
+
+        let config: Config = toml::from_str("[server.go]\ncommand = \"gopls\"\n")?;
+        assert!(
+            config
+                .server
+                .get("go")
+                .context("missing go server")?
+                .env
+                .is_empty()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_document_selector_set() -> Result<()> {
+        let config: Config = toml::from_str(
+            r#"
+            [server.javascript]
+            command = "vue-language-server"
+            document_selector = ["src/**/*.js", "*.mjs"]
+            "#,
+        )?;
+        let server = config
+            .server
+            .get("javascript")
+            .context("missing javascript server")?;
+        let set = server
+            .document_selector_set()?
+            .context("selector should compile")?;
+        assert!(set.is_match("src/app/main.js"));
+        assert!(set.is_match("lib/util.mjs"));
+        assert!(!set.is_match("scripts/build.js"));
+
+        let plain: Config = toml::from_str("[server.go]\ncommand = \"gopls\"\n")?;
+        let go = plain.server.get("go").context("missing go server")?;
+        assert!(go.document_selector_set()?.is_none());
+
+        let bad = ServerConfig {
+            document_selector: vec!["src/[".to_string()],
+            ..go.clone()
+        };
+        assert!(bad.document_selector_set().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_language_overrides() -> Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("languages.toml");
+        fs::write(
+            &path,
+            r#"
+            [languages]
+            "*.tpl" = "php"
+            "Dockerfile*" = "dockerfile"
+            "config/*.conf" = "nginx"
+            "config/special.conf" = "ini"
+            "#,
+        )?;
+        let config = Config::load(Some(path))?;
+        let overrides = config.language_overrides()?;
+
+        assert_eq!(
+            overrides.lookup(Path::new("/src/views/page.tpl")),
+            Some("php")
+        );
+        assert_eq!(
+            overrides.lookup(Path::new("/repo/Dockerfile.dev")),
+            Some("dockerfile")
+        );
+        assert_eq!(
+            overrides.lookup(Path::new("/repo/config/site.conf")),
+            Some("nginx")
+        );
+        assert_eq!(
+            overrides.lookup(Path::new("/repo/config/special.conf")),
+            Some("ini")
+        );
+        assert_eq!(overrides.lookup(Path::new("/repo/site.conf")), None);
+        assert_eq!(overrides.lookup(Path::new("main.rs")), None);
+
+        let bad = Config {
+            languages: HashMap::from([("src/[".to_string(), "c".to_string())]),
+            ..config
+        };
+        assert!(bad.language_overrides().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_server_transport() -> Result<()> {
+        let config: Config = toml::from_str(
+            r#"
+            [server.go]
+            command = "gopls"
+
+            [server.java]
+            transport = "tcp"
+            host = "jdtls.internal"
+            port = 5036
+
+            [server.rust]
+            transport = "tcp"
+            port = 9257
+            "#,
+        )?;
+        let go = config.server.get("go").context("missing go server")?;
+        assert_eq!(go.transport, Transport::Stdio);
+        assert_eq!(go.tcp_address()?, None);
+        go.validate()?;
+
+        let java = config.server.get("java").context("missing java server")?;
+        assert_eq!(
+            java.tcp_address()?,
+            Some(("jdtls.internal".to_string(), 5036))
+        );
+        java.validate()?;
+
+        let rust = config.server.get("rust").context("missing rust server")?;
+        assert_eq!(rust.tcp_address()?, Some(("127.0.0.1".to_string(), 9257)));
+
+        let no_port = ServerConfig {
+            port: None,
+            ..rust.clone()
+        };
+        assert!(no_port.validate().is_err());
+        let no_command = ServerConfig {
+            command: String::new(),
+            ..go.clone()
+        };
+        assert!(no_command.validate().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_server_cwd_resolution() -> Result<()> {
+        let config: Config = toml::from_str(
+            r#"
+            [server.go]
+            command = "gopls"
+            cwd = "services/api"
+            "#,
+        )?;
+        let go = config.server.get("go").context("missing go server")?;
+        let root = Path::new("/work/monorepo");
+        assert_eq!(
+            go.resolved_cwd(Some(root)),
+            Some(PathBuf::from("/work/monorepo/services/api"))
+        );
+        assert_eq!(go.resolved_cwd(None), Some(PathBuf::from("services/api")));
+
+        let absolute = ServerConfig {
+            cwd: Some(PathBuf::from("/opt/go")),
+            ..go.clone()
+        };
+        assert_eq!(
+            absolute.resolved_cwd(Some(root)),
+            Some(PathBuf::from("/opt/go"))
+        );
+
+        let unset = ServerConfig {
+            cwd: None,
+            ..go.clone()
+        };
+        assert_eq!(unset.resolved_cwd(Some(root)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagnostics_wait_strategy_deserialize() -> Result<()> {
+        let config: Config = toml::from_str(
+            r#"
+            diagnostics_wait_strategy = "settle"
+            diagnostics_settle_ms = 250
+            "#,
+        )?;
+        assert_eq!(
+            config.diagnostics_wait_strategy,
+            DiagnosticsWaitStrategy::Settle
+        );
+        assert_eq!(config.diagnostics_settle_ms, 250);
+
+        let config: Config = toml::from_str(r#"diagnostics_wait_strategy = "immediate""#)?;
+        assert_eq!(
+            config.diagnostics_wait_strategy,
+            DiagnosticsWaitStrategy::Immediate
+        );
+
+        assert!(toml::from_str::<Config>(r#"diagnostics_wait_strategy = "bogus""#).is_err());
+        Ok(())
+    }
 }
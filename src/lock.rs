@@ -21,6 +21,13 @@
 //! 3. **Grace expiry**: after the grace period, any agent can reclaim the lock.
 //! 4. **Stale recovery**: if `last_activity` exceeds the staleness threshold,
 //!    the lock is considered abandoned.
+//!
+//! ## Lock modes
+//!
+//! Reads take a [`LockMode::Shared`] lock, which any number of agents can hold
+//! at once; edits take a [`LockMode::Exclusive`] lock, which excludes every
+//! other holder. A shared lock in its grace period can be taken over by another
+//! reader, since the grace only protects a follow-up read by the same agent.
 
 use anyhow::{Result, anyhow};
 use serde::{Deserialize, Serialize};
@@ -47,11 +54,28 @@ pub struct FileLockManager {
     locks_dir: PathBuf,
 }
 
+/// How a lock is held.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LockMode {
+    /// Held by readers; any number of owners may share it.
+    Shared,
+    /// Held by a single writer.
+    #[default]
+    Exclusive,
+}
+
 /// Persistent lock state stored as JSON on disk.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LockState {
     /// Owner identity (e.g. `"session_id"` or `"session_id:agent_id"`).
     pub owner: String,
+    /// Whether the lock is shared by readers or held by one writer.
+    #[serde(default)]
+    pub mode: LockMode,
+    /// Further owners holding a shared lock alongside `owner`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub readers: Vec<String>,
     /// Absolute path of the locked file (for human readability).
     pub file_path: String,
     /// Unix timestamp when the lock was first acquired.
@@ -63,6 +87,29 @@ pub struct LockState {
     pub last_activity: u64,
 }
 
+impl LockState {
+    /// Every owner holding the lock: `owner` followed by any other readers.
+    pub fn holders(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.owner.as_str()).chain(self.readers.iter().map(String::as_str))
+    }
+
+    /// Unix timestamp from which another owner can reclaim the lock: the end
+    /// of its grace period if released, otherwise the point at which it is
+    /// considered abandoned under the default timeout.
+    #[must_use]
+    pub const fn expires_at(&self) -> u64 {
+        match self.grace_until {
+            Some(grace_until) => grace_until,
+            None => {
+                self.last_activity
+                    + DEFAULT_TIMEOUT_SECS
+                    + DEFAULT_GRACE_SECS
+                    + STALENESS_MARGIN_SECS
+            }
+        }
+    }
+}
+
 /// Result of an acquire attempt.
 #[derive(Debug)]
 pub enum AcquireResult {
@@ -77,6 +124,9 @@ pub enum AcquireResult {
     Denied {
         /// Human-readable reason for denial.
         reason: String,
+        /// Owners holding the lock when the wait gave up, comma-separated;
+        /// `None` if the lock vanished at the last moment.
+        held_by: Option<String>,
     },
 }
 
@@ -123,9 +173,16 @@ impl FileLockManager {
     ///
     /// * `file_path` — Absolute path to the file being locked.
     /// * `owner` — Identity of the agent acquiring the lock.
+    /// * `mode` — Whether to share the lock with other readers.
     /// * `timeout_secs` — Maximum time to wait for the lock.
     #[must_use]
-    pub fn acquire(&self, file_path: &str, owner: &str, timeout_secs: u64) -> AcquireResult {
+    pub fn acquire(
+        &self,
+        file_path: &str,
+        owner: &str,
+        mode: LockMode,
+        timeout_secs: u64,
+    ) -> AcquireResult {
         let lock_path = self.lock_path(file_path);
         let now = unix_now();
         let deadline = now + timeout_secs;
@@ -143,37 +200,59 @@ impl FileLockManager {
                             "File {file_path} is locked by another agent (held for {held_secs}s). \
                              Work on a different file and retry later."
                         ),
+                        held_by: Some(state.holders().collect::<Vec<_>>().join(", ")),
                     };
                 }
                 // Lock disappeared between check and timeout — try once more
-                if self.try_claim(&lock_path, file_path, owner, now) {
+                if self.try_claim(&lock_path, file_path, owner, mode, now) {
                     return self.check_stale_read(file_path, owner);
                 }
                 return AcquireResult::Denied {
                     reason: format!("File {file_path} lock acquisition timed out. Retry later."),
+                    held_by: None,
                 };
             }
 
             match self.read_lock(&lock_path) {
                 None => {
                     // No lock — claim it
-                    if self.try_claim(&lock_path, file_path, owner, now) {
+                    if self.try_claim(&lock_path, file_path, owner, mode, now) {
                         return self.check_stale_read(file_path, owner);
                     }
                     // Someone else claimed between read and write — retry
                 }
-                Some(state) if state.owner == owner => {
-                    // Same owner — refresh
-                    if self.try_claim(&lock_path, file_path, owner, now) {
+                Some(state) if state.owner == owner && state.readers.is_empty() => {
+                    // Sole holder — refresh, switching mode if asked
+                    if self.try_claim(&lock_path, file_path, owner, mode, now) {
+                        return self.check_stale_read(file_path, owner);
+                    }
+                }
+                Some(state)
+                    if state.mode == LockMode::Shared
+                        && mode == LockMode::Shared
+                        && state.grace_until.is_none() =>
+                {
+                    // Active shared lock — join the readers
+                    let mut updated = LockState {
+                        last_activity: now,
+                        ..state
+                    };
+                    if updated.owner != owner && !updated.readers.iter().any(|r| r == owner) {
+                        updated.readers.push(owner.to_string());
+                    }
+                    if self.atomic_write(&lock_path, &updated).is_ok() {
                         return self.check_stale_read(file_path, owner);
                     }
                 }
                 Some(state) => {
                     // Different owner — check if reclaimable
                     let reclaimable = state.grace_until.is_some_and(|g| now >= g)
-                        || state.last_activity < staleness_threshold;
+                        || state.last_activity < staleness_threshold
+                        || (state.mode == LockMode::Shared
+                            && mode == LockMode::Shared
+                            && state.grace_until.is_some());
 
-                    if reclaimable && self.try_claim(&lock_path, file_path, owner, now) {
+                    if reclaimable && self.try_claim(&lock_path, file_path, owner, mode, now) {
                         return self.check_stale_read(file_path, owner);
                     }
                     // Still locked — wait and retry
@@ -186,8 +265,9 @@ impl FileLockManager {
 
     /// Releases a lock with an optional grace period.
     ///
-    /// If `grace_secs` is 0, the lock file is removed immediately.
-    /// Otherwise, `grace_until` is set to `now + grace_secs`.
+    /// A reader leaving a shared lock that others still hold just drops out
+    /// of it. Otherwise, if `grace_secs` is 0, the lock file is removed
+    /// immediately, and if not, `grace_until` is set to `now + grace_secs`.
     ///
     /// # Errors
     ///
@@ -195,14 +275,27 @@ impl FileLockManager {
     pub fn release(&self, file_path: &str, owner: &str, grace_secs: u64) -> Result<()> {
         let lock_path = self.lock_path(file_path);
 
-        let Some(state) = self.read_lock(&lock_path) else {
+        let Some(mut state) = self.read_lock(&lock_path) else {
             // No lock file — nothing to release
             return Ok(());
         };
 
         if state.owner != owner {
-            // Not our lock — don't touch it
-            return Ok(());
+            // Not the owner — leave the readers if among them, else don't touch it
+            let before = state.readers.len();
+            state.readers.retain(|r| r != owner);
+            if state.readers.len() == before {
+                return Ok(());
+            }
+            state.last_activity = unix_now();
+            return self.atomic_write(&lock_path, &state);
+        }
+
+        if !state.readers.is_empty() {
+            // Hand the shared lock to the next reader
+            state.owner = state.readers.remove(0);
+            state.last_activity = unix_now();
+            return self.atomic_write(&lock_path, &state);
         }
 
         if grace_secs == 0 {
@@ -221,6 +314,51 @@ impl FileLockManager {
         self.atomic_write(&lock_path, &updated)
     }
 
+    /// Returns the current lock on `file_path`, if any.
+    #[must_use]
+    pub fn lock_state(&self, file_path: &str) -> Option<LockState> {
+        self.read_lock(&self.lock_path(file_path))
+    }
+
+    /// Removes the lock on `file_path` whoever holds it, for recovering from
+    /// an agent that died while holding it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lock file exists but cannot be removed.
+    pub fn force_release(&self, file_path: &str) -> Result<()> {
+        let lock_path = self.lock_path(file_path);
+        match std::fs::remove_file(&lock_path) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(anyhow!(
+                "Failed to remove lock file {}: {e}",
+                lock_path.display()
+            )),
+        }
+    }
+
+    /// Returns the state of every lock file, ordered by locked file path.
+    /// Unreadable lock files are skipped.
+    #[must_use]
+    pub fn list(&self) -> Vec<LockState> {
+        let Ok(entries) = std::fs::read_dir(&self.locks_dir) else {
+            return Vec::new();
+        };
+        let mut locks: Vec<LockState> = entries
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                if path.extension().is_some_and(|ext| ext == "json") {
+                    self.read_lock(&path)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        locks.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+        locks
+    }
+
     /// Records the current modification time of a file for change detection.
     ///
     /// # Errors
@@ -239,6 +377,17 @@ impl FileLockManager {
         self.atomic_write_bytes(&track_path, &bytes)
     }
 
+    /// Records the modification time left by the owner's own edit, so the
+    /// next [`Self::acquire`] does not mistake it for another agent's change.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the tracking file cannot be written.
+    pub fn track_write(&self, file_path: &str, owner: &str) -> Result<()> {
+        // The owner now knows the file as it left it, exactly as after a read
+        self.track_read(file_path, owner)
+    }
+
     /// Returns the lock file path for a given file.
     fn lock_path(&self, file_path: &str) -> PathBuf {
         self.locks_dir
@@ -259,9 +408,18 @@ impl FileLockManager {
     }
 
     /// Attempts to claim a lock via atomic write. Returns `true` on success.
-    fn try_claim(&self, lock_path: &Path, file_path: &str, owner: &str, now: u64) -> bool {
+    fn try_claim(
+        &self,
+        lock_path: &Path,
+        file_path: &str,
+        owner: &str,
+        mode: LockMode,
+        now: u64,
+    ) -> bool {
         let state = LockState {
             owner: owner.to_string(),
+            mode,
+            readers: Vec::new(),
             file_path: file_path.to_string(),
             acquired_at: now,
             grace_until: None,
@@ -333,9 +491,17 @@ impl FileLockManager {
 
 /// Returns the base directory for lock files.
 ///
-/// Uses the same directory resolution as `session::sessions_dir()`:
+/// Agents sharing a sessions directory must share locks, so with
+/// `--sessions-dir` or `$CATENARY_SESSIONS_DIR` this is `<sessions-dir>/locks`.
+/// Otherwise `<config-home>/locks` when `--config-home` is set, then
 /// `$XDG_STATE_HOME/catenary/locks/` with fallback to `$XDG_DATA_HOME` or `/tmp`.
 pub fn locks_dir() -> PathBuf {
+    if let Some(dir) = crate::session::sessions_dir_override() {
+        return dir.join("locks");
+    }
+    if let Some(home) = crate::config::config_home() {
+        return home.join("locks");
+    }
     let state_dir = dirs::state_dir()
         .or_else(dirs::data_local_dir)
         .unwrap_or_else(|| PathBuf::from("/tmp"));
@@ -364,8 +530,7 @@ fn fnv1a_hash(input: &str) -> String {
 fn unix_now() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0)
+        .map_or(0, |d| d.as_secs())
 }
 
 /// Returns the modification time of a file as milliseconds since UNIX epoch.
@@ -396,21 +561,21 @@ mod tests {
     #[test]
     fn acquire_uncontested() {
         let (mgr, _dir) = setup();
-        let result = mgr.acquire("/tmp/test.rs", "agent-a", 5);
+        let result = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Exclusive, 5);
         assert!(matches!(result, AcquireResult::Acquired));
     }
 
     #[test]
     fn reacquire_same_owner() {
         let (mgr, _dir) = setup();
-        let result = mgr.acquire("/tmp/test.rs", "agent-a", 5);
+        let result = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Exclusive, 5);
         assert!(matches!(result, AcquireResult::Acquired));
 
         // Release with grace
         mgr.release("/tmp/test.rs", "agent-a", 30).ok().unwrap();
 
         // Re-acquire by same owner should succeed immediately
-        let result = mgr.acquire("/tmp/test.rs", "agent-a", 5);
+        let result = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Exclusive, 5);
         assert!(matches!(result, AcquireResult::Acquired));
     }
 
@@ -419,12 +584,15 @@ mod tests {
         let (mgr, _dir) = setup();
 
         // Agent A acquires
-        let result = mgr.acquire("/tmp/test.rs", "agent-a", 5);
+        let result = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Exclusive, 5);
         assert!(matches!(result, AcquireResult::Acquired));
 
         // Agent B should be denied (lock is active, no grace, 1s timeout)
-        let result = mgr.acquire("/tmp/test.rs", "agent-b", 1);
-        assert!(matches!(result, AcquireResult::Denied { .. }));
+        let result = mgr.acquire("/tmp/test.rs", "agent-b", LockMode::Exclusive, 1);
+        assert!(
+            matches!(result, AcquireResult::Denied { held_by: Some(ref h), .. } if h == "agent-a"),
+            "got {result:?}"
+        );
     }
 
     #[test]
@@ -432,14 +600,14 @@ mod tests {
         let (mgr, _dir) = setup();
 
         // Agent A acquires
-        let result = mgr.acquire("/tmp/test.rs", "agent-a", 5);
+        let result = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Exclusive, 5);
         assert!(matches!(result, AcquireResult::Acquired));
 
         // Agent A releases with 0 grace (immediate)
         mgr.release("/tmp/test.rs", "agent-a", 0).ok().unwrap();
 
         // Agent B should acquire immediately
-        let result = mgr.acquire("/tmp/test.rs", "agent-b", 1);
+        let result = mgr.acquire("/tmp/test.rs", "agent-b", LockMode::Exclusive, 1);
         assert!(matches!(result, AcquireResult::Acquired));
     }
 
@@ -448,14 +616,14 @@ mod tests {
         let (mgr, _dir) = setup();
 
         // Agent A acquires and releases with 1s grace
-        let result = mgr.acquire("/tmp/test.rs", "agent-a", 5);
+        let result = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Exclusive, 5);
         assert!(matches!(result, AcquireResult::Acquired));
         mgr.release("/tmp/test.rs", "agent-a", 1).ok().unwrap();
 
         // Agent B tries immediately — lock is in grace, should wait then succeed
         // With 1s grace and 5s timeout, B should get it after ~1s
         let start = std::time::Instant::now();
-        let result = mgr.acquire("/tmp/test.rs", "agent-b", 5);
+        let result = mgr.acquire("/tmp/test.rs", "agent-b", LockMode::Exclusive, 5);
         let elapsed = start.elapsed();
 
         assert!(matches!(result, AcquireResult::Acquired));
@@ -471,17 +639,102 @@ mod tests {
         let (mgr, _dir) = setup();
 
         // Agent A acquires
-        let result = mgr.acquire("/tmp/test.rs", "agent-a", 5);
+        let result = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Exclusive, 5);
         assert!(matches!(result, AcquireResult::Acquired));
 
         // Agent B tries to release — should be a no-op
         mgr.release("/tmp/test.rs", "agent-b", 0).ok().unwrap();
 
         // Agent A's lock should still be active
-        let result = mgr.acquire("/tmp/test.rs", "agent-b", 1);
+        let result = mgr.acquire("/tmp/test.rs", "agent-b", LockMode::Exclusive, 1);
         assert!(matches!(result, AcquireResult::Denied { .. }));
     }
 
+    #[test]
+    fn shared_locks_coexist() {
+        let (mgr, _dir) = setup();
+
+        let result = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Shared, 5);
+        assert!(matches!(result, AcquireResult::Acquired));
+        let result = mgr.acquire("/tmp/test.rs", "agent-b", LockMode::Shared, 1);
+        assert!(matches!(result, AcquireResult::Acquired));
+
+        // A writer waits for every reader
+        let result = mgr.acquire("/tmp/test.rs", "agent-c", LockMode::Exclusive, 1);
+        assert!(
+            matches!(result, AcquireResult::Denied { held_by: Some(ref h), .. } if h == "agent-a, agent-b"),
+            "got {result:?}"
+        );
+
+        // Upgrading needs the other reader gone
+        let result = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Exclusive, 1);
+        assert!(matches!(result, AcquireResult::Denied { .. }));
+        mgr.release("/tmp/test.rs", "agent-b", 0).ok().unwrap();
+        let result = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Exclusive, 1);
+        assert!(matches!(result, AcquireResult::Acquired));
+    }
+
+    #[test]
+    fn exclusive_lock_blocks_readers() {
+        let (mgr, _dir) = setup();
+
+        let result = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Exclusive, 5);
+        assert!(matches!(result, AcquireResult::Acquired));
+        let result = mgr.acquire("/tmp/test.rs", "agent-b", LockMode::Shared, 1);
+        assert!(matches!(result, AcquireResult::Denied { .. }));
+    }
+
+    #[test]
+    fn shared_release_hands_lock_to_remaining_reader() {
+        let (mgr, _dir) = setup();
+
+        let _ = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Shared, 5);
+        let _ = mgr.acquire("/tmp/test.rs", "agent-b", LockMode::Shared, 5);
+        mgr.release("/tmp/test.rs", "agent-a", 30).ok().unwrap();
+
+        let state = mgr.read_lock(&mgr.lock_path("/tmp/test.rs")).unwrap();
+        assert_eq!(state.owner, "agent-b");
+        assert!(state.readers.is_empty());
+        assert!(state.grace_until.is_none());
+
+        // A reader in grace does not keep another reader out
+        mgr.release("/tmp/test.rs", "agent-b", 30).ok().unwrap();
+        let result = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Shared, 1);
+        assert!(matches!(result, AcquireResult::Acquired));
+    }
+
+    #[test]
+    fn list_reports_held_locks() {
+        let (mgr, _dir) = setup();
+        assert!(mgr.list().is_empty());
+
+        let _ = mgr.acquire("/tmp/b.rs", "agent-a", LockMode::Exclusive, 5);
+        let _ = mgr.acquire("/tmp/a.rs", "agent-b", LockMode::Shared, 5);
+        mgr.release("/tmp/b.rs", "agent-a", 30).ok().unwrap();
+
+        let locks = mgr.list();
+        let files: Vec<&str> = locks.iter().map(|l| l.file_path.as_str()).collect();
+        assert_eq!(files, ["/tmp/a.rs", "/tmp/b.rs"]);
+        assert_eq!(locks[0].owner, "agent-b");
+        assert_eq!(locks[0].mode, LockMode::Shared);
+        assert_eq!(Some(locks[1].expires_at()), locks[1].grace_until);
+    }
+
+    #[test]
+    fn force_release_ignores_owner() {
+        let (mgr, _dir) = setup();
+
+        let _ = mgr.acquire("/tmp/test.rs", "agent-a", LockMode::Exclusive, 5);
+        mgr.force_release("/tmp/test.rs").ok().unwrap();
+        assert!(mgr.lock_state("/tmp/test.rs").is_none());
+
+        let result = mgr.acquire("/tmp/test.rs", "agent-b", LockMode::Exclusive, 1);
+        assert!(matches!(result, AcquireResult::Acquired));
+
+        // Nothing to remove is not an error
+        mgr.force_release("/tmp/other.rs").ok().unwrap();
+    }
+
     #[test]
     fn release_nonexistent_is_ok() {
         let (mgr, _dir) = setup();
@@ -508,7 +761,7 @@ mod tests {
         std::fs::write(&test_file, "modified content").ok().unwrap();
 
         // Acquire should detect the stale read
-        let result = mgr.acquire(&file_str, "agent-a", 5);
+        let result = mgr.acquire(&file_str, "agent-a", LockMode::Exclusive, 5);
         assert!(
             matches!(result, AcquireResult::AcquiredStaleRead { .. }),
             "Expected AcquiredStaleRead, got {result:?}"
@@ -533,13 +786,39 @@ mod tests {
         mgr.track_read(&file_str, "agent-a").ok().unwrap();
 
         // Acquire should NOT warn about stale read — the agent updated its own mtime
-        let result = mgr.acquire(&file_str, "agent-a", 5);
+        let result = mgr.acquire(&file_str, "agent-a", LockMode::Exclusive, 5);
         assert!(
             matches!(result, AcquireResult::Acquired),
             "Expected Acquired after self-edit + track_read, got {result:?}"
         );
     }
 
+    #[test]
+    fn track_write_clears_self_edit_but_not_external_edit() {
+        let (mgr, dir) = setup();
+
+        let test_file = dir.path().join("written.rs");
+        std::fs::write(&test_file, "original content").ok().unwrap();
+        let file_str = test_file.to_string_lossy().to_string();
+        mgr.track_read(&file_str, "agent-a").ok().unwrap();
+
+        // Own edit, recorded by the post-edit hook
+        std::thread::sleep(Duration::from_millis(50));
+        std::fs::write(&test_file, "edited content").ok().unwrap();
+        mgr.track_write(&file_str, "agent-a").ok().unwrap();
+        let result = mgr.acquire(&file_str, "agent-a", LockMode::Exclusive, 5);
+        assert!(matches!(result, AcquireResult::Acquired), "got {result:?}");
+
+        // Another agent's edit still warns
+        std::thread::sleep(Duration::from_millis(50));
+        std::fs::write(&test_file, "external content").ok().unwrap();
+        let result = mgr.acquire(&file_str, "agent-a", LockMode::Exclusive, 5);
+        assert!(
+            matches!(result, AcquireResult::AcquiredStaleRead { .. }),
+            "got {result:?}"
+        );
+    }
+
     #[test]
     fn read_tracking_no_change() {
         let (mgr, dir) = setup();
@@ -553,7 +832,7 @@ mod tests {
         mgr.track_read(&file_str, "agent-a").ok().unwrap();
 
         // Acquire without modifying — should be clean
-        let result = mgr.acquire(&file_str, "agent-a", 5);
+        let result = mgr.acquire(&file_str, "agent-a", LockMode::Exclusive, 5);
         assert!(matches!(result, AcquireResult::Acquired));
     }
 
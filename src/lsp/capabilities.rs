@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+// Copyright (C) 2026 Mark Wells <contact@markwells.dev>
+
+//! Mapping from advertised server capabilities to Catenary tools.
+
+use lsp_types::ServerCapabilities;
+
+/// Extracts Catenary tool names from LSP `ServerCapabilities`.
+#[must_use]
+pub fn extract_capabilities(caps: &ServerCapabilities) -> Vec<&'static str> {
+    let mut tools = Vec::new();
+
+    if caps.hover_provider.is_some() {
+        tools.push("hover");
+    }
+    if caps.definition_provider.is_some() {
+        tools.push("definition");
+    }
+    if caps.type_definition_provider.is_some() {
+        tools.push("type_definition");
+    }
+    if caps.implementation_provider.is_some() {
+        tools.push("implementation");
+    }
+    if caps.references_provider.is_some() {
+        tools.push("references");
+    }
+    if caps.document_symbol_provider.is_some() {
+        tools.push("document_symbols");
+    }
+    if caps.workspace_symbol_provider.is_some() {
+        tools.push("search");
+    }
+    if caps.code_action_provider.is_some() {
+        tools.push("code_actions");
+    }
+    if caps.rename_provider.is_some() {
+        tools.push("rename");
+    }
+    if caps.call_hierarchy_provider.is_some() {
+        tools.push("call_hierarchy");
+    }
+    // type_hierarchy_provider is not exposed as a direct field in lsp_types 0.97;
+    // type hierarchy support is probed at call time, so we omit it here.
+
+    tools
+}
@@ -4,30 +4,37 @@
 use anyhow::{Context, Result, anyhow};
 use bytes::BytesMut;
 use lsp_types::{
-    CallHierarchyIncomingCall, CallHierarchyIncomingCallsParams, CallHierarchyItem,
-    CallHierarchyOutgoingCall, CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams,
-    ClientCapabilities, CodeActionParams, CodeActionResponse, CompletionParams, CompletionResponse,
-    Diagnostic, DidChangeTextDocumentParams, DidChangeWorkspaceFoldersParams,
-    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DidSaveTextDocumentParams,
-    DocumentFormattingParams, DocumentRangeFormattingParams, DocumentSymbolParams,
-    DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverParams,
-    InitializeParams, InitializeResult, InitializedParams, PositionEncodingKind, ProgressParams,
-    PublishDiagnosticsParams, ReferenceParams, RenameParams, SignatureHelp, SignatureHelpParams,
-    TextDocumentIdentifier, TextEdit, TypeHierarchyItem, TypeHierarchyPrepareParams,
-    TypeHierarchySubtypesParams, TypeHierarchySupertypesParams, Uri, WorkspaceEdit,
-    WorkspaceFolder, WorkspaceFoldersChangeEvent, WorkspaceSymbolParams, WorkspaceSymbolResponse,
+    ApplyWorkspaceEditParams, ApplyWorkspaceEditResponse, CallHierarchyIncomingCall,
+    CallHierarchyIncomingCallsParams, CallHierarchyItem, CallHierarchyOutgoingCall,
+    CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams, ClientCapabilities,
+    CodeActionParams, CodeActionResponse, CodeLens, CodeLensParams, CompletionParams,
+    CompletionResponse, Diagnostic, DidChangeTextDocumentParams, DidChangeWatchedFilesParams,
+    DidChangeWorkspaceFoldersParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
+    DidSaveTextDocumentParams, DocumentFormattingParams, DocumentHighlight,
+    DocumentHighlightParams, DocumentRangeFormattingParams, DocumentSymbolParams,
+    DocumentSymbolResponse, ExecuteCommandParams, FileEvent, FoldingRange, FoldingRangeParams,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverParams, InitializeParams,
+    InitializeResult, InitializedParams, InlayHint, InlayHintParams, LogMessageParams, MessageType,
+    PositionEncodingKind, PrepareRenameResponse, ProgressParams, PublishDiagnosticsParams,
+    ReferenceParams, RenameParams, SelectionRange, SelectionRangeParams, SignatureHelp,
+    SignatureHelpParams, TextDocumentIdentifier, TextDocumentPositionParams, TextDocumentSyncKind,
+    TextEdit, TypeHierarchyItem, TypeHierarchyPrepareParams, TypeHierarchySubtypesParams,
+    TypeHierarchySupertypesParams, Uri, WorkspaceEdit, WorkspaceFolder,
+    WorkspaceFoldersChangeEvent, WorkspaceSymbolParams, WorkspaceSymbolResponse,
 };
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU8, AtomicU32, AtomicU64, Ordering};
 use std::time::{Duration, Instant};
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
-use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, ChildStderr, Command};
 use tokio::sync::{Mutex, Notify, oneshot};
 use tracing::{debug, error, trace, warn};
 
+use super::capabilities::extract_capabilities;
 use super::protocol::{self, NotificationMessage, RequestId, RequestMessage, ResponseMessage};
 use super::state::{ProgressTracker, ServerState, ServerStatus};
 use crate::session::{EventBroadcaster, EventKind};
@@ -37,6 +44,10 @@ use crate::session::{EventBroadcaster, EventKind};
 /// `version` is the document version from `publishDiagnostics`, if the
 /// server includes it. Used by [`DiagnosticsStrategy::Version`] to
 /// match diagnostics to a specific document change.
+///
+/// Keyed by every URI the server publishes for, whether or not the document
+/// was opened by us — servers like rust-analyzer report diagnostics for the
+/// whole crate.
 pub type DiagnosticsCache = Arc<Mutex<HashMap<Uri, (Option<i32>, Vec<Diagnostic>)>>>;
 
 /// Result of waiting for diagnostics to update after a file change.
@@ -48,7 +59,27 @@ pub enum DiagnosticsWaitResult {
     ServerDied,
 }
 
-/// Default timeout for LSP requests.
+/// Workspace symbol result sets at or above this size may have been
+/// truncated by the server, so they are not reused for narrower queries.
+const SYMBOL_CACHE_MAX_RESULTS: usize = 100;
+
+/// Lines of stderr kept for servers spawned with [`LspClient::spawn_quiet`].
+const STDERR_TAIL_LINES: usize = 50;
+
+/// How long [`LspClient::stderr_tail`] waits for an exited server's last
+/// stderr output.
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Where a spawned server's stderr goes.
+enum StderrMode {
+    /// Shared with Catenary's own stderr.
+    Inherit,
+    /// Read by Catenary for [`LspClient::stderr_tail`], and appended to the
+    /// given log file if any.
+    Capture(Option<std::fs::File>),
+}
+
+/// Default timeout for LSP requests without a `[timeouts]` entry.
 const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Time after spawn during which we consider the server to be "warming up".
@@ -59,10 +90,29 @@ pub const WARMUP_PERIOD: Duration = Duration::from_secs(10);
 /// active progress tokens) and as the Phase 2 settle timeout.
 pub(crate) const DIAGNOSTICS_TIMEOUT: Duration = Duration::from_secs(30);
 
-/// Manages communication with an LSP server process.
+/// The half of a transport that carries messages to the server.
+type ServerWriter = Box<dyn AsyncWrite + Send + Unpin>;
+
+/// The half of a transport that carries messages from the server.
+type ServerReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// What running a `workspace/executeCommand` produced.
+#[derive(Debug, Clone, Default)]
+pub struct CommandOutcome {
+    /// The command's result, if it returned one.
+    pub result: Option<serde_json::Value>,
+    /// Edits the server proposed via `workspace/applyEdit` while running.
+    pub edits: Vec<WorkspaceEdit>,
+}
+
+/// Manages communication with an LSP server over stdio or TCP.
+#[allow(
+    clippy::struct_excessive_bools,
+    reason = "Capability flags negotiated at initialize are independent"
+)]
 pub struct LspClient {
     next_id: AtomicI64,
-    stdin: Arc<Mutex<ChildStdin>>,
+    writer: Arc<Mutex<ServerWriter>>,
     pending: Arc<Mutex<HashMap<RequestId, oneshot::Sender<ResponseMessage>>>>,
     diagnostics: DiagnosticsCache,
     /// Per-URI generation counter, incremented on each `publishDiagnostics`.
@@ -76,6 +126,8 @@ pub struct LspClient {
     activity_counter: Arc<AtomicU64>,
     alive: Arc<AtomicBool>,
     encoding: PositionEncodingKind,
+    /// How the server wants `didChange` content (full text or edits).
+    sync_kind: TextDocumentSyncKind,
     /// Progress tracking for `$/progress` notifications.
     progress: Arc<Mutex<ProgressTracker>>,
     /// Time when this client was spawned.
@@ -87,6 +139,24 @@ pub struct LspClient {
     /// Whether the server supports dynamic workspace folder changes
     /// (both `supported` and `change_notifications` are advertised).
     supports_workspace_folders: bool,
+    /// Characters the server advertises as completion triggers.
+    completion_trigger_characters: Vec<String>,
+    /// Whether the server advertises `selectionRangeProvider`.
+    supports_selection_range: bool,
+    /// Whether the server answers `completionItem/resolve`.
+    supports_completion_resolve: bool,
+    /// Whether the server advertises `inlayHintProvider`.
+    supports_inlay_hints: bool,
+    /// Whether the server advertises `foldingRangeProvider`.
+    supports_folding_ranges: bool,
+    /// Whether the server advertises `renameProvider.prepareProvider`.
+    supports_prepare_rename: bool,
+    /// Whether the server advertises `codeLensProvider`.
+    supports_code_lens: bool,
+    /// Whether the server answers `codeLens/resolve`.
+    supports_code_lens_resolve: bool,
+    /// Catenary tools the server's advertised capabilities support.
+    supported_tools: Vec<&'static str>,
     /// Whether the server has ever included `version` in `publishDiagnostics`.
     publishes_version: Arc<AtomicBool>,
     /// Whether the server has ever sent `$/progress` notifications.
@@ -95,11 +165,23 @@ pub struct LspClient {
     /// when `publishDiagnostics` arrives. Used by `ProcessMonitor` to
     /// decay patience.
     cpu_trust_failures: Arc<AtomicU32>,
+    /// Edits the server asked to apply via `workspace/applyEdit`, recorded
+    /// instead of applied. Drained by [`Self::execute_command`].
+    proposed_edits: Arc<Mutex<Vec<WorkspaceEdit>>>,
     /// Last document version sent via `did_open`/`did_change` per URI.
     /// Used to detect stale diagnostics from prior document versions.
     last_sent_version: Arc<Mutex<HashMap<Uri, i32>>>,
+    /// Last `workspace/symbol` result and the query that produced it.
+    /// Cleared on every `didOpen`/`didChange`.
+    symbol_cache: Mutex<Option<(String, WorkspaceSymbolResponse)>>,
+    /// Last lines written to stderr, when it is captured.
+    stderr_tail: Arc<Mutex<VecDeque<String>>>,
+    stderr_handle: Option<tokio::task::JoinHandle<()>>,
     _reader_handle: tokio::task::JoinHandle<()>,
-    child: Child,
+    /// The server process, unless connected over TCP.
+    child: Option<Child>,
+    /// Per-method overrides of [`REQUEST_TIMEOUT`], keyed as in `[timeouts]`.
+    request_timeouts: HashMap<String, Duration>,
 }
 
 impl LspClient {
@@ -116,10 +198,41 @@ impl LspClient {
         language: &str,
         broadcaster: EventBroadcaster,
     ) -> Result<Self> {
-        Self::spawn_inner(program, args, language, broadcaster, Stdio::inherit())
+        Self::spawn_with_env(program, args, &HashMap::new(), None, language, broadcaster)
+    }
+
+    /// Spawns the LSP server with extra environment variables set, in `cwd`
+    /// when given.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The server process cannot be spawned.
+    /// - Stdin or stdout cannot be captured.
+    pub fn spawn_with_env(
+        program: &str,
+        args: &[&str],
+        env: &HashMap<String, String>,
+        cwd: Option<&Path>,
+        language: &str,
+        broadcaster: EventBroadcaster,
+    ) -> Result<Self> {
+        // Persist stderr to the session's `<lang>.log` when there is a session
+        let log = broadcaster.server_log_path(language).and_then(|path| {
+            std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .map_err(|e| warn!("Cannot open server log {}: {e}", path.display()))
+                .ok()
+        });
+        let stderr = log.map_or(StderrMode::Inherit, |file| StderrMode::Capture(Some(file)));
+        Self::spawn_inner(program, args, env, cwd, language, broadcaster, stderr)
     }
 
-    /// Spawns the LSP server with stderr suppressed (for `catenary doctor`).
+    /// Spawns the LSP server with stderr captured instead of shown (for
+    /// `catenary doctor`). The last 50 lines are kept for
+    /// [`Self::stderr_tail`].
     ///
     /// # Errors
     ///
@@ -127,21 +240,42 @@ impl LspClient {
     pub fn spawn_quiet(
         program: &str,
         args: &[&str],
+        env: &HashMap<String, String>,
+        cwd: Option<&Path>,
         language: &str,
         broadcaster: EventBroadcaster,
     ) -> Result<Self> {
-        Self::spawn_inner(program, args, language, broadcaster, Stdio::null())
+        Self::spawn_inner(
+            program,
+            args,
+            env,
+            cwd,
+            language,
+            broadcaster,
+            StderrMode::Capture(None),
+        )
     }
 
     fn spawn_inner(
         program: &str,
         args: &[&str],
+        env: &HashMap<String, String>,
+        cwd: Option<&Path>,
         language: &str,
         broadcaster: EventBroadcaster,
-        stderr: Stdio,
+        stderr: StderrMode,
     ) -> Result<Self> {
-        let mut child = Command::new(program)
+        let (stderr, log) = match stderr {
+            StderrMode::Inherit => (Stdio::inherit(), None),
+            StderrMode::Capture(log) => (Stdio::piped(), log),
+        };
+        let mut command = Command::new(program);
+        if let Some(cwd) = cwd {
+            command.current_dir(cwd);
+        }
+        let mut child = command
             .args(args)
+            .envs(env)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(stderr)
@@ -156,8 +290,53 @@ impl LspClient {
             .stdout
             .take()
             .ok_or_else(|| anyhow::anyhow!("stdout not captured"))?;
+        let stderr_tail = Arc::new(Mutex::new(VecDeque::new()));
+        let stderr_handle = child
+            .stderr
+            .take()
+            .map(|stderr| tokio::spawn(Self::stderr_task(stderr, stderr_tail.clone(), log)));
+
+        let mut client =
+            Self::from_transport(Box::new(stdout), Box::new(stdin), language, broadcaster);
+        client.stderr_tail = stderr_tail;
+        client.stderr_handle = stderr_handle;
+        client.child = Some(child);
+        Ok(client)
+    }
 
-        let stdin = Arc::new(Mutex::new(stdin));
+    /// Connects to an LSP server listening on `host`:`port` and starts the
+    /// response reader task. Catenary does not own the server process.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the connection cannot be established.
+    pub async fn connect(
+        host: &str,
+        port: u16,
+        language: &str,
+        broadcaster: EventBroadcaster,
+    ) -> Result<Self> {
+        let stream = TcpStream::connect((host, port))
+            .await
+            .with_context(|| format!("Failed to connect to LSP server at {host}:{port}"))?;
+        stream.set_nodelay(true)?;
+        let (reader, writer) = stream.into_split();
+        Ok(Self::from_transport(
+            Box::new(reader),
+            Box::new(writer),
+            language,
+            broadcaster,
+        ))
+    }
+
+    /// Builds a client around an established transport.
+    fn from_transport(
+        reader: ServerReader,
+        writer: ServerWriter,
+        language: &str,
+        broadcaster: EventBroadcaster,
+    ) -> Self {
+        let writer = Arc::new(Mutex::new(writer));
         let pending: Arc<Mutex<HashMap<RequestId, oneshot::Sender<ResponseMessage>>>> =
             Arc::new(Mutex::new(HashMap::new()));
         let diagnostics: DiagnosticsCache = Arc::new(Mutex::new(HashMap::new()));
@@ -172,6 +351,7 @@ impl LspClient {
         let publishes_version = Arc::new(AtomicBool::new(false));
         let has_sent_progress = Arc::new(AtomicBool::new(false));
         let cpu_trust_failures = Arc::new(AtomicU32::new(0));
+        let proposed_edits = Arc::new(Mutex::new(Vec::new()));
 
         // Broadcast initial state
         broadcaster.send(EventKind::ServerState {
@@ -180,8 +360,8 @@ impl LspClient {
         });
 
         let reader_handle = tokio::spawn(Self::reader_task(
-            stdin.clone(),
-            stdout,
+            writer.clone(),
+            reader,
             pending.clone(),
             diagnostics.clone(),
             diagnostics_generation.clone(),
@@ -196,11 +376,12 @@ impl LspClient {
             publishes_version.clone(),
             has_sent_progress.clone(),
             cpu_trust_failures.clone(),
+            proposed_edits.clone(),
         ));
 
-        Ok(Self {
+        Self {
             next_id: AtomicI64::new(1),
-            stdin,
+            writer,
             pending,
             diagnostics,
             diagnostics_generation,
@@ -209,18 +390,74 @@ impl LspClient {
             activity_counter,
             alive,
             encoding: PositionEncodingKind::UTF16, // Default per spec
+            sync_kind: TextDocumentSyncKind::FULL,
             progress,
             spawn_time: Instant::now(),
             state,
             language: language.to_string(),
             supports_workspace_folders: false,
+            completion_trigger_characters: Vec::new(),
+            supports_selection_range: false,
+            supports_completion_resolve: false,
+            supports_inlay_hints: false,
+            supports_folding_ranges: false,
+            supports_prepare_rename: false,
+            supports_code_lens: false,
+            supports_code_lens_resolve: false,
+            supported_tools: Vec::new(),
             publishes_version,
             has_sent_progress,
             cpu_trust_failures,
+            proposed_edits,
             last_sent_version: Arc::new(Mutex::new(HashMap::new())),
+            symbol_cache: Mutex::new(None),
+            stderr_tail: Arc::new(Mutex::new(VecDeque::new())),
+            stderr_handle: None,
             _reader_handle: reader_handle,
-            child,
-        })
+            child: None,
+            request_timeouts: HashMap::new(),
+        }
+    }
+
+    /// Background task that keeps the last `STDERR_TAIL_LINES` lines the
+    /// server writes to stderr, appending every line to `log` if given.
+    async fn stderr_task(
+        stderr: ChildStderr,
+        tail: Arc<Mutex<VecDeque<String>>>,
+        mut log: Option<std::fs::File>,
+    ) {
+        use std::io::Write;
+
+        let mut lines = BufReader::new(stderr).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            if let Some(file) = &mut log
+                && writeln!(file, "{line}").is_err()
+            {
+                log = None;
+            }
+            let mut tail = tail.lock().await;
+            if tail.len() == STDERR_TAIL_LINES {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+    }
+
+    /// Returns the last `max_lines` lines the server wrote to stderr, oldest
+    /// first. Only servers started with [`Self::spawn_quiet`], or within a
+    /// session, capture stderr.
+    ///
+    /// If the server has exited, waits briefly for its final output to be
+    /// read.
+    pub async fn stderr_tail(&mut self, max_lines: usize) -> Vec<String> {
+        if !self.is_alive()
+            && let Some(handle) = self.stderr_handle.take()
+        {
+            let _ = tokio::time::timeout(STDERR_DRAIN_TIMEOUT, handle).await;
+        }
+        let tail = self.stderr_tail.lock().await;
+        let skip = tail.len().saturating_sub(max_lines);
+        tail.iter().skip(skip).cloned().collect()
     }
 
     /// Background task that reads LSP messages and routes responses to pending requests.
@@ -230,8 +467,8 @@ impl LspClient {
         reason = "Internal task requires many handles to manage client state"
     )]
     async fn reader_task(
-        stdin: Arc<Mutex<ChildStdin>>,
-        stdout: ChildStdout,
+        writer: Arc<Mutex<ServerWriter>>,
+        reader: ServerReader,
         pending: Arc<Mutex<HashMap<RequestId, oneshot::Sender<ResponseMessage>>>>,
         diagnostics: DiagnosticsCache,
         diagnostics_generation: Arc<Mutex<HashMap<Uri, u64>>>,
@@ -246,8 +483,9 @@ impl LspClient {
         publishes_version: Arc<AtomicBool>,
         has_sent_progress: Arc<AtomicBool>,
         cpu_trust_failures: Arc<AtomicU32>,
+        proposed_edits: Arc<Mutex<Vec<WorkspaceEdit>>>,
     ) {
-        let mut reader = BufReader::new(stdout);
+        let mut reader = BufReader::new(reader);
         let mut buffer = BytesMut::with_capacity(8192);
 
         loop {
@@ -293,6 +531,10 @@ impl LspClient {
                             "workspace/configuration" => {
                                 Self::handle_configuration_request(&value, request_id)
                             }
+                            "workspace/applyEdit" => {
+                                Self::handle_apply_edit_request(&value, request_id, &proposed_edits)
+                                    .await
+                            }
                             "window/workDoneProgress/create" => {
                                 // Accept progress token registration so the
                                 // server sends $/progress notifications.
@@ -322,12 +564,12 @@ impl LspClient {
 
                         if let Ok(body) = serde_json::to_string(&response) {
                             let header = format!("Content-Length: {}\r\n\r\n", body.len());
-                            let mut stdin_guard = stdin.lock().await;
-                            if let Err(e) = stdin_guard.write_all(header.as_bytes()).await {
+                            let mut writer_guard = writer.lock().await;
+                            if let Err(e) = writer_guard.write_all(header.as_bytes()).await {
                                 warn!("Failed to write response header: {}", e);
-                            } else if let Err(e) = stdin_guard.write_all(body.as_bytes()).await {
+                            } else if let Err(e) = writer_guard.write_all(body.as_bytes()).await {
                                 warn!("Failed to write response body: {}", e);
-                            } else if let Err(e) = stdin_guard.flush().await {
+                            } else if let Err(e) = writer_guard.flush().await {
                                 warn!("Failed to flush response: {}", e);
                             }
                         }
@@ -372,9 +614,11 @@ impl LspClient {
             }
         }
 
-        // Mark server as dead and clean up orphaned progress tokens
+        // Mark server as dead, fail in-flight requests and clean up orphaned
+        // progress tokens
         alive.store(false, Ordering::SeqCst);
         state.store(ServerState::Dead.as_u8(), Ordering::SeqCst);
+        pending.lock().await.clear();
         progress.lock().await.clear();
         diagnostics_notify.notify_waiters();
         warn!("LSP reader task exiting - server connection lost");
@@ -403,6 +647,50 @@ impl LspClient {
         }
     }
 
+    /// Records the edit from a `workspace/applyEdit` request without
+    /// applying it. Catenary only proposes edits, so the server is told the
+    /// edit was not applied.
+    async fn handle_apply_edit_request(
+        value: &serde_json::Value,
+        id: RequestId,
+        proposed_edits: &Mutex<Vec<WorkspaceEdit>>,
+    ) -> ResponseMessage {
+        let params = value
+            .get("params")
+            .cloned()
+            .and_then(|p| serde_json::from_value::<ApplyWorkspaceEditParams>(p).ok());
+        let Some(params) = params else {
+            return ResponseMessage {
+                jsonrpc: "2.0".to_string(),
+                id: Some(id),
+                result: None,
+                error: Some(protocol::ResponseError {
+                    code: -32602,
+                    message: "Invalid workspace/applyEdit params".to_string(),
+                    data: None,
+                }),
+            };
+        };
+
+        debug!(
+            "Recording proposed edit: {}",
+            params.label.as_deref().unwrap_or("(unlabelled)")
+        );
+        proposed_edits.lock().await.push(params.edit);
+
+        let response = ApplyWorkspaceEditResponse {
+            applied: false,
+            failure_reason: Some("Catenary proposes edits without applying them".to_string()),
+            failed_change: None,
+        };
+        ResponseMessage {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            result: serde_json::to_value(response).ok(),
+            error: None,
+        }
+    }
+
     /// Handles incoming LSP notifications.
     #[allow(
         clippy::too_many_arguments,
@@ -496,9 +784,16 @@ impl LspClient {
                 }
             }
             "window/logMessage" | "window/showMessage" => {
-                // Log messages from the server
-                if let Some(message) = notification.params.get("message").and_then(|m| m.as_str()) {
-                    debug!("LSP server message: {}", message);
+                // Forward messages from the server to the monitor
+                if let Ok(params) =
+                    serde_json::from_value::<LogMessageParams>(notification.params.clone())
+                {
+                    debug!("LSP server message: {}", params.message);
+                    broadcaster.send(EventKind::ServerMessage {
+                        language: language.to_string(),
+                        level: message_level(params.typ).to_string(),
+                        message: params.message,
+                    });
                 }
             }
             _ => {
@@ -510,6 +805,27 @@ impl LspClient {
         }
     }
 
+    /// Sets per-method request timeouts in seconds, keyed by method name.
+    ///
+    /// Keys are either the full LSP method (`workspace/symbol`) or its
+    /// [`timeout_key`] (`workspace_symbol`). Zero entries are ignored.
+    pub fn set_request_timeouts(&mut self, timeouts: &HashMap<String, u64>) {
+        self.request_timeouts = timeouts
+            .iter()
+            .filter(|&(_, &secs)| secs > 0)
+            .map(|(method, &secs)| (method.clone(), Duration::from_secs(secs)))
+            .collect();
+    }
+
+    /// Returns how long to wait for a response to `method`.
+    fn request_timeout(&self, method: &str) -> Duration {
+        self.request_timeouts
+            .get(method)
+            .or_else(|| self.request_timeouts.get(&timeout_key(method)))
+            .copied()
+            .unwrap_or(REQUEST_TIMEOUT)
+    }
+
     /// Sends a request and waits for the response with timeout.
     async fn request<P: serde::Serialize, R: serde::de::DeserializeOwned>(
         &self,
@@ -532,18 +848,27 @@ impl LspClient {
             let (tx, rx) = oneshot::channel();
             {
                 let mut pending = self.pending.lock().await;
+                // The reader clears `pending` on exit; don't wait on a dead server
+                if !self.is_alive() {
+                    return Err(anyhow!("[{}] server closed connection", self.language));
+                }
                 pending.insert(id.clone(), tx);
             }
 
             self.send_message(&request).await?;
 
             // Wait for response with timeout
-            let response = match tokio::time::timeout(REQUEST_TIMEOUT, rx).await {
+            let timeout = self.request_timeout(method);
+            let response = match tokio::time::timeout(timeout, rx).await {
                 Ok(Ok(response)) => response,
                 Ok(Err(_)) => return Err(anyhow!("[{}] server closed connection", self.language)),
                 Err(_) => {
                     self.pending.lock().await.remove(&id);
-                    return Err(anyhow!("[{}] request '{method}' timed out", self.language));
+                    return Err(anyhow!(
+                        "[{}] LSP request '{method}' timed out after {}s",
+                        self.language,
+                        timeout.as_secs()
+                    ));
                 }
             };
 
@@ -598,11 +923,11 @@ impl LspClient {
 
         trace!("Sending LSP message: {}", body);
 
-        let mut stdin = self.stdin.lock().await;
-        stdin.write_all(header.as_bytes()).await?;
-        stdin.write_all(body.as_bytes()).await?;
-        stdin.flush().await?;
-        drop(stdin);
+        let mut writer = self.writer.lock().await;
+        writer.write_all(header.as_bytes()).await?;
+        writer.write_all(body.as_bytes()).await?;
+        writer.flush().await?;
+        drop(writer);
 
         Ok(())
     }
@@ -671,11 +996,36 @@ impl LspClient {
                         }),
                         ..Default::default()
                     }),
+                    selection_range: Some(lsp_types::SelectionRangeClientCapabilities {
+                        dynamic_registration: Some(false),
+                    }),
+                    folding_range: Some(lsp_types::FoldingRangeClientCapabilities {
+                        line_folding_only: Some(true),
+                        ..Default::default()
+                    }),
+                    code_lens: Some(lsp_types::CodeLensClientCapabilities {
+                        dynamic_registration: Some(false),
+                    }),
+                    rename: Some(lsp_types::RenameClientCapabilities {
+                        prepare_support: Some(true),
+                        ..Default::default()
+                    }),
+                    inlay_hint: Some(lsp_types::InlayHintClientCapabilities {
+                        dynamic_registration: Some(false),
+                        resolve_support: None,
+                    }),
                     ..Default::default()
                 }),
                 workspace: Some(lsp_types::WorkspaceClientCapabilities {
                     workspace_folders: Some(true),
                     configuration: Some(true),
+                    apply_edit: Some(true),
+                    did_change_watched_files: Some(
+                        lsp_types::DidChangeWatchedFilesClientCapabilities {
+                            dynamic_registration: Some(false),
+                            relative_pattern_support: Some(false),
+                        },
+                    ),
                     ..Default::default()
                 }),
                 window: Some(lsp_types::WindowClientCapabilities {
@@ -723,6 +1073,70 @@ impl LspClient {
             self.supports_workspace_folders, supported, accepts_changes
         );
 
+        self.completion_trigger_characters = result
+            .capabilities
+            .completion_provider
+            .as_ref()
+            .and_then(|cp| cp.trigger_characters.clone())
+            .unwrap_or_default();
+        self.supports_completion_resolve = result
+            .capabilities
+            .completion_provider
+            .as_ref()
+            .and_then(|cp| cp.resolve_provider)
+            .unwrap_or(false);
+
+        self.supports_selection_range = result
+            .capabilities
+            .selection_range_provider
+            .as_ref()
+            .is_some_and(|p| {
+                !matches!(
+                    p,
+                    lsp_types::SelectionRangeProviderCapability::Simple(false)
+                )
+            });
+
+        self.supports_inlay_hints = result
+            .capabilities
+            .inlay_hint_provider
+            .as_ref()
+            .is_some_and(|p| !matches!(p, lsp_types::OneOf::Left(false)));
+
+        self.supports_folding_ranges = result
+            .capabilities
+            .folding_range_provider
+            .as_ref()
+            .is_some_and(|p| {
+                !matches!(p, lsp_types::FoldingRangeProviderCapability::Simple(false))
+            });
+
+        self.supports_prepare_rename = matches!(
+            result.capabilities.rename_provider,
+            Some(lsp_types::OneOf::Right(lsp_types::RenameOptions {
+                prepare_provider: Some(true),
+                ..
+            }))
+        );
+
+        self.sync_kind = match &result.capabilities.text_document_sync {
+            Some(lsp_types::TextDocumentSyncCapability::Kind(kind)) => *kind,
+            Some(lsp_types::TextDocumentSyncCapability::Options(options)) => {
+                options.change.unwrap_or(TextDocumentSyncKind::FULL)
+            }
+            None => TextDocumentSyncKind::FULL,
+        };
+
+        self.supports_code_lens = result.capabilities.code_lens_provider.is_some();
+        self.supports_code_lens_resolve = result
+            .capabilities
+            .code_lens_provider
+            .as_ref()
+            .and_then(|p| p.resolve_provider)
+            .unwrap_or(false);
+
+        self.supported_tools = extract_capabilities(&result.capabilities);
+
         // Send initialized notification
         self.notify("initialized", InitializedParams {}).await?;
 
@@ -738,6 +1152,57 @@ impl LspClient {
         self.encoding.clone()
     }
 
+    /// Returns the document sync kind the server advertised for `didChange`.
+    pub const fn sync_kind(&self) -> TextDocumentSyncKind {
+        self.sync_kind
+    }
+
+    /// Returns the completion trigger characters advertised by the server.
+    pub fn completion_trigger_characters(&self) -> &[String] {
+        &self.completion_trigger_characters
+    }
+
+    /// Returns whether the server answers `completionItem/resolve`.
+    pub const fn supports_completion_resolve(&self) -> bool {
+        self.supports_completion_resolve
+    }
+
+    /// Returns whether the server answers `textDocument/selectionRange`.
+    pub const fn supports_selection_range(&self) -> bool {
+        self.supports_selection_range
+    }
+
+    /// Returns whether the server answers `textDocument/inlayHint`.
+    pub const fn supports_inlay_hints(&self) -> bool {
+        self.supports_inlay_hints
+    }
+
+    /// Returns whether the server answers `textDocument/foldingRange`.
+    pub const fn supports_folding_ranges(&self) -> bool {
+        self.supports_folding_ranges
+    }
+
+    /// Returns whether the server answers `textDocument/prepareRename`.
+    pub const fn supports_prepare_rename(&self) -> bool {
+        self.supports_prepare_rename
+    }
+
+    /// Returns whether the server answers `textDocument/codeLens`.
+    pub const fn supports_code_lens(&self) -> bool {
+        self.supports_code_lens
+    }
+
+    /// Returns whether the server answers `codeLens/resolve`.
+    pub const fn supports_code_lens_resolve(&self) -> bool {
+        self.supports_code_lens_resolve
+    }
+
+    /// Returns the Catenary tools the server's capabilities support, as
+    /// listed by [`extract_capabilities`].
+    pub fn supported_tools(&self) -> &[&'static str] {
+        &self.supported_tools
+    }
+
     /// Sends shutdown request and exit notification.
     ///
     /// # Errors
@@ -750,6 +1215,19 @@ impl LspClient {
         Ok(())
     }
 
+    /// Force-kills the server process and waits for it to exit. A server
+    /// reached over TCP is disconnected instead.
+    pub async fn kill(&mut self) {
+        if let Some(child) = &mut self.child {
+            if let Err(e) = child.kill().await {
+                warn!("[{}] failed to kill server process: {e}", self.language);
+            }
+        } else if let Err(e) = self.writer.lock().await.shutdown().await {
+            debug!("[{}] failed to close connection: {e}", self.language);
+        }
+        self.alive.store(false, Ordering::SeqCst);
+    }
+
     /// Notifies the LSP server that a document was opened.
     ///
     /// # Errors
@@ -759,9 +1237,20 @@ impl LspClient {
         let uri = params.text_document.uri.clone();
         let version = params.text_document.version;
         self.last_sent_version.lock().await.insert(uri, version);
+        *self.symbol_cache.lock().await = None;
         self.notify("textDocument/didOpen", params).await
     }
 
+    /// Returns whether `uri` has been opened on this server.
+    pub async fn has_opened(&self, uri: &Uri) -> bool {
+        self.last_sent_version.lock().await.contains_key(uri)
+    }
+
+    /// Returns the version of `uri` last sent to this server, if any.
+    pub async fn sent_version(&self, uri: &Uri) -> Option<i32> {
+        self.last_sent_version.lock().await.get(uri).copied()
+    }
+
     /// Notifies the LSP server that a document changed.
     ///
     /// # Errors
@@ -771,6 +1260,7 @@ impl LspClient {
         let uri = params.text_document.uri.clone();
         let version = params.text_document.version;
         self.last_sent_version.lock().await.insert(uri, version);
+        *self.symbol_cache.lock().await = None;
         self.notify("textDocument/didChange", params).await
     }
 
@@ -793,6 +1283,20 @@ impl LspClient {
         .await
     }
 
+    /// Notifies the LSP server that files changed on disk, for changes its
+    /// own file watching may not see.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the notification fails.
+    pub async fn did_change_watched_files(&self, changes: Vec<FileEvent>) -> Result<()> {
+        self.notify(
+            "workspace/didChangeWatchedFiles",
+            DidChangeWatchedFilesParams { changes },
+        )
+        .await
+    }
+
     /// Notifies the LSP server that a document was closed.
     ///
     /// # Errors
@@ -902,6 +1406,44 @@ impl LspClient {
         self.request("workspace/symbol", params).await
     }
 
+    /// Searches for symbols across the workspace, reusing the previous
+    /// result when the query extends the previously sent query.
+    ///
+    /// Agents often narrow a search iteratively (`Foo` → `FooBar`). When the
+    /// new query starts with the cached one, the cached result set is
+    /// filtered client-side instead of re-querying the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or times out.
+    pub async fn workspace_symbols_cached(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> Result<Option<WorkspaceSymbolResponse>> {
+        let query = params.query.clone();
+
+        if let Some((prefix, cached)) = self.symbol_cache.lock().await.as_ref()
+            && !prefix.is_empty()
+            && query.starts_with(prefix.as_str())
+        {
+            trace!("workspace/symbol cache hit: {query:?} extends {prefix:?}");
+            return Ok(Some(filter_workspace_symbols(cached, &query)));
+        }
+
+        let response = self.workspace_symbols(params).await?;
+
+        let cacheable = response
+            .as_ref()
+            .is_some_and(|r| workspace_symbol_count(r) < SYMBOL_CACHE_MAX_RESULTS);
+        *self.symbol_cache.lock().await = if cacheable {
+            response.clone().map(|r| (query, r))
+        } else {
+            None
+        };
+
+        Ok(response)
+    }
+
     /// Gets code actions (quick fixes, refactorings) for a range.
     ///
     /// # Errors
@@ -914,6 +1456,51 @@ impl LspClient {
         self.request("textDocument/codeAction", params).await
     }
 
+    /// Gets the nested selection ranges around each position.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or times out.
+    pub async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> Result<Option<Vec<SelectionRange>>> {
+        self.request("textDocument/selectionRange", params).await
+    }
+
+    /// Gets the occurrences of the symbol at a position within its document.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or times out.
+    pub async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        self.request("textDocument/documentHighlight", params).await
+    }
+
+    /// Gets the collapsible regions of a document.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or times out.
+    pub async fn folding_ranges(
+        &self,
+        params: FoldingRangeParams,
+    ) -> Result<Option<Vec<FoldingRange>>> {
+        self.request("textDocument/foldingRange", params).await
+    }
+
+    /// Gets the inlay hints (inferred types, parameter names) in a range.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or times out.
+    pub async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        self.request("textDocument/inlayHint", params).await
+    }
+
     /// Resolves a code action (e.g. fills in the 'edit' property).
     ///
     /// # Errors
@@ -926,6 +1513,65 @@ impl LspClient {
         self.request("codeAction/resolve", code_action).await
     }
 
+    /// Runs `workspace/executeCommand`, capturing any edits the server
+    /// sends back through `workspace/applyEdit` instead of applying them.
+    ///
+    /// A server may report failure because the edit was not applied; as
+    /// long as it proposed an edit, that is not treated as an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or times out without the
+    /// server proposing any edit.
+    pub async fn execute_command(
+        &self,
+        command: &str,
+        arguments: Vec<serde_json::Value>,
+    ) -> Result<CommandOutcome> {
+        self.proposed_edits.lock().await.clear();
+        let params = ExecuteCommandParams {
+            command: command.to_string(),
+            arguments,
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+        };
+        let result: Result<Option<serde_json::Value>> =
+            self.request("workspace/executeCommand", params).await;
+        let edits = std::mem::take(&mut *self.proposed_edits.lock().await);
+
+        match result {
+            Ok(result) => Ok(CommandOutcome { result, edits }),
+            Err(e) if edits.is_empty() => Err(e),
+            Err(e) => {
+                debug!(
+                    "[{}] {command} failed after proposing edits: {e}",
+                    self.language
+                );
+                Ok(CommandOutcome {
+                    result: None,
+                    edits,
+                })
+            }
+        }
+    }
+
+    /// Gets the code lenses for a document.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or times out.
+    pub async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        self.request("textDocument/codeLens", params).await
+    }
+
+    /// Resolves a code lens (fills in its command).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or times out.
+    pub async fn resolve_code_lens(&self, code_lens: CodeLens) -> Result<CodeLens> {
+        self.request("codeLens/resolve", code_lens).await
+    }
+
     /// Computes a rename operation across the workspace.
     ///
     /// # Errors
@@ -935,6 +1581,18 @@ impl LspClient {
         self.request("textDocument/rename", params).await
     }
 
+    /// Checks whether the symbol at a position can be renamed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or times out.
+    pub async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> Result<Option<PrepareRenameResponse>> {
+        self.request("textDocument/prepareRename", params).await
+    }
+
     /// Gets completion suggestions at a position.
     ///
     /// # Errors
@@ -944,6 +1602,18 @@ impl LspClient {
         self.request("textDocument/completion", params).await
     }
 
+    /// Resolves a completion item (e.g. fills in documentation and detail).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the request fails or times out.
+    pub async fn resolve_completion_item(
+        &self,
+        item: lsp_types::CompletionItem,
+    ) -> Result<lsp_types::CompletionItem> {
+        self.request("completionItem/resolve", item).await
+    }
+
     /// Gets signature help for a function call.
     ///
     /// # Errors
@@ -1063,6 +1733,30 @@ impl LspClient {
             .unwrap_or_default()
     }
 
+    /// Drops cached diagnostics for a URI.
+    ///
+    /// The generation counter is left untouched so waiters snapshotting it
+    /// still see the next `publishDiagnostics` as fresh.
+    pub async fn clear_diagnostics(&self, uri: &Uri) {
+        self.diagnostics.lock().await.remove(uri);
+    }
+
+    /// Gets all cached diagnostics, including files that were never opened.
+    ///
+    /// URIs whose latest publication was empty are omitted. Results are
+    /// sorted by URI.
+    pub async fn all_diagnostics(&self) -> Vec<(Uri, Vec<Diagnostic>)> {
+        let cache = self.diagnostics.lock().await;
+        let mut all: Vec<(Uri, Vec<Diagnostic>)> = cache
+            .iter()
+            .filter(|(_, (_, diags))| !diags.is_empty())
+            .map(|(uri, (_, diags))| (uri.clone(), diags.clone()))
+            .collect();
+        drop(cache);
+        all.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+        all
+    }
+
     /// Gets the cached diagnostics version for a URI.
     ///
     /// Returns `None` if no diagnostics have been published for this URI
@@ -1166,7 +1860,7 @@ impl LspClient {
 
     /// Returns the PID of the server process, if available.
     pub(crate) fn pid(&self) -> Option<u32> {
-        self.child.id()
+        self.child.as_ref().and_then(Child::id)
     }
 
     /// Waits for fresh diagnostics after a file change, using the
@@ -1377,6 +2071,45 @@ impl LspClient {
         }
     }
 
+    /// Waits until diagnostics for `uri` stop changing.
+    ///
+    /// Returns once no `publishDiagnostics` has arrived for `uri` within
+    /// `settle_duration`, or when `timeout` expires. Unlike
+    /// [`wait_for_diagnostics_update`], this ignores progress and CPU
+    /// signals, which suits servers with unreliable progress reporting.
+    pub async fn wait_for_diagnostics_settle(
+        &self,
+        uri: &Uri,
+        settle_duration: Duration,
+        timeout: Duration,
+    ) -> DiagnosticsWaitResult {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let poll_interval = Duration::from_millis(100);
+        let mut last_generation = self.diagnostics_generation(uri).await;
+        let mut last_change = tokio::time::Instant::now();
+
+        loop {
+            let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+            if remaining.is_zero() {
+                return DiagnosticsWaitResult::Updated;
+            }
+
+            tokio::time::sleep(remaining.min(poll_interval)).await;
+
+            if !self.is_alive() {
+                return DiagnosticsWaitResult::ServerDied;
+            }
+
+            let generation = self.diagnostics_generation(uri).await;
+            if generation != last_generation {
+                last_generation = generation;
+                last_change = tokio::time::Instant::now();
+            } else if last_change.elapsed() >= settle_duration {
+                return DiagnosticsWaitResult::Updated;
+            }
+        }
+    }
+
     /// Returns the language identifier for this client (e.g., "rust", "python").
     pub fn language(&self) -> &str {
         &self.language
@@ -1416,7 +2149,7 @@ impl LspClient {
 
         // Even if state is Ready, if we just spawned, wait a bit to see if
         // the server starts indexing (e.g. rust-analyzer takes a moment to send $/progress).
-        if self.spawn_time.elapsed() < Duration::from_millis(3000) {
+        if self.spawn_time.elapsed() < Duration::from_secs(3) {
             return false;
         }
 
@@ -1442,6 +2175,7 @@ impl LspClient {
             progress_message: message,
             progress_percentage: percentage,
             uptime_secs: self.uptime().as_secs(),
+            restarts: 0,
         }
     }
 
@@ -1467,15 +2201,158 @@ impl LspClient {
     /// Includes a grace period to allow the server to start indexing.
     pub async fn wait_for_analysis(&self) -> bool {
         // Give server a moment to start indexing
-        tokio::time::sleep(Duration::from_millis(1000)).await;
+        tokio::time::sleep(Duration::from_secs(1)).await;
         self.wait_ready().await
     }
 }
 
+/// Returns the number of symbols in a `workspace/symbol` response.
+const fn workspace_symbol_count(response: &WorkspaceSymbolResponse) -> usize {
+    match response {
+        WorkspaceSymbolResponse::Flat(symbols) => symbols.len(),
+        WorkspaceSymbolResponse::Nested(symbols) => symbols.len(),
+    }
+}
+
+/// Filters a cached `workspace/symbol` response down to symbols matching `query`.
+fn filter_workspace_symbols(
+    response: &WorkspaceSymbolResponse,
+    query: &str,
+) -> WorkspaceSymbolResponse {
+    match response {
+        WorkspaceSymbolResponse::Flat(symbols) => WorkspaceSymbolResponse::Flat(
+            symbols
+                .iter()
+                .filter(|s| fuzzy_matches(&s.name, query))
+                .cloned()
+                .collect(),
+        ),
+        WorkspaceSymbolResponse::Nested(symbols) => WorkspaceSymbolResponse::Nested(
+            symbols
+                .iter()
+                .filter(|s| fuzzy_matches(&s.name, query))
+                .cloned()
+                .collect(),
+        ),
+    }
+}
+
+/// Maps a `window/*Message` type to the level shown by `catenary monitor`.
+const fn message_level(typ: MessageType) -> &'static str {
+    match typ {
+        MessageType::ERROR => "error",
+        MessageType::WARNING => "warning",
+        MessageType::INFO => "info",
+        _ => "log",
+    }
+}
+
+/// Case-insensitive subsequence match, mirroring the fuzzy matching most
+/// servers apply to `workspace/symbol` queries.
+fn fuzzy_matches(name: &str, query: &str) -> bool {
+    let mut name_chars = name.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|q| name_chars.any(|n| n == q))
+}
+
 impl Drop for LspClient {
     fn drop(&mut self) {
         // We can't await a graceful LSP shutdown here because drop is sync.
         // But we MUST ensure the child process doesn't become a zombie.
-        let _ = self.child.start_kill();
+        if let Some(child) = &mut self.child {
+            let _ = child.start_kill();
+        }
+    }
+}
+
+/// Returns the `[timeouts]` key for an LSP method: the method in snake case
+/// without its `textDocument/` prefix, e.g. `workspace/symbol` ->
+/// `workspace_symbol` and `textDocument/documentSymbol` -> `document_symbol`.
+fn timeout_key(method: &str) -> String {
+    let name = method.strip_prefix("textDocument/").unwrap_or(method);
+    let mut key = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        if c == '/' {
+            key.push('_');
+        } else if c.is_ascii_uppercase() {
+            key.push('_');
+            key.push(c.to_ascii_lowercase());
+        } else {
+            key.push(c);
+        }
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timeout_key() {
+        assert_eq!(timeout_key("textDocument/hover"), "hover");
+        assert_eq!(
+            timeout_key("textDocument/documentSymbol"),
+            "document_symbol"
+        );
+        assert_eq!(timeout_key("workspace/symbol"), "workspace_symbol");
+        assert_eq!(
+            timeout_key("workspace/executeCommand"),
+            "workspace_execute_command"
+        );
+        assert_eq!(
+            timeout_key("callHierarchy/incomingCalls"),
+            "call_hierarchy_incoming_calls"
+        );
+        assert_eq!(timeout_key("shutdown"), "shutdown");
+    }
+
+    #[allow(
+        deprecated,
+        reason = "SymbolInformation::deprecated is required by lsp-types"
+    )]
+    fn make_symbol(name: &str) -> Result<lsp_types::SymbolInformation> {
+        Ok(lsp_types::SymbolInformation {
+            name: name.to_string(),
+            kind: lsp_types::SymbolKind::FUNCTION,
+            tags: None,
+            deprecated: None,
+            location: lsp_types::Location {
+                uri: "file:///test.rs".parse()?,
+                range: lsp_types::Range::default(),
+            },
+            container_name: None,
+        })
+    }
+
+    #[test]
+    fn test_fuzzy_matches() {
+        assert!(fuzzy_matches("FooBar", "foo"));
+        assert!(fuzzy_matches("FooBar", "fb"));
+        assert!(fuzzy_matches("foo_bar", "FOOBAR"));
+        assert!(!fuzzy_matches("FooBar", "baz"));
+        assert!(!fuzzy_matches("FooBar", "rab"));
+        assert!(fuzzy_matches("anything", ""));
+    }
+
+    #[test]
+    fn test_filter_workspace_symbols_narrows() -> Result<()> {
+        let response = WorkspaceSymbolResponse::Flat(vec![
+            make_symbol("parse")?,
+            make_symbol("parse_args")?,
+            make_symbol("parser_state")?,
+        ]);
+
+        let WorkspaceSymbolResponse::Flat(filtered) =
+            filter_workspace_symbols(&response, "parse_ar")
+        else {
+            anyhow::bail!("filtering should preserve the response shape");
+        };
+        let names: Vec<&str> = filtered.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["parse_args"]);
+        assert_eq!(workspace_symbol_count(&response), 3);
+        Ok(())
     }
 }
@@ -124,8 +124,8 @@ impl ProcessMonitor {
     /// patience. After 3+ failures, only a 5-second settle is allowed.
     pub fn patience(&self) -> std::time::Duration {
         match self.trust_failures.load(Ordering::SeqCst) {
-            0 => std::time::Duration::from_secs(120),
-            1 => std::time::Duration::from_secs(60),
+            0 => std::time::Duration::from_mins(2),
+            1 => std::time::Duration::from_mins(1),
             2 => std::time::Duration::from_secs(30),
             _ => std::time::Duration::from_secs(5),
         }
@@ -280,10 +280,10 @@ mod tests {
         let trust = Arc::new(AtomicU32::new(0));
         let monitor = ProcessMonitor::new(1, alive, trust.clone());
 
-        assert_eq!(monitor.patience(), std::time::Duration::from_secs(120));
+        assert_eq!(monitor.patience(), std::time::Duration::from_mins(2));
 
         trust.store(1, Ordering::SeqCst);
-        assert_eq!(monitor.patience(), std::time::Duration::from_secs(60));
+        assert_eq!(monitor.patience(), std::time::Duration::from_mins(1));
 
         trust.store(2, Ordering::SeqCst);
         assert_eq!(monitor.patience(), std::time::Duration::from_secs(30));
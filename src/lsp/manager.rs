@@ -2,35 +2,91 @@
 // Copyright (C) 2026 Mark Wells <contact@markwells.dev>
 
 use anyhow::{Result, anyhow};
+use globset::GlobSet;
 use ignore::WalkBuilder;
+use lsp_types::DidCloseTextDocumentParams;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
-use tracing::{info, warn};
+use tracing::{debug, info, warn};
 
-use crate::config::Config;
+use crate::bridge::DocumentManager;
+use crate::config::{Config, LanguageOverrides};
 use crate::lsp::LspClient;
-use crate::lsp::state::ServerStatus;
-use crate::session::EventBroadcaster;
+use crate::lsp::state::{ServerState, ServerStatus};
+use crate::session::{EventBroadcaster, EventKind};
+
+/// How many times a crashed server is restarted before Catenary gives up on it.
+const MAX_RESTARTS: u32 = 5;
+
+/// Delay before the first restart of a crashed server; doubled for each
+/// further restart.
+const RESTART_BACKOFF: Duration = Duration::from_millis(200);
+
+/// How long a login shell may take to resolve a server command before
+/// Catenary falls back to its own `PATH`.
+const LOGIN_SHELL_TIMEOUT: Duration = Duration::from_secs(3);
 
 /// Manages the lifecycle of LSP clients (spawning, caching, shutdown).
 pub struct ClientManager {
     config: Config,
     roots: Mutex<Vec<PathBuf>>,
     active_clients: Mutex<HashMap<String, Arc<Mutex<LspClient>>>>,
+    /// Compiled `document_selector` globs, for servers that set one.
+    selectors: HashMap<String, GlobSet>,
     broadcaster: EventBroadcaster,
+    /// Number of times each client key has been restarted after a crash.
+    restarts: Mutex<HashMap<String, u32>>,
+    /// When each client key was first seen with no open documents.
+    last_document_closed_at: Mutex<HashMap<String, Instant>>,
+    /// Number of [`Self::spawn_all`] runs started or queued but not finished.
+    pending_spawns: Arc<AtomicUsize>,
+}
+
+/// Decrements a pending-spawn counter when dropped, so a cancelled
+/// [`ClientManager::spawn_all`] does not leave it raised.
+struct PendingSpawn(Arc<AtomicUsize>);
+
+impl PendingSpawn {
+    fn new(counter: &Arc<AtomicUsize>) -> Self {
+        counter.fetch_add(1, Ordering::SeqCst);
+        Self(counter.clone())
+    }
+}
+
+impl Drop for PendingSpawn {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
 }
 
 impl ClientManager {
     /// Creates a new `ClientManager`.
     #[must_use]
     pub fn new(config: Config, roots: Vec<PathBuf>, broadcaster: EventBroadcaster) -> Self {
+        let selectors = config
+            .server
+            .iter()
+            .filter_map(|(lang, server)| match server.document_selector_set() {
+                Ok(set) => set.map(|set| (lang.clone(), set)),
+                Err(e) => {
+                    warn!("Ignoring document_selector for {lang}: {e:#}");
+                    None
+                }
+            })
+            .collect();
         Self {
             config,
             roots: Mutex::new(roots),
             active_clients: Mutex::new(HashMap::new()),
+            selectors,
             broadcaster,
+            restarts: Mutex::new(HashMap::new()),
+            last_document_closed_at: Mutex::new(HashMap::new()),
+            pending_spawns: Arc::new(AtomicUsize::new(0)),
         }
     }
 
@@ -39,29 +95,95 @@ impl ClientManager {
     /// Scans workspace roots for file types, matches against configured
     /// server keys, and only spawns servers for languages actually present.
     /// Servers that fail to spawn are logged and skipped — a misconfigured
-    /// server should not prevent other servers from starting.
+    /// server should not prevent other servers from starting. Languages
+    /// with `isolate_roots` start one server pinned to each root.
     pub async fn spawn_all(&self) {
+        let _pending = PendingSpawn::new(&self.pending_spawns);
         let roots = self.roots.lock().await.clone();
         let configured_keys: HashSet<&str> =
             self.config.server.keys().map(String::as_str).collect();
-        let relevant = detect_workspace_languages(&roots, &configured_keys);
+        let overrides = self.config.language_overrides().unwrap_or_else(|e| {
+            warn!("Ignoring [languages]: {e:#}");
+            LanguageOverrides::default()
+        });
+        // With a cap, the languages with the most files take the available
+        // slots, which takes a full walk instead of stopping at the first match.
+        let max_eager = self.config.max_concurrent_servers;
+        let detected = if max_eager == 0 {
+            let mut sorted: Vec<String> =
+                detect_workspace_languages(&roots, &configured_keys, &overrides)
+                    .into_iter()
+                    .collect();
+            sorted.sort_unstable();
+            sorted
+        } else {
+            rank_by_file_count(count_workspace_languages(
+                &roots,
+                &configured_keys,
+                &overrides,
+            ))
+        };
 
-        if relevant.is_empty() {
+        if detected.is_empty() {
             info!("No configured languages detected in workspace");
             return;
         }
 
-        let mut sorted: Vec<&str> = relevant.iter().map(String::as_str).collect();
-        sorted.sort_unstable();
-        info!("Detected languages in workspace: {}", sorted.join(", "));
+        let ranked: Vec<&str> = detected.iter().map(String::as_str).collect();
+        info!("Detected languages in workspace: {}", ranked.join(", "));
 
-        for lang in &relevant {
-            if let Err(e) = self.get_client(lang).await {
+        let (eager, deferred) = split_eager(&ranked, max_eager);
+        if !deferred.is_empty() {
+            info!(
+                "max_concurrent_servers is {max_eager}; deferring until first use: {}",
+                deferred.join(", ")
+            );
+        }
+
+        for lang in eager {
+            // Languages with `isolate_roots` get one server per root and
+            // never a shared one spanning every root.
+            if self
+                .config
+                .server
+                .get(*lang)
+                .is_some_and(|s| s.isolate_roots)
+            {
+                for root in &roots {
+                    let key = isolated_key(lang, root);
+                    if let Err(e) = self.get_or_spawn(&key, lang, Some(root.clone())).await {
+                        warn!("Failed to spawn LSP server for {key}: {e}");
+                    }
+                }
+            } else if let Err(e) = self.get_client(lang).await {
                 warn!("Failed to spawn LSP server for {lang}: {e}");
             }
         }
     }
 
+    /// Runs [`Self::spawn_all`] on a background task. [`Self::is_spawning`]
+    /// reports it from the moment this returns, before the task is polled.
+    pub fn spawn_all_in_background(self: &Arc<Self>) -> tokio::task::JoinHandle<()> {
+        let pending = PendingSpawn::new(&self.pending_spawns);
+        let manager = self.clone();
+        tokio::spawn(async move {
+            manager.spawn_all().await;
+            drop(pending);
+        })
+    }
+
+    /// Returns whether a [`Self::spawn_all`] is queued or still running.
+    #[must_use]
+    pub fn is_spawning(&self) -> bool {
+        self.pending_spawns.load(Ordering::SeqCst) > 0
+    }
+
+    /// Returns the loaded configuration.
+    #[must_use]
+    pub const fn config(&self) -> &Config {
+        &self.config
+    }
+
     /// Returns the current workspace roots.
     pub async fn roots(&self) -> Vec<PathBuf> {
         self.roots.lock().await.clone()
@@ -92,6 +214,10 @@ impl ClientManager {
         let clients = self.active_clients.lock().await.clone();
         let mut to_restart = Vec::new();
         for (lang, client_mutex) in &clients {
+            // Servers isolated to one root never see other roots.
+            if split_client_key(lang).1.is_some() {
+                continue;
+            }
             let client = client_mutex.lock().await;
             if !client.is_alive() {
                 continue;
@@ -146,7 +272,15 @@ impl ClientManager {
         // restart those that don't.
         let clients = self.active_clients.lock().await.clone();
         let mut to_restart = Vec::new();
+        let mut orphaned = Vec::new();
         for (lang, client_mutex) in &clients {
+            // Servers isolated to one root only care about their own.
+            if let Some(pinned) = split_client_key(lang).1 {
+                if pinned == root {
+                    orphaned.push(lang.clone());
+                }
+                continue;
+            }
             let client = client_mutex.lock().await;
             if !client.is_alive() {
                 continue;
@@ -174,6 +308,14 @@ impl ClientManager {
             self.shutdown_client(lang).await;
         }
 
+        for key in &orphaned {
+            info!(
+                "Workspace root for {} server was removed, shutting it down",
+                key
+            );
+            self.shutdown_client(key).await;
+        }
+
         Ok(())
     }
 
@@ -181,12 +323,17 @@ impl ClientManager {
     ///
     /// Diffs against current roots: adds new ones, removes stale ones.
     /// Sends a single `didChangeWorkspaceFolders` notification per client
-    /// with both additions and removals.
+    /// with both additions and removals. Documents tracked under a removed
+    /// root are closed first, so their diagnostics do not outlive the root.
     ///
     /// # Errors
     ///
     /// Returns an error if any root path cannot be converted to a valid URI.
-    pub async fn sync_roots(&self, new_roots: Vec<PathBuf>) -> Result<()> {
+    pub async fn sync_roots(
+        &self,
+        new_roots: Vec<PathBuf>,
+        doc_manager: &Mutex<DocumentManager>,
+    ) -> Result<()> {
         let current_roots = self.roots.lock().await.clone();
 
         let to_add: Vec<&PathBuf> = new_roots
@@ -240,6 +387,14 @@ impl ClientManager {
             })
             .collect::<Result<Vec<_>>>()?;
 
+        if !to_remove.is_empty() {
+            let removed: Vec<PathBuf> = to_remove.iter().map(|r| (*r).clone()).collect();
+            let closed = doc_manager.lock().await.close_under(&removed, &new_roots);
+            if !closed.is_empty() {
+                self.close_documents(closed).await;
+            }
+        }
+
         // Update internal state
         *self.roots.lock().await = new_roots;
 
@@ -247,7 +402,15 @@ impl ClientManager {
         // restart those that don't.
         let clients = self.active_clients.lock().await.clone();
         let mut to_restart = Vec::new();
+        let mut orphaned = Vec::new();
         for (lang, client_mutex) in &clients {
+            // Servers isolated to one root only care about their own.
+            if let Some(pinned) = split_client_key(lang).1 {
+                if to_remove.iter().any(|r| r.as_path() == pinned) {
+                    orphaned.push(lang.clone());
+                }
+                continue;
+            }
             let client = client_mutex.lock().await;
             if !client.is_alive() {
                 continue;
@@ -275,9 +438,37 @@ impl ClientManager {
             self.shutdown_client(lang).await;
         }
 
+        for key in &orphaned {
+            info!(
+                "Workspace root for {} server was removed, shutting it down",
+                key
+            );
+            self.shutdown_client(key).await;
+        }
+
         Ok(())
     }
 
+    /// Sends `didClose` for each document to every live server that has it
+    /// open, and drops the diagnostics cached for it.
+    async fn close_documents(&self, closed: Vec<DidCloseTextDocumentParams>) {
+        let clients = self.active_clients.lock().await.clone();
+        for params in closed {
+            let uri = params.text_document.uri.clone();
+            for (lang, client_mutex) in &clients {
+                let client = client_mutex.lock().await;
+                if !client.is_alive() || client.sent_version(&uri).await.is_none() {
+                    continue;
+                }
+                if let Err(e) = client.did_close(params.clone()).await {
+                    warn!("Failed to close {} on {} server: {}", uri.as_str(), lang, e);
+                }
+                client.clear_diagnostics(&uri).await;
+                drop(client);
+            }
+        }
+    }
+
     /// Gets an active client for the given language, spawning it if necessary.
     ///
     /// # Errors
@@ -287,19 +478,197 @@ impl ClientManager {
     /// - The server fails to spawn.
     /// - The server fails to initialize.
     pub async fn get_client(&self, lang: &str) -> Result<Arc<Mutex<LspClient>>> {
-        if let Some(client) = self.active_clients.lock().await.get(lang) {
-            // Check if it's still alive
-            let is_alive = client.lock().await.is_alive();
+        self.get_or_spawn(lang, lang, None).await
+    }
 
-            if is_alive {
-                return Ok(client.clone());
+    /// Gets the client that handles `path`, spawning it if necessary.
+    ///
+    /// For languages with `isolate_roots` set, each workspace root gets its
+    /// own server, keyed `<lang>@<root>`, and `path` is routed to the server
+    /// of the root that contains it. Paths outside every root, and languages
+    /// without `isolate_roots`, use the shared per-language client.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error under the same conditions as [`Self::get_client`].
+    pub async fn get_client_for_file(
+        &self,
+        lang: &str,
+        path: &Path,
+    ) -> Result<Arc<Mutex<LspClient>>> {
+        if self.config.server.contains_key(lang) && !self.handles_file(lang, path).await {
+            return Err(anyhow!(
+                "{} is not matched by the document_selector of the {lang} server",
+                path.display()
+            ));
+        }
+        match self.isolated_root(lang, path).await {
+            Some(root) => {
+                let key = isolated_key(lang, &root);
+                self.get_or_spawn(&key, lang, Some(root)).await
+            }
+            None => self.get_client(lang).await,
+        }
+    }
+
+    /// Returns the companion servers of `lang` that accept `path`, spawning
+    /// them if necessary, as `(key, client)` pairs sorted by key.
+    ///
+    /// Companions are servers that list `lang` in their `languages`. One
+    /// that fails to start is logged and left out, so a broken linter never
+    /// hides the primary server's results.
+    pub async fn companion_clients_for_file(
+        &self,
+        lang: &str,
+        path: &Path,
+    ) -> Vec<(String, Arc<Mutex<LspClient>>)> {
+        let mut companions: Vec<&String> = self
+            .config
+            .server
+            .iter()
+            .filter(|(key, server)| *key != lang && server.languages.iter().any(|l| l == lang))
+            .map(|(key, _)| key)
+            .collect();
+        companions.sort();
+
+        let mut clients = Vec::new();
+        for companion in companions {
+            if !self.handles_file(companion, path).await {
+                continue;
+            }
+            match self.get_client_for_file(companion, path).await {
+                Ok(client) => {
+                    let key = self.client_key_for_file(companion, path).await;
+                    clients.push((key, client));
+                }
+                Err(e) => warn!("[{companion}] companion server unavailable: {e}"),
             }
-            warn!("LSP server for {} died, restarting...", lang);
-            self.active_clients.lock().await.remove(lang);
         }
+        clients
+    }
+
+    /// Returns whether the server configured for `lang` should receive
+    /// `path`: it must exist and, if it sets a `document_selector`, one of
+    /// its globs must match the path relative to the root containing it.
+    pub async fn handles_file(&self, lang: &str, path: &Path) -> bool {
+        if !self.config.server.contains_key(lang) {
+            return false;
+        }
+        let Some(selector) = self.selectors.get(lang) else {
+            return true;
+        };
+        let root = owning_root(&self.roots.lock().await, path).cloned();
+        let relative = root
+            .as_deref()
+            .and_then(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path);
+        selector.is_match(relative)
+    }
+
+    /// Returns the key of the client that handles `path`, matching the
+    /// keys of [`Self::active_clients`].
+    pub async fn client_key_for_file(&self, lang: &str, path: &Path) -> String {
+        self.isolated_root(lang, path)
+            .await
+            .map_or_else(|| lang.to_string(), |root| isolated_key(lang, &root))
+    }
+
+    /// Returns the root whose server should handle `path`, if `lang` runs one
+    /// server per root.
+    async fn isolated_root(&self, lang: &str, path: &Path) -> Option<PathBuf> {
+        if !self
+            .config
+            .server
+            .get(lang)
+            .is_some_and(|s| s.isolate_roots)
+        {
+            return None;
+        }
+        let roots = self.roots.lock().await;
+        owning_root(&roots, path).cloned()
+    }
+
+    /// Refuses to spawn `key` when `max` other servers are already running.
+    fn check_server_cap(
+        clients: &HashMap<String, Arc<Mutex<LspClient>>>,
+        key: &str,
+        max: usize,
+    ) -> Result<()> {
+        if max == 0 {
+            return Ok(());
+        }
+        // Dead clients do not count; busy ones (locked for a request) do.
+        let mut running: Vec<&str> = clients
+            .iter()
+            .filter(|(other, client)| {
+                other.as_str() != key && client.try_lock().map_or(true, |c| c.is_alive())
+            })
+            .map(|(other, _)| other.as_str())
+            .collect();
+        if running.len() >= max {
+            running.sort_unstable();
+            return Err(anyhow!(
+                "[{key}] max_concurrent_servers ({max}) reached, running: {}; \
+                 raise the limit or wait for an idle server to shut down",
+                running.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the client stored under `key`, spawning the server for `lang`
+    /// if it is missing or dead. A `pinned_root` initializes the server with
+    /// that root alone instead of every workspace root.
+    ///
+    /// A dead server is restarted after an exponential backoff, at most
+    /// [`MAX_RESTARTS`] times per key.
+    async fn get_or_spawn(
+        &self,
+        key: &str,
+        lang: &str,
+        pinned_root: Option<PathBuf>,
+    ) -> Result<Arc<Mutex<LspClient>>> {
+        let existing = self.active_clients.lock().await.get(key).cloned();
+        let restart = match existing {
+            Some(client) => {
+                if client.lock().await.is_alive() {
+                    return Ok(client);
+                }
+                let attempt = self.restart_count(key).await + 1;
+                if attempt > MAX_RESTARTS {
+                    return Err(anyhow!(
+                        "[{key}] server is no longer running (restarted {MAX_RESTARTS} times, giving up)"
+                    ));
+                }
+                let delay = restart_backoff(attempt);
+                warn!(
+                    "LSP server for {key} died, restarting in {}ms (attempt {attempt}/{MAX_RESTARTS})",
+                    delay.as_millis()
+                );
+                tokio::time::sleep(delay).await;
+                Some(attempt)
+            }
+            None => None,
+        };
 
         let mut clients = self.active_clients.lock().await;
 
+        if let Some(attempt) = restart {
+            // Another caller may have restarted it while we backed off.
+            if let Some(client) = clients.get(key)
+                && client.lock().await.is_alive()
+            {
+                return Ok(client.clone());
+            }
+            self.restarts.lock().await.insert(key.to_string(), attempt);
+            self.broadcaster.send(EventKind::ServerState {
+                language: key.to_string(),
+                state: format!("Restarting (attempt {attempt}/{MAX_RESTARTS})"),
+            });
+        }
+
+        Self::check_server_cap(&clients, key, self.config.max_concurrent_servers)?;
+
         // Spawn new client
         let server_config = self
             .config
@@ -307,38 +676,65 @@ impl ClientManager {
             .get(lang)
             .ok_or_else(|| anyhow!("No LSP server configured for language '{lang}'"))?;
 
-        info!(
-            "Spawning LSP server for {}: {} {}",
-            lang,
-            server_config.command,
-            server_config.args.join(" ")
-        );
+        let roots = match pinned_root {
+            Some(root) => vec![root],
+            None => self.roots.lock().await.clone(),
+        };
+        let mut client = if let Some((host, port)) = server_config.tcp_address()? {
+            info!("Connecting to LSP server for {key} at {host}:{port}");
+            LspClient::connect(&host, port, key, self.broadcaster.clone()).await?
+        } else {
+            let program = if self.config.resolve_in_login_shell {
+                resolve_in_login_shell(&server_config.command, roots.first().map(PathBuf::as_path))
+                    .await
+                    .unwrap_or_else(|| server_config.command.clone())
+            } else {
+                server_config.command.clone()
+            };
 
-        let args: Vec<&str> = server_config
-            .args
-            .iter()
-            .map(|s: &String| s.as_str())
-            .collect();
-        let mut client = LspClient::spawn(
-            &server_config.command,
-            &args,
-            lang,
-            self.broadcaster.clone(),
-        )?;
+            info!(
+                "Spawning LSP server for {}: {} {}",
+                key,
+                program,
+                server_config.args.join(" ")
+            );
+
+            let args: Vec<&str> = server_config
+                .args
+                .iter()
+                .map(|s: &String| s.as_str())
+                .collect();
+            let cwd = server_config.resolved_cwd(roots.first().map(PathBuf::as_path));
+            LspClient::spawn_with_env(
+                &program,
+                &args,
+                &server_config.env,
+                cwd.as_deref(),
+                key,
+                self.broadcaster.clone(),
+            )?
+        };
+
+        client.set_request_timeouts(&self.config.timeouts);
 
         // Initialize
-        let roots = self.roots.lock().await.clone();
         client
             .initialize(&roots, server_config.initialization_options.clone())
             .await?;
 
         let client_mutex = Arc::new(Mutex::new(client));
-        clients.insert(lang.to_string(), client_mutex.clone());
+        clients.insert(key.to_string(), client_mutex.clone());
         drop(clients);
 
         Ok(client_mutex)
     }
 
+    /// Returns how many times the server under `key` has been restarted
+    /// after crashing.
+    pub async fn restart_count(&self, key: &str) -> u32 {
+        self.restarts.lock().await.get(key).copied().unwrap_or(0)
+    }
+
     /// Returns a snapshot of all currently active clients.
     pub async fn active_clients(&self) -> HashMap<String, Arc<Mutex<LspClient>>> {
         self.active_clients.lock().await.clone()
@@ -350,24 +746,61 @@ impl ClientManager {
         let mut statuses = Vec::new();
 
         for (lang, client_mutex) in clients {
-            let status = client_mutex.lock().await.status(lang).await;
+            let restarts = self.restart_count(&lang).await;
+            let mut status = client_mutex.lock().await.status(lang).await;
+            status.restarts = restarts;
             statuses.push(status);
         }
 
         statuses
     }
 
+    /// Returns each active server's state, sorted by language, without
+    /// waiting on any lock or talking to the servers.
+    ///
+    /// Returns `None` while a server is being spawned (the client table is
+    /// locked). A server whose client is busy with a request reports `None`.
+    pub fn try_server_states(&self) -> Option<Vec<(String, Option<ServerState>)>> {
+        let clients = self.active_clients.try_lock().ok()?;
+        let mut states: Vec<(String, Option<ServerState>)> = clients
+            .iter()
+            .map(|(lang, client)| {
+                let state = client.try_lock().ok().map(|c| c.server_state());
+                (lang.clone(), state)
+            })
+            .collect();
+        drop(clients);
+        states.sort_by(|a, b| a.0.cmp(&b.0));
+        Some(states)
+    }
+
+    /// Records that `key` has no open documents and returns how long it has
+    /// been without any.
+    ///
+    /// The timer starts at the first call and runs until
+    /// [`Self::mark_documents_open`] or [`Self::shutdown_client`] resets it.
+    pub async fn idle_duration(&self, key: &str) -> Duration {
+        self.last_document_closed_at
+            .lock()
+            .await
+            .entry(key.to_string())
+            .or_insert_with(Instant::now)
+            .elapsed()
+    }
+
+    /// Resets the idle timer of `key` because it has open documents again.
+    pub async fn mark_documents_open(&self, key: &str) {
+        self.last_document_closed_at.lock().await.remove(key);
+    }
+
     /// Shuts down a specific client if it exists.
     pub async fn shutdown_client(&self, lang: &str) {
+        self.last_document_closed_at.lock().await.remove(lang);
         let mut clients = self.active_clients.lock().await;
         if let Some(client_mutex) = clients.remove(lang) {
             info!("Shutting down idle LSP server for {}", lang);
             let mut client = client_mutex.lock().await;
-            if client.is_alive()
-                && let Err(e) = client.shutdown().await
-            {
-                warn!("Failed to shutdown LSP server for {}: {}", lang, e);
-            }
+            self.shutdown_or_kill(lang, &mut client).await;
         }
     }
 
@@ -375,20 +808,136 @@ impl ClientManager {
     pub async fn shutdown_all(&self) {
         let mut clients = self.active_clients.lock().await;
         for (lang, client_mutex) in clients.drain() {
-            {
-                let mut client = client_mutex.lock().await;
-                if client.is_alive()
-                    && let Err(e) = client.shutdown().await
-                {
-                    warn!("Failed to shutdown LSP server for {}: {}", lang, e);
-                }
+            let mut client = client_mutex.lock().await;
+            self.shutdown_or_kill(&lang, &mut client).await;
+        }
+    }
+
+    /// Sends `shutdown`/`exit` to a live client, force-killing the process if
+    /// it does not complete within `shutdown_timeout` seconds.
+    async fn shutdown_or_kill(&self, lang: &str, client: &mut LspClient) {
+        if !client.is_alive() {
+            return;
+        }
+        let timeout = Duration::from_secs(self.config.shutdown_timeout);
+        match tokio::time::timeout(timeout, client.shutdown()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => warn!("Failed to shutdown LSP server for {}: {}", lang, e),
+            Err(_) => {
+                warn!(
+                    "LSP server for {} did not shut down within {}s, killing it",
+                    lang, self.config.shutdown_timeout
+                );
+                client.kill().await;
             }
         }
     }
 }
 
+/// Delay before restart `attempt` (1-based) of a crashed server.
+fn restart_backoff(attempt: u32) -> Duration {
+    RESTART_BACKOFF * 2u32.saturating_pow(attempt.saturating_sub(1))
+}
+
+/// Builds the client key for a `lang` server pinned to `root`.
+fn isolated_key(lang: &str, root: &Path) -> String {
+    format!("{lang}@{}", root.display())
+}
+
+/// Splits a client key into its language and, for servers isolated to one
+/// workspace root, that root.
+#[must_use]
+pub fn split_client_key(key: &str) -> (&str, Option<&Path>) {
+    key.split_once('@')
+        .map_or((key, None), |(lang, root)| (lang, Some(Path::new(root))))
+}
+
+/// Returns the most specific root containing `path`.
+fn owning_root<'a>(roots: &'a [PathBuf], path: &Path) -> Option<&'a PathBuf> {
+    roots
+        .iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+}
+
+/// Resolves `command` to an absolute path by asking a login shell started
+/// in `dir` (`sh -lc 'command -v <command>'`).
+///
+/// Version managers such as mise and asdf put servers on a per-directory
+/// `PATH` that only exists inside a shell. Commands that already contain a
+/// `/` are returned unchanged. Returns `None` if the shell cannot find it
+/// or takes longer than [`LOGIN_SHELL_TIMEOUT`] (e.g. a slow or prompting
+/// profile), so callers fall back to the inherited `PATH`.
+pub async fn resolve_in_login_shell(command: &str, dir: Option<&Path>) -> Option<String> {
+    if command.contains('/') {
+        return Some(command.to_string());
+    }
+
+    let mut shell = tokio::process::Command::new("sh");
+    // The name is passed as `$1` so it is never interpreted by the shell.
+    shell
+        .args(["-lc", "command -v \"$1\"", "sh", command])
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .kill_on_drop(true);
+    if let Some(dir) = dir {
+        shell.current_dir(dir);
+    }
+
+    let Ok(output) = tokio::time::timeout(LOGIN_SHELL_TIMEOUT, shell.output()).await else {
+        warn!(
+            "Login shell did not resolve {command} within {}s; using PATH",
+            LOGIN_SHELL_TIMEOUT.as_secs()
+        );
+        return None;
+    };
+    let output = output.ok()?;
+    if !output.status.success() {
+        debug!("Login shell could not resolve {command}");
+        return None;
+    }
+    let resolved = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()?
+        .trim()
+        .to_string();
+    // `command -v` prints the bare name for functions and builtins.
+    resolved.starts_with('/').then(|| {
+        debug!("Resolved {command} via login shell: {resolved}");
+        resolved
+    })
+}
+
+/// Splits ranked languages into those spawned up front and those left
+/// for on-demand spawning, given a cap (0 = no cap).
+fn split_eager<'a>(ranked: &'a [&'a str], max: usize) -> (&'a [&'a str], &'a [&'a str]) {
+    if max == 0 {
+        (ranked, &[])
+    } else {
+        ranked.split_at(max.min(ranked.len()))
+    }
+}
+
+/// Orders languages by file count, most files first; ties break by name.
+fn rank_by_file_count(counts: HashMap<String, usize>) -> Vec<String> {
+    let mut ranked: Vec<(String, usize)> = counts.into_iter().collect();
+    ranked.sort_unstable_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+    ranked.into_iter().map(|(lang, _)| lang).collect()
+}
+
+/// Returns the nearest directory at or above `start` that contains one of
+/// `markers` (e.g. `.git`, `Cargo.toml`), or `None` if no ancestor does.
+#[must_use]
+pub fn find_project_root(start: &Path, markers: &[String]) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| markers.iter().any(|marker| dir.join(marker).exists()))
+        .map(Path::to_path_buf)
+}
+
 /// Scans workspace roots for files and returns the set of configured
-/// language keys that have matching files present.
+/// language keys that have matching files present. `overrides` take
+/// precedence over the built-in file name and extension tables.
 ///
 /// Respects `.gitignore` and skips hidden files. Exits early once all
 /// configured languages have been detected.
@@ -397,6 +946,7 @@ impl ClientManager {
 pub fn detect_workspace_languages(
     roots: &[PathBuf],
     configured_keys: &HashSet<&str>,
+    overrides: &LanguageOverrides,
 ) -> HashSet<String> {
     let mut detected = HashSet::new();
 
@@ -408,30 +958,7 @@ pub fn detect_workspace_languages(
         let walker = WalkBuilder::new(root).git_ignore(true).hidden(true).build();
 
         for entry in walker.flatten() {
-            let path = entry.path();
-
-            // Filename-based detection
-            if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                let lang = match name {
-                    "Dockerfile" => Some("dockerfile"),
-                    "Makefile" => Some("makefile"),
-                    "CMakeLists.txt" => Some("cmake"),
-                    _ => None,
-                };
-                if let Some(l) = lang {
-                    if configured_keys.contains(l) {
-                        detected.insert(l.to_string());
-                    }
-                    if detected.len() == configured_keys.len() {
-                        return detected;
-                    }
-                    continue;
-                }
-            }
-
-            // Extension-based detection
-            if let Some(ext) = path.extension().and_then(|e| e.to_str())
-                && let Some(lang) = extension_to_config_key(ext)
+            if let Some(lang) = path_language(entry.path(), overrides)
                 && configured_keys.contains(lang)
             {
                 detected.insert(lang.to_string());
@@ -446,6 +973,54 @@ pub fn detect_workspace_languages(
     detected
 }
 
+/// Like [`detect_workspace_languages`], but walks every file and returns
+/// how many belong to each configured language.
+fn count_workspace_languages(
+    roots: &[PathBuf],
+    configured_keys: &HashSet<&str>,
+    overrides: &LanguageOverrides,
+) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+
+    for root in roots {
+        if !root.exists() {
+            continue;
+        }
+
+        let walker = WalkBuilder::new(root).git_ignore(true).hidden(true).build();
+
+        for entry in walker.flatten() {
+            if let Some(lang) = path_language(entry.path(), overrides)
+                && configured_keys.contains(lang)
+            {
+                *counts.entry(lang.to_string()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    counts
+}
+
+/// Returns the language key of `path`: user overrides from `[languages]`
+/// first, then well-known file names, then the extension.
+fn path_language<'a>(path: &Path, overrides: &'a LanguageOverrides) -> Option<&'a str> {
+    if let Some(lang) = overrides.lookup(path) {
+        return Some(lang);
+    }
+
+    let by_name = match path.file_name().and_then(|n| n.to_str()) {
+        Some("Dockerfile") => Some("dockerfile"),
+        Some("Makefile") => Some("makefile"),
+        Some("CMakeLists.txt") => Some("cmake"),
+        _ => None,
+    };
+    by_name.or_else(|| {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(extension_to_config_key)
+    })
+}
+
 /// Maps a file extension to the language config key used in
 /// `config.server`.
 fn extension_to_config_key(ext: &str) -> Option<&'static str> {
@@ -493,16 +1068,123 @@ fn extension_to_config_key(ext: &str) -> Option<&'static str> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::ServerConfig;
-    use anyhow::Result;
+    use crate::config::{ServerConfig, Transport};
+    use anyhow::{Context, Result};
 
     fn test_config() -> Config {
         Config {
             server: HashMap::new(),
             idle_timeout: 300,
+            server_idle_timeout: 900,
+            diagnostics_wait_strategy: crate::config::DiagnosticsWaitStrategy::default(),
+            diagnostics_settle_ms: 1000,
+            max_symbol_depth: 64,
+            notify_new_diagnostics_only: false,
+            notify_dependents: false,
+            symbol_index: false,
+            symbol_cache_size: 1024,
+            block_on_errors: false,
+            block_error_threshold: 1,
+            strict_roots: false,
+            allow_edits: false,
+            allowed_server_commands: Vec::new(),
+            shutdown_timeout: 3,
+            max_file_bytes: 10 * 1024 * 1024,
+            max_concurrent_servers: 0,
+            max_result_bytes: 256 * 1024,
+            resolve_in_login_shell: false,
+            init_timeout: 0,
+            warmup_retries: 0,
+            warmup_retry_ms: 0,
+            root_markers: Vec::new(),
+            timeouts: HashMap::new(),
+            languages: HashMap::new(),
         }
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_resolve_in_login_shell() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let sh = resolve_in_login_shell("sh", Some(dir.path()))
+            .await
+            .context("sh should resolve")?;
+        assert!(sh.starts_with('/') && sh.ends_with("sh"), "got: {sh}");
+
+        assert_eq!(
+            resolve_in_login_shell("/opt/custom/server", None).await,
+            Some("/opt/custom/server".to_string())
+        );
+        assert_eq!(
+            resolve_in_login_shell("catenary-no-such-server; echo pwned", None).await,
+            None
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_eager_caps_spawned_servers() {
+        let langs = ["go", "python", "rust"];
+        assert_eq!(split_eager(&langs, 0), (&langs[..], &[][..]));
+        assert_eq!(split_eager(&langs, 2), (&langs[..2], &langs[2..]));
+        assert_eq!(split_eager(&langs, 5), (&langs[..], &[][..]));
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_servers_caps_running_servers() -> Result<()> {
+        let mut config = mockls_config();
+        let shell = config.server["shellscript"].clone();
+        config.server.insert("python".to_string(), shell.clone());
+        config.server.insert("go".to_string(), shell);
+        config.max_concurrent_servers = 2;
+        let broadcaster = EventBroadcaster::noop()?;
+        let manager = ClientManager::new(config, vec![PathBuf::from("/tmp")], broadcaster);
+
+        manager.get_client("shellscript").await?;
+        manager.get_client("python").await?;
+        // Already running servers are still returned at the cap
+        manager.get_client("python").await?;
+
+        let err = manager
+            .get_client("go")
+            .await
+            .err()
+            .context("third server should be refused")?;
+        assert!(
+            err.to_string()
+                .contains("max_concurrent_servers (2) reached, running: python, shellscript"),
+            "got: {err}"
+        );
+        assert_eq!(manager.active_clients().await.len(), 2);
+
+        // Shutting one down frees its slot
+        manager.shutdown_client("python").await;
+        manager.get_client("go").await?;
+
+        manager.shutdown_all().await;
+        Ok(())
+    }
+
+    #[test]
+    fn test_count_workspace_languages_ranks_by_file_count() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for name in ["a.rs", "b.rs", "c.rs", "d.py", "e.py", "f.go", "Makefile"] {
+            std::fs::write(dir.path().join(name), "")?;
+        }
+        let keys: HashSet<&str> = ["rust", "python", "go", "makefile", "zig"].into();
+        let counts = count_workspace_languages(
+            &[dir.path().to_path_buf()],
+            &keys,
+            &LanguageOverrides::default(),
+        );
+        assert_eq!(counts.get("rust"), Some(&3));
+        assert_eq!(counts.get("zig"), None);
+
+        let ranked = rank_by_file_count(counts);
+        assert_eq!(ranked, ["rust", "python", "go", "makefile"]);
+        Ok(())
+    }
+
     /// Locate the mockls binary in the same directory as the test executable.
     /// During `cargo test`, all binaries are built into the same `target/debug/deps`
     /// parent directory.
@@ -524,11 +1206,43 @@ mod tests {
                 command: bin.to_string_lossy().to_string(),
                 args: vec![],
                 initialization_options: None,
+                env: HashMap::new(),
+                cwd: None,
+                isolate_roots: false,
+                document_selector: Vec::new(),
+                languages: Vec::new(),
+                transport: Transport::Stdio,
+                host: None,
+                port: None,
             },
         );
         Config {
             server,
             idle_timeout: 300,
+            server_idle_timeout: 900,
+            diagnostics_wait_strategy: crate::config::DiagnosticsWaitStrategy::default(),
+            diagnostics_settle_ms: 1000,
+            max_symbol_depth: 64,
+            notify_new_diagnostics_only: false,
+            notify_dependents: false,
+            symbol_index: false,
+            symbol_cache_size: 1024,
+            block_on_errors: false,
+            block_error_threshold: 1,
+            strict_roots: false,
+            allow_edits: false,
+            allowed_server_commands: Vec::new(),
+            shutdown_timeout: 3,
+            max_file_bytes: 10 * 1024 * 1024,
+            max_concurrent_servers: 0,
+            max_result_bytes: 256 * 1024,
+            resolve_in_login_shell: false,
+            init_timeout: 0,
+            warmup_retries: 0,
+            warmup_retry_ms: 0,
+            root_markers: Vec::new(),
+            timeouts: HashMap::new(),
+            languages: HashMap::new(),
         }
     }
 
@@ -541,14 +1255,66 @@ mod tests {
                 command: bin.to_string_lossy().to_string(),
                 args: vec!["--workspace-folders".to_string()],
                 initialization_options: None,
+                env: HashMap::new(),
+                cwd: None,
+                isolate_roots: false,
+                document_selector: Vec::new(),
+                languages: Vec::new(),
+                transport: Transport::Stdio,
+                host: None,
+                port: None,
             },
         );
         Config {
             server,
             idle_timeout: 300,
+            server_idle_timeout: 900,
+            diagnostics_wait_strategy: crate::config::DiagnosticsWaitStrategy::default(),
+            diagnostics_settle_ms: 1000,
+            max_symbol_depth: 64,
+            notify_new_diagnostics_only: false,
+            notify_dependents: false,
+            symbol_index: false,
+            symbol_cache_size: 1024,
+            block_on_errors: false,
+            block_error_threshold: 1,
+            strict_roots: false,
+            allow_edits: false,
+            allowed_server_commands: Vec::new(),
+            shutdown_timeout: 3,
+            max_file_bytes: 10 * 1024 * 1024,
+            max_concurrent_servers: 0,
+            max_result_bytes: 256 * 1024,
+            resolve_in_login_shell: false,
+            init_timeout: 0,
+            warmup_retries: 0,
+            warmup_retry_ms: 0,
+            root_markers: Vec::new(),
+            timeouts: HashMap::new(),
+            languages: HashMap::new(),
         }
     }
 
+    #[tokio::test]
+    async fn test_idle_duration_resets_when_documents_open() -> Result<()> {
+        let manager = ClientManager::new(test_config(), Vec::new(), EventBroadcaster::noop()?);
+
+        let first = manager.idle_duration("rust").await;
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let later = manager.idle_duration("rust").await;
+        assert!(
+            later >= first + Duration::from_millis(20),
+            "the timer should keep running: {first:?} -> {later:?}"
+        );
+
+        manager.mark_documents_open("rust").await;
+        assert!(
+            manager.idle_duration("rust").await < Duration::from_millis(20),
+            "opening a document should reset the timer"
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_roots_returns_initial_roots() -> Result<()> {
         let broadcaster = EventBroadcaster::noop()?;
@@ -624,10 +1390,10 @@ mod tests {
 
         // Sync: remove /tmp/root_a, keep /tmp/root_b, add /tmp/root_c
         manager
-            .sync_roots(vec![
-                PathBuf::from("/tmp/root_b"),
-                PathBuf::from("/tmp/root_c"),
-            ])
+            .sync_roots(
+                vec![PathBuf::from("/tmp/root_b"), PathBuf::from("/tmp/root_c")],
+                &Mutex::new(DocumentManager::new()),
+            )
             .await?;
 
         let roots = manager.roots().await;
@@ -647,7 +1413,10 @@ mod tests {
         );
 
         manager
-            .sync_roots(vec![PathBuf::from("/tmp/root_a")])
+            .sync_roots(
+                vec![PathBuf::from("/tmp/root_a")],
+                &Mutex::new(DocumentManager::new()),
+            )
             .await?;
 
         let roots = manager.roots().await;
@@ -656,6 +1425,159 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_find_project_root_walks_up_to_nearest_marker() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let project = dir.path().join("project");
+        let src = project.join("crates").join("core").join("src");
+        std::fs::create_dir_all(&src)?;
+        std::fs::create_dir(project.join(".git"))?;
+        std::fs::write(project.join("crates").join("core").join("Cargo.toml"), "")?;
+
+        let markers = vec![".git".to_string(), "Cargo.toml".to_string()];
+        assert_eq!(
+            find_project_root(&src, &markers),
+            Some(project.join("crates").join("core"))
+        );
+        assert_eq!(
+            find_project_root(&src, &[".git".to_string()]),
+            Some(project)
+        );
+        assert_eq!(
+            find_project_root(&src, &["missing.marker".to_string()]),
+            None
+        );
+        assert_eq!(find_project_root(&src, &[]), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_owning_root_and_client_keys() {
+        let roots = vec![PathBuf::from("/ws"), PathBuf::from("/ws/nested")];
+        assert_eq!(
+            owning_root(&roots, Path::new("/ws/nested/a.rs")),
+            Some(&PathBuf::from("/ws/nested"))
+        );
+        assert_eq!(
+            owning_root(&roots, Path::new("/ws/b.rs")),
+            Some(&PathBuf::from("/ws"))
+        );
+        assert_eq!(owning_root(&roots, Path::new("/other/c.rs")), None);
+
+        let key = isolated_key("rust", Path::new("/ws/nested"));
+        assert_eq!(key, "rust@/ws/nested");
+        assert_eq!(
+            split_client_key(&key),
+            ("rust", Some(Path::new("/ws/nested")))
+        );
+        assert_eq!(split_client_key("rust"), ("rust", None));
+    }
+
+    #[tokio::test]
+    async fn test_document_selector_limits_routed_files() -> Result<()> {
+        let mut config = mockls_config();
+        for server in config.server.values_mut() {
+            server.document_selector = vec!["scripts/**".to_string()];
+        }
+        let broadcaster = EventBroadcaster::noop()?;
+        let manager = ClientManager::new(config, vec![PathBuf::from("/ws")], broadcaster);
+
+        assert!(
+            manager
+                .handles_file("shellscript", Path::new("/ws/scripts/build.sh"))
+                .await
+        );
+        assert!(
+            !manager
+                .handles_file("shellscript", Path::new("/ws/other/run.sh"))
+                .await
+        );
+        assert!(
+            !manager
+                .handles_file("rust", Path::new("/ws/scripts/a.rs"))
+                .await
+        );
+
+        let err = manager
+            .get_client_for_file("shellscript", Path::new("/ws/other/run.sh"))
+            .await
+            .err()
+            .context("file outside the selector should be rejected")?;
+        assert!(err.to_string().contains("document_selector"), "got: {err}");
+        assert!(manager.active_clients().await.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_isolate_roots_spawns_client_per_root() -> Result<()> {
+        let mut config = mockls_config();
+        for server in config.server.values_mut() {
+            server.isolate_roots = true;
+        }
+        let root_a = PathBuf::from("/tmp/root_a");
+        let root_b = PathBuf::from("/tmp/root_b");
+        let broadcaster = EventBroadcaster::noop()?;
+        let manager = ClientManager::new(config, vec![root_a.clone(), root_b.clone()], broadcaster);
+
+        let a = manager
+            .get_client_for_file("shellscript", &root_a.join("a.sh"))
+            .await?;
+        let b = manager
+            .get_client_for_file("shellscript", &root_b.join("b.sh"))
+            .await?;
+        assert!(!Arc::ptr_eq(&a, &b), "each root should get its own server");
+        let again = manager
+            .get_client_for_file("shellscript", &root_a.join("other.sh"))
+            .await?;
+        assert!(Arc::ptr_eq(&a, &again));
+
+        let mut keys: Vec<String> = manager.active_clients().await.into_keys().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec!["shellscript@/tmp/root_a", "shellscript@/tmp/root_b"]
+        );
+
+        // Removing a root stops only the server pinned to it.
+        manager.remove_root(&root_a).await?;
+        let keys: Vec<String> = manager.active_clients().await.into_keys().collect();
+        assert_eq!(keys, vec!["shellscript@/tmp/root_b"]);
+
+        manager.shutdown_all().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spawn_all_isolate_roots_skips_shared_client() -> Result<()> {
+        let mut config = mockls_config();
+        for server in config.server.values_mut() {
+            server.isolate_roots = true;
+        }
+        let dir_a = tempfile::tempdir()?;
+        let dir_b = tempfile::tempdir()?;
+        std::fs::write(dir_a.path().join("a.sh"), "echo a\n")?;
+        std::fs::write(dir_b.path().join("b.sh"), "echo b\n")?;
+        let root_a = dir_a.path().to_path_buf();
+        let root_b = dir_b.path().to_path_buf();
+        let broadcaster = EventBroadcaster::noop()?;
+        let manager = ClientManager::new(config, vec![root_a.clone(), root_b.clone()], broadcaster);
+
+        manager.spawn_all().await;
+
+        let mut keys: Vec<String> = manager.active_clients().await.into_keys().collect();
+        keys.sort();
+        let mut expected = vec![
+            isolated_key("shellscript", &root_a),
+            isolated_key("shellscript", &root_b),
+        ];
+        expected.sort();
+        assert_eq!(keys, expected);
+        assert!(!keys.iter().any(|k| k == "shellscript"));
+
+        manager.shutdown_all().await;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_sync_roots_shuts_down_unsupported_client() -> Result<()> {
         // mockls without --workspace-folders does NOT advertise workspace folder support.
@@ -674,7 +1596,10 @@ mod tests {
 
         // sync_roots should shut down the unsupported client
         manager
-            .sync_roots(vec![PathBuf::from("/tmp"), PathBuf::from("/var")])
+            .sync_roots(
+                vec![PathBuf::from("/tmp"), PathBuf::from("/var")],
+                &Mutex::new(DocumentManager::new()),
+            )
             .await?;
 
         assert!(
@@ -685,6 +1610,127 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_restart_backoff_doubles() {
+        assert_eq!(restart_backoff(1), RESTART_BACKOFF);
+        assert_eq!(restart_backoff(2), RESTART_BACKOFF * 2);
+        assert_eq!(restart_backoff(MAX_RESTARTS), RESTART_BACKOFF * 16);
+    }
+
+    #[tokio::test]
+    async fn test_crashed_server_restarts_until_limit() -> Result<()> {
+        let broadcaster = EventBroadcaster::noop()?;
+        let manager = ClientManager::new(mockls_config(), vec![PathBuf::from("/tmp")], broadcaster);
+
+        let first = manager.get_client("shellscript").await?;
+        first.lock().await.kill().await;
+        let second = manager.get_client("shellscript").await?;
+        assert!(
+            !Arc::ptr_eq(&first, &second),
+            "dead server should be replaced"
+        );
+        assert!(second.lock().await.is_alive());
+        assert_eq!(manager.restart_count("shellscript").await, 1);
+        let statuses = manager.all_server_status().await;
+        assert_eq!(statuses.first().map(|s| s.restarts), Some(1));
+
+        manager
+            .restarts
+            .lock()
+            .await
+            .insert("shellscript".to_string(), MAX_RESTARTS);
+        second.lock().await.kill().await;
+        let err = manager
+            .get_client("shellscript")
+            .await
+            .err()
+            .context("server past the restart limit should not be respawned")?;
+        assert!(err.to_string().contains("giving up"), "{err}");
+
+        manager.shutdown_all().await;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_shutdown_all_kills_unresponsive_server() -> Result<()> {
+        let broadcaster = EventBroadcaster::noop()?;
+        let mut config = mockls_config();
+        config.shutdown_timeout = 1;
+        if let Some(server) = config.server.get_mut("shellscript") {
+            server.args = vec!["--hang-on".to_string(), "shutdown".to_string()];
+        }
+        let manager = ClientManager::new(config, vec![PathBuf::from("/tmp")], broadcaster);
+
+        let client = manager.get_client("shellscript").await?;
+        let pid = client.lock().await.pid().context("mockls has no pid")?;
+        drop(client);
+
+        let start = std::time::Instant::now();
+        manager.shutdown_all().await;
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "shutdown should give up after the timeout, took {:?}",
+            start.elapsed()
+        );
+        assert!(
+            !Path::new(&format!("/proc/{pid}")).exists(),
+            "hung mockls (pid {pid}) should have been killed"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tcp_transport_connects_to_listening_server() -> Result<()> {
+        // Expose mockls on a local port, as a containerized server would be.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let port = listener.local_addr()?.port();
+        let bin = mockls_bin();
+        let proxy = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await?;
+            let mut server = tokio::process::Command::new(bin)
+                .stdin(std::process::Stdio::piped())
+                .stdout(std::process::Stdio::piped())
+                .kill_on_drop(true)
+                .spawn()?;
+            let mut stdin = server.stdin.take().context("mockls stdin")?;
+            let mut stdout = server.stdout.take().context("mockls stdout")?;
+            let (mut from_client, mut to_client) = stream.into_split();
+            tokio::select! {
+                _ = tokio::io::copy(&mut from_client, &mut stdin) => {}
+                _ = tokio::io::copy(&mut stdout, &mut to_client) => {}
+            }
+            Ok::<_, anyhow::Error>(())
+        });
+
+        let mut config = mockls_config();
+        if let Some(server) = config.server.get_mut("shellscript") {
+            server.command = String::new();
+            server.transport = Transport::Tcp;
+            server.port = Some(port);
+        }
+        config
+            .server
+            .values()
+            .try_for_each(ServerConfig::validate)?;
+        let manager = ClientManager::new(
+            config,
+            vec![PathBuf::from("/tmp")],
+            EventBroadcaster::noop()?,
+        );
+
+        let client = manager.get_client("shellscript").await?;
+        let guard = client.lock().await;
+        assert!(guard.is_alive());
+        assert!(guard.pid().is_none(), "a TCP server is not our child");
+        drop(guard);
+        drop(client);
+
+        manager.shutdown_all().await;
+        tokio::time::timeout(Duration::from_secs(10), proxy).await???;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_sync_roots_notifies_supported_client() -> Result<()> {
         // mockls with --workspace-folders DOES advertise workspace folder support.
@@ -707,7 +1753,10 @@ mod tests {
 
         // sync_roots should send notification, NOT shut down the client
         manager
-            .sync_roots(vec![PathBuf::from("/tmp"), PathBuf::from("/var")])
+            .sync_roots(
+                vec![PathBuf::from("/tmp"), PathBuf::from("/var")],
+                &Mutex::new(DocumentManager::new()),
+            )
             .await?;
 
         // Client should still be active (not removed)
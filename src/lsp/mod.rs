@@ -1,6 +1,8 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 // Copyright (C) 2026 Mark Wells <contact@markwells.dev>
 
+/// Mapping from server capabilities to the tools they enable.
+pub mod capabilities;
 /// Low-level LSP client for communicating with a server process.
 pub mod client;
 /// Diagnostics strategy selection and activity monitoring.
@@ -12,8 +14,12 @@ pub mod protocol;
 /// Server state and progress tracking.
 pub mod state;
 
+pub use capabilities::extract_capabilities;
 pub(crate) use client::DIAGNOSTICS_TIMEOUT;
 pub use client::DiagnosticsWaitResult;
-pub use client::{LspClient, WARMUP_PERIOD};
-pub use manager::{ClientManager, detect_workspace_languages};
-pub use state::{ProgressTracker, ServerState, ServerStatus};
+pub use client::{CommandOutcome, LspClient, WARMUP_PERIOD};
+pub use manager::{
+    ClientManager, detect_workspace_languages, find_project_root, resolve_in_login_shell,
+    split_client_key,
+};
+pub use state::{ProgressTracker, ServerState, ServerStatus, progress_summary};
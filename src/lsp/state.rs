@@ -4,7 +4,7 @@
 //! Server state and progress tracking types.
 
 use lsp_types::{NumberOrString, ProgressParams, ProgressParamsValue, WorkDoneProgress};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::time::Instant;
 
@@ -25,7 +25,7 @@ pub struct ProgressState {
 }
 
 /// Overall server readiness state.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ServerState {
     /// Server just spawned, may be initializing.
@@ -63,7 +63,7 @@ impl ServerState {
 }
 
 /// Detailed status for a single LSP server.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerStatus {
     /// The language ID this server handles.
     pub language: String,
@@ -80,6 +80,49 @@ pub struct ServerStatus {
     pub progress_percentage: Option<u32>,
     /// Seconds since spawn.
     pub uptime_secs: u64,
+    /// Times the server was restarted after crashing.
+    #[serde(default)]
+    pub restarts: u32,
+}
+
+/// Summarizes readiness across servers in one line, e.g.
+/// `2/3 servers ready; rust indexing 40%`, so an agent can tell at a glance
+/// whether to wait before querying.
+#[must_use]
+pub fn progress_summary(statuses: &[ServerStatus]) -> String {
+    let ready = statuses
+        .iter()
+        .filter(|s| s.state == ServerState::Ready)
+        .count();
+    let noun = if statuses.len() == 1 {
+        "server"
+    } else {
+        "servers"
+    };
+    let mut summary = format!("{ready}/{} {noun} ready", statuses.len());
+
+    let pending: Vec<String> = statuses
+        .iter()
+        .filter_map(|s| {
+            let activity = match s.state {
+                ServerState::Ready => return None,
+                ServerState::Initializing => "initializing",
+                ServerState::Indexing => "indexing",
+                ServerState::Dead => "dead",
+            };
+            Some(match s.progress_percentage {
+                Some(pct) if s.state == ServerState::Indexing => {
+                    format!("{} {activity} {pct}%", s.language)
+                }
+                _ => format!("{} {activity}", s.language),
+            })
+        })
+        .collect();
+    if !pending.is_empty() {
+        summary.push_str("; ");
+        summary.push_str(&pending.join(", "));
+    }
+    summary
 }
 
 /// Manages progress state for a single LSP client.
@@ -307,4 +350,40 @@ mod tests {
         assert_eq!(ServerState::Ready.as_u8(), 2);
         assert_eq!(ServerState::Dead.as_u8(), 3);
     }
+
+    fn status(language: &str, state: ServerState, percentage: Option<u32>) -> ServerStatus {
+        ServerStatus {
+            language: language.to_string(),
+            state,
+            progress_title: percentage.map(|_| "Indexing".to_string()),
+            progress_message: None,
+            progress_percentage: percentage,
+            uptime_secs: 0,
+            restarts: 0,
+        }
+    }
+
+    #[test]
+    fn test_progress_summary() {
+        assert_eq!(
+            progress_summary(&[status("rust", ServerState::Ready, None)]),
+            "1/1 server ready"
+        );
+        assert_eq!(
+            progress_summary(&[
+                status("python", ServerState::Ready, None),
+                status("rust", ServerState::Indexing, Some(40)),
+                status("toml", ServerState::Ready, None),
+            ]),
+            "2/3 servers ready; rust indexing 40%"
+        );
+        assert_eq!(
+            progress_summary(&[
+                status("go", ServerState::Initializing, None),
+                status("rust", ServerState::Indexing, None),
+                status("sh", ServerState::Dead, None),
+            ]),
+            "0/3 servers ready; go initializing, rust indexing, sh dead"
+        );
+    }
 }
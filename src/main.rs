@@ -20,17 +20,20 @@ use tokio::sync::Mutex;
 use tracing::{debug, info, warn};
 use tracing_subscriber::EnvFilter;
 
-use catenary_mcp::bridge::{DocumentManager, LspBridgeHandler, PathValidator};
-use catenary_mcp::cli::{self, ColorConfig, ColumnWidths};
+use catenary_mcp::bridge::{
+    DocumentManager, LineEnding, LspBridgeHandler, PathValidator, SymbolIndex,
+};
+use catenary_mcp::cli::{self, ColorChoice, ColorConfig, ColumnWidths};
 use catenary_mcp::lsp;
 use catenary_mcp::mcp::McpServer;
 use catenary_mcp::session::{self, EventKind, Session, SessionEvent};
 
 /// Output format for hook commands.
 ///
-/// Determines how hook output is structured for the host CLI.
-/// Required on all hook-facing subcommands (`acquire`, `release`, `sync-roots`).
-#[derive(Clone, Copy, Debug, ValueEnum)]
+/// Determines how hook output is structured for the host CLI. Resolved
+/// from `--format` on the hook-facing subcommands (`acquire`, `release`,
+/// `sync-roots`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum HostFormat {
     /// Claude Code hooks (`PostToolUse` / `PreToolUse`).
     Claude,
@@ -38,6 +41,24 @@ enum HostFormat {
     Gemini,
 }
 
+/// Value of `--format` on hook commands.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum FormatArg {
+    /// Claude Code hooks.
+    Claude,
+    /// Gemini CLI hooks.
+    Gemini,
+    /// Detect the host from the hook JSON and environment.
+    Auto,
+}
+
+/// Schemas printed by `catenary schema`.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum SchemaTarget {
+    /// Session events, one per line of `monitor --raw` output.
+    Events,
+}
+
 /// Command-line arguments for Catenary.
 #[derive(Parser, Debug)]
 #[command(name = "catenary")]
@@ -57,6 +78,18 @@ struct Args {
     #[arg(long, global = true)]
     config: Option<PathBuf>,
 
+    /// Base directory for Catenary's user config and state. Config is read
+    /// from `<dir>/config.toml`; sessions and locks are kept under `<dir>`.
+    /// Project-local `.catenary.toml` and `--config` still apply.
+    #[arg(long, global = true)]
+    config_home: Option<PathBuf>,
+
+    /// Directory holding session data (event logs, notify sockets, locks).
+    /// Overrides `CATENARY_SESSIONS_DIR` and `--config-home`. Use the same
+    /// value for every command that should see the same sessions.
+    #[arg(long, global = true)]
+    sessions_dir: Option<PathBuf>,
+
     /// Workspace root directories. Can be specified multiple times.
     #[arg(short, long, global = true)]
     root: Vec<PathBuf>,
@@ -65,54 +98,152 @@ struct Args {
     /// Overrides config file if set (default in config is 300).
     #[arg(long, global = true)]
     idle_timeout: Option<u64>,
+
+    /// Reject any tool input path outside the workspace roots (hover,
+    /// definition, `codebase_map` paths, ...), not just file I/O tools.
+    #[arg(long, global = true)]
+    strict_roots: bool,
+
+    /// Run at most this many language servers at once, starting those with
+    /// the most files first (0 for no limit). Overrides config if set.
+    #[arg(long, global = true)]
+    max_servers: Option<usize>,
+
+    /// Seconds after startup during which tool calls wait for their language
+    /// server to be spawned (0 to disable). Overrides config if set.
+    #[arg(long, global = true)]
+    init_timeout: Option<u64>,
+
+    /// Extra environment variable for a language's server in "lang:KEY=VALUE"
+    /// format (e.g., `rust:RA_LOG=info`). Can be specified multiple times.
+    /// Applied on top of the server's configured `env`.
+    #[arg(long = "lsp-env", global = true)]
+    lsp_env: Vec<String>,
+
+    /// When to use colored output in `list`, `status`, `monitor` and `doctor`.
+    /// `auto` colors only when stdout is a terminal.
+    #[arg(long, value_enum, global = true, default_value_t = ColorChoice::Auto)]
+    color: ColorChoice,
 }
 
 /// Subcommands supported by Catenary.
 #[derive(Subcommand, Debug)]
 enum Command {
     /// Run the MCP server (default if no subcommand given).
-    Serve,
+    Serve {
+        /// Human-readable label for this session (shown in `catenary list`).
+        #[arg(long)]
+        name: Option<String>,
+    },
 
     /// List active Catenary sessions.
     List,
 
+    /// Remove the data of sessions whose process is no longer running.
+    Prune {
+        /// Only list the dead sessions, don't remove them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// Monitor events from a session.
     Monitor {
-        /// Session ID or row number (use 'catenary list' to see available sessions).
+        /// Session ID, name, row number, or `latest`/`first` (use 'catenary list' to see
+        /// available sessions). May be omitted when exactly one session is running.
+        #[arg(default_value = "")]
         id: String,
 
         /// Show raw JSON output.
         #[arg(long)]
         raw: bool,
 
-        /// Disable colored output.
-        #[arg(long)]
+        /// Deprecated alias for `--color never`.
+        #[arg(long, hide = true)]
         nocolor: bool,
 
         /// Filter events by regex pattern.
         #[arg(long, short)]
         filter: Option<String>,
+
+        /// Replay recent history before following: a duration (`10m`, `1h`)
+        /// or a number of events (`50`).
+        #[arg(long, value_name = "DURATION|COUNT")]
+        since: Option<session::EventWindow>,
+    },
+
+    /// Show a session's language server output (stderr).
+    Logs {
+        /// Language key of the server (as in `[server.<lang>]`).
+        lang: String,
+
+        /// Session ID, name, row number, or `latest`/`first` (use 'catenary list' to see
+        /// available sessions). May be omitted when exactly one session is running.
+        #[arg(default_value = "")]
+        id: String,
+
+        /// Print the log so far and exit instead of following it.
+        #[arg(long)]
+        no_follow: bool,
     },
 
     /// Show status of a session.
     Status {
-        /// Session ID (use 'catenary list' to see available sessions).
+        /// Session ID, name, row number, or `latest`/`first` (use 'catenary list' to see
+        /// available sessions). May be omitted when exactly one session is running.
+        #[arg(default_value = "")]
         id: String,
     },
 
     /// Check language server health for the current workspace.
     Doctor {
-        /// Disable colored output.
-        #[arg(long)]
+        /// Deprecated alias for `--color never`.
+        #[arg(long, hide = true)]
         nocolor: bool,
+
+        /// Skip the post-initialize responsiveness probe.
+        #[arg(long)]
+        no_probe: bool,
+
+        /// Re-run the checks every SECONDS (default 2, at least 1), reloading
+        /// the config each time, until interrupted with Ctrl+C.
+        #[arg(
+            long,
+            value_name = "SECONDS",
+            num_args = 0..=1,
+            default_missing_value = "2",
+            value_parser = clap::value_parser!(u64).range(1..)
+        )]
+        watch: Option<u64>,
+    },
+
+    /// Start a configured language server and show what it advertises.
+    Capabilities {
+        /// Language key of the server (as in `[server.<lang>]`).
+        language: String,
+
+        /// Print the full `ServerCapabilities` object as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show which config files are loaded, or with `--show` the effective
+    /// configuration after merging them with the command-line overrides.
+    Config {
+        /// Print the effective configuration instead of the file list.
+        #[arg(long)]
+        show: bool,
+
+        /// Print the configuration as JSON instead of TOML.
+        #[arg(long, requires = "show")]
+        json: bool,
     },
 
     /// Sync /add-dir roots from Claude Code transcript to a running session.
     /// Designed for `PreToolUse` hooks — reads hook JSON from stdin.
     SyncRoots {
-        /// Output format: "claude" or "gemini".
+        /// Output format: "claude", "gemini" or "auto".
         #[arg(long, value_enum)]
-        format: HostFormat,
+        format: FormatArg,
     },
 
     /// Acquire a file lock before reading or editing.
@@ -122,9 +253,9 @@ enum Command {
         #[arg(long, default_value = "180")]
         timeout: u64,
 
-        /// Output format: "claude" or "gemini".
+        /// Output format: "claude", "gemini" or "auto".
         #[arg(long, value_enum)]
-        format: HostFormat,
+        format: FormatArg,
     },
 
     /// Release a file lock after reading or editing.
@@ -137,8 +268,51 @@ enum Command {
 
         /// Output format. When set, runs diagnostics and track-read before releasing.
         #[arg(long, value_enum)]
-        format: Option<HostFormat>,
+        format: Option<FormatArg>,
+    },
+
+    /// Inspect file locks held by agents.
+    Lock {
+        /// What to do with the locks.
+        #[command(subcommand)]
+        action: LockAction,
+    },
+
+    /// Print a JSON schema for external consumers.
+    Schema {
+        /// Which schema to print.
+        #[arg(value_enum)]
+        target: SchemaTarget,
+    },
+}
+
+/// Actions of the `lock` subcommand.
+#[derive(Subcommand, Debug)]
+enum LockAction {
+    /// Show every held lock: file, owner, age and time until it can be
+    /// reclaimed.
+    List {
+        /// Print the locks as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Remove the lock on a file, e.g. one left behind by an agent that
+    /// died. Without `--force`, only a lock past its expiry is removed.
+    Release {
+        /// The locked file.
+        #[arg(long)]
+        file: PathBuf,
+
+        /// Remove the lock even if its owner may still be active.
+        #[arg(long)]
+        force: bool,
     },
+
+    /// Record a file's mtime after the agent's own edit, so the next
+    /// `acquire` does not warn that another agent modified it.
+    /// Designed for `PostToolUse` hooks on edits — reads hook JSON from stdin.
+    TrackWrite,
 }
 
 /// Entry point for the Catenary binary.
@@ -150,17 +324,53 @@ enum Command {
 async fn main() -> Result<()> {
     let args = Args::parse();
 
+    if let Some(home) = &args.config_home {
+        catenary_mcp::config::set_config_home(home.clone())?;
+    }
+    if let Some(dir) = &args.sessions_dir {
+        session::set_sessions_dir(dir.clone())?;
+    }
+
     match args.command {
-        None | Some(Command::Serve) => run_server(args).await,
-        Some(Command::List) => run_list(),
+        None => run_server(args, None).await,
+        Some(Command::Serve { ref name }) => {
+            let name = name.clone();
+            run_server(args, name).await
+        }
+        Some(Command::List) => run_list(args.color),
+        Some(Command::Prune { dry_run }) => run_prune(dry_run),
         Some(Command::Monitor {
             id,
             raw,
             nocolor,
             filter,
-        }) => run_monitor(&id, raw, nocolor, filter.as_deref()),
-        Some(Command::Status { id }) => run_status(&id),
-        Some(Command::Doctor { nocolor }) => run_doctor(args, nocolor).await,
+            since,
+        }) => run_monitor(
+            &id,
+            raw,
+            color_choice(args.color, nocolor),
+            filter.as_deref(),
+            since,
+        ),
+        Some(Command::Logs {
+            ref lang,
+            ref id,
+            no_follow,
+        }) => run_logs(id, lang, !no_follow),
+        Some(Command::Status { id }) => run_status(&id, args.color),
+        Some(Command::Doctor {
+            nocolor,
+            no_probe,
+            watch,
+        }) => {
+            let color = color_choice(args.color, nocolor);
+            run_doctor(&args, color, !no_probe, watch).await
+        }
+        Some(Command::Capabilities { ref language, json }) => {
+            let language = language.clone();
+            run_capabilities(args, &language, json).await
+        }
+        Some(Command::Config { show, json }) => run_config(&args, show, json),
         Some(Command::SyncRoots { format }) => {
             run_sync_roots(format);
             Ok(())
@@ -173,6 +383,15 @@ async fn main() -> Result<()> {
             run_release(grace, format);
             Ok(())
         }
+        Some(Command::Lock { action }) => match action {
+            LockAction::List { json } => run_lock_list(json),
+            LockAction::Release { file, force } => run_lock_release(&file, force),
+            LockAction::TrackWrite => {
+                run_lock_track_write();
+                Ok(())
+            }
+        },
+        Some(Command::Schema { target }) => run_schema(target),
     }
 }
 
@@ -186,57 +405,15 @@ async fn main() -> Result<()> {
     clippy::too_many_lines,
     reason = "Server setup requires sequential initialization steps"
 )]
-async fn run_server(args: Args) -> Result<()> {
+async fn run_server(args: Args, name: Option<String>) -> Result<()> {
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env().add_directive("catenary=info".parse()?))
         .with_writer(std::io::stderr)
         .init();
 
-    // Load configuration
-    let mut config = catenary_mcp::config::Config::load(args.config.clone())?;
-
-    // Override idle_timeout if provided on CLI
-    if let Some(timeout) = args.idle_timeout {
-        config.idle_timeout = timeout;
-    }
-
-    // Merge CLI LSPs into config
-    for lsp_spec in args.lsps {
-        let (lang, command_str) = lsp_spec.split_once(':').ok_or_else(|| {
-            anyhow::anyhow!("Invalid LSP spec: {lsp_spec}. Expected 'lang:command'")
-        })?;
-
-        let lang = lang.trim().to_string();
-        let command_str = command_str.trim();
-
-        // Parse command into program and arguments
-        let mut parts = command_str.split_whitespace();
-        let program = parts
-            .next()
-            .ok_or_else(|| anyhow::anyhow!("command cannot be empty"))?
-            .to_string();
-        let cmd_args: Vec<String> = parts.map(std::string::ToString::to_string).collect();
-
-        config.server.insert(
-            lang,
-            catenary_mcp::config::ServerConfig {
-                command: program,
-                args: cmd_args,
-                initialization_options: None,
-            },
-        );
-    }
+    let config = load_cli_config(&args)?;
 
-    // Default to current directory if no roots specified
-    let raw_roots = if args.root.is_empty() {
-        vec![PathBuf::from(".")]
-    } else {
-        args.root
-    };
-    let roots: Vec<PathBuf> = raw_roots
-        .into_iter()
-        .map(|r| r.canonicalize())
-        .collect::<std::io::Result<Vec<_>>>()?;
+    let roots = resolve_cli_roots(&args.root, &config.root_markers)?;
 
     let workspace_display = roots
         .iter()
@@ -245,7 +422,9 @@ async fn run_server(args: Args) -> Result<()> {
         .join(", ");
 
     // Create session for observability
-    let session = Arc::new(std::sync::Mutex::new(Session::create(&workspace_display)?));
+    let mut session = Session::create_named(&workspace_display, name.as_deref())?;
+    session.set_block_on_errors(config.block_on_errors);
+    let session = Arc::new(std::sync::Mutex::new(session));
     let broadcaster = session
         .lock()
         .map_err(|_| anyhow::anyhow!("mutex poisoned"))?
@@ -269,9 +448,20 @@ async fn run_server(args: Args) -> Result<()> {
         roots,
         broadcaster.clone(),
     ));
-    client_manager.spawn_all().await;
+    // With a startup window, accept tool calls at once and let them wait for
+    // the spawn; otherwise finish spawning before serving.
+    let spawn_handle = if config.init_timeout > 0 {
+        Some(client_manager.spawn_all_in_background())
+    } else {
+        client_manager.spawn_all().await;
+        None
+    };
 
-    let doc_manager = Arc::new(Mutex::new(DocumentManager::new()));
+    let doc_manager = Arc::new(Mutex::new(
+        DocumentManager::new()
+            .with_max_file_bytes(config.max_file_bytes)
+            .with_language_overrides(config.language_overrides()?),
+    ));
     let runtime = tokio::runtime::Handle::current();
 
     // Start document cleanup task if timeout is enabled
@@ -312,17 +502,25 @@ async fn run_server(args: Args) -> Result<()> {
 
     let handler = LspBridgeHandler::new(
         client_manager.clone(),
-        doc_manager,
+        doc_manager.clone(),
         runtime,
         broadcaster.clone(),
         path_validator.clone(),
     );
 
+    // Write recorded symbols periodically rather than after every tool call
+    let symbol_index = handler.symbol_index();
+    let index_flush_handle = symbol_index
+        .clone()
+        .map(|index| tokio::spawn(symbol_index_flush_task(index)));
+
     // Run MCP server (blocking - reads from stdin)
     let session_for_callback = session.clone();
     let client_manager_for_roots = client_manager.clone();
     let path_validator_for_roots = path_validator.clone();
+    let doc_manager_for_roots = doc_manager.clone();
     let runtime_for_roots = tokio::runtime::Handle::current();
+    let shutdown_broadcaster = broadcaster.clone();
     let mut mcp_server = McpServer::new(handler, broadcaster)
         .on_client_info(Box::new(move |name: &str, version: &str| {
             if let Ok(mut session) = session_for_callback.lock() {
@@ -351,7 +549,8 @@ async fn run_server(args: Args) -> Result<()> {
                 .block_on(path_validator_for_roots.write())
                 .update_roots(paths.clone());
 
-            runtime_for_roots.block_on(client_manager_for_roots.sync_roots(paths))?;
+            runtime_for_roots
+                .block_on(client_manager_for_roots.sync_roots(paths, &doc_manager_for_roots))?;
             runtime_for_roots.block_on(client_manager_for_roots.spawn_all());
             Ok(())
         }));
@@ -362,10 +561,15 @@ async fn run_server(args: Args) -> Result<()> {
     // Wait for either the MCP task to finish or a termination signal
     let mcp_result = tokio::select! {
         res = mcp_task => {
+            info!("MCP client disconnected");
+            shutdown_broadcaster.send(EventKind::ClientDisconnected);
             res?
         }
-        _ = tokio::signal::ctrl_c() => {
-            info!("Received shutdown signal");
+        signal = shutdown_signal() => {
+            info!("Received {signal}, shutting down");
+            shutdown_broadcaster.send(EventKind::SignalShutdown {
+                signal: signal.to_string(),
+            });
             Ok(())
         }
     };
@@ -380,6 +584,19 @@ async fn run_server(args: Args) -> Result<()> {
         let _ = handle.await;
     }
 
+    if let Some(handle) = spawn_handle {
+        handle.abort();
+        let _ = handle.await;
+    }
+
+    if let Some(handle) = index_flush_handle {
+        handle.abort();
+        let _ = handle.await;
+    }
+    if let Some(index) = symbol_index {
+        flush_symbol_index(index).await;
+    }
+
     // Shutdown LSP clients gracefully
     info!("Shutting down LSP servers");
     client_manager.shutdown_all().await;
@@ -389,13 +606,86 @@ async fn run_server(args: Args) -> Result<()> {
     mcp_result
 }
 
+/// Waits for a termination signal and returns its name.
+///
+/// Handles Ctrl+C everywhere and additionally `SIGTERM` on Unix, so an
+/// external `kill` still shuts servers down and records why the session ended.
+async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        if let Ok(mut term) = signal(SignalKind::terminate()) {
+            return tokio::select! {
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+                _ = term.recv() => "SIGTERM",
+            };
+        }
+    }
+    let _ = tokio::signal::ctrl_c().await;
+    "SIGINT"
+}
+
+/// Applies `--lsp-env lang:KEY=VALUE` overrides to the configured servers.
+///
+/// # Errors
+///
+/// Returns an error if a spec is malformed or names a language with no
+/// configured server.
+fn apply_lsp_env(config: &mut catenary_mcp::config::Config, specs: &[String]) -> Result<()> {
+    for spec in specs {
+        let (lang, key, value) = parse_lsp_env(spec)?;
+        let server = config.server.get_mut(lang).ok_or_else(|| {
+            anyhow::anyhow!("--lsp-env {spec}: no LSP server configured for language '{lang}'")
+        })?;
+        server.env.insert(key.to_string(), value.to_string());
+    }
+    Ok(())
+}
+
+/// Splits a `lang:KEY=VALUE` spec into its parts.
+fn parse_lsp_env(spec: &str) -> Result<(&str, &str, &str)> {
+    let invalid = || anyhow::anyhow!("Invalid LSP env spec: {spec}. Expected 'lang:KEY=VALUE'");
+    let (lang, assignment) = spec.split_once(':').ok_or_else(invalid)?;
+    let (key, value) = assignment.split_once('=').ok_or_else(invalid)?;
+    let (lang, key) = (lang.trim(), key.trim());
+    if lang.is_empty() || key.is_empty() {
+        return Err(invalid());
+    }
+    Ok((lang, key, value))
+}
+
+/// Removes (or with `dry_run`, lists) dead sessions.
+///
+/// # Errors
+///
+/// Returns an error if the sessions directory cannot be read or cleaned.
+fn run_prune(dry_run: bool) -> Result<()> {
+    let dead = session::prune_sessions(dry_run)?;
+
+    for s in &dead {
+        println!("{}  pid {}  {}", s.id, s.pid, s.workspace);
+    }
+
+    let noun = if dead.len() == 1 {
+        "session"
+    } else {
+        "sessions"
+    };
+    if dry_run {
+        println!("Would remove {} dead {noun}", dead.len());
+    } else {
+        println!("Removed {} dead {noun}", dead.len());
+    }
+    Ok(())
+}
+
 /// List all active sessions
 /// Runs the session list command.
 ///
 /// # Errors
 ///
 /// Returns an error if listing sessions fails.
-fn run_list() -> Result<()> {
+fn run_list(color: ColorChoice) -> Result<()> {
     let sessions = session::list_sessions()?;
 
     if sessions.is_empty() {
@@ -405,19 +695,23 @@ fn run_list() -> Result<()> {
 
     let term_width = cli::terminal_width();
     let widths = ColumnWidths::calculate(term_width);
-    let colors = cli::ColorConfig::new(false);
+    let colors = ColorConfig::new(color);
 
     // Print header
     println!(
-        "{:>width_num$} {:<width_id$} {:<width_pid$} {:<width_client$} {:<width_ws$} STARTED",
+        "{:>width_num$} {:<width_id$} {:<width_name$} {:<width_pid$} {:<width_health$} {:<width_client$} {:<width_ws$} STARTED",
         "#",
         "ID",
+        "NAME",
         "PID",
+        "HEALTH",
         "CLIENT",
         "WORKSPACE",
         width_num = widths.row_num,
         width_id = widths.id,
+        width_name = widths.name,
         width_pid = widths.pid,
+        width_health = widths.health,
         width_client = widths.client,
         width_ws = widths.workspace,
     );
@@ -437,19 +731,42 @@ fn run_list() -> Result<()> {
 
         // Truncate fields to fit column widths
         let id = cli::truncate(&s.id, widths.id);
+        let name = cli::truncate(s.name.as_deref().unwrap_or("-"), widths.name);
         let workspace = cli::truncate(&s.workspace, widths.workspace);
         let client = cli::truncate(&client, widths.client);
 
+        // Pad before coloring so escape codes don't skew the column width
+        let statuses = query_server_status(&s.id);
+        let health = format!(
+            "{:<width$}",
+            health_summary(statuses.as_deref()),
+            width = widths.health
+        );
+        let health = match statuses.as_deref() {
+            None => colors.dim(&health),
+            Some(list) if list.iter().any(|st| st.state == lsp::ServerState::Dead) => {
+                colors.red(&health)
+            }
+            Some(list) if list.iter().any(|st| st.state != lsp::ServerState::Ready) => {
+                colors.yellow(&health)
+            }
+            Some(list) if !list.is_empty() => colors.green(&health),
+            Some(_) => health,
+        };
+
         println!(
-            "{:>width_num$} {:<width_id$} {:<width_pid$} {:<width_client$} {:<width_ws$} {}",
+            "{:>width_num$} {:<width_id$} {:<width_name$} {:<width_pid$} {} {:<width_client$} {:<width_ws$} {}",
             idx + 1,
             id,
+            name,
             s.pid,
+            health,
             client,
             workspace,
             ago,
             width_num = widths.row_num,
             width_id = widths.id,
+            width_name = widths.name,
             width_pid = widths.pid,
             width_client = widths.client,
             width_ws = widths.workspace,
@@ -469,8 +786,61 @@ fn run_list() -> Result<()> {
     Ok(())
 }
 
-/// Resolve a session ID from either a row number or ID prefix
+/// How long `catenary list` waits for a session to report server status.
+const STATUS_QUERY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Asks a session's notify endpoint for the status of its LSP servers.
+///
+/// Returns `None` if the session cannot be reached or does not answer
+/// within [`STATUS_QUERY_TIMEOUT`], so a wedged session never stalls `list`.
+fn query_server_status(session_id: &str) -> Option<Vec<lsp::ServerStatus>> {
+    let stream = notify_connect(&notify_endpoint(session_id))?;
+    #[cfg(unix)]
+    let _ = stream.set_read_timeout(Some(STATUS_QUERY_TIMEOUT));
+    let lines = ipc_exchange(stream, &serde_json::json!({ "status": true }));
+    serde_json::from_str(lines.first()?).ok()
+}
+
+/// Summarizes server states as a compact health cell, e.g. `2✓ 1✗`.
+///
+/// `✓` counts ready servers, `…` servers still initializing or indexing and
+/// `✗` dead ones. Sessions without servers show `-`; unreachable ones `?`.
+fn health_summary(statuses: Option<&[lsp::ServerStatus]>) -> String {
+    let Some(statuses) = statuses else {
+        return "?".to_string();
+    };
+    let count =
+        |pred: fn(lsp::ServerState) -> bool| statuses.iter().filter(|s| pred(s.state)).count();
+    let parts: Vec<String> = [
+        (count(|s| s == lsp::ServerState::Ready), '✓'),
+        (
+            count(|s| {
+                matches!(
+                    s,
+                    lsp::ServerState::Initializing | lsp::ServerState::Indexing
+                )
+            }),
+            '…',
+        ),
+        (count(|s| s == lsp::ServerState::Dead), '✗'),
+    ]
+    .into_iter()
+    .filter(|(n, _)| *n > 0)
+    .map(|(n, mark)| format!("{n}{mark}"))
+    .collect();
+    if parts.is_empty() {
+        "-".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+/// Resolve a session ID from a shortcut keyword, row number, session name, or ID prefix
 fn resolve_session_id(id: &str) -> Result<session::SessionInfo> {
+    if let Some(session) = resolve_session_shortcut(id, &session::list_sessions()?) {
+        return session;
+    }
+
     // Try parsing as a row number first (1-indexed)
     if let Ok(row_num) = id.parse::<usize>()
         && row_num > 0
@@ -488,24 +858,56 @@ fn resolve_session_id(id: &str) -> Result<session::SessionInfo> {
         anyhow::bail!("Row number {} out of range (1-{})", row_num, sessions.len());
     }
 
-    // Fall back to find_session (ID prefix matching)
+    // Fall back to find_session (name and ID prefix matching)
     find_session(id)
 }
 
+/// Resolves the `latest`/`last`/`first` keywords and the empty ID.
+///
+/// `sessions` must be sorted newest first, as returned by
+/// `session::list_sessions()`. Returns `None` if `id` is not a shortcut.
+fn resolve_session_shortcut(
+    id: &str,
+    sessions: &[session::SessionInfo],
+) -> Option<Result<session::SessionInfo>> {
+    let found = match id {
+        "latest" | "last" => sessions.first(),
+        "first" => sessions.last(),
+        "" => {
+            if sessions.len() > 1 {
+                return Some(Err(anyhow::anyhow!(
+                    "{} sessions are running; specify a session ID, name, or row number",
+                    sessions.len()
+                )));
+            }
+            sessions.first()
+        }
+        _ => return None,
+    };
+    Some(
+        found
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No active Catenary sessions")),
+    )
+}
+
 /// Monitor events from a session
 /// Runs the monitor command.
 ///
 /// # Errors
 ///
 /// Returns an error if the session cannot be found or monitoring fails.
-fn run_monitor(id: &str, raw: bool, nocolor: bool, filter: Option<&str>) -> Result<()> {
+fn run_monitor(
+    id: &str,
+    raw: bool,
+    color: ColorChoice,
+    filter: Option<&str>,
+    since: Option<session::EventWindow>,
+) -> Result<()> {
     // Resolve session ID (supports row numbers and prefix matching)
     let session = resolve_session_id(id)?;
     let full_id = session.id;
 
-    let colors = ColorConfig::new(nocolor);
-    let term_width = cli::terminal_width();
-
     // Compile filter regex if provided
     let filter_regex = filter
         .as_ref()
@@ -513,45 +915,35 @@ fn run_monitor(id: &str, raw: bool, nocolor: bool, filter: Option<&str>) -> Resu
         .transpose()
         .map_err(|e| anyhow::anyhow!("Invalid filter regex: {e}"))?;
 
-    println!("Monitoring session {full_id} (Ctrl+C to stop)\n");
+    let mut printer = MonitorPrinter {
+        raw,
+        colors: ColorConfig::new(color),
+        term_width: cli::terminal_width(),
+        filter: filter_regex,
+        last_progress: None,
+    };
 
-    let mut reader = session::tail_events(&full_id)?;
+    println!("Monitoring session {full_id} (Ctrl+C to stop)\n");
 
-    // Track last progress (language, title) for line collapsing.
-    // When consecutive progress events share the same title, the monitor
-    // overwrites the previous line instead of scrolling.
-    let mut last_progress: Option<(String, String)> = None;
+    // With `--since`, replay only the requested slice of history and then
+    // follow from the end; otherwise the tail replays everything.
+    let mut reader = if let Some(window) = since {
+        let history: Vec<_> = session::monitor_events(&full_id)?
+            .filter(|event| printer.matches(event))
+            .collect();
+        let reader = session::tail_new_events(&full_id)?;
+        for event in window.select(history) {
+            printer.print(&event);
+        }
+        reader
+    } else {
+        session::tail_events(&full_id)?
+    };
 
     loop {
         if let Some(event) = reader.next_event()? {
-            // Apply filter if set
-            if let Some(ref re) = filter_regex {
-                let event_str = format!("{:?}", event.kind);
-                if !re.is_match(&event_str) {
-                    continue;
-                }
-            }
-
-            if raw {
-                print_event_raw(&event);
-            } else {
-                // Collapse consecutive progress lines with the same title
-                if let EventKind::Progress {
-                    ref language,
-                    ref title,
-                    ..
-                } = event.kind
-                {
-                    let key = (language.clone(), title.clone());
-                    if last_progress.as_ref() == Some(&key) {
-                        // Same progress context — erase previous line
-                        print!("\x1b[A\x1b[2K");
-                    }
-                    last_progress = Some(key);
-                } else {
-                    last_progress = None;
-                }
-                print_event_annotated(&event, &colors, term_width);
+            if printer.matches(&event) {
+                printer.print(&event);
             }
         } else {
             println!("\nSession ended");
@@ -562,16 +954,99 @@ fn run_monitor(id: &str, raw: bool, nocolor: bool, filter: Option<&str>) -> Resu
     Ok(())
 }
 
+/// Formats events for `catenary monitor`.
+struct MonitorPrinter {
+    raw: bool,
+    colors: ColorConfig,
+    term_width: usize,
+    filter: Option<Regex>,
+    /// Last progress (language, title), for line collapsing. When
+    /// consecutive progress events share the same title, the monitor
+    /// overwrites the previous line instead of scrolling.
+    last_progress: Option<(String, String)>,
+}
+
+impl MonitorPrinter {
+    /// Whether the event passes `--filter`.
+    fn matches(&self, event: &SessionEvent) -> bool {
+        self.filter
+            .as_ref()
+            .is_none_or(|re| re.is_match(&format!("{:?}", event.kind)))
+    }
+
+    fn print(&mut self, event: &SessionEvent) {
+        if self.raw {
+            print_event_raw(event);
+            return;
+        }
+
+        // Collapse consecutive progress lines with the same title
+        if let EventKind::Progress {
+            ref language,
+            ref title,
+            ..
+        } = event.kind
+        {
+            let key = (language.clone(), title.clone());
+            if self.last_progress.as_ref() == Some(&key) {
+                // Same progress context — erase previous line
+                print!("\x1b[A\x1b[2K");
+            }
+            self.last_progress = Some(key);
+        } else {
+            self.last_progress = None;
+        }
+        print_event_annotated(event, &self.colors, self.term_width);
+    }
+}
+
+/// Prints the requested schema as pretty JSON.
+///
+/// # Errors
+///
+/// Returns an error if the schema cannot be serialized.
+fn run_schema(target: SchemaTarget) -> Result<()> {
+    let schema = match target {
+        SchemaTarget::Events => session::events_schema(),
+    };
+    println!("{}", serde_json::to_string_pretty(&schema)?);
+    Ok(())
+}
+
+/// Prints a server's stderr log, following it until the session ends when
+/// `follow` is set.
+///
+/// # Errors
+///
+/// Returns an error if the session or its log cannot be found.
+fn run_logs(id: &str, lang: &str, follow: bool) -> Result<()> {
+    let session = resolve_session_id(id)?;
+    if !follow {
+        let content = std::fs::read_to_string(session::server_log(&session.id, lang)?)?;
+        print!("{content}");
+        return Ok(());
+    }
+    let mut reader = session::tail_server_log(&session.id, lang)?;
+    while let Some(line) = reader.next_line()? {
+        println!("{line}");
+    }
+    Ok(())
+}
+
 /// Show status of a session
 /// Runs the status command.
 ///
 /// # Errors
 ///
 /// Returns an error if the session cannot be found.
-fn run_status(id: &str) -> Result<()> {
-    let session = find_session(id)?;
+fn run_status(id: &str, color: ColorChoice) -> Result<()> {
+    let session = resolve_session_id(id)?;
+    let colors = ColorConfig::new(color);
 
     println!("Session: {}", session.id);
+    if let Some(name) = &session.name {
+        println!("Name: {name}");
+    }
     println!("PID: {}", session.pid);
     println!("Workspace: {}", session.workspace);
     println!(
@@ -597,7 +1072,7 @@ fn run_status(id: &str) -> Result<()> {
     let recent: Vec<_> = events.iter().rev().take(10).collect();
 
     for event in recent.iter().rev() {
-        print_event(event);
+        print_event(event, &colors);
     }
 
     Ok(())
@@ -618,33 +1093,143 @@ fn notify_endpoint(session_id: &str) -> PathBuf {
     }
 }
 
+/// Connection attempts made while a notify endpoint exists but is not yet
+/// accepting connections (the session is still starting up).
+const NOTIFY_CONNECT_ATTEMPTS: u32 = 5;
+
+/// Delay before the first retry; each later retry waits one step longer.
+const NOTIFY_CONNECT_RETRY_STEP: Duration = Duration::from_millis(50);
+
+/// Runs `connect`, retrying with a short linear backoff while it fails with
+/// an error that means "not accepting yet" rather than "not there".
+fn connect_with_retry<T>(mut connect: impl FnMut() -> std::io::Result<T>) -> Option<T> {
+    for attempt in 1..=NOTIFY_CONNECT_ATTEMPTS {
+        match connect() {
+            Ok(stream) => return Some(stream),
+            Err(e) if attempt < NOTIFY_CONNECT_ATTEMPTS && is_transient_connect_error(&e) => {
+                std::thread::sleep(NOTIFY_CONNECT_RETRY_STEP * attempt);
+            }
+            Err(_) => return None,
+        }
+    }
+    None
+}
+
+/// Returns whether a connect error is worth retrying: the listener refused
+/// the connection (bound but not listening yet) or, on Windows, every pipe
+/// instance was busy.
+fn is_transient_connect_error(error: &std::io::Error) -> bool {
+    /// `ERROR_PIPE_BUSY`: all instances of the named pipe are in use.
+    const ERROR_PIPE_BUSY: i32 = 231;
+    error.kind() == std::io::ErrorKind::ConnectionRefused
+        || (cfg!(windows) && error.raw_os_error() == Some(ERROR_PIPE_BUSY))
+}
+
 /// Connects to a notify IPC endpoint and returns a stream for I/O.
 ///
-/// Returns `None` silently on failure (hooks must not break Claude Code's flow).
+/// Retries briefly if the endpoint exists but is not accepting connections
+/// yet. Returns `None` silently on failure (hooks must not break Claude
+/// Code's flow).
 #[cfg(unix)]
 fn notify_connect(endpoint: &std::path::Path) -> Option<std::os::unix::net::UnixStream> {
     if !endpoint.exists() {
         return None;
     }
-    let stream = std::os::unix::net::UnixStream::connect(endpoint).ok()?;
-    let _ = stream.set_read_timeout(Some(Duration::from_secs(60)));
+    let stream = connect_with_retry(|| std::os::unix::net::UnixStream::connect(endpoint))?;
+    let _ = stream.set_read_timeout(Some(Duration::from_mins(1)));
     let _ = stream.set_write_timeout(Some(Duration::from_secs(5)));
     Some(stream)
 }
 
 /// Connects to a notify IPC endpoint and returns a stream for I/O.
 ///
-/// Returns `None` silently on failure (hooks must not break Claude Code's flow).
+/// Retries briefly while every pipe instance is busy. Returns `None`
+/// silently on failure (hooks must not break Claude Code's flow).
 #[cfg(windows)]
 fn notify_connect(endpoint: &std::path::Path) -> Option<std::fs::File> {
     use std::os::windows::fs::OpenOptionsExt;
     // SECURITY_IDENTIFICATION (0x0001_0000) prevents impersonation attacks
-    std::fs::OpenOptions::new()
-        .read(true)
-        .write(true)
-        .security_qos_flags(0x0001_0000)
-        .open(endpoint)
-        .ok()
+    connect_with_retry(|| {
+        std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .security_qos_flags(0x0001_0000)
+            .open(endpoint)
+    })
+}
+
+/// Returns the session whose workspace contains `file_path`.
+fn owning_session(file_path: &str) -> Option<session::SessionInfo> {
+    session::list_sessions()
+        .unwrap_or_default()
+        .into_iter()
+        .find(|s| file_path.starts_with(&s.workspace))
+}
+
+/// Sends a request to the notify endpoint of `session`.
+fn session_request(session: &session::SessionInfo, request: &serde_json::Value) -> Vec<String> {
+    notify_connect(&notify_endpoint(&session.id))
+        .map(|stream| ipc_exchange(stream, request))
+        .unwrap_or_default()
+}
+
+/// Sends a request to the notify endpoint of the session whose workspace
+/// contains `file_path`. Returns no lines if there is no such session.
+fn session_exchange(file_path: &str, request: &serde_json::Value) -> Vec<String> {
+    owning_session(file_path)
+        .map(|session| session_request(&session, request))
+        .unwrap_or_default()
+}
+
+/// Asks the owning session to preview a proposed edit (`block_on_errors`).
+///
+/// Returns the denial reason if the edit would introduce new errors. Tools
+/// that don't carry proposed content (reads) and sessions with
+/// `block_on_errors` disabled never deny, and are not contacted.
+fn check_proposed_edit(hook_json: &serde_json::Value, file_path: &str) -> Option<String> {
+    let session = owning_session(file_path).filter(|s| s.block_on_errors)?;
+    let current = std::fs::read_to_string(file_path).unwrap_or_default();
+    let content = proposed_content(hook_json.get("tool_input")?, &current)?;
+    let request = serde_json::json!({ "check": file_path, "content": content });
+    let reason = session_request(&session, &request).join("\n");
+    let reason = reason.trim();
+    (!reason.is_empty()).then(|| reason.to_string())
+}
+
+/// Computes the file content after applying a write or edit tool input.
+///
+/// Supports full writes (`content`), single edits (`old_string`/`new_string`
+/// with optional `replace_all`) and multi-edits (`edits`). Returns `None` if
+/// the input carries no edit or an `old_string` is not present.
+fn proposed_content(tool_input: &serde_json::Value, current: &str) -> Option<String> {
+    // Edit strings usually arrive with `\n` even when the file uses `\r\n`.
+    let eol = LineEnding::detect(current);
+    let apply = |edit: &serde_json::Value, text: &str| -> Option<String> {
+        let old = eol.apply(edit.get("old_string")?.as_str()?);
+        let new = eol.apply(edit.get("new_string")?.as_str()?);
+        if old.is_empty() || !text.contains(&old) {
+            return None;
+        }
+        let replace_all = edit
+            .get("replace_all")
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false);
+        Some(if replace_all {
+            text.replace(&old, &new)
+        } else {
+            text.replacen(&old, &new, 1)
+        })
+    };
+
+    if let Some(content) = tool_input.get("content").and_then(|c| c.as_str()) {
+        return Some(content.to_string());
+    }
+    if let Some(edits) = tool_input.get("edits").and_then(|e| e.as_array()) {
+        return edits
+            .iter()
+            .try_fold(current.to_string(), |text, edit| apply(edit, &text));
+    }
+    apply(tool_input, current)
 }
 
 /// Sends a JSON request over an IPC stream and reads response lines.
@@ -677,7 +1262,7 @@ fn ipc_exchange(
 /// Reads hook JSON from stdin, extracts the owner and file path, and acquires
 /// the lock. Blocks until the lock is available or the timeout expires.
 /// Silently succeeds on any error to avoid breaking the host CLI's flow.
-fn run_acquire(timeout: u64, format: HostFormat) {
+fn run_acquire(timeout: u64, format: FormatArg) {
     use catenary_mcp::lock::AcquireResult;
 
     let Ok(stdin_data) = std::io::read_to_string(std::io::stdin()) else {
@@ -688,6 +1273,10 @@ fn run_acquire(timeout: u64, format: HostFormat) {
         return;
     };
 
+    let Some(format) = resolve_host_format(format, &hook_json) else {
+        std::process::exit(1);
+    };
+
     let owner = extract_owner(&hook_json);
     let tool = extract_tool_name(&hook_json);
     let Some(file_path) = extract_file_path(&hook_json) else {
@@ -698,7 +1287,19 @@ fn run_acquire(timeout: u64, format: HostFormat) {
         return;
     };
 
-    let result = mgr.acquire(&file_path, &owner, timeout);
+    let mode = lock_mode_for_tool(tool.as_deref());
+    let result = mgr.acquire(&file_path, &owner, mode, timeout);
+
+    // With block_on_errors, deny edits that would break the file before
+    // they are applied, and give the lock back since the tool won't run.
+    if !matches!(result, AcquireResult::Denied { .. })
+        && let Some(reason) = check_proposed_edit(&hook_json, &file_path)
+    {
+        let _ = mgr.release(&file_path, &owner, 0);
+        let output = format_lock_output(format, None, Some(&reason));
+        print!("{output}");
+        return;
+    }
 
     match result {
         AcquireResult::Acquired => {
@@ -723,13 +1324,13 @@ fn run_acquire(timeout: u64, format: HostFormat) {
             let output = format_lock_output(format, Some(&context), None);
             print!("{output}");
         }
-        AcquireResult::Denied { reason } => {
+        AcquireResult::Denied { reason, held_by } => {
             broadcast_lock_event(
                 &hook_json,
                 EventKind::LockDenied {
                     file: file_path,
                     owner,
-                    held_by: "unknown".to_string(),
+                    held_by: held_by.unwrap_or_else(|| "unknown".to_string()),
                 },
             );
             let output = format_lock_output(format, None, Some(&reason));
@@ -751,7 +1352,7 @@ fn run_acquire(timeout: u64, format: HostFormat) {
     clippy::too_many_lines,
     reason = "Sequential post-tool pipeline with early returns"
 )]
-fn run_release(grace: u64, format: Option<HostFormat>) {
+fn run_release(grace: u64, format: Option<FormatArg>) {
     let Ok(stdin_data) = std::io::read_to_string(std::io::stdin()) else {
         return;
     };
@@ -760,6 +1361,10 @@ fn run_release(grace: u64, format: Option<HostFormat>) {
         return;
     };
 
+    // An undetectable host skips diagnostics, but the lock is still released.
+    let host = format.map(|arg| resolve_host_format(arg, &hook_json));
+    let format = host.flatten();
+
     let owner = extract_owner(&hook_json);
     let tool = extract_tool_name(&hook_json);
     let Some(file_path) = extract_file_path(&hook_json) else {
@@ -773,27 +1378,18 @@ fn run_release(grace: u64, format: Option<HostFormat>) {
     // When format is provided, run diagnostics and track-read before releasing
     if let Some(fmt) = format {
         // Step 1: Notify session for diagnostics
-        let abs_path = PathBuf::from(&file_path);
-        let sessions = session::list_sessions().unwrap_or_default();
-        let session = sessions
-            .iter()
-            .find(|s| abs_path.to_string_lossy().starts_with(&s.workspace));
-
-        if let Some(session) = session {
-            let endpoint = notify_endpoint(&session.id);
-            if let Some(stream) = notify_connect(&endpoint) {
-                let request = serde_json::json!({ "file": abs_path.to_string_lossy() });
-                let lines = ipc_exchange(stream, &request);
-
-                if !lines.is_empty() {
-                    let output = format_diagnostics(&lines, fmt, "PostToolUse");
-                    print!("{output}");
-                }
-            }
+        let request = serde_json::json!({ "file": file_path });
+        let lines = session_exchange(&file_path, &request);
+        if !lines.is_empty() {
+            let output = format_diagnostics(&lines, fmt, "PostToolUse");
+            print!("{output}");
         }
 
-        // Step 2: Track read (record mtime)
-        let _ = mgr.track_read(&file_path, &owner);
+        // Step 2: Track read or write (record mtime)
+        let _ = match lock_mode_for_tool(tool.as_deref()) {
+            catenary_mcp::lock::LockMode::Shared => mgr.track_read(&file_path, &owner),
+            catenary_mcp::lock::LockMode::Exclusive => mgr.track_write(&file_path, &owner),
+        };
     }
 
     // Step 3: Release lock
@@ -804,9 +1400,130 @@ fn run_release(grace: u64, format: Option<HostFormat>) {
                 file: file_path,
                 owner,
                 tool,
+                forced: false,
             },
         );
     }
+
+    if host == Some(None) {
+        std::process::exit(1);
+    }
+}
+
+/// Prints the held file locks, one per line or as a JSON array.
+///
+/// # Errors
+///
+/// Returns an error if the locks directory cannot be created.
+fn run_lock_list(json: bool) -> Result<()> {
+    let locks = catenary_mcp::lock::FileLockManager::new()?.list();
+    let now = u64::try_from(Utc::now().timestamp()).unwrap_or(0);
+
+    if json {
+        let entries: Vec<serde_json::Value> = locks
+            .iter()
+            .map(|lock| {
+                serde_json::json!({
+                    "file": lock.file_path,
+                    "owner": lock.owner,
+                    "mode": lock.mode,
+                    "readers": lock.readers,
+                    "acquired_at": lock.acquired_at,
+                    "grace_until": lock.grace_until,
+                    "expires_at": lock.expires_at(),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    if locks.is_empty() {
+        println!("No file locks held");
+        return Ok(());
+    }
+    for lock in &locks {
+        println!("{}", format_lock_line(lock, now));
+    }
+    Ok(())
+}
+
+/// Removes the lock on `file` on its owner's behalf and tells the monitor.
+///
+/// # Errors
+///
+/// Returns an error if the lock is still live and `force` is not set, or if
+/// the lock file cannot be removed.
+fn run_lock_release(file: &Path, force: bool) -> Result<()> {
+    let file = std::path::absolute(file)?.to_string_lossy().into_owned();
+    let mgr = catenary_mcp::lock::FileLockManager::new()?;
+    let now = u64::try_from(Utc::now().timestamp()).unwrap_or(0);
+
+    let Some(lock) = mgr.lock_state(&file) else {
+        println!("{file} is not locked");
+        return Ok(());
+    };
+    if !force && lock.expires_at() > now {
+        anyhow::bail!(
+            "{file} is locked by {} (expires in {}s); pass --force to remove it anyway",
+            lock.owner,
+            lock.expires_at() - now
+        );
+    }
+
+    mgr.force_release(&file)?;
+    broadcast_event_at(
+        &file,
+        EventKind::LockReleased {
+            file: file.clone(),
+            owner: lock.owner.clone(),
+            tool: None,
+            forced: true,
+        },
+    );
+    println!("Released lock on {file} (held by {})", lock.owner);
+    Ok(())
+}
+
+/// Records the post-edit mtime for the hook's owner and file (`PostToolUse`
+/// hook handler). Silently succeeds on any error to avoid breaking the host
+/// CLI's flow.
+fn run_lock_track_write() {
+    let Ok(stdin_data) = std::io::read_to_string(std::io::stdin()) else {
+        return;
+    };
+    let Ok(hook_json) = serde_json::from_str::<serde_json::Value>(&stdin_data) else {
+        return;
+    };
+    let owner = extract_owner(&hook_json);
+    let Some(file_path) = extract_file_path(&hook_json) else {
+        return;
+    };
+    let Ok(mgr) = catenary_mcp::lock::FileLockManager::new() else {
+        return;
+    };
+    let _ = mgr.track_write(&file_path, &owner);
+}
+
+/// Formats a lock as `file — owner — acquired Ns ago — expires in Ns`.
+fn format_lock_line(lock: &catenary_mcp::lock::LockState, now: u64) -> String {
+    use catenary_mcp::lock::LockMode;
+
+    let mut owners = lock.holders().collect::<Vec<_>>().join(", ");
+    if lock.mode == LockMode::Shared {
+        owners.push_str(" (shared)");
+    }
+    let acquired = now.saturating_sub(lock.acquired_at);
+    let expires = lock.expires_at();
+    let expiry = if expires > now {
+        format!("expires in {}s", expires - now)
+    } else {
+        "expired".to_string()
+    };
+    format!(
+        "{} \u{2014} {owners} \u{2014} acquired {acquired}s ago \u{2014} {expiry}",
+        lock.file_path
+    )
 }
 
 /// Sync workspace roots from Claude Code transcript to a running Catenary session.
@@ -824,7 +1541,7 @@ fn run_release(grace: u64, format: Option<HostFormat>) {
     clippy::too_many_lines,
     reason = "Sequential hook processing with early returns"
 )]
-fn run_sync_roots(format: HostFormat) {
+fn run_sync_roots(format: FormatArg) {
     use std::io::{BufRead, Seek, SeekFrom};
 
     let Ok(stdin_data) = std::io::read_to_string(std::io::stdin()) else {
@@ -835,6 +1552,10 @@ fn run_sync_roots(format: HostFormat) {
         return;
     };
 
+    let Some(format) = resolve_host_format(format, &hook_json) else {
+        std::process::exit(1);
+    };
+
     // Extract transcript_path and cwd from hook input
     let Some(transcript_path) = hook_json.get("transcript_path").and_then(|v| v.as_str()) else {
         return;
@@ -1040,6 +1761,69 @@ fn extract_owner(hook_json: &serde_json::Value) -> String {
     )
 }
 
+/// Resolves `--format` to a host, detecting it for `auto`.
+///
+/// Returns `None` after printing an error when the host cannot be told
+/// apart, so a misdetected host never receives the other one's JSON.
+fn resolve_host_format(arg: FormatArg, hook_json: &serde_json::Value) -> Option<HostFormat> {
+    match arg {
+        FormatArg::Claude => Some(HostFormat::Claude),
+        FormatArg::Gemini => Some(HostFormat::Gemini),
+        FormatArg::Auto => {
+            let detected = detect_host_format(hook_json, |name| std::env::var_os(name).is_some());
+            if detected.is_none() {
+                eprintln!(
+                    "catenary: --format auto could not tell Claude Code from Gemini CLI; \
+                     pass --format claude or --format gemini"
+                );
+            }
+            detected
+        }
+    }
+}
+
+/// Claude Code tool names Catenary's hooks match on.
+const CLAUDE_HOOK_TOOLS: &[&str] = &["Read", "Edit", "Write", "MultiEdit", "NotebookEdit"];
+
+/// Gemini CLI tool names Catenary's hooks match on.
+const GEMINI_HOOK_TOOLS: &[&str] = &["read_file", "write_file", "replace"];
+
+/// Detects the host CLI that invoked a hook.
+///
+/// Checks, in order: the hook event name (`PreToolUse` vs `BeforeTool`),
+/// the tool name, then the environment (`CLAUDECODE` vs
+/// `GEMINI_PROJECT_DIR`/`GEMINI_CLI`). Gemini CLI also sets
+/// `CLAUDE_PROJECT_DIR` for compatibility, so that variable is not a
+/// signal. Returns `None` when the signals are missing or conflict.
+fn detect_host_format(
+    hook_json: &serde_json::Value,
+    env_is_set: impl Fn(&str) -> bool,
+) -> Option<HostFormat> {
+    match hook_json.get("hook_event_name").and_then(|v| v.as_str()) {
+        Some("PreToolUse" | "PostToolUse" | "PostToolUseFailure" | "UserPromptSubmit") => {
+            return Some(HostFormat::Claude);
+        }
+        Some("BeforeTool" | "AfterTool" | "BeforeAgent" | "AfterAgent") => {
+            return Some(HostFormat::Gemini);
+        }
+        _ => {}
+    }
+
+    match extract_tool_name(hook_json).as_deref() {
+        Some(tool) if CLAUDE_HOOK_TOOLS.contains(&tool) => return Some(HostFormat::Claude),
+        Some(tool) if GEMINI_HOOK_TOOLS.contains(&tool) => return Some(HostFormat::Gemini),
+        _ => {}
+    }
+
+    let claude = env_is_set("CLAUDECODE");
+    let gemini = env_is_set("GEMINI_PROJECT_DIR") || env_is_set("GEMINI_CLI");
+    match (claude, gemini) {
+        (true, false) => Some(HostFormat::Claude),
+        (false, true) => Some(HostFormat::Gemini),
+        _ => None,
+    }
+}
+
 /// Extracts the tool name from hook JSON (e.g. "Edit", "Read", "Write").
 fn extract_tool_name(hook_json: &serde_json::Value) -> Option<String> {
     hook_json
@@ -1048,6 +1832,17 @@ fn extract_tool_name(hook_json: &serde_json::Value) -> Option<String> {
         .map(String::from)
 }
 
+/// Picks the lock mode for a hooked tool: reads share the lock, everything
+/// else takes it exclusively.
+fn lock_mode_for_tool(tool: Option<&str>) -> catenary_mcp::lock::LockMode {
+    use catenary_mcp::lock::LockMode;
+
+    match tool {
+        Some("Read" | "read_file") => LockMode::Shared,
+        _ => LockMode::Exclusive,
+    }
+}
+
 /// Extracts the file path from hook JSON's `tool_input`.
 fn extract_file_path(hook_json: &serde_json::Value) -> Option<String> {
     let file_path = hook_json
@@ -1122,12 +1917,17 @@ fn format_lock_output(
 /// via the session's event broadcaster. Silently does nothing if no session
 /// is found or the broadcast fails.
 fn broadcast_lock_event(hook_json: &serde_json::Value, event: EventKind) {
-    use std::io::Write;
-
     let cwd = hook_json.get("cwd").and_then(|v| v.as_str()).unwrap_or("");
+    broadcast_event_at(cwd, event);
+}
+
+/// Appends `event` to the log of the session whose workspace contains
+/// `path` (best-effort).
+fn broadcast_event_at(path: &str, event: EventKind) {
+    use std::io::Write;
 
     let sessions = session::list_sessions().unwrap_or_default();
-    let Some(session_info) = sessions.iter().find(|s| cwd.starts_with(&s.workspace)) else {
+    let Some(session_info) = sessions.iter().find(|s| path.starts_with(&s.workspace)) else {
         return;
     };
 
@@ -1163,7 +1963,36 @@ fn broadcast_lock_event(hook_json: &serde_json::Value, event: EventKind) {
 /// - Claude: includes `hookEventName` + `additionalContext` for Claude Code
 ///   hooks (required by the Claude Code hook contract).
 fn format_diagnostics(lines: &[String], format: HostFormat, hook_event: &str) -> String {
-    let diagnostics = lines.join("\n");
+    format_grouped_diagnostics(&[("", lines)], format, hook_event)
+}
+
+/// Format diagnostic lines from one or more files for output.
+///
+/// Each group is `(file, lines)`. With more than one non-empty group, every
+/// group is preceded by a `=== <file> ===` header so each diagnostic can be
+/// attributed to its file; a single group is rendered exactly like
+/// [`format_diagnostics`]. Empty groups are skipped.
+fn format_grouped_diagnostics(
+    groups: &[(&str, &[String])],
+    format: HostFormat,
+    hook_event: &str,
+) -> String {
+    let groups: Vec<_> = groups
+        .iter()
+        .filter(|(_, lines)| !lines.is_empty())
+        .collect();
+    let diagnostics = if groups.len() > 1 {
+        groups
+            .iter()
+            .map(|(file, lines)| format!("=== {file} ===\n{}", lines.join("\n")))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    } else {
+        groups
+            .first()
+            .map(|(_, lines)| lines.join("\n"))
+            .unwrap_or_default()
+    };
     // serde_json::to_string cannot fail on Value
     match format {
         HostFormat::Gemini => serde_json::json!({
@@ -1435,24 +2264,30 @@ fn normalize_json(s: &str) -> String {
         .unwrap_or_else(|| s.trim().to_string())
 }
 
-/// Run the doctor command: check language server health for the current workspace.
+/// Loads configuration and applies the command-line overrides (`--lsp`,
+/// `--lsp-env`, `--idle-timeout`, ...). Shared by the server and the
+/// subcommands that inspect its configuration.
 ///
 /// # Errors
 ///
-/// Returns an error if the configuration cannot be loaded or roots cannot be resolved.
-#[allow(
-    clippy::too_many_lines,
-    reason = "Doctor command has sequential output logic"
-)]
-async fn run_doctor(args: Args, nocolor: bool) -> Result<()> {
-    let colors = ColorConfig::new(nocolor);
+/// Returns an error if the configuration cannot be loaded or an override is
+/// malformed.
+fn load_cli_config(args: &Args) -> Result<catenary_mcp::config::Config> {
+    let mut config = catenary_mcp::config::Config::load(args.config.clone())?;
 
-    // Print version header
-    println!("Catenary {}", env!("CATENARY_VERSION"));
-    println!();
+    if let Some(timeout) = args.idle_timeout {
+        config.idle_timeout = timeout;
+    }
+    if args.strict_roots {
+        config.strict_roots = true;
+    }
+    if let Some(max) = args.max_servers {
+        config.max_concurrent_servers = max;
+    }
+    if let Some(timeout) = args.init_timeout {
+        config.init_timeout = timeout;
+    }
 
-    // Load configuration (same as run_server)
-    let mut config = catenary_mcp::config::Config::load(args.config.clone())?;
     for lsp_spec in &args.lsps {
         let (lang, command_str) = lsp_spec.split_once(':').ok_or_else(|| {
             anyhow::anyhow!("Invalid LSP spec: {lsp_spec}. Expected 'lang:command'")
@@ -1471,20 +2306,102 @@ async fn run_doctor(args: Args, nocolor: bool) -> Result<()> {
                 command: program,
                 args: cmd_args,
                 initialization_options: None,
+                env: std::collections::HashMap::new(),
+                cwd: None,
+                isolate_roots: false,
+                document_selector: Vec::new(),
+                languages: Vec::new(),
+                transport: catenary_mcp::config::Transport::Stdio,
+                host: None,
+                port: None,
             },
         );
     }
+    apply_lsp_env(&mut config, &args.lsp_env)?;
 
-    // Resolve workspace roots
-    let raw_roots = if args.root.is_empty() {
-        vec![PathBuf::from(".")]
-    } else {
-        args.root
-    };
-    let roots: Vec<PathBuf> = raw_roots
-        .into_iter()
+    Ok(config)
+}
+
+/// Canonicalizes the `--root` arguments. Without any, defaults to the
+/// nearest ancestor of the current directory holding one of `markers`, or the
+/// current directory itself.
+///
+/// # Errors
+///
+/// Returns an error if a root does not exist.
+fn resolve_cli_roots(roots: &[PathBuf], markers: &[String]) -> Result<Vec<PathBuf>> {
+    if roots.is_empty() {
+        let cwd = Path::new(".").canonicalize()?;
+        let root = lsp::find_project_root(&cwd, markers).unwrap_or(cwd);
+        debug!("Using {} as the workspace root", root.display());
+        return Ok(vec![root]);
+    }
+    Ok(roots
+        .iter()
         .map(|r| r.canonicalize())
-        .collect::<std::io::Result<Vec<_>>>()?;
+        .collect::<std::io::Result<Vec<_>>>()?)
+}
+
+/// Run the doctor command: check language server health for the current
+/// workspace, once or every `watch` seconds until Ctrl+C.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or roots cannot be
+/// resolved. In watch mode such errors are shown and the checks retried.
+async fn run_doctor(
+    args: &Args,
+    color: ColorChoice,
+    probe: bool,
+    watch: Option<u64>,
+) -> Result<()> {
+    let Some(interval) = watch else {
+        return doctor_report(args, color, probe).await;
+    };
+
+    let colors = ColorConfig::new(color);
+    loop {
+        cli::clear_screen()?;
+        // Ctrl+C may arrive while a server is starting; dropping the report
+        // kills it.
+        tokio::select! {
+            result = doctor_report(args, color, probe) => {
+                if let Err(e) = result {
+                    println!("{}", colors.red(&format!("Error: {e:#}")));
+                }
+            }
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+        println!();
+        println!(
+            "{}",
+            colors.dim(&format!("Every {interval}s; press Ctrl+C to exit."))
+        );
+        tokio::select! {
+            () = tokio::time::sleep(Duration::from_secs(interval)) => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+    }
+}
+
+/// Prints one round of `catenary doctor` checks.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or roots cannot be resolved.
+#[allow(
+    clippy::too_many_lines,
+    reason = "Doctor command has sequential output logic"
+)]
+async fn doctor_report(args: &Args, color: ColorChoice, probe: bool) -> Result<()> {
+    let colors = ColorConfig::new(color);
+
+    // Print version header
+    println!("Catenary {}", env!("CATENARY_VERSION"));
+    println!();
+
+    let config = load_cli_config(args)?;
+    let roots = resolve_cli_roots(&args.root, &config.root_markers)?;
 
     // Print config and roots
     let config_source = args
@@ -1511,7 +2428,8 @@ async fn run_doctor(args: Args, nocolor: bool) -> Result<()> {
     // Detect which languages have files in the workspace
     let configured_keys: std::collections::HashSet<&str> =
         config.server.keys().map(String::as_str).collect();
-    let detected = lsp::detect_workspace_languages(&roots, &configured_keys);
+    let detected =
+        lsp::detect_workspace_languages(&roots, &configured_keys, &config.language_overrides()?);
 
     // Sort servers alphabetically
     let mut servers: Vec<(&String, &catenary_mcp::config::ServerConfig)> =
@@ -1522,7 +2440,7 @@ async fn run_doctor(args: Args, nocolor: bool) -> Result<()> {
     let max_lang_width = servers.iter().map(|(l, _)| l.len()).max().unwrap_or(10);
     let max_cmd_width = servers
         .iter()
-        .map(|(_, s)| s.command.len())
+        .map(|(_, s)| server_endpoint(s).len())
         .max()
         .unwrap_or(10);
 
@@ -1531,7 +2449,10 @@ async fn run_doctor(args: Args, nocolor: bool) -> Result<()> {
 
     for (lang, server_config) in &servers {
         let lang_display = format!("{lang:<max_lang_width$}");
-        let cmd_display = format!("{cmd:<max_cmd_width$}", cmd = server_config.command);
+        let cmd_display = format!(
+            "{cmd:<max_cmd_width$}",
+            cmd = server_endpoint(server_config)
+        );
 
         // Check if any files for this language exist
         if !detected.contains(lang.as_str()) {
@@ -1544,25 +2465,56 @@ async fn run_doctor(args: Args, nocolor: bool) -> Result<()> {
             continue;
         }
 
-        // Check if binary exists on PATH
-        if !binary_exists(&server_config.command) {
-            println!(
-                "{}  {}  {}",
-                lang_display,
-                cmd_display,
-                colors.red("✗ command not found"),
-            );
-            continue;
-        }
+        // Show configured environment so it is obvious it was picked up
+        let indent = " ".repeat(max_lang_width + max_cmd_width + 4);
+        let env_line = format_server_env(&server_config.env);
+        let print_env = || {
+            if let Some(line) = &env_line {
+                println!("{indent}  {}", colors.dim(line));
+            }
+        };
 
-        // Spawn and initialize the server
-        let args_refs: Vec<&str> = server_config.args.iter().map(String::as_str).collect();
-        let spawn_result = lsp::LspClient::spawn_quiet(
-            &server_config.command,
-            &args_refs,
-            lang,
-            broadcaster.clone(),
-        );
+        let spawn_result = match server_config.tcp_address() {
+            Ok(Some((host, port))) => {
+                lsp::LspClient::connect(&host, port, lang, broadcaster.clone()).await
+            }
+            Ok(None) => {
+                // Check if binary exists on PATH (or in the login shell's PATH)
+                let program = if config.resolve_in_login_shell {
+                    lsp::resolve_in_login_shell(
+                        &server_config.command,
+                        roots.first().map(PathBuf::as_path),
+                    )
+                    .await
+                    .unwrap_or_else(|| server_config.command.clone())
+                } else {
+                    server_config.command.clone()
+                };
+                if !binary_exists(&program) {
+                    println!(
+                        "{}  {}  {}",
+                        lang_display,
+                        cmd_display,
+                        colors.red("✗ command not found"),
+                    );
+                    print_env();
+                    continue;
+                }
+
+                // Spawn and initialize the server
+                let args_refs: Vec<&str> = server_config.args.iter().map(String::as_str).collect();
+                let cwd = server_config.resolved_cwd(roots.first().map(PathBuf::as_path));
+                lsp::LspClient::spawn_quiet(
+                    &program,
+                    &args_refs,
+                    &server_config.env,
+                    cwd.as_deref(),
+                    lang,
+                    broadcaster.clone(),
+                )
+            }
+            Err(e) => Err(e),
+        };
 
         let mut client = match spawn_result {
             Ok(client) => client,
@@ -1573,28 +2525,35 @@ async fn run_doctor(args: Args, nocolor: bool) -> Result<()> {
                     cmd_display,
                     colors.red(&format!("✗ spawn failed: {e}")),
                 );
+                print_env();
                 continue;
             }
         };
 
+        let mut exited = false;
         match client
             .initialize(&roots, server_config.initialization_options.clone())
             .await
         {
             Ok(result) => {
-                let tools = extract_capabilities(&result.capabilities);
-                println!(
-                    "{}  {}  {}",
-                    lang_display,
-                    cmd_display,
-                    colors.green("✓ ready"),
-                );
+                let tools = lsp::extract_capabilities(&result.capabilities);
+                let status = if probe {
+                    match probe_server(&client).await {
+                        ProbeResult::Responsive => colors.green("✓ ready and responsive"),
+                        ProbeResult::NotResponding => {
+                            colors.yellow("⚠ initialized but not responding")
+                        }
+                        ProbeResult::Exited => {
+                            exited = true;
+                            colors.red("✗ exited after initialize")
+                        }
+                    }
+                } else {
+                    colors.green("✓ ready")
+                };
+                println!("{lang_display}  {cmd_display}  {status}");
                 if !tools.is_empty() {
-                    println!(
-                        "{}  {}",
-                        " ".repeat(max_lang_width + max_cmd_width + 4),
-                        colors.dim(&tools.join(" ")),
-                    );
+                    println!("{indent}  {}", colors.dim(&tools.join(" ")));
                 }
             }
             Err(e) => {
@@ -1604,6 +2563,16 @@ async fn run_doctor(args: Args, nocolor: bool) -> Result<()> {
                     cmd_display,
                     colors.red(&format!("✗ initialize failed: {e}")),
                 );
+                exited = true;
+            }
+        }
+
+        print_env();
+
+        // The real reason a server failed is often only on its stderr
+        if exited {
+            for line in client.stderr_tail(DOCTOR_STDERR_LINES).await {
+                println!("{indent}  {}", colors.dim(&format!("stderr: {line}")));
             }
         }
 
@@ -1621,6 +2590,80 @@ async fn run_doctor(args: Args, nocolor: bool) -> Result<()> {
     Ok(())
 }
 
+/// Returns how `catenary doctor` shows where a server runs: its command, or
+/// `tcp://host:port` for the `tcp` transport.
+fn server_endpoint(server: &catenary_mcp::config::ServerConfig) -> String {
+    match server.tcp_address() {
+        Ok(Some((host, port))) => format!("tcp://{host}:{port}"),
+        _ => server.command.clone(),
+    }
+}
+
+/// Shown in place of server environment values, which may hold secrets.
+const REDACTED_ENV_VALUE: &str = "***";
+
+/// Formats a server's extra environment as `env: KEY=*** ...`, sorted by
+/// key, or `None` if it has none. Values are masked.
+fn format_server_env(env: &std::collections::HashMap<String, String>) -> Option<String> {
+    if env.is_empty() {
+        return None;
+    }
+    let mut vars: Vec<String> = env
+        .keys()
+        .map(|k| format!("{k}={REDACTED_ENV_VALUE}"))
+        .collect();
+    vars.sort();
+    Some(format!("env: {}", vars.join(" ")))
+}
+
+/// Lines of a failed server's stderr shown by `doctor`.
+const DOCTOR_STDERR_LINES: usize = 5;
+
+/// How long a single doctor probe request may take.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Number of probe requests sent before a server is reported as hung.
+const PROBE_ATTEMPTS: u32 = 2;
+
+/// Outcome of the doctor's post-initialize responsiveness probe.
+enum ProbeResult {
+    /// The server answered a request.
+    Responsive,
+    /// Every probe request timed out.
+    NotResponding,
+    /// The server process exited after initializing.
+    Exited,
+}
+
+/// Sends a trivial request to an initialized server to check that it is
+/// actually serving requests, not just past the handshake.
+///
+/// An empty `workspace/symbol` query is used because it needs no file. Any
+/// answer counts, including an error response (e.g. method not supported).
+/// A timed-out attempt is retried, since servers that are still loading
+/// the project may answer the second request.
+async fn probe_server(client: &lsp::LspClient) -> ProbeResult {
+    let params = lsp_types::WorkspaceSymbolParams {
+        query: String::new(),
+        work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+        partial_result_params: lsp_types::PartialResultParams::default(),
+    };
+
+    for _ in 0..PROBE_ATTEMPTS {
+        let answered =
+            tokio::time::timeout(PROBE_TIMEOUT, client.workspace_symbols(params.clone()))
+                .await
+                .is_ok();
+        if !client.is_alive() {
+            return ProbeResult::Exited;
+        }
+        if answered {
+            return ProbeResult::Responsive;
+        }
+    }
+    ProbeResult::NotResponding
+}
+
 /// Checks whether a binary can be found on `$PATH`.
 fn binary_exists(command: &str) -> bool {
     // If the command contains a path separator, check it directly
@@ -1633,55 +2676,291 @@ fn binary_exists(command: &str) -> bool {
     std::env::split_paths(&path_var).any(|dir| dir.join(command).is_file())
 }
 
-/// Extracts Catenary tool names from LSP `ServerCapabilities`.
-fn extract_capabilities(caps: &lsp_types::ServerCapabilities) -> Vec<&'static str> {
-    let mut tools = Vec::new();
-
-    if caps.hover_provider.is_some() {
-        tools.push("hover");
-    }
-    if caps.definition_provider.is_some() {
-        tools.push("definition");
-    }
-    if caps.type_definition_provider.is_some() {
-        tools.push("type_definition");
-    }
-    if caps.implementation_provider.is_some() {
-        tools.push("implementation");
-    }
-    if caps.references_provider.is_some() {
-        tools.push("references");
+/// Run the config command: list the config files that are merged, or with
+/// `show` print the effective configuration the server would start with,
+/// server env values masked.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be loaded or serialized.
+fn run_config(args: &Args, show: bool, json: bool) -> Result<()> {
+    if !show {
+        let files = catenary_mcp::config::Config::files(args.config.clone());
+        if files.is_empty() {
+            println!("No config files found; using defaults.");
+        }
+        for file in files {
+            println!("{}", file.display());
+        }
+        return Ok(());
     }
-    if caps.document_symbol_provider.is_some() {
-        tools.push("document_symbols");
+
+    let mut config = load_cli_config(args)?;
+    for server in config.server.values_mut() {
+        for value in server.env.values_mut() {
+            REDACTED_ENV_VALUE.clone_into(value);
+        }
     }
-    if caps.workspace_symbol_provider.is_some() {
-        tools.push("search");
+    if json {
+        println!("{}", serde_json::to_string_pretty(&config)?);
+    } else {
+        print!("{}", toml::to_string(&config)?);
     }
-    if caps.code_action_provider.is_some() {
-        tools.push("code_actions");
+    Ok(())
+}
+
+/// Run the capabilities command: initialize one server and print the
+/// capabilities it advertised.
+///
+/// # Errors
+///
+/// Returns an error if the language has no configured server or the server
+/// fails to start or initialize.
+async fn run_capabilities(args: Args, language: &str, json: bool) -> Result<()> {
+    let config = load_cli_config(&args)?;
+    let roots = resolve_cli_roots(&args.root, &config.root_markers)?;
+    let server_config = config
+        .server
+        .get(language)
+        .ok_or_else(|| anyhow::anyhow!("No server configured for '{language}'"))?;
+
+    let broadcaster = catenary_mcp::session::EventBroadcaster::noop()?;
+    let mut client = if let Some((host, port)) = server_config.tcp_address()? {
+        lsp::LspClient::connect(&host, port, language, broadcaster).await?
+    } else {
+        let program = if config.resolve_in_login_shell {
+            lsp::resolve_in_login_shell(&server_config.command, roots.first().map(PathBuf::as_path))
+                .await
+                .unwrap_or_else(|| server_config.command.clone())
+        } else {
+            server_config.command.clone()
+        };
+
+        let args_refs: Vec<&str> = server_config.args.iter().map(String::as_str).collect();
+        let cwd = server_config.resolved_cwd(roots.first().map(PathBuf::as_path));
+        lsp::LspClient::spawn_quiet(
+            &program,
+            &args_refs,
+            &server_config.env,
+            cwd.as_deref(),
+            language,
+            broadcaster,
+        )?
+    };
+
+    let result = match client
+        .initialize(&roots, server_config.initialization_options.clone())
+        .await
+    {
+        Ok(result) => result,
+        Err(e) => {
+            for line in client.stderr_tail(DOCTOR_STDERR_LINES).await {
+                eprintln!("stderr: {line}");
+            }
+            return Err(e.context(format!("{language} server failed to initialize")));
+        }
+    };
+    let _ = client.shutdown().await;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&result.capabilities)?);
+    } else {
+        print!("{}", format_capabilities(language, &result));
     }
-    if caps.rename_provider.is_some() {
-        tools.push("rename");
+    Ok(())
+}
+
+/// Formats the subset of an `InitializeResult` that decides which tools work
+/// and how Catenary talks to the server.
+fn format_capabilities(language: &str, result: &lsp_types::InitializeResult) -> String {
+    use lsp_types::OneOf;
+    use std::fmt::Write;
+
+    let caps = &result.capabilities;
+    let yes_no = |b: bool| if b { "yes" } else { "no" };
+    let mut rows: Vec<(&str, String)> = Vec::new();
+
+    let server = result.server_info.as_ref().map_or_else(
+        || language.to_string(),
+        |info| {
+            info.version.as_ref().map_or_else(
+                || info.name.clone(),
+                |version| format!("{} {version}", info.name),
+            )
+        },
+    );
+    rows.push(("Server", server));
+
+    rows.push((
+        "Position encoding",
+        caps.position_encoding.as_ref().map_or_else(
+            || "utf-16 (default)".to_string(),
+            |e| e.as_str().to_string(),
+        ),
+    ));
+
+    rows.push(("Text sync", text_sync_summary(caps)));
+
+    let tools = lsp::extract_capabilities(caps);
+    rows.push((
+        "Tools",
+        if tools.is_empty() {
+            "none".to_string()
+        } else {
+            tools.join(" ")
+        },
+    ));
+
+    let rename = match &caps.rename_provider {
+        None | Some(OneOf::Left(false)) => "no",
+        Some(OneOf::Left(true)) => "yes",
+        Some(OneOf::Right(opts)) => {
+            if opts.prepare_provider.unwrap_or(false) {
+                "yes (prepare)"
+            } else {
+                "yes"
+            }
+        }
+    };
+    rows.push(("Rename", rename.to_string()));
+
+    rows.push(("Code actions", code_action_summary(caps)));
+
+    rows.push((
+        "Commands",
+        caps.execute_command_provider
+            .as_ref()
+            .filter(|opts| !opts.commands.is_empty())
+            .map_or_else(|| "none".to_string(), |opts| opts.commands.join(", ")),
+    ));
+
+    rows.push((
+        "Diagnostics",
+        if caps.diagnostic_provider.is_some() {
+            "push and pull"
+        } else {
+            "push"
+        }
+        .to_string(),
+    ));
+
+    let folders = caps
+        .workspace
+        .as_ref()
+        .and_then(|w| w.workspace_folders.as_ref());
+    rows.push((
+        "Workspace folders",
+        folders.map_or_else(
+            || "no".to_string(),
+            |f| {
+                let changes = matches!(
+                    f.change_notifications,
+                    Some(OneOf::Left(true) | OneOf::Right(_))
+                );
+                format!(
+                    "{} (change notifications: {})",
+                    yes_no(f.supported.unwrap_or(false)),
+                    yes_no(changes)
+                )
+            },
+        ),
+    ));
+
+    let width = rows.iter().map(|(label, _)| label.len()).max().unwrap_or(0) + 1;
+    let mut out = String::new();
+    for (label, value) in rows {
+        let label = format!("{label}:");
+        let _ = writeln!(out, "{label:<width$} {value}");
     }
-    if caps.call_hierarchy_provider.is_some() {
-        tools.push("call_hierarchy");
+    out
+}
+
+/// Describes how the server wants document changes synced.
+fn text_sync_summary(caps: &lsp_types::ServerCapabilities) -> String {
+    use lsp_types::{
+        TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncSaveOptions,
+    };
+
+    let kind_name = |kind: TextDocumentSyncKind| match kind {
+        TextDocumentSyncKind::FULL => "full",
+        TextDocumentSyncKind::INCREMENTAL => "incremental",
+        _ => "none",
+    };
+    match &caps.text_document_sync {
+        None => "none".to_string(),
+        Some(TextDocumentSyncCapability::Kind(kind)) => kind_name(*kind).to_string(),
+        Some(TextDocumentSyncCapability::Options(opts)) => {
+            let save = match &opts.save {
+                Some(TextDocumentSyncSaveOptions::Supported(b)) => *b,
+                Some(TextDocumentSyncSaveOptions::SaveOptions(_)) => true,
+                None => false,
+            };
+            let yes_no = |b: bool| if b { "yes" } else { "no" };
+            format!(
+                "{} (open/close: {}, save: {})",
+                opts.change.map_or("none", kind_name),
+                yes_no(opts.open_close.unwrap_or(false)),
+                yes_no(save),
+            )
+        }
     }
-    // type_hierarchy_provider is not exposed as a direct field in lsp_types 0.97;
-    // type hierarchy support is probed at call time, so we omit it here.
+}
+
+/// Describes code action support and the kinds the server offers.
+fn code_action_summary(caps: &lsp_types::ServerCapabilities) -> String {
+    use lsp_types::CodeActionProviderCapability;
 
-    tools
+    match &caps.code_action_provider {
+        None | Some(CodeActionProviderCapability::Simple(false)) => "no".to_string(),
+        Some(CodeActionProviderCapability::Simple(true)) => {
+            "yes (kinds not advertised)".to_string()
+        }
+        Some(CodeActionProviderCapability::Options(opts)) => {
+            let kinds = opts.code_action_kinds.as_ref().map_or_else(
+                || "kinds not advertised".to_string(),
+                |kinds| {
+                    kinds
+                        .iter()
+                        .map(lsp_types::CodeActionKind::as_str)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                },
+            );
+            if opts.resolve_provider.unwrap_or(false) {
+                format!("yes ({kinds}; resolve)")
+            } else {
+                format!("yes ({kinds})")
+            }
+        }
+    }
 }
 
-/// Find session by ID or prefix
+/// Find session by ID, name, or ID prefix
 fn find_session(id: &str) -> Result<session::SessionInfo> {
     // Try exact match first
     if let Some(s) = session::get_session(id)? {
         return Ok(s);
     }
 
-    // Try prefix match
+    // Try exact name match
     let sessions = session::list_sessions()?;
+    let named: Vec<_> = sessions
+        .iter()
+        .filter(|s| s.name.as_deref() == Some(id))
+        .collect();
+    match named.len() {
+        0 => {}
+        1 => return Ok(named[0].clone()),
+        _ => {
+            eprintln!("Multiple sessions are named '{id}':");
+            for s in named {
+                eprintln!("  {}", s.id);
+            }
+            anyhow::bail!("Please specify a session ID instead")
+        }
+    }
+
+    // Try prefix match
     let matches: Vec<_> = sessions.iter().filter(|s| s.id.starts_with(id)).collect();
 
     match matches.len() {
@@ -1731,6 +3010,31 @@ fn print_event_raw(event: &SessionEvent) {
     }
 }
 
+/// Formats a server-originated log line or message for the monitor.
+///
+/// Errors are red, warnings yellow, info plain and logs dim. Only the first
+/// line of multi-line messages is shown, truncated to the terminal width.
+fn format_server_output(
+    language: &str,
+    level: &str,
+    message: &str,
+    colors: &ColorConfig,
+    term_width: usize,
+) -> String {
+    let lang = colors.cyan(language);
+    let first_line = message.lines().next().unwrap_or_default().trim_end();
+    // Format: [HH:MM:SS] lang: message
+    let max_len = term_width.saturating_sub(10 + 1 + language.len() + 2);
+    let text = cli::truncate(first_line, max_len);
+    let text = match level {
+        "error" => colors.red(&text),
+        "warning" => colors.yellow(&text),
+        "info" => text,
+        _ => colors.dim(&text),
+    };
+    format!("{lang}: {text}")
+}
+
 /// Print an event with annotations and colors
 #[allow(clippy::too_many_lines, reason = "Match arms for each event kind")]
 fn print_event_annotated(event: &SessionEvent, colors: &ColorConfig, term_width: usize) {
@@ -1744,6 +3048,15 @@ fn print_event_annotated(event: &SessionEvent, colors: &ColorConfig, term_width:
         EventKind::Shutdown => {
             println!("{time_str} Session shutting down");
         }
+        EventKind::ClientDisconnected => {
+            println!("{time_str} MCP client disconnected");
+        }
+        EventKind::SignalShutdown { signal } => {
+            println!(
+                "{time_str} {}",
+                colors.yellow(&format!("Received {signal}"))
+            );
+        }
         EventKind::ServerState { language, state } => {
             let lang = colors.cyan(language);
             println!("{time_str} {lang}: {state}");
@@ -1823,12 +3136,21 @@ fn print_event_annotated(event: &SessionEvent, colors: &ColorConfig, term_width:
             let short_owner = cli::truncate(owner, 20);
             println!("{time_str} {basename}: {lock_icon}{tool_label} by {short_owner}");
         }
-        EventKind::LockReleased { file, owner, tool } => {
+        EventKind::LockReleased {
+            file,
+            owner,
+            tool,
+            forced,
+        } => {
             let basename = std::path::Path::new(file.as_str())
                 .file_name()
                 .and_then(|n| n.to_str())
                 .unwrap_or(file);
-            let unlock_icon = colors.dim("unlocked");
+            let unlock_icon = if *forced {
+                colors.yellow("force-unlocked")
+            } else {
+                colors.dim("unlocked")
+            };
             let tool_label = tool.as_ref().map(|t| format!(" ({t})")).unwrap_or_default();
             let short_owner = cli::truncate(owner, 20);
             println!("{time_str} {basename}: {unlock_icon}{tool_label} by {short_owner}");
@@ -1847,6 +3169,22 @@ fn print_event_annotated(event: &SessionEvent, colors: &ColorConfig, term_width:
             let short_held = cli::truncate(held_by, 20);
             println!("{time_str} {basename}: {denied} for {short_owner} (held by {short_held})");
         }
+        EventKind::ServerLog { language, message } => {
+            println!(
+                "{time_str} {}",
+                format_server_output(language, "log", message, colors, term_width)
+            );
+        }
+        EventKind::ServerMessage {
+            language,
+            level,
+            message,
+        } => {
+            println!(
+                "{time_str} {}",
+                format_server_output(language, level, message, colors, term_width)
+            );
+        }
         EventKind::McpMessage { direction, message } => {
             let arrow_colored = if direction == "in" {
                 colors.green("→")
@@ -1954,11 +3292,42 @@ fn extract_mcp_summary(message: &serde_json::Value, colors: &ColorConfig) -> Str
     )
 }
 
+/// Resolve the effective color choice, honouring the deprecated `--nocolor`.
+const fn color_choice(color: ColorChoice, nocolor: bool) -> ColorChoice {
+    if nocolor { ColorChoice::Never } else { color }
+}
+
 /// Print an event in human-readable format (used by `run_status`)
-fn print_event(event: &SessionEvent) {
-    let colors = ColorConfig::new(false);
+fn print_event(event: &SessionEvent, colors: &ColorConfig) {
     let term_width = cli::terminal_width();
-    print_event_annotated(event, &colors, term_width);
+    print_event_annotated(event, colors, term_width);
+}
+
+/// How often the symbol index is written to disk while serving.
+const SYMBOL_INDEX_FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Background task that periodically writes the symbol index to disk.
+async fn symbol_index_flush_task(index: Arc<std::sync::Mutex<SymbolIndex>>) {
+    loop {
+        tokio::time::sleep(SYMBOL_INDEX_FLUSH_INTERVAL).await;
+        flush_symbol_index(index.clone()).await;
+    }
+}
+
+/// Writes pending symbol index changes to disk off the async runtime.
+async fn flush_symbol_index(index: Arc<std::sync::Mutex<SymbolIndex>>) {
+    let result = tokio::task::spawn_blocking(move || {
+        index
+            .lock()
+            .map_err(|_| anyhow::anyhow!("mutex poisoned"))?
+            .flush()
+    })
+    .await;
+    match result {
+        Ok(Err(e)) => warn!("Failed to write symbol index: {e}"),
+        Err(e) => warn!("Symbol index flush task failed: {e}"),
+        Ok(Ok(())) => {}
+    }
 }
 
 /// Background task that periodically closes idle documents.
@@ -1967,8 +3336,15 @@ async fn document_cleanup_task(
     doc_manager: Arc<Mutex<DocumentManager>>,
     idle_timeout_secs: u64,
 ) {
-    // Check every 60 seconds or half the timeout, whichever is smaller
-    let check_interval = Duration::from_secs(idle_timeout_secs.min(60));
+    let server_idle_timeout = Duration::from_secs(client_manager.config().server_idle_timeout);
+    // Check every 60 seconds or the shortest timeout, whichever is smaller
+    let check_interval = Duration::from_secs(
+        [idle_timeout_secs, server_idle_timeout.as_secs(), 60]
+            .into_iter()
+            .filter(|&secs| secs > 0)
+            .min()
+            .unwrap_or(60),
+    );
 
     loop {
         tokio::time::sleep(check_interval).await;
@@ -1991,8 +3367,9 @@ async fn document_cleanup_task(
 
                 if let Ok(Some(params)) = close_params {
                     // Only try to close if the client is active
+                    let key = client_manager.client_key_for_file(&lang, &path).await;
                     let active_clients = client_manager.active_clients().await;
-                    if let Some(client_mutex) = active_clients.get(&lang) {
+                    if let Some(client_mutex) = active_clients.get(&key) {
                         let client = client_mutex.lock().await;
                         if let Err(e) = client.did_close(params).await {
                             warn!("Failed to close document {}: {}", path.display(), e);
@@ -2014,15 +3391,22 @@ async fn document_cleanup_task(
         for lang in active_langs {
             let has_docs = {
                 let doc_manager = doc_manager.lock().await;
-                doc_manager.has_open_documents(&lang)
+                match catenary_mcp::lsp::split_client_key(&lang) {
+                    (language, Some(root)) => doc_manager.has_open_documents_under(language, root),
+                    (language, None) => doc_manager.has_open_documents(language),
+                }
             };
 
-            if !has_docs {
-                // No open documents for this language? Shut it down.
-                // Note: This might be aggressive if the user just closed the last file
-                // and intends to open another one soon.
-                // But since we check on `idle_timeout` interval (e.g. 60s), it's probably fine.
-                // Ideally we'd track "server idle time" separately, but this is a good start.
+            if has_docs {
+                client_manager.mark_documents_open(&lang).await;
+                continue;
+            }
+
+            // Keep the server around for a while so reopening a file soon
+            // after closing the last one skips its cold start.
+            if !server_idle_timeout.is_zero()
+                && client_manager.idle_duration(&lang).await >= server_idle_timeout
+            {
                 client_manager.shutdown_client(&lang).await;
             }
         }
@@ -2034,6 +3418,143 @@ mod tests {
     use super::*;
     use anyhow::Context;
 
+    #[test]
+    fn test_connect_with_retry_retries_refused_connections() {
+        let mut attempts = 0;
+        let result = connect_with_retry(|| {
+            attempts += 1;
+            if attempts < 3 {
+                Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused))
+            } else {
+                Ok(attempts)
+            }
+        });
+        assert_eq!(result, Some(3));
+
+        let mut attempts = 0;
+        let result: Option<()> = connect_with_retry(|| {
+            attempts += 1;
+            Err(std::io::Error::from(std::io::ErrorKind::NotFound))
+        });
+        assert_eq!(result, None);
+        assert_eq!(attempts, 1, "missing endpoints are not retried");
+
+        let mut attempts = 0;
+        let result: Option<()> = connect_with_retry(|| {
+            attempts += 1;
+            Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused))
+        });
+        assert_eq!(result, None);
+        assert_eq!(attempts, NOTIFY_CONNECT_ATTEMPTS);
+    }
+
+    fn make_session_info(id: &str) -> session::SessionInfo {
+        session::SessionInfo {
+            id: id.to_string(),
+            pid: 1,
+            workspace: "/tmp".to_string(),
+            started_at: Utc::now(),
+            client_name: None,
+            client_version: None,
+            name: None,
+            block_on_errors: false,
+        }
+    }
+
+    #[test]
+    fn test_resolve_session_shortcut_keywords() -> Result<()> {
+        // Newest first, as returned by list_sessions()
+        let sessions = vec![
+            make_session_info("c"),
+            make_session_info("b"),
+            make_session_info("a"),
+        ];
+
+        let latest = resolve_session_shortcut("latest", &sessions).context("not a shortcut")??;
+        assert_eq!(latest.id, "c");
+        let last = resolve_session_shortcut("last", &sessions).context("not a shortcut")??;
+        assert_eq!(last.id, "c");
+        let first = resolve_session_shortcut("first", &sessions).context("not a shortcut")??;
+        assert_eq!(first.id, "a");
+
+        assert!(resolve_session_shortcut("abc", &sessions).is_none());
+        assert!(resolve_session_shortcut("1", &sessions).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_session_shortcut_empty_id() -> Result<()> {
+        let one = vec![make_session_info("only")];
+        let session = resolve_session_shortcut("", &one).context("not a shortcut")??;
+        assert_eq!(session.id, "only");
+
+        let many = vec![make_session_info("b"), make_session_info("a")];
+        let result = resolve_session_shortcut("", &many).context("not a shortcut")?;
+        assert!(
+            result.is_err(),
+            "empty id is ambiguous with several sessions"
+        );
+
+        let result = resolve_session_shortcut("latest", &[]).context("not a shortcut")?;
+        assert!(result.is_err(), "no sessions to resolve");
+        Ok(())
+    }
+
+    #[test]
+    fn test_proposed_content() {
+        let current = "a b a\n";
+
+        let write = serde_json::json!({ "file_path": "/f", "content": "new\n" });
+        assert_eq!(proposed_content(&write, current).as_deref(), Some("new\n"));
+
+        let edit = serde_json::json!({ "old_string": "a", "new_string": "c" });
+        assert_eq!(proposed_content(&edit, current).as_deref(), Some("c b a\n"));
+
+        let all = serde_json::json!({ "old_string": "a", "new_string": "c", "replace_all": true });
+        assert_eq!(proposed_content(&all, current).as_deref(), Some("c b c\n"));
+
+        let multi = serde_json::json!({ "edits": [
+            { "old_string": "b", "new_string": "x" },
+            { "old_string": "x a", "new_string": "y" }
+        ] });
+        assert_eq!(proposed_content(&multi, current).as_deref(), Some("a y\n"));
+
+        let missing = serde_json::json!({ "old_string": "zzz", "new_string": "c" });
+        assert_eq!(proposed_content(&missing, current), None);
+
+        let read = serde_json::json!({ "file_path": "/f" });
+        assert_eq!(proposed_content(&read, current), None);
+    }
+
+    #[test]
+    fn test_proposed_content_keeps_crlf() {
+        let current = "one\r\ntwo\r\n";
+        let edit = serde_json::json!({ "old_string": "one\ntwo", "new_string": "1\n2" });
+        assert_eq!(
+            proposed_content(&edit, current).as_deref(),
+            Some("1\r\n2\r\n")
+        );
+    }
+
+    #[test]
+    fn test_parse_lsp_env() -> Result<()> {
+        assert_eq!(
+            parse_lsp_env("rust:RA_LOG=info")?,
+            ("rust", "RA_LOG", "info")
+        );
+        // Value may itself contain '=' and ':'
+        assert_eq!(
+            parse_lsp_env("python:PYTHONPATH=/a:/b=c")?,
+            ("python", "PYTHONPATH", "/a:/b=c")
+        );
+        assert_eq!(parse_lsp_env("go:GOFLAGS=")?, ("go", "GOFLAGS", ""));
+        assert!(parse_lsp_env("rust").is_err());
+        assert!(parse_lsp_env("rust:RA_LOG").is_err());
+        assert!(parse_lsp_env(":KEY=value").is_err());
+        assert!(parse_lsp_env("rust:=value").is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_format_diagnostics_claude() -> Result<()> {
         let lines = vec![
@@ -2097,6 +3618,91 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_format_grouped_diagnostics_headers() -> Result<()> {
+        let foo = vec![
+            "Diagnostics (1):".to_string(),
+            "  3:1 [error] foo".to_string(),
+        ];
+        let bar = vec![
+            "Diagnostics (1):".to_string(),
+            "  7:2 [warning] bar".to_string(),
+        ];
+        let empty: Vec<String> = Vec::new();
+
+        let output = format_grouped_diagnostics(
+            &[
+                ("src/foo.rs", &foo),
+                ("src/empty.rs", &empty),
+                ("src/bar.rs", &bar),
+            ],
+            HostFormat::Claude,
+            "PostToolUse",
+        );
+        let parsed: serde_json::Value = serde_json::from_str(&output)?;
+        let context = parsed["hookSpecificOutput"]["additionalContext"]
+            .as_str()
+            .context("additionalContext should be a string")?;
+        assert_eq!(
+            context,
+            "=== src/foo.rs ===\nDiagnostics (1):\n  3:1 [error] foo\n\n\
+             === src/bar.rs ===\nDiagnostics (1):\n  7:2 [warning] bar"
+        );
+
+        // A single file keeps the ungrouped layout
+        let single = format_grouped_diagnostics(
+            &[("src/foo.rs", &foo), ("src/empty.rs", &empty)],
+            HostFormat::Claude,
+            "PostToolUse",
+        );
+        assert_eq!(
+            single,
+            format_diagnostics(&foo, HostFormat::Claude, "PostToolUse")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_health_summary() {
+        let status = |state| lsp::ServerStatus {
+            language: "rust".to_string(),
+            state,
+            progress_title: None,
+            progress_message: None,
+            progress_percentage: None,
+            uptime_secs: 0,
+            restarts: 0,
+        };
+        let statuses = [
+            status(lsp::ServerState::Ready),
+            status(lsp::ServerState::Dead),
+            status(lsp::ServerState::Ready),
+            status(lsp::ServerState::Indexing),
+        ];
+        assert_eq!(health_summary(Some(&statuses)), "2✓ 1… 1✗");
+        assert_eq!(health_summary(Some(&statuses[..1])), "1✓");
+        assert_eq!(health_summary(Some(&[])), "-");
+        assert_eq!(health_summary(None), "?");
+    }
+
+    #[test]
+    fn test_format_server_output() {
+        let plain = ColorConfig::new(ColorChoice::Never);
+        assert_eq!(
+            format_server_output(
+                "rust",
+                "warning",
+                "cargo metadata failed\ndetails",
+                &plain,
+                80
+            ),
+            "rust: cargo metadata failed"
+        );
+        let truncated = format_server_output("rust", "log", &"x".repeat(200), &plain, 40);
+        assert!(truncated.chars().count() <= 40 - 10, "got: {truncated}");
+        assert!(truncated.ends_with("..."));
+    }
+
     #[test]
     fn test_format_lock_output_claude_stale_read() -> Result<()> {
         let output = format_lock_output(
@@ -2164,6 +3770,101 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_detect_host_format() {
+        let no_env = |_: &str| false;
+
+        let claude = serde_json::json!({ "hook_event_name": "PreToolUse", "tool_name": "Edit" });
+        assert_eq!(
+            detect_host_format(&claude, no_env),
+            Some(HostFormat::Claude)
+        );
+        let gemini = serde_json::json!({ "hook_event_name": "AfterTool", "tool_name": "replace" });
+        assert_eq!(
+            detect_host_format(&gemini, no_env),
+            Some(HostFormat::Gemini)
+        );
+
+        // The event name wins over a conflicting environment
+        let both = |name: &str| name == "CLAUDECODE" || name == "GEMINI_CLI";
+        assert_eq!(detect_host_format(&gemini, both), Some(HostFormat::Gemini));
+
+        // Without an event name, fall back to the tool name
+        let tool_only = serde_json::json!({ "tool_name": "write_file" });
+        assert_eq!(
+            detect_host_format(&tool_only, no_env),
+            Some(HostFormat::Gemini)
+        );
+
+        // Then the environment
+        let bare = serde_json::json!({ "cwd": "/tmp" });
+        let claude_env = |name: &str| name == "CLAUDECODE";
+        assert_eq!(
+            detect_host_format(&bare, claude_env),
+            Some(HostFormat::Claude)
+        );
+        let gemini_env = |name: &str| name == "GEMINI_PROJECT_DIR" || name == "CLAUDE_PROJECT_DIR";
+        assert_eq!(
+            detect_host_format(&bare, gemini_env),
+            Some(HostFormat::Gemini)
+        );
+
+        // Ambiguous or missing signals are not guessed
+        assert_eq!(detect_host_format(&bare, both), None);
+        assert_eq!(detect_host_format(&bare, no_env), None);
+        let shared_event = serde_json::json!({ "hook_event_name": "SessionStart" });
+        assert_eq!(detect_host_format(&shared_event, no_env), None);
+    }
+
+    #[test]
+    fn test_resolve_host_format_explicit() {
+        let hook = serde_json::json!({ "hook_event_name": "BeforeTool" });
+        assert_eq!(
+            resolve_host_format(FormatArg::Claude, &hook),
+            Some(HostFormat::Claude)
+        );
+        assert_eq!(
+            resolve_host_format(FormatArg::Auto, &hook),
+            Some(HostFormat::Gemini)
+        );
+    }
+
+    #[test]
+    fn test_lock_mode_for_tool() {
+        use catenary_mcp::lock::LockMode;
+
+        assert_eq!(lock_mode_for_tool(Some("Read")), LockMode::Shared);
+        assert_eq!(lock_mode_for_tool(Some("read_file")), LockMode::Shared);
+        assert_eq!(lock_mode_for_tool(Some("Edit")), LockMode::Exclusive);
+        assert_eq!(lock_mode_for_tool(None), LockMode::Exclusive);
+    }
+
+    #[test]
+    fn test_format_lock_line() {
+        use catenary_mcp::lock::{LockMode, LockState};
+
+        let mut lock = LockState {
+            owner: "sess-a".to_string(),
+            mode: LockMode::Exclusive,
+            readers: Vec::new(),
+            file_path: "/work/src/lib.rs".to_string(),
+            acquired_at: 1_000,
+            grace_until: Some(1_030),
+            last_activity: 1_000,
+        };
+        assert_eq!(
+            format_lock_line(&lock, 1_010),
+            "/work/src/lib.rs \u{2014} sess-a \u{2014} acquired 10s ago \u{2014} expires in 20s"
+        );
+        assert!(format_lock_line(&lock, 1_040).ends_with("\u{2014} expired"));
+
+        lock.mode = LockMode::Shared;
+        lock.readers.push("sess-b".to_string());
+        assert!(
+            format_lock_line(&lock, 1_010).contains("\u{2014} sess-a, sess-b (shared) \u{2014}")
+        );
+    }
+
     #[test]
     fn test_format_lock_output_no_output() {
         let output = format_lock_output(HostFormat::Claude, None, None);
@@ -2172,4 +3873,77 @@ mod tests {
         let output = format_lock_output(HostFormat::Gemini, None, None);
         assert!(output.is_empty());
     }
+
+    #[test]
+    fn test_format_server_env() {
+        assert_eq!(format_server_env(&std::collections::HashMap::new()), None);
+        let env = std::collections::HashMap::from([
+            ("RA_LOG".to_string(), "info".to_string()),
+            ("CARGO_HOME".to_string(), "/opt/cargo".to_string()),
+        ]);
+        assert_eq!(
+            format_server_env(&env).as_deref(),
+            Some("env: CARGO_HOME=*** RA_LOG=***")
+        );
+    }
+
+    #[test]
+    fn test_format_capabilities() -> anyhow::Result<()> {
+        let result: lsp_types::InitializeResult = serde_json::from_value(serde_json::json!({
+            "serverInfo": { "name": "demo-ls", "version": "1.2.0" },
+            "capabilities": {
+                "positionEncoding": "utf-8",
+                "hoverProvider": true,
+                "renameProvider": { "prepareProvider": true },
+                "codeActionProvider": {
+                    "codeActionKinds": ["quickfix", "refactor.extract"],
+                    "resolveProvider": true
+                },
+                "executeCommandProvider": { "commands": ["demo.restart"] },
+                "textDocumentSync": { "openClose": true, "change": 2 }
+            }
+        }))?;
+        let output = format_capabilities("demo", &result);
+
+        assert!(
+            output.contains("Server:            demo-ls 1.2.0\n"),
+            "{output}"
+        );
+        assert!(output.contains("Position encoding: utf-8\n"), "{output}");
+        assert!(
+            output.contains("Text sync:         incremental (open/close: yes, save: no)\n"),
+            "{output}"
+        );
+        assert!(
+            output.contains("Tools:             hover code_actions rename\n"),
+            "{output}"
+        );
+        assert!(
+            output.contains("Rename:            yes (prepare)\n"),
+            "{output}"
+        );
+        assert!(
+            output.contains("Code actions:      yes (quickfix, refactor.extract; resolve)\n"),
+            "{output}"
+        );
+        assert!(
+            output.contains("Commands:          demo.restart\n"),
+            "{output}"
+        );
+        assert!(output.contains("Diagnostics:       push\n"), "{output}");
+        assert!(output.contains("Workspace folders: no\n"), "{output}");
+
+        // A bare server falls back to the spec defaults
+        let result: lsp_types::InitializeResult =
+            serde_json::from_value(serde_json::json!({ "capabilities": {} }))?;
+        let output = format_capabilities("demo", &result);
+        assert!(output.contains("Server:            demo\n"), "{output}");
+        assert!(
+            output.contains("Position encoding: utf-16 (default)\n"),
+            "{output}"
+        );
+        assert!(output.contains("Rename:            no\n"), "{output}");
+        assert!(output.contains("Tools:             none\n"), "{output}");
+        Ok(())
+    }
 }
@@ -144,24 +144,63 @@ impl<H: ToolHandler> McpServer<H> {
     }
 
     /// Dispatches a single message line, writing any response to `writer`.
+    ///
+    /// A line holding a JSON array is treated as a JSON-RPC batch: each
+    /// element is handled in order and the responses are written back as a
+    /// single array. Batches made up only of notifications get no reply,
+    /// and an empty batch gets a single `Invalid Request` error.
     fn dispatch_message(&mut self, line: &str, writer: &mut impl Write) -> Result<()> {
-        match self.handle_message(line) {
-            Ok(Some(response)) => {
-                self.write_response(&response, writer)?;
+        if line.starts_with('[')
+            && let Ok(batch) = serde_json::from_str::<Vec<serde_json::Value>>(line)
+        {
+            if batch.is_empty() {
+                warn!("Rejecting empty JSON-RPC batch");
+                return self.write_response(&Response::invalid_request("Empty batch"), writer);
             }
-            Ok(None) => {
-                // Notification, no response needed
+            let responses = self.handle_batch(&batch);
+            if !responses.is_empty() {
+                self.write_batch(&responses, writer)?;
             }
+            return Ok(());
+        }
+
+        if let Some(response) = self.respond(line) {
+            self.write_response(&response, writer)?;
+        }
+        Ok(())
+    }
+
+    /// Handles each message of a batch, collecting the responses.
+    ///
+    /// Elements that are not objects get an `Invalid Request` error.
+    fn handle_batch(&mut self, batch: &[serde_json::Value]) -> Vec<Response> {
+        batch
+            .iter()
+            .filter_map(|message| {
+                if message.is_object() {
+                    self.respond(&message.to_string())
+                } else {
+                    warn!("Rejecting non-object batch element: {message}");
+                    Some(Response::invalid_request("Batch element is not an object"))
+                }
+            })
+            .collect()
+    }
+
+    /// Handles one message, turning handler errors into error responses.
+    ///
+    /// Returns `None` for notifications.
+    fn respond(&mut self, line: &str) -> Option<Response> {
+        match self.handle_message(line) {
+            Ok(response) => response,
             Err(e) => {
                 error!("Error handling message: {}", e);
                 // Try to send error response if we can parse the id
-                if let Ok(req) = serde_json::from_str::<Request>(line) {
-                    let response = Response::error(req.id, INTERNAL_ERROR, e.to_string());
-                    self.write_response(&response, writer)?;
-                }
+                serde_json::from_str::<Request>(line)
+                    .ok()
+                    .map(|req| Response::error(req.id, INTERNAL_ERROR, e.to_string()))
             }
         }
-        Ok(())
     }
 
     /// Serializes, broadcasts, and writes a response.
@@ -170,16 +209,35 @@ impl<H: ToolHandler> McpServer<H> {
             serde_json::to_string(response).context("Failed to serialize response")?;
         trace!("Sending: {}", response_json);
 
+        self.broadcast_response(response);
+
+        writeln!(writer, "{response_json}")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Serializes, broadcasts, and writes a batch of responses as one array.
+    fn write_batch(&self, responses: &[Response], writer: &mut impl Write) -> Result<()> {
+        let batch_json =
+            serde_json::to_string(responses).context("Failed to serialize batch response")?;
+        trace!("Sending batch: {}", batch_json);
+
+        for response in responses {
+            self.broadcast_response(response);
+        }
+
+        writeln!(writer, "{batch_json}")?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    fn broadcast_response(&self, response: &Response) {
         if let Ok(json) = serde_json::to_value(response) {
             self.broadcaster.send(EventKind::McpMessage {
                 direction: "out".to_string(),
                 message: json,
             });
         }
-
-        writeln!(writer, "{response_json}")?;
-        writer.flush()?;
-        Ok(())
     }
 
     fn handle_message(&mut self, line: &str) -> Result<Option<Response>> {
@@ -420,7 +478,7 @@ impl<H: ToolHandler> McpServer<H> {
             if is_response {
                 let response: Response =
                     serde_json::from_value(json).context("Failed to parse roots/list response")?;
-                if response.id == request_id {
+                if response.id.as_ref() == Some(&request_id) {
                     let result = self.handle_roots_response(response);
                     // Replay buffered requests against the updated roots
                     for msg in &buffered {
@@ -487,6 +545,7 @@ impl<H: ToolHandler> McpServer<H> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mcp::INVALID_REQUEST;
 
     struct TestHandler;
 
@@ -648,6 +707,81 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_dispatch_batch_returns_array() -> Result<()> {
+        let mut server = McpServer::new(TestHandler, EventBroadcaster::noop()?);
+        let batch = serde_json::json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "ping"},
+            {"jsonrpc": "2.0", "method": "notifications/cancelled"},
+            {"jsonrpc": "2.0", "id": "b", "method": "unknown/method"}
+        ]);
+
+        let mut out = Vec::new();
+        server.dispatch_message(&batch.to_string(), &mut out)?;
+
+        let output = String::from_utf8(out)?;
+        assert_eq!(
+            output.lines().count(),
+            1,
+            "batch reply is one line: {output}"
+        );
+        let responses: Vec<Response> = serde_json::from_str(output.trim())?;
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].id, Some(RequestId::Number(1)));
+        assert!(responses[0].result.is_some());
+        assert_eq!(responses[1].id, Some(RequestId::String("b".to_string())));
+        assert_eq!(
+            responses[1].error.as_ref().context("missing error")?.code,
+            METHOD_NOT_FOUND
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_notification_only_batch_writes_nothing() -> Result<()> {
+        let mut server = McpServer::new(TestHandler, EventBroadcaster::noop()?);
+        let batch = serde_json::json!([
+            {"jsonrpc": "2.0", "method": "notifications/initialized"}
+        ]);
+
+        let mut out = Vec::new();
+        server.dispatch_message(&batch.to_string(), &mut out)?;
+        assert!(out.is_empty());
+        assert!(server.initialized);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dispatch_invalid_batches() -> Result<()> {
+        let mut server = McpServer::new(TestHandler, EventBroadcaster::noop()?);
+
+        // An empty batch gets a single error object, not an array
+        let mut out = Vec::new();
+        server.dispatch_message("[]", &mut out)?;
+        let response: Response = serde_json::from_slice(&out)?;
+        assert_eq!(response.id, None);
+        assert_eq!(
+            response.error.context("missing error")?.code,
+            INVALID_REQUEST
+        );
+
+        // Non-object elements get an error each, alongside valid replies
+        let batch = serde_json::json!([1, {"jsonrpc": "2.0", "id": 7, "method": "ping"}, "x"]);
+        let mut out = Vec::new();
+        server.dispatch_message(&batch.to_string(), &mut out)?;
+        let output = String::from_utf8(out)?;
+        assert!(output.contains(r#""id":null"#), "got: {output}");
+        let responses: Vec<Response> = serde_json::from_str(output.trim())?;
+        let codes: Vec<Option<i64>> = responses
+            .iter()
+            .map(|r| r.error.as_ref().map(|e| e.code))
+            .collect();
+        assert_eq!(codes, [Some(INVALID_REQUEST), None, Some(INVALID_REQUEST)]);
+        assert_eq!(responses[1].id, Some(RequestId::Number(7)));
+        Ok(())
+    }
+
     fn initialize_server(server: &mut McpServer<TestHandler>, with_roots: bool) -> Result<()> {
         let caps = if with_roots {
             serde_json::json!({"roots": {"listChanged": true}})
@@ -55,8 +55,8 @@ pub enum RequestId {
 pub struct Response {
     /// The JSON-RPC version.
     pub jsonrpc: String,
-    /// The request ID.
-    pub id: RequestId,
+    /// The request ID; `null` when it could not be read from the request.
+    pub id: Option<RequestId>,
     /// The result of the request, if successful.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub result: Option<Value>,
@@ -74,7 +74,7 @@ impl Response {
     pub fn success(id: RequestId, result: impl Serialize) -> Result<Self, serde_json::Error> {
         Ok(Self {
             jsonrpc: "2.0".to_string(),
-            id,
+            id: Some(id),
             result: Some(serde_json::to_value(result)?),
             error: None,
         })
@@ -84,7 +84,7 @@ impl Response {
     pub fn error(id: RequestId, code: i64, message: impl Into<String>) -> Self {
         Self {
             jsonrpc: "2.0".to_string(),
-            id,
+            id: Some(id),
             result: None,
             error: Some(ResponseError {
                 code,
@@ -93,6 +93,21 @@ impl Response {
             }),
         }
     }
+
+    /// Creates an `Invalid Request` error response with a `null` id, for
+    /// messages that are not request objects.
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: None,
+            result: None,
+            error: Some(ResponseError {
+                code: INVALID_REQUEST,
+                message: message.into(),
+                data: None,
+            }),
+        }
+    }
 }
 
 /// JSON-RPC response error.
@@ -107,6 +122,8 @@ pub struct ResponseError {
     pub data: Option<Value>,
 }
 
+/// The message is not a valid request object.
+pub const INVALID_REQUEST: i64 = -32600;
 /// The method was not found.
 pub const METHOD_NOT_FOUND: i64 = -32601;
 /// An internal error occurred.
@@ -243,6 +260,10 @@ pub struct CallToolResult {
     /// Whether the tool call resulted in an error.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_error: Option<bool>,
+    /// The same result as a JSON object, for clients that read MCP
+    /// structured content instead of parsing the text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
 }
 
 /// Content returned from a tool call.
@@ -279,9 +300,30 @@ impl CallToolResult {
         Self {
             content: vec![ToolContent::Text { text: text.into() }],
             is_error: None,
+            structured_content: None,
         }
     }
 
+    /// Creates a successful tool result carrying `value` as structured
+    /// content, with its serialized form as the text content.
+    #[must_use]
+    pub fn json(value: Value) -> Self {
+        Self {
+            content: vec![ToolContent::Text {
+                text: value.to_string(),
+            }],
+            is_error: None,
+            structured_content: Some(value),
+        }
+    }
+
+    /// Attaches `value` as structured content, keeping the text content.
+    #[must_use]
+    pub fn with_json(mut self, value: Value) -> Self {
+        self.structured_content = Some(value);
+        self
+    }
+
     /// Creates an error tool result with an error message.
     pub fn error(message: impl Into<String>) -> Self {
         Self {
@@ -289,6 +331,7 @@ impl CallToolResult {
                 text: message.into(),
             }],
             is_error: Some(true),
+            structured_content: None,
         }
     }
 }
@@ -368,6 +411,23 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_call_tool_result_json() -> Result<()> {
+        let result = CallToolResult::json(serde_json::json!({ "count": 2 }));
+        let json = serde_json::to_value(&result)?;
+        assert_eq!(json["structuredContent"]["count"], 2);
+        assert_eq!(json["content"][0]["text"], r#"{"count":2}"#);
+
+        let result = CallToolResult::text("2 items").with_json(serde_json::json!({ "count": 2 }));
+        let json = serde_json::to_value(&result)?;
+        assert_eq!(json["content"][0]["text"], "2 items");
+        assert_eq!(json["structuredContent"]["count"], 2);
+
+        let json = serde_json::to_string(&CallToolResult::text("plain"))?;
+        assert!(!json.contains("structuredContent"));
+        Ok(())
+    }
+
     #[test]
     fn test_call_tool_result_error() -> Result<()> {
         let result = CallToolResult::error("Something went wrong");
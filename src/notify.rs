@@ -8,6 +8,13 @@
 //! and sends the changed file path. The server notifies the LSP, waits for
 //! fresh diagnostics, and returns them so they appear in the model's context.
 //!
+//! When `block_on_errors` is enabled, the `PreToolUse` hook (`catenary
+//! acquire`) sends a `check` request with the proposed file content. The
+//! server previews it against the LSP and returns a denial reason if the
+//! edit would introduce new errors.
+//!
+//! `catenary list` sends a `status` request to summarize server health.
+//!
 //! The server also accepts `sync_roots` requests from `catenary sync-roots`,
 //! which synchronize workspace roots discovered from `/add-dir` and removal
 //! commands in the Claude Code transcript. The older `add_roots` request type
@@ -16,17 +23,19 @@
 //! Transport: Unix domain sockets on Unix, named pipes on Windows.
 
 use anyhow::{Result, anyhow};
-use lsp_types::Diagnostic;
+use lsp_types::{Diagnostic, DiagnosticSeverity, FileChangeType, FileEvent, Uri};
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
 #[cfg(unix)]
 use tokio::net::UnixListener;
 use tokio::sync::{Mutex, RwLock};
 use tracing::{debug, info, warn};
 
-use crate::bridge::{DocumentManager, DocumentNotification, PathValidator};
+use crate::bridge::{DocumentManager, PathValidator, check_file_size};
 use crate::lsp::{ClientManager, DIAGNOSTICS_TIMEOUT, DiagnosticsWaitResult, LspClient};
 use crate::session::{EventBroadcaster, EventKind};
 
@@ -39,6 +48,13 @@ enum NotifyRequest {
         /// Absolute path to the changed file.
         file: String,
     },
+    /// A request to preview proposed content before an edit is applied.
+    Check {
+        /// Absolute path to the file about to be written.
+        check: String,
+        /// Full file content after the proposed edit.
+        content: String,
+    },
     /// A request to synchronize workspace roots (full replacement).
     SyncRoots {
         /// Complete set of workspace roots — server diffs against current state.
@@ -49,6 +65,12 @@ enum NotifyRequest {
         /// Absolute paths of directories to add as roots.
         add_roots: Vec<String>,
     },
+    /// A request for the status of every active LSP server (`catenary list`).
+    Status {
+        /// Always `true`; present only to select this variant.
+        #[allow(dead_code, reason = "Field exists only to tag the request")]
+        status: bool,
+    },
 }
 
 /// Listens on an IPC endpoint (Unix socket or named pipe) for file-change
@@ -58,12 +80,15 @@ pub struct NotifyServer {
     doc_manager: Arc<Mutex<DocumentManager>>,
     path_validator: Arc<RwLock<PathValidator>>,
     broadcaster: EventBroadcaster,
+    /// Diagnostics returned by the last notification for each open file,
+    /// used by `notify_new_diagnostics_only` to suppress already-known issues.
+    reported: Mutex<HashMap<PathBuf, Vec<Diagnostic>>>,
 }
 
 impl NotifyServer {
     /// Creates a new `NotifyServer`.
     #[must_use]
-    pub const fn new(
+    pub fn new(
         client_manager: Arc<ClientManager>,
         doc_manager: Arc<Mutex<DocumentManager>>,
         path_validator: Arc<RwLock<PathValidator>>,
@@ -74,6 +99,7 @@ impl NotifyServer {
             doc_manager,
             path_validator,
             broadcaster,
+            reported: Mutex::new(HashMap::new()),
         }
     }
 
@@ -193,6 +219,10 @@ impl NotifyServer {
                 debug!("Notify: processing file {file}");
                 self.process_file(&file).await
             }
+            NotifyRequest::Check { check, content } => {
+                debug!("Notify: checking proposed edit to {check}");
+                self.process_check(&check, &content).await
+            }
             NotifyRequest::SyncRoots { sync_roots } => {
                 debug!("Notify: syncing {} root(s)", sync_roots.len());
                 self.process_sync_roots(&sync_roots).await
@@ -201,6 +231,10 @@ impl NotifyServer {
                 debug!("Notify: adding {} root(s)", add_roots.len());
                 self.process_add_roots(&add_roots).await
             }
+            NotifyRequest::Status { .. } => {
+                debug!("Notify: reporting server status");
+                self.process_status().await
+            }
         };
 
         writer.write_all(response.as_bytes()).await?;
@@ -210,6 +244,12 @@ impl NotifyServer {
         Ok(())
     }
 
+    /// Returns the status of every active LSP server as a single JSON line.
+    async fn process_status(&self) -> String {
+        let statuses = self.client_manager.all_server_status().await;
+        serde_json::to_string(&statuses).unwrap_or_else(|e| format!("Notify error: {e}"))
+    }
+
     /// Processes a file change notification and returns diagnostics text.
     async fn process_file(&self, file_path: &str) -> String {
         match self.process_file_inner(file_path).await {
@@ -238,11 +278,14 @@ impl NotifyServer {
             doc_manager.language_id_for_path(&canonical).to_string()
         };
 
-        let client_mutex: Arc<Mutex<LspClient>> =
-            match self.client_manager.get_client(&lang_id).await {
-                Ok(c) => c,
-                Err(_) => return Ok(String::new()), // No LSP server for this language
-            };
+        let client_mutex: Arc<Mutex<LspClient>> = match self
+            .client_manager
+            .get_client_for_file(&lang_id, &canonical)
+            .await
+        {
+            Ok(c) => c,
+            Err(_) => return Ok(String::new()), // No LSP server for this language
+        };
 
         let mut doc_manager = self.doc_manager.lock().await;
         let client = client_mutex.lock().await;
@@ -256,20 +299,34 @@ impl NotifyServer {
 
         let uri = doc_manager.uri_for_path(&canonical)?;
 
+        let dependents = if self.client_manager.config().notify_dependents {
+            let others = doc_manager.open_uris_for(&lang);
+            Some(DependentsSnapshot::take(&client, &uri, others).await)
+        } else {
+            None
+        };
+
+        // Snapshot generation *before* sending the change
+        let snapshot = client.diagnostics_generation(&uri).await;
+        // A restarted server needs the tracked documents reopened first
+        let reopened = doc_manager.reopen_on(&client).await? > 0;
         // ensure_open detects disk changes and returns didOpen/didChange
-        if let Some(notification) = doc_manager.ensure_open(&canonical).await? {
-            // Snapshot generation *before* sending the change
-            let snapshot = client.diagnostics_generation(&uri).await;
+        let notification = doc_manager.ensure_open(&canonical).await?;
 
-            match notification {
-                DocumentNotification::Open(params) => {
-                    client.did_open(params).await?;
-                }
-                DocumentNotification::Change(params) => {
-                    client.did_change(params).await?;
-                }
+        if reopened || notification.is_some() {
+            if let Some(notification) = notification {
+                notification.send(&client).await?;
             }
 
+            // The file changed behind the server's back; tell its file
+            // watching so project-wide state catches up
+            client
+                .did_change_watched_files(vec![FileEvent::new(
+                    uri.clone(),
+                    FileChangeType::CHANGED,
+                )])
+                .await?;
+
             // Trigger flycheck on servers that only run diagnostics on save
             client.did_save(uri.clone()).await?;
 
@@ -291,8 +348,26 @@ impl NotifyServer {
         }
 
         let diagnostics = client.get_diagnostics(&uri).await;
+        let broken = match dependents {
+            Some(snapshot) => snapshot.new_errors(&client).await,
+            None => Vec::new(),
+        };
         drop(client);
 
+        let total = diagnostics.len();
+        let diagnostics = if self.client_manager.config().notify_new_diagnostics_only {
+            let open = self.doc_manager.lock().await.open_paths();
+            let mut reported = self.reported.lock().await;
+            // Only open documents are remembered, so closing a file (or the
+            // idle cleanup closing it) forgets what was reported for it.
+            reported.retain(|path, _| open.binary_search(path).is_ok());
+            let previous = reported.insert(canonical, diagnostics.clone());
+            drop(reported);
+            new_diagnostics(previous.as_deref().unwrap_or_default(), &diagnostics)
+        } else {
+            diagnostics
+        };
+
         let count = diagnostics.len();
         let compact = if diagnostics.is_empty() {
             String::new()
@@ -313,13 +388,142 @@ impl NotifyServer {
             preview,
         });
 
-        if diagnostics.is_empty() {
-            Ok(String::new())
+        let own = if diagnostics.is_empty() {
+            String::new()
+        } else if count < total {
+            format!("New diagnostics ({count} of {total}):\n{compact}")
         } else {
-            Ok(format!("Diagnostics ({count}):\n{compact}"))
+            format!("Diagnostics ({count}):\n{compact}")
+        };
+        if broken.is_empty() {
+            return Ok(own);
+        }
+
+        let roots = self.client_manager.roots().await;
+        let section = format_broken_dependents(&broken, &roots);
+        if own.is_empty() {
+            Ok(section)
+        } else {
+            Ok(format!("{own}\n{section}"))
+        }
+    }
+
+    /// Previews a proposed edit and returns a denial reason, or an empty
+    /// string if the edit may proceed.
+    ///
+    /// Errors are never fatal here: if the preview cannot be run, the edit
+    /// is allowed.
+    async fn process_check(&self, file_path: &str, content: &str) -> String {
+        let config = self.client_manager.config();
+        if !config.block_on_errors {
+            return String::new();
+        }
+        let threshold = config.block_error_threshold.max(1);
+
+        match self.process_check_inner(file_path, content).await {
+            Ok(new_errors) if new_errors.len() >= threshold => {
+                let count = new_errors.len();
+                self.broadcaster.send(EventKind::Diagnostics {
+                    file: file_path.to_string(),
+                    count,
+                    preview: format!("edit blocked: {count} new error(s)"),
+                });
+                format!(
+                    "Edit blocked: it would introduce {count} new error(s):\n{}",
+                    format_diagnostics_compact(&new_errors)
+                )
+            }
+            Ok(_) => String::new(),
+            Err(e) => {
+                debug!("Notify: edit check skipped for {file_path}: {e}");
+                String::new()
+            }
         }
     }
 
+    /// Inner implementation for `process_check`.
+    ///
+    /// Syncs the on-disk content first so the baseline is current, then sends
+    /// the proposed content as a `didChange`, collects its errors, and
+    /// reverts the document to the on-disk content.
+    #[allow(
+        clippy::significant_drop_tightening,
+        reason = "Locks held across async operations by design"
+    )]
+    async fn process_check_inner(&self, file_path: &str, content: &str) -> Result<Vec<Diagnostic>> {
+        let path = resolve_path(file_path)?;
+        let canonical = self.path_validator.read().await.validate_write(&path)?;
+
+        let lang_id = {
+            let doc_manager = self.doc_manager.lock().await;
+            doc_manager.language_id_for_path(&canonical).to_string()
+        };
+        let client_mutex = self
+            .client_manager
+            .get_client_for_file(&lang_id, &canonical)
+            .await?;
+
+        let mut doc_manager = self.doc_manager.lock().await;
+        let client = client_mutex.lock().await;
+        if !client.is_alive() {
+            return Err(anyhow!("[{lang_id}] server is not running"));
+        }
+
+        let uri = doc_manager.uri_for_path(&canonical)?;
+
+        let snapshot = client.diagnostics_generation(&uri).await;
+        let reopened = doc_manager.reopen_on(&client).await? > 0;
+        let notification = doc_manager.ensure_open(&canonical).await?;
+        if reopened || notification.is_some() {
+            if let Some(notification) = notification {
+                notification.send(&client).await?;
+            }
+            if client
+                .wait_for_diagnostics_update(&uri, snapshot, DIAGNOSTICS_TIMEOUT)
+                .await
+                == DiagnosticsWaitResult::ServerDied
+            {
+                return Err(anyhow!("[{lang_id}] server died"));
+            }
+        }
+        let baseline = client.get_diagnostics(&uri).await;
+
+        let metadata = tokio::fs::metadata(&canonical).await?;
+        check_file_size(&canonical, metadata.len(), doc_manager.max_file_bytes())?;
+        check_file_size(
+            &canonical,
+            content.len() as u64,
+            doc_manager.max_file_bytes(),
+        )?;
+        let original = tokio::fs::read_to_string(&canonical).await?;
+        let mtime = metadata.modified()?;
+
+        let snapshot = client.diagnostics_generation(&uri).await;
+        doc_manager
+            .notify_external_write(&canonical, content, mtime)?
+            .send(&client)
+            .await?;
+        let result = client
+            .wait_for_diagnostics_update(&uri, snapshot, DIAGNOSTICS_TIMEOUT)
+            .await;
+        let proposed = client.get_diagnostics(&uri).await;
+
+        // Always restore the on-disk content, even if the server died
+        doc_manager
+            .notify_external_write(&canonical, &original, mtime)?
+            .send(&client)
+            .await?;
+
+        if result == DiagnosticsWaitResult::ServerDied {
+            return Err(anyhow!("[{lang_id}] server died"));
+        }
+
+        let is_error = |d: &Diagnostic| d.severity == Some(DiagnosticSeverity::ERROR);
+        let baseline: Vec<Diagnostic> = baseline.into_iter().filter(is_error).collect();
+        let proposed: Vec<Diagnostic> = proposed.into_iter().filter(is_error).collect();
+        Ok(new_diagnostics(&baseline, &proposed))
+    }
+
     /// Synchronizes the full workspace root set.
     ///
     /// Canonicalizes incoming paths, diffs against the current root set, and
@@ -378,7 +582,11 @@ impl NotifyServer {
             .update_roots(new_roots.clone());
 
         // Sync LSP clients (handles both additions and removals)
-        if let Err(e) = self.client_manager.sync_roots(new_roots).await {
+        if let Err(e) = self
+            .client_manager
+            .sync_roots(new_roots, &self.doc_manager)
+            .await
+        {
             warn!("Failed to sync roots with LSP clients: {e}");
         }
 
@@ -477,6 +685,129 @@ fn resolve_path(file: &str) -> Result<PathBuf> {
     }
 }
 
+/// Returns the diagnostics in `current` that were not in `previous`.
+///
+/// Matching ignores ranges so that issues which merely moved because of
+/// inserted or deleted lines are not reported again. Duplicates are
+/// counted, so a second copy of an existing error is still new.
+fn new_diagnostics(previous: &[Diagnostic], current: &[Diagnostic]) -> Vec<Diagnostic> {
+    let same = |a: &Diagnostic, b: &Diagnostic| {
+        a.severity == b.severity
+            && a.code == b.code
+            && a.source == b.source
+            && a.message == b.message
+    };
+    let mut unmatched: Vec<&Diagnostic> = previous.iter().collect();
+    current
+        .iter()
+        .filter(|d| {
+            let matched = unmatched.iter().position(|p| same(p, d));
+            if let Some(i) = matched {
+                unmatched.swap_remove(i);
+            }
+            matched.is_none()
+        })
+        .cloned()
+        .collect()
+}
+
+/// How long to wait for re-checked dependents to republish diagnostics.
+const DEPENDENTS_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Diagnostics of the edited file's dependents from before the edit, for
+/// `notify_dependents`.
+struct DependentsSnapshot {
+    edited: Uri,
+    /// Other open documents on the same server and their diagnostics
+    /// generation before the edit.
+    open: Vec<(Uri, u64)>,
+    before: HashMap<Uri, Vec<Diagnostic>>,
+}
+
+impl DependentsSnapshot {
+    async fn take(client: &LspClient, edited: &Uri, others: Vec<Uri>) -> Self {
+        let mut open = Vec::new();
+        for uri in others.into_iter().filter(|uri| uri != edited) {
+            let generation = client.diagnostics_generation(&uri).await;
+            open.push((uri, generation));
+        }
+        Self {
+            edited: edited.clone(),
+            open,
+            before: client.all_diagnostics().await.into_iter().collect(),
+        }
+    }
+
+    /// Re-checks the open dependents the server did not republish on its
+    /// own, then returns every other file that gained errors, sorted by URI.
+    async fn new_errors(self, client: &LspClient) -> Vec<(Uri, Vec<Diagnostic>)> {
+        let mut pending = Vec::new();
+        for (uri, generation) in self.open {
+            if client.diagnostics_generation(&uri).await == generation {
+                if let Err(e) = client.did_save(uri.clone()).await {
+                    debug!("Notify: could not re-check {}: {e}", uri.as_str());
+                    continue;
+                }
+                pending.push((uri, generation));
+            }
+        }
+
+        let deadline = tokio::time::Instant::now() + DEPENDENTS_TIMEOUT;
+        while !pending.is_empty() && tokio::time::Instant::now() < deadline && client.is_alive() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let mut still_pending = Vec::new();
+            for (uri, generation) in pending {
+                if client.diagnostics_generation(&uri).await == generation {
+                    still_pending.push((uri, generation));
+                }
+            }
+            pending = still_pending;
+        }
+
+        let errors_only = |diagnostics: &[Diagnostic]| -> Vec<Diagnostic> {
+            diagnostics
+                .iter()
+                .filter(|d| d.severity == Some(DiagnosticSeverity::ERROR))
+                .cloned()
+                .collect()
+        };
+        client
+            .all_diagnostics()
+            .await
+            .into_iter()
+            .filter(|(uri, _)| *uri != self.edited)
+            .filter_map(|(uri, diagnostics)| {
+                let previous = self
+                    .before
+                    .get(&uri)
+                    .map_or_else(Vec::new, |d| errors_only(d));
+                let broken = new_diagnostics(&previous, &errors_only(&diagnostics));
+                (!broken.is_empty()).then_some((uri, broken))
+            })
+            .collect()
+    }
+}
+
+/// Formats new errors in dependent files, one root-relative path heading
+/// per file.
+fn format_broken_dependents(broken: &[(Uri, Vec<Diagnostic>)], roots: &[PathBuf]) -> String {
+    let count: usize = broken
+        .iter()
+        .map(|(_, diagnostics)| diagnostics.len())
+        .sum();
+    let mut lines = vec![format!("New errors in dependents ({count}):")];
+    for (uri, diagnostics) in broken {
+        let path = Path::new(uri.path().as_str());
+        let shown = roots
+            .iter()
+            .find_map(|root| path.strip_prefix(root).ok())
+            .unwrap_or(path);
+        lines.push(format!("{}:", shown.display()));
+        lines.push(format_diagnostics_compact(diagnostics));
+    }
+    lines.join("\n")
+}
+
 /// Formats diagnostics with line/column and severity.
 pub(crate) fn format_diagnostics_compact(diagnostics: &[Diagnostic]) -> String {
     diagnostics
@@ -513,3 +844,71 @@ pub(crate) fn format_diagnostics_compact(diagnostics: &[Diagnostic]) -> String {
         .collect::<Vec<_>>()
         .join("\n")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{DiagnosticSeverity, Position, Range};
+
+    fn diag(line: u32, message: &str) -> Diagnostic {
+        Diagnostic {
+            range: Range::new(Position::new(line, 0), Position::new(line, 1)),
+            severity: Some(DiagnosticSeverity::ERROR),
+            message: message.to_string(),
+            ..Diagnostic::default()
+        }
+    }
+
+    #[test]
+    fn test_new_diagnostics_ignores_moved_issues() {
+        let previous = vec![diag(3, "unused variable"), diag(7, "type mismatch")];
+        let current = vec![
+            diag(5, "unused variable"),
+            diag(9, "type mismatch"),
+            diag(1, "missing semicolon"),
+        ];
+
+        let new = new_diagnostics(&previous, &current);
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].message, "missing semicolon");
+    }
+
+    #[test]
+    fn test_new_diagnostics_counts_duplicates() {
+        let previous = vec![diag(3, "unused variable")];
+        let current = vec![diag(3, "unused variable"), diag(8, "unused variable")];
+
+        let new = new_diagnostics(&previous, &current);
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].range.start.line, 8);
+
+        assert_eq!(new_diagnostics(&[], &current).len(), 2);
+        assert!(new_diagnostics(&current, &previous).is_empty());
+    }
+
+    #[test]
+    fn test_format_broken_dependents() -> anyhow::Result<()> {
+        let roots = vec![PathBuf::from("/work/app")];
+        let broken = vec![
+            (
+                "file:///work/app/src/caller.rs".parse::<Uri>()?,
+                vec![diag(4, "expected 2 arguments, found 1")],
+            ),
+            (
+                "file:///opt/other/lib.rs".parse::<Uri>()?,
+                vec![diag(0, "unresolved import"), diag(9, "type mismatch")],
+            ),
+        ];
+
+        assert_eq!(
+            format_broken_dependents(&broken, &roots),
+            "New errors in dependents (3):\n\
+             src/caller.rs:\n\
+             \x20 5:1 [error] : expected 2 arguments, found 1\n\
+             /opt/other/lib.rs:\n\
+             \x20 1:1 [error] : unresolved import\n\
+             \x20 10:1 [error] : type mismatch"
+        );
+        Ok(())
+    }
+}
@@ -12,7 +12,7 @@ use serde::{Deserialize, Serialize};
 use std::fs::{self, File, OpenOptions};
 use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 use tracing::warn;
 
 /// Session metadata stored in info.json.
@@ -32,6 +32,13 @@ pub struct SessionInfo {
     /// Version of the connected MCP client.
     #[serde(default)]
     pub client_version: Option<String>,
+    /// Human-readable session label (from `catenary serve --name`).
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Whether the session previews edits for `block_on_errors`, so hooks
+    /// can skip the round-trip when it is off.
+    #[serde(default)]
+    pub block_on_errors: bool,
 }
 
 /// An event that can be broadcast to listeners.
@@ -96,10 +103,34 @@ pub enum EventKind {
         /// Short preview of the first diagnostic.
         preview: String,
     },
+    /// A line a language server wrote to stderr.
+    ServerLog {
+        /// The language ID of the server.
+        language: String,
+        /// The logged line.
+        message: String,
+    },
+    /// A `window/logMessage` or `window/showMessage` from a language server.
+    ServerMessage {
+        /// The language ID of the server.
+        language: String,
+        /// Message severity: "error", "warning", "info" or "log".
+        level: String,
+        /// The message text.
+        message: String,
+    },
     /// Session started.
     Started,
     /// Session ending.
     Shutdown,
+    /// The MCP client closed the connection (stdin reached EOF), ending
+    /// the session.
+    ClientDisconnected,
+    /// A termination signal ended the session.
+    SignalShutdown {
+        /// Name of the signal received (e.g. "SIGINT", "SIGTERM").
+        signal: String,
+    },
     /// Raw MCP message (incoming or outgoing).
     McpMessage {
         /// Direction of the message ("in" or "out").
@@ -126,6 +157,10 @@ pub enum EventKind {
         /// The host tool that triggered the lock (e.g. "Read", "Edit").
         #[serde(default, skip_serializing_if = "Option::is_none")]
         tool: Option<String>,
+        /// Whether the lock was removed by `catenary lock release` on the
+        /// owner's behalf rather than released by the owner.
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        forced: bool,
     },
     /// File lock acquisition denied (timeout).
     LockDenied {
@@ -138,8 +173,265 @@ pub enum EventKind {
     },
 }
 
+/// Field types used in the event schema.
+const STRING: &str = "string";
+const INTEGER: &str = "integer";
+const BOOLEAN: &str = "boolean";
+const OPTIONAL_STRING: &str = "string?";
+const OPTIONAL_INTEGER: &str = "integer?";
+const OPTIONAL_BOOLEAN: &str = "boolean?";
+const ANY: &str = "any";
+
+/// An event field as `(name, type, description)`; optional types end in `?`.
+type EventField = (&'static str, &'static str, &'static str);
+
+/// Every [`EventKind`] variant as `(tag, description, fields)`.
+const EVENT_VARIANTS: &[(&str, &str, &[EventField])] = &[
+    (
+        "server_state",
+        "Server state changed.",
+        &[
+            ("language", STRING, "The language ID of the server."),
+            ("state", STRING, "The new state."),
+        ],
+    ),
+    (
+        "progress",
+        "Progress update from LSP server.",
+        &[
+            ("language", STRING, "The language ID of the server."),
+            ("title", STRING, "The title of the progress operation."),
+            ("message", OPTIONAL_STRING, "The optional progress message."),
+            (
+                "percentage",
+                OPTIONAL_INTEGER,
+                "The optional progress percentage (0-100).",
+            ),
+        ],
+    ),
+    (
+        "progress_end",
+        "Progress completed.",
+        &[("language", STRING, "The language ID of the server.")],
+    ),
+    (
+        "tool_call",
+        "Tool was called.",
+        &[
+            ("tool", STRING, "The name of the tool called."),
+            ("file", OPTIONAL_STRING, "The optional file path involved."),
+        ],
+    ),
+    (
+        "tool_result",
+        "Tool call completed.",
+        &[
+            ("tool", STRING, "The name of the tool called."),
+            ("success", BOOLEAN, "Whether the tool call was successful."),
+            (
+                "duration_ms",
+                INTEGER,
+                "How long the tool call took in milliseconds.",
+            ),
+        ],
+    ),
+    (
+        "diagnostics",
+        "Diagnostics returned from notify hook.",
+        &[
+            ("file", STRING, "File that was checked."),
+            ("count", INTEGER, "Number of diagnostics found."),
+            ("preview", STRING, "Short preview of the first diagnostic."),
+        ],
+    ),
+    (
+        "server_log",
+        "A line a language server wrote to stderr.",
+        &[
+            ("language", STRING, "The language ID of the server."),
+            ("message", STRING, "The logged line."),
+        ],
+    ),
+    (
+        "server_message",
+        "A `window/logMessage` or `window/showMessage` from a language server.",
+        &[
+            ("language", STRING, "The language ID of the server."),
+            (
+                "level",
+                STRING,
+                "Message severity: \"error\", \"warning\", \"info\" or \"log\".",
+            ),
+            ("message", STRING, "The message text."),
+        ],
+    ),
+    ("started", "Session started.", &[]),
+    ("shutdown", "Session ending.", &[]),
+    (
+        "client_disconnected",
+        "The MCP client closed the connection (stdin reached EOF), ending the session.",
+        &[],
+    ),
+    (
+        "signal_shutdown",
+        "A termination signal ended the session.",
+        &[(
+            "signal",
+            STRING,
+            "Name of the signal received (e.g. \"SIGINT\", \"SIGTERM\").",
+        )],
+    ),
+    (
+        "mcp_message",
+        "Raw MCP message (incoming or outgoing).",
+        &[
+            (
+                "direction",
+                STRING,
+                "Direction of the message (\"in\" or \"out\").",
+            ),
+            ("message", ANY, "The raw JSON-RPC message."),
+        ],
+    ),
+    (
+        "lock_acquired",
+        "File lock acquired by an agent.",
+        &[
+            ("file", STRING, "The locked file path."),
+            ("owner", STRING, "The lock owner identity."),
+            (
+                "tool",
+                OPTIONAL_STRING,
+                "The host tool that triggered the lock (e.g. \"Read\", \"Edit\"). Omitted when unknown.",
+            ),
+        ],
+    ),
+    (
+        "lock_released",
+        "File lock released by an agent.",
+        &[
+            ("file", STRING, "The released file path."),
+            ("owner", STRING, "The lock owner identity."),
+            (
+                "tool",
+                OPTIONAL_STRING,
+                "The host tool that triggered the lock (e.g. \"Read\", \"Edit\"). Omitted when unknown.",
+            ),
+            (
+                "forced",
+                OPTIONAL_BOOLEAN,
+                "Whether the lock was force-released by `catenary lock release` instead of by its owner. Omitted when false.",
+            ),
+        ],
+    ),
+    (
+        "lock_denied",
+        "File lock acquisition denied (timeout).",
+        &[
+            ("file", STRING, "The file that could not be locked."),
+            ("owner", STRING, "The agent that was denied."),
+            ("held_by", STRING, "The agent currently holding the lock."),
+        ],
+    ),
+];
+
+/// Returns the JSON schema of a [`SessionEvent`] as written to the event log
+/// and printed by `catenary monitor --raw`.
+///
+/// Each event is an object with a `timestamp` and a `type` tag naming its
+/// [`EventKind`]; the variant's fields sit alongside them. Optional fields
+/// may be `null` or, where noted, omitted.
+#[must_use]
+pub fn events_schema() -> serde_json::Value {
+    let variants: Vec<serde_json::Value> = EVENT_VARIANTS
+        .iter()
+        .map(|(tag, description, fields)| {
+            let mut properties = serde_json::Map::new();
+            properties.insert(
+                "timestamp".to_string(),
+                serde_json::json!({
+                    "type": "string",
+                    "format": "date-time",
+                    "description": "When the event occurred (RFC 3339)."
+                }),
+            );
+            properties.insert("type".to_string(), serde_json::json!({ "const": tag }));
+            let mut required = vec!["timestamp", "type"];
+            for (name, ty, field_description) in *fields {
+                let mut property = match *ty {
+                    ANY => serde_json::json!({}),
+                    OPTIONAL_STRING => serde_json::json!({ "type": ["string", "null"] }),
+                    OPTIONAL_INTEGER => {
+                        serde_json::json!({ "type": ["integer", "null"], "minimum": 0 })
+                    }
+                    OPTIONAL_BOOLEAN => serde_json::json!({ "type": "boolean" }),
+                    INTEGER => serde_json::json!({ "type": "integer", "minimum": 0 }),
+                    other => serde_json::json!({ "type": other }),
+                };
+                property["description"] = serde_json::Value::from(*field_description);
+                properties.insert((*name).to_string(), property);
+                if !ty.ends_with('?') {
+                    required.push(*name);
+                }
+            }
+            serde_json::json!({
+                "title": tag,
+                "description": description,
+                "type": "object",
+                "properties": properties,
+                "required": required,
+                "additionalProperties": false
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "Catenary session event",
+        "description": "One line of a Catenary session event log (`catenary monitor --raw`).",
+        "oneOf": variants
+    })
+}
+
+/// Environment variable overriding the sessions directory.
+pub const SESSIONS_DIR_ENV: &str = "CATENARY_SESSIONS_DIR";
+
+/// Process-wide override set by `--sessions-dir`.
+static SESSIONS_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Overrides the sessions directory for this process (`--sessions-dir`).
+///
+/// # Errors
+///
+/// Returns an error if the override was already set.
+pub fn set_sessions_dir(dir: PathBuf) -> Result<()> {
+    SESSIONS_DIR_OVERRIDE
+        .set(dir)
+        .map_err(|dir| anyhow::anyhow!("sessions dir already set (to {})", dir.display()))
+}
+
+/// Returns the sessions directory set by `--sessions-dir` or
+/// `$CATENARY_SESSIONS_DIR`, in that order, if either is set.
+pub fn sessions_dir_override() -> Option<PathBuf> {
+    SESSIONS_DIR_OVERRIDE.get().cloned().or_else(|| {
+        std::env::var_os(SESSIONS_DIR_ENV)
+            .filter(|d| !d.is_empty())
+            .map(PathBuf::from)
+    })
+}
+
 /// Returns the base directory for session data.
+///
+/// Resolved in order: `--sessions-dir`, `$CATENARY_SESSIONS_DIR`,
+/// `<config-home>/sessions` when `--config-home` is set, then
+/// `$XDG_STATE_HOME/catenary/sessions/`.
 pub fn sessions_dir() -> PathBuf {
+    if let Some(dir) = sessions_dir_override() {
+        return dir;
+    }
+    if let Some(home) = crate::config::config_home() {
+        return home.join("sessions");
+    }
     let state_dir = dirs::state_dir()
         .or_else(dirs::data_local_dir)
         .unwrap_or_else(|| PathBuf::from("/tmp"));
@@ -168,6 +460,17 @@ impl Session {
     /// - The session directory cannot be created.
     /// - Metadata or event files cannot be created.
     pub fn create(workspace: &str) -> Result<Self> {
+        Self::create_named(workspace, None)
+    }
+
+    /// Create a new session with an optional human-readable label.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The session directory cannot be created.
+    /// - Metadata or event files cannot be created.
+    pub fn create_named(workspace: &str, name: Option<&str>) -> Result<Self> {
         let id = Self::generate_id();
 
         let sessions_base = sessions_dir();
@@ -189,6 +492,10 @@ impl Session {
             client_name: None,
 
             client_version: None,
+
+            name: name.map(str::to_string),
+
+            block_on_errors: false,
         };
 
         // Write info.json
@@ -279,8 +586,18 @@ impl Session {
 
         self.info.client_version = Some(version.to_string());
 
-        // Rewrite info.json
+        self.write_info();
+    }
 
+    /// Records whether `block_on_errors` is enabled for this session.
+    pub fn set_block_on_errors(&mut self, enabled: bool) {
+        self.info.block_on_errors = enabled;
+
+        self.write_info();
+    }
+
+    /// Rewrites info.json from the current metadata.
+    fn write_info(&self) {
         let info_path = self.dir.join("info.json");
 
         if let Ok(file) = File::create(&info_path) {
@@ -310,6 +627,7 @@ impl Session {
     pub fn broadcaster(&self) -> EventBroadcaster {
         EventBroadcaster {
             events_file: self.events_file.clone(),
+            dir: Some(self.dir.clone()),
         }
     }
 }
@@ -339,6 +657,8 @@ impl Drop for Session {
 #[derive(Clone)]
 pub struct EventBroadcaster {
     events_file: Arc<Mutex<File>>,
+    /// The session directory, where server logs are written.
+    dir: Option<PathBuf>,
 }
 
 impl EventBroadcaster {
@@ -377,8 +697,75 @@ impl EventBroadcaster {
             })?;
         Ok(Self {
             events_file: Arc::new(Mutex::new(file)),
+            dir: None,
+        })
+    }
+
+    /// Path of the stderr log for the server under client key `key`, or
+    /// `None` for a no-op broadcaster.
+    #[must_use]
+    pub fn server_log_path(&self, key: &str) -> Option<PathBuf> {
+        self.dir.as_ref().map(|dir| dir.join(server_log_name(key)))
+    }
+}
+
+/// File name of a server's stderr log: the client key with anything but
+/// letters, digits, `-`, `_` and `.` replaced by `_`, plus `.log`.
+fn server_log_name(key: &str) -> String {
+    let name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
         })
+        .collect();
+    format!("{name}.log")
+}
+
+/// Returns the stderr log of the `lang` server in session `id`.
+///
+/// # Errors
+///
+/// Returns an error if the session has no log for `lang`; the message lists
+/// the logs it does have.
+pub fn server_log(id: &str, lang: &str) -> Result<PathBuf> {
+    let session_dir = sessions_dir().join(id);
+    let path = session_dir.join(server_log_name(lang));
+    if path.exists() {
+        return Ok(path);
     }
+
+    let mut available: Vec<String> = fs::read_dir(&session_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| {
+                    let name = entry.ok()?.file_name().to_string_lossy().to_string();
+                    name.strip_suffix(".log").map(str::to_string)
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    available.sort();
+    if available.is_empty() {
+        anyhow::bail!("No server logs in session {id}");
+    }
+    anyhow::bail!(
+        "No log for '{lang}' in session {id} (available: {})",
+        available.join(", ")
+    )
+}
+
+/// Follows the stderr log of the `lang` server in session `id` from its
+/// start.
+///
+/// # Errors
+///
+/// Returns an error if the log does not exist or cannot be opened.
+pub fn tail_server_log(id: &str, lang: &str) -> Result<TailReader> {
+    TailReader::new(server_log(id, lang)?, false)
 }
 
 /// List all active sessions.
@@ -415,11 +802,51 @@ pub fn list_sessions() -> Result<Vec<SessionInfo>> {
     }
 
     // Sort by start time (most recent first)
-    sessions.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.started_at));
 
     Ok(sessions)
 }
 
+/// Removes the directories of sessions whose process is no longer running
+/// and returns them, most recent first.
+///
+/// With `dry_run`, nothing is removed. Directories without a readable
+/// `info.json` are left alone: they may belong to a session that is still
+/// starting.
+///
+/// # Errors
+///
+/// Returns an error if the sessions directory cannot be read or a dead
+/// session's directory cannot be removed.
+pub fn prune_sessions(dry_run: bool) -> Result<Vec<SessionInfo>> {
+    let sessions_base = sessions_dir();
+
+    if !sessions_base.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut dead = Vec::new();
+
+    for entry in fs::read_dir(&sessions_base)? {
+        let entry = entry?;
+        let info_path = entry.path().join("info.json");
+
+        if let Ok(file) = File::open(&info_path)
+            && let Ok(info) = serde_json::from_reader::<_, SessionInfo>(file)
+            && !is_process_alive(info.pid)
+        {
+            if !dry_run {
+                fs::remove_dir_all(entry.path())?;
+            }
+            dead.push(info);
+        }
+    }
+
+    dead.sort_by_key(|s| std::cmp::Reverse(s.started_at));
+
+    Ok(dead)
+}
+
 /// Get a specific session by ID.
 ///
 /// # Errors
@@ -467,12 +894,81 @@ pub fn monitor_events(id: &str) -> Result<impl Iterator<Item = SessionEvent>> {
     }))
 }
 
+/// How much event history `catenary monitor --since` replays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventWindow {
+    /// Events newer than this age.
+    Age(chrono::Duration),
+    /// The most recent N events.
+    Count(usize),
+}
+
+impl EventWindow {
+    /// Keeps the events that fall inside the window, in their original order.
+    #[must_use]
+    pub fn select(self, mut events: Vec<SessionEvent>) -> Vec<SessionEvent> {
+        match self {
+            Self::Age(age) => {
+                let cutoff = Utc::now() - age;
+                events.retain(|e| e.timestamp >= cutoff);
+            }
+            Self::Count(count) => {
+                events.drain(..events.len().saturating_sub(count));
+            }
+        }
+        events
+    }
+}
+
+impl std::str::FromStr for EventWindow {
+    type Err = String;
+
+    /// Parses a bare count (`50`) or an age with an `s`, `m`, `h` or `d`
+    /// suffix (`10m`, `1h`).
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(count) = s.parse::<usize>() {
+            return Ok(Self::Count(count));
+        }
+
+        let invalid =
+            || format!("invalid value '{s}': expected a count (50) or a duration (10m, 1h)");
+        let unit_at = s.len().checked_sub(1).ok_or_else(invalid)?;
+        let (amount, unit) = s.split_at(unit_at);
+        let amount: i64 = amount.parse().map_err(|_| invalid())?;
+        let age = match unit {
+            "s" => chrono::Duration::try_seconds(amount),
+            "m" => chrono::Duration::try_minutes(amount),
+            "h" => chrono::Duration::try_hours(amount),
+            "d" => chrono::Duration::try_days(amount),
+            _ => None,
+        }
+        .filter(|age| *age >= chrono::Duration::zero())
+        .ok_or_else(invalid)?;
+        Ok(Self::Age(age))
+    }
+}
+
 /// Tail events from a session (follows new events).
 ///
 /// # Errors
 ///
 /// Returns an error if the session does not exist or the events file cannot be opened.
 pub fn tail_events(id: &str) -> Result<TailReader> {
+    open_tail(id, false)
+}
+
+/// Like [`tail_events`], but skips the events already written, for callers
+/// that replay history themselves via [`monitor_events`].
+///
+/// # Errors
+///
+/// Returns an error if the session does not exist or the events file cannot be opened.
+pub fn tail_new_events(id: &str) -> Result<TailReader> {
+    open_tail(id, true)
+}
+
+fn open_tail(id: &str, from_end: bool) -> Result<TailReader> {
     let sessions_base = sessions_dir();
     let events_path = sessions_base.join(id).join("events.jsonl");
 
@@ -480,7 +976,7 @@ pub fn tail_events(id: &str) -> Result<TailReader> {
         anyhow::bail!("Session not found: {id}");
     }
 
-    TailReader::new(events_path)
+    TailReader::new(events_path, from_end)
 }
 
 /// Reader that tails a file for new content.
@@ -491,9 +987,14 @@ pub struct TailReader {
 }
 
 impl TailReader {
-    fn new(path: PathBuf) -> Result<Self> {
-        let file = File::open(&path)?;
+    fn new(path: PathBuf, from_end: bool) -> Result<Self> {
+        use std::io::{Seek, SeekFrom};
+
+        let mut file = File::open(&path)?;
         let metadata = file.metadata()?;
+        if from_end {
+            file.seek(SeekFrom::Start(metadata.len()))?;
+        }
         let reader = BufReader::new(file);
 
         Ok(Self {
@@ -509,6 +1010,24 @@ impl TailReader {
     ///
     /// Returns an error if reading from the file fails.
     pub fn next_event(&mut self) -> Result<Option<SessionEvent>> {
+        while let Some(line) = self.next_line()? {
+            let line = line.trim();
+            if !line.is_empty()
+                && let Ok(event) = serde_json::from_str::<SessionEvent>(line)
+            {
+                return Ok(Some(event));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read the next line without its line ending, blocking if necessary.
+    /// Returns `None` once the file is deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading from the file fails.
+    pub fn next_line(&mut self) -> Result<Option<String>> {
         use std::io::Seek;
 
         loop {
@@ -516,38 +1035,35 @@ impl TailReader {
             let bytes_read = self.reader.read_line(&mut line)?;
 
             if bytes_read > 0 {
-                let line = line.trim();
-                if !line.is_empty()
-                    && let Ok(event) = serde_json::from_str::<SessionEvent>(line)
-                {
-                    return Ok(Some(event));
-                }
-            } else {
-                // Check if file was truncated or if we should wait
-                if let Ok(metadata) = fs::metadata(&self.path) {
-                    if metadata.len() < self.last_size {
-                        // File was truncated, reopen
-                        let file = File::open(&self.path)?;
-                        self.reader = BufReader::new(file);
-                        self.last_size = 0;
-                        continue;
-                    }
+                let len = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(len);
+                return Ok(Some(line));
+            }
 
-                    if metadata.len() > self.last_size {
-                        // File grew — reset BufReader's EOF state so
-                        // it reads new data on the next iteration.
-                        self.reader.stream_position()?;
-                    }
+            // Check if file was truncated or if we should wait
+            if let Ok(metadata) = fs::metadata(&self.path) {
+                if metadata.len() < self.last_size {
+                    // File was truncated, reopen
+                    let file = File::open(&self.path)?;
+                    self.reader = BufReader::new(file);
+                    self.last_size = 0;
+                    continue;
+                }
 
-                    self.last_size = metadata.len();
-                } else {
-                    // File was deleted, session ended
-                    return Ok(None);
+                if metadata.len() > self.last_size {
+                    // File grew — reset BufReader's EOF state so
+                    // it reads new data on the next iteration.
+                    self.reader.stream_position()?;
                 }
 
-                // Wait a bit before checking again
-                std::thread::sleep(std::time::Duration::from_millis(100));
+                self.last_size = metadata.len();
+            } else {
+                // File was deleted, session ended
+                return Ok(None);
             }
+
+            // Wait a bit before checking again
+            std::thread::sleep(std::time::Duration::from_millis(100));
         }
     }
 }
@@ -625,6 +1141,134 @@ mod tests {
     use super::*;
     use anyhow::{Context, Result};
 
+    /// One event of every kind. The match makes adding a variant without
+    /// updating `EVENT_VARIANTS` (and this list) a compile error.
+    fn sample_events() -> Vec<EventKind> {
+        let samples = vec![
+            EventKind::ServerState {
+                language: "rust".into(),
+                state: "Ready".into(),
+            },
+            EventKind::Progress {
+                language: "rust".into(),
+                title: "Indexing".into(),
+                message: None,
+                percentage: Some(40),
+            },
+            EventKind::ProgressEnd {
+                language: "rust".into(),
+            },
+            EventKind::ToolCall {
+                tool: "hover".into(),
+                file: Some("/src/main.rs".into()),
+            },
+            EventKind::ToolResult {
+                tool: "hover".into(),
+                success: true,
+                duration_ms: 12,
+            },
+            EventKind::Diagnostics {
+                file: "/src/main.rs".into(),
+                count: 1,
+                preview: "unused".into(),
+            },
+            EventKind::ServerLog {
+                language: "rust".into(),
+                message: "starting".into(),
+            },
+            EventKind::ServerMessage {
+                language: "rust".into(),
+                level: "info".into(),
+                message: "hello".into(),
+            },
+            EventKind::Started,
+            EventKind::Shutdown,
+            EventKind::ClientDisconnected,
+            EventKind::SignalShutdown {
+                signal: "SIGTERM".into(),
+            },
+            EventKind::McpMessage {
+                direction: "in".into(),
+                message: serde_json::json!({ "jsonrpc": "2.0" }),
+            },
+            EventKind::LockAcquired {
+                file: "/src/main.rs".into(),
+                owner: "agent".into(),
+                tool: None,
+            },
+            EventKind::LockReleased {
+                file: "/src/main.rs".into(),
+                owner: "agent".into(),
+                tool: Some("Edit".into()),
+                forced: true,
+            },
+            EventKind::LockDenied {
+                file: "/src/main.rs".into(),
+                owner: "agent".into(),
+                held_by: "other".into(),
+            },
+        ];
+        for kind in &samples {
+            match kind {
+                EventKind::ServerState { .. }
+                | EventKind::Progress { .. }
+                | EventKind::ProgressEnd { .. }
+                | EventKind::ToolCall { .. }
+                | EventKind::ToolResult { .. }
+                | EventKind::Diagnostics { .. }
+                | EventKind::ServerLog { .. }
+                | EventKind::ServerMessage { .. }
+                | EventKind::Started
+                | EventKind::Shutdown
+                | EventKind::ClientDisconnected
+                | EventKind::SignalShutdown { .. }
+                | EventKind::McpMessage { .. }
+                | EventKind::LockAcquired { .. }
+                | EventKind::LockReleased { .. }
+                | EventKind::LockDenied { .. } => {}
+            }
+        }
+        samples
+    }
+
+    #[test]
+    fn test_events_schema_matches_serialized_events() -> Result<()> {
+        let schema = events_schema();
+        let variants = schema["oneOf"].as_array().context("oneOf")?;
+        let samples = sample_events();
+        assert_eq!(
+            variants.len(),
+            samples.len(),
+            "one schema entry per variant"
+        );
+
+        for kind in samples {
+            let event = serde_json::to_value(SessionEvent {
+                timestamp: Utc::now(),
+                kind,
+            })?;
+            let object = event.as_object().context("event should be an object")?;
+            let tag = object["type"].as_str().context("type tag")?;
+            let variant = variants
+                .iter()
+                .find(|v| v["properties"]["type"]["const"] == tag)
+                .with_context(|| format!("no schema entry for {tag}"))?;
+
+            let properties = variant["properties"].as_object().context("properties")?;
+            for key in object.keys() {
+                assert!(
+                    properties.contains_key(key),
+                    "{tag}: unexpected field {key}"
+                );
+            }
+            for required in variant["required"].as_array().context("required")? {
+                let required = required.as_str().context("required name")?;
+                assert!(object.contains_key(required), "{tag}: missing {required}");
+            }
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_session_create_and_list() -> Result<()> {
         let session = Session::create("/tmp/test-workspace")?;
@@ -648,6 +1292,16 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_session_create_named() -> Result<()> {
+        let session = Session::create_named("/tmp/test-named", Some("frontend-refactor"))?;
+        let id = session.info.id.clone();
+
+        let found = get_session(&id)?.context("missing session")?;
+        assert_eq!(found.name.as_deref(), Some("frontend-refactor"));
+        Ok(())
+    }
+
     #[test]
     fn test_event_broadcast() -> Result<()> {
         let session = Session::create("/tmp/test-events")?;
@@ -755,4 +1409,67 @@ mod tests {
         drop(session);
         Ok(())
     }
+
+    #[test]
+    fn test_event_window_parse() {
+        assert_eq!("50".parse(), Ok(EventWindow::Count(50)));
+        assert_eq!(
+            "10m".parse(),
+            Ok(EventWindow::Age(chrono::Duration::minutes(10)))
+        );
+        assert_eq!(
+            "1h".parse(),
+            Ok(EventWindow::Age(chrono::Duration::hours(1)))
+        );
+        assert_eq!(
+            "2d".parse(),
+            Ok(EventWindow::Age(chrono::Duration::days(2)))
+        );
+        assert!("".parse::<EventWindow>().is_err());
+        assert!("10x".parse::<EventWindow>().is_err());
+        assert!("m".parse::<EventWindow>().is_err());
+        assert!("-5m".parse::<EventWindow>().is_err());
+    }
+
+    #[test]
+    fn test_event_window_select() {
+        let now = Utc::now();
+        let event = |minutes_ago: i64, tool: &str| SessionEvent {
+            timestamp: now - chrono::Duration::minutes(minutes_ago),
+            kind: EventKind::ToolCall {
+                tool: tool.into(),
+                file: None,
+            },
+        };
+        let events = || vec![event(30, "old"), event(5, "recent"), event(1, "newest")];
+        let tools = |events: Vec<SessionEvent>| -> Vec<String> {
+            events
+                .into_iter()
+                .filter_map(|e| match e.kind {
+                    EventKind::ToolCall { tool, .. } => Some(tool),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            tools(EventWindow::Count(2).select(events())),
+            ["recent", "newest"]
+        );
+        assert_eq!(tools(EventWindow::Count(10).select(events())).len(), 3);
+        assert!(EventWindow::Count(0).select(events()).is_empty());
+        assert_eq!(
+            tools(EventWindow::Age(chrono::Duration::minutes(10)).select(events())),
+            ["recent", "newest"]
+        );
+    }
+
+    #[test]
+    fn test_server_log_name() {
+        assert_eq!(server_log_name("rust"), "rust.log");
+        assert_eq!(
+            server_log_name("rust@/home/me/my proj"),
+            "rust__home_me_my_proj.log"
+        );
+    }
 }
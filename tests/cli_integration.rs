@@ -15,15 +15,20 @@ use serde_json::{Value, json};
 /// Helper to spawn the bridge and capture stderr to find session ID
 struct ServerProcess {
     child: std::process::Child,
-    stdin: std::process::ChildStdin,
+    stdin: Option<std::process::ChildStdin>,
     stdout: BufReader<std::process::ChildStdout>,
     stderr: BufReader<std::process::ChildStderr>,
 }
 
 impl ServerProcess {
     fn spawn() -> Result<Self> {
+        Self::spawn_with_args(&[])
+    }
+
+    fn spawn_with_args(extra_args: &[&str]) -> Result<Self> {
         let mut cmd = Command::new(env!("CARGO_BIN_EXE_catenary"));
         cmd.arg("serve");
+        cmd.args(extra_args);
         cmd.arg("--root").arg(".");
         // Isolate from user-level config
         cmd.env("XDG_CONFIG_HOME", ".");
@@ -40,7 +45,7 @@ impl ServerProcess {
 
         Ok(Self {
             child,
-            stdin,
+            stdin: Some(stdin),
             stdout,
             stderr,
         })
@@ -67,11 +72,17 @@ impl ServerProcess {
 
     fn send(&mut self, request: &Value) -> Result<()> {
         let json = serde_json::to_string(request)?;
-        writeln!(self.stdin, "{json}").context("Failed to write to stdin")?;
-        self.stdin.flush().context("Failed to flush stdin")?;
+        let stdin = self.stdin.as_mut().context("Stdin already closed")?;
+        writeln!(stdin, "{json}").context("Failed to write to stdin")?;
+        stdin.flush().context("Failed to flush stdin")?;
         Ok(())
     }
 
+    /// Closes the server's stdin, as an MCP host does when it disconnects.
+    fn close_stdin(&mut self) {
+        self.stdin.take();
+    }
+
     fn recv(&mut self) -> Result<Value> {
         let mut line = String::new();
         self.stdout
@@ -164,6 +175,481 @@ fn test_list_shows_language_servers_line() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_list_shows_server_health() -> Result<()> {
+    let name = format!("health-{}", std::process::id());
+    let lsp = format!("rust:{}", env!("CARGO_BIN_EXE_mockls"));
+    let mut server = ServerProcess::spawn_with_args(&["--name", &name, "--lsp", &lsp])?;
+    let _session_id = server.get_session_id()?;
+
+    // The server spawns in the background; poll until it reports ready.
+    let mut row = String::new();
+    for _ in 0..50 {
+        let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+            .arg("list")
+            .output()
+            .context("Failed to run list command")?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(
+            stdout.contains("HEALTH"),
+            "List output should contain HEALTH column header, got:\n{stdout}"
+        );
+        row = stdout
+            .lines()
+            .find(|line| line.contains(&name))
+            .unwrap_or_default()
+            .to_string();
+        if row.contains("1✓") {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    Err(anyhow!("Session row never showed a ready server: {row}"))
+}
+
+#[test]
+fn test_status_resolves_session_name() -> Result<()> {
+    let name = format!("cli-test-{}", std::process::id());
+    let mut server = ServerProcess::spawn_with_args(&["--name", &name])?;
+    let session_id = server.get_session_id()?;
+
+    // Give the session time to register
+    thread::sleep(Duration::from_millis(100));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .arg("list")
+        .output()
+        .context("Failed to run list command")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("NAME"),
+        "List output should contain NAME column header"
+    );
+    assert!(
+        stdout.contains(&name),
+        "List output should contain session name '{name}', got:\n{stdout}"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .arg("status")
+        .arg(&name)
+        .output()
+        .context("Failed to run status command")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!("Session: {session_id}")),
+        "Status by name should resolve to session {session_id}, got:\n{stdout}"
+    );
+    assert!(
+        stdout.contains(&format!("Name: {name}")),
+        "Status should show the session name, got:\n{stdout}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_config_home_isolates_sessions() -> Result<()> {
+    let home = tempfile::tempdir()?;
+    let home_arg = home.path().to_str().context("path")?;
+    let name = format!("cfghome-{}", std::process::id());
+
+    let mut server = ServerProcess::spawn_with_args(&["--name", &name, "--config-home", home_arg])?;
+    let _session_id = server.get_session_id()?;
+    thread::sleep(Duration::from_millis(100));
+
+    assert!(
+        home.path().join("sessions").is_dir(),
+        "Sessions should be created under the config home"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .args(["--config-home", home_arg, "list"])
+        .output()
+        .context("Failed to run list command")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&name),
+        "List with the same config home should show the session, got:\n{stdout}"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .arg("list")
+        .output()
+        .context("Failed to run list command")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        !stdout.contains(&name),
+        "Default list should not see isolated sessions, got:\n{stdout}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_sessions_dir_flag_and_env() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let dir_arg = dir.path().to_str().context("path")?;
+    let name = format!("sessdir-{}", std::process::id());
+
+    let mut server = ServerProcess::spawn_with_args(&["--name", &name, "--sessions-dir", dir_arg])?;
+    let _session_id = server.get_session_id()?;
+    thread::sleep(Duration::from_millis(100));
+
+    // The env var reaches the same directory as the flag
+    let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .arg("list")
+        .env("CATENARY_SESSIONS_DIR", dir_arg)
+        .output()
+        .context("Failed to run list command")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&name),
+        "List via CATENARY_SESSIONS_DIR should show the session, got:\n{stdout}"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .arg("list")
+        .output()
+        .context("Failed to run list command")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        !stdout.contains(&name),
+        "Default list should not see sessions in a custom dir, got:\n{stdout}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_prune_removes_dead_sessions() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let dir_arg = dir.path().to_str().context("path")?;
+
+    // A process that has exited leaves a pid nothing is running under
+    let mut exited = Command::new("true").spawn()?;
+    exited.wait()?;
+    let write_session = |id: &str, pid: u32| -> Result<()> {
+        let session_dir = dir.path().join(id);
+        std::fs::create_dir_all(&session_dir)?;
+        std::fs::write(
+            session_dir.join("info.json"),
+            json!({
+                "id": id,
+                "pid": pid,
+                "workspace": "/tmp/prune-test",
+                "started_at": "2026-01-01T00:00:00Z"
+            })
+            .to_string(),
+        )?;
+        Ok(())
+    };
+    write_session("deadbeef", exited.id())?;
+    write_session("alive", std::process::id())?;
+
+    let prune = |extra: &[&str]| -> Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+            .args(["--sessions-dir", dir_arg, "prune"])
+            .args(extra)
+            .output()
+            .context("Failed to run prune")?;
+        assert!(output.status.success());
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+
+    let stdout = prune(&["--dry-run"])?;
+    assert!(
+        stdout.contains("deadbeef") && stdout.contains("Would remove 1 dead session"),
+        "Dry run should list the dead session, got:\n{stdout}"
+    );
+    assert!(
+        dir.path().join("deadbeef").exists(),
+        "Dry run must not remove"
+    );
+
+    let stdout = prune(&[])?;
+    assert!(
+        stdout.contains("Removed 1 dead session"),
+        "Prune should report the removal, got:\n{stdout}"
+    );
+    assert!(!dir.path().join("deadbeef").exists());
+    assert!(dir.path().join("alive").exists(), "Live sessions are kept");
+
+    let stdout = prune(&[])?;
+    assert!(stdout.contains("Removed 0 dead sessions"), "got:\n{stdout}");
+    Ok(())
+}
+
+/// Runs `catenary doctor` against a workspace with one shell file.
+fn run_doctor_with_mockls(mockls_flags: &str) -> Result<String> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("script.sh"), "echo hi\n")?;
+    let lsp = format!(
+        "shellscript:{} {mockls_flags}",
+        env!("CARGO_BIN_EXE_mockls")
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .args(["doctor", "--nocolor", "--lsp", &lsp, "--root"])
+        .arg(dir.path())
+        .env("XDG_CONFIG_HOME", dir.path())
+        .output()
+        .context("Failed to run doctor")?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[test]
+fn test_doctor_probe_reports_responsiveness() -> Result<()> {
+    let stdout = run_doctor_with_mockls("")?;
+    assert!(
+        stdout.contains("✓ ready and responsive"),
+        "Healthy server should pass the probe, got:\n{stdout}"
+    );
+
+    let stdout = run_doctor_with_mockls("--hang-on workspace/symbol")?;
+    assert!(
+        stdout.contains("⚠ initialized but not responding"),
+        "Hung server should be flagged, got:\n{stdout}"
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_doctor_starts_server_in_configured_cwd() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir()?;
+    let workspace = dir.path().canonicalize()?;
+    std::fs::write(workspace.join("script.sh"), "echo hi\n")?;
+    std::fs::create_dir(workspace.join("module"))?;
+
+    // A "server" that reports where it was started, then fails
+    let server = workspace.join("pwd-ls");
+    std::fs::write(&server, "#!/bin/sh\necho \"cwd=$(pwd)\" >&2\nexit 1\n")?;
+    std::fs::set_permissions(&server, std::fs::Permissions::from_mode(0o755))?;
+
+    let config_dir = workspace.join("catenary");
+    std::fs::create_dir(&config_dir)?;
+    std::fs::write(
+        config_dir.join("config.toml"),
+        format!(
+            "[server.shellscript]\ncommand = \"{}\"\ncwd = \"module\"\n",
+            server.display()
+        ),
+    )?;
+
+    let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .args(["doctor", "--nocolor", "--root"])
+        .arg(&workspace)
+        .env("XDG_CONFIG_HOME", &workspace)
+        .output()
+        .context("Failed to run doctor")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let expected = format!("stderr: cwd={}", workspace.join("module").display());
+    assert!(
+        stdout.contains(&expected),
+        "Server should start in the module dir, got:\n{stdout}"
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_doctor_watch_reloads_config() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("script.sh"), "echo hi\n")?;
+    let config = dir.path().join("watch.toml");
+    std::fs::write(&config, "")?;
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .args(["doctor", "--nocolor", "--watch", "1", "--config"])
+        .arg(&config)
+        .arg("--root")
+        .arg(dir.path())
+        .env("XDG_CONFIG_HOME", dir.path())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to spawn doctor")?;
+    let mut lines = BufReader::new(child.stdout.take().context("stdout")?).lines();
+    let mut wait_for = |needle: &str| -> Result<()> {
+        for line in lines.by_ref() {
+            if line?.contains(needle) {
+                return Ok(());
+            }
+        }
+        Err(anyhow!("doctor exited before printing {needle:?}"))
+    };
+
+    wait_for("No language servers configured.")?;
+    std::fs::write(
+        &config,
+        format!(
+            "[server.shellscript]\ncommand = \"{}\"\n",
+            env!("CARGO_BIN_EXE_mockls")
+        ),
+    )?;
+    wait_for("✓ ready and responsive")?;
+
+    let status = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()?;
+    assert!(status.success());
+    assert!(child.wait()?.success(), "Ctrl+C should exit cleanly");
+    Ok(())
+}
+
+#[test]
+fn test_doctor_watch_rejects_zero() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .args(["doctor", "--watch", "0"])
+        .env("XDG_CONFIG_HOME", dir.path())
+        .output()
+        .context("Failed to run doctor")?;
+    assert!(!output.status.success(), "--watch 0 should be rejected");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("--watch"), "got:\n{stderr}");
+    Ok(())
+}
+
+#[test]
+fn test_default_root_uses_project_markers() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let project = dir.path().canonicalize()?.join("project");
+    let nested = project.join("src").join("deep");
+    std::fs::create_dir_all(&nested)?;
+    std::fs::write(project.join("go.mod"), "module example\n")?;
+    let no_markers = dir.path().join("no-markers.toml");
+    std::fs::write(&no_markers, "root_markers = []\n")?;
+
+    let doctor = |extra: &[&std::ffi::OsStr]| {
+        Command::new(env!("CARGO_BIN_EXE_catenary"))
+            .args(["doctor", "--nocolor"])
+            .args(extra)
+            .current_dir(&nested)
+            .env("XDG_CONFIG_HOME", dir.path())
+            .output()
+            .context("Failed to run doctor")
+    };
+
+    let stdout = String::from_utf8_lossy(&doctor(&[])?.stdout).into_owned();
+    assert!(
+        stdout.contains(&format!("Roots:  {}\n", project.display())),
+        "Root should be the directory holding go.mod, got:\n{stdout}"
+    );
+
+    let output = doctor(&["--config".as_ref(), no_markers.as_os_str()])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!("Roots:  {}\n", nested.display())),
+        "Empty root_markers should keep the current directory, got:\n{stdout}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_doctor_shows_server_env() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("script.sh"), "echo hi\n")?;
+    let lsp = format!("shellscript:{}", env!("CARGO_BIN_EXE_mockls"));
+    let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .args(["doctor", "--nocolor", "--lsp", &lsp])
+        .args(["--lsp-env", "shellscript:MOCK_HOME=/opt/mock", "--root"])
+        .arg(dir.path())
+        .env("XDG_CONFIG_HOME", dir.path())
+        .output()
+        .context("Failed to run doctor")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("env: MOCK_HOME=***"),
+        "Configured env should be shown, got:\n{stdout}"
+    );
+    assert!(
+        !stdout.contains("/opt/mock"),
+        "Env values should be masked, got:\n{stdout}"
+    );
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn test_doctor_shows_stderr_of_failed_server() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("script.sh"), "echo hi\n")?;
+    let server = dir.path().join("broken-ls");
+    std::fs::write(
+        &server,
+        "#!/bin/sh\necho \"license key invalid\" >&2\nexit 3\n",
+    )?;
+    std::fs::set_permissions(&server, std::fs::Permissions::from_mode(0o755))?;
+
+    let lsp = format!("shellscript:{}", server.display());
+    let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .args(["doctor", "--nocolor", "--lsp", &lsp, "--root"])
+        .arg(dir.path())
+        .env("XDG_CONFIG_HOME", dir.path())
+        .output()
+        .context("Failed to run doctor")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(
+        stdout.contains("initialize failed"),
+        "Server should fail to initialize, got:\n{stdout}"
+    );
+    assert!(
+        stdout.contains("stderr: license key invalid"),
+        "Server stderr should be shown, got:\n{stdout}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_capabilities_reports_advertised_features() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let lsp = format!(
+        "shellscript:{} --prepare-rename --incremental-sync",
+        env!("CARGO_BIN_EXE_mockls")
+    );
+    let run = |extra: &[&str]| -> Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+            .args(["capabilities", "shellscript", "--lsp", &lsp, "--root"])
+            .arg(dir.path())
+            .args(extra)
+            .env("XDG_CONFIG_HOME", dir.path())
+            .output()
+            .context("Failed to run capabilities")?;
+        assert!(
+            output.status.success(),
+            "capabilities failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+
+    let stdout = run(&[])?;
+    assert!(
+        stdout.contains("Rename:") && stdout.contains("yes (prepare)"),
+        "Rename support should be shown, got:\n{stdout}"
+    );
+    assert!(
+        stdout.contains("incremental (open/close: yes, save: yes)"),
+        "Sync kind should be shown, got:\n{stdout}"
+    );
+
+    let json: serde_json::Value = serde_json::from_str(&run(&["--json"])?)?;
+    assert_eq!(json["hoverProvider"], true);
+    assert_eq!(json["textDocumentSync"]["change"], 2);
+
+    // Unknown languages are an error, not an empty report
+    let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .args(["capabilities", "cobol", "--lsp", &lsp, "--root"])
+        .arg(dir.path())
+        .env("XDG_CONFIG_HOME", dir.path())
+        .output()?;
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("No server configured for 'cobol'"));
+    Ok(())
+}
+
 #[test]
 fn test_monitor_by_row_number_starts() -> Result<()> {
     use std::sync::mpsc;
@@ -415,6 +901,128 @@ fn test_monitor_nocolor_flag() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_color_flag_controls_ansi_output() -> Result<()> {
+    let mut server = ServerProcess::spawn()?;
+    let session_id = server.get_session_id()?;
+
+    server.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 77777,
+        "method": "ping"
+    }))?;
+    let _response = server.recv()?;
+    thread::sleep(Duration::from_millis(100));
+
+    let status = |color: &str| -> Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_catenary"))
+            .args(["status", &session_id, "--color", color])
+            .output()
+            .context("Failed to run status command")?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    };
+
+    // stdout is piped here, so `auto` must behave like `never`.
+    let always = status("always")?;
+    assert!(
+        always.contains("\x1b["),
+        "--color always should emit ANSI codes even when piped, got:\n{always}"
+    );
+    for choice in ["never", "auto"] {
+        let plain = status(choice)?;
+        assert!(
+            !plain.contains("\x1b["),
+            "--color {choice} should not emit ANSI codes when piped, got:\n{plain}"
+        );
+    }
+    Ok(())
+}
+
+/// Spawns `catenary monitor` for a session and returns a channel of its lines.
+fn spawn_monitor(
+    session_id: &str,
+) -> Result<(std::process::Child, std::sync::mpsc::Receiver<String>)> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .args(["monitor", session_id, "--color", "never"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to spawn monitor")?;
+    let stdout = child
+        .stdout
+        .take()
+        .context("failed to take monitor stdout")?;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    // The banner prints before the events file is opened; wait for the
+    // replayed "Session started" event so the monitor is really tailing.
+    let _ = wait_for_line(&rx, "Session started");
+    Ok((child, rx))
+}
+
+/// Collects monitor lines until one contains `needle` or the timeout expires.
+fn wait_for_line(rx: &std::sync::mpsc::Receiver<String>, needle: &str) -> String {
+    let mut output = String::new();
+    let start = std::time::Instant::now();
+    while start.elapsed() < Duration::from_secs(5) {
+        if let Ok(line) = rx.recv_timeout(Duration::from_millis(100)) {
+            output.push_str(&line);
+            output.push('\n');
+            if line.contains(needle) {
+                break;
+            }
+        }
+    }
+    output
+}
+
+#[test]
+fn test_shutdown_reason_client_disconnected() -> Result<()> {
+    let mut server = ServerProcess::spawn()?;
+    let session_id = server.get_session_id()?;
+    let (mut monitor, rx) = spawn_monitor(&session_id)?;
+
+    server.close_stdin();
+
+    let output = wait_for_line(&rx, "MCP client disconnected");
+    let _ = monitor.kill();
+    let _ = monitor.wait();
+    assert!(output.contains("MCP client disconnected"), "got:\n{output}");
+    assert!(!output.contains("Received SIG"), "got:\n{output}");
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_shutdown_reason_signal() -> Result<()> {
+    let mut server = ServerProcess::spawn()?;
+    let session_id = server.get_session_id()?;
+    let (mut monitor, rx) = spawn_monitor(&session_id)?;
+
+    Command::new("kill")
+        .args(["-TERM", &server.child.id().to_string()])
+        .status()
+        .context("Failed to send SIGTERM")?;
+
+    let output = wait_for_line(&rx, "Received SIGTERM");
+    let _ = monitor.kill();
+    let _ = monitor.wait();
+    assert!(output.contains("Received SIGTERM"), "got:\n{output}");
+    assert!(
+        !output.contains("MCP client disconnected"),
+        "got:\n{output}"
+    );
+    Ok(())
+}
+
 #[test]
 fn test_monitor_filter_flag() -> Result<()> {
     use std::sync::mpsc;
@@ -481,6 +1089,127 @@ fn test_monitor_filter_flag() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_monitor_since_count() -> Result<()> {
+    use std::sync::mpsc;
+
+    let mut server = ServerProcess::spawn()?;
+    let session_id = server.get_session_id()?;
+
+    // History written before the monitor attaches
+    for id in [11111, 22222] {
+        server.send(&json!({ "jsonrpc": "2.0", "id": id, "method": "ping" }))?;
+        let _response = server.recv()?;
+    }
+
+    // Replay only the last matching event, then follow
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_catenary"));
+    cmd.arg("monitor")
+        .arg(&session_id)
+        .arg("--raw")
+        .arg("--filter")
+        .arg("ping")
+        .arg("--since")
+        .arg("1");
+    cmd.stdout(Stdio::piped()).stderr(Stdio::null());
+    let mut child = cmd.spawn().context("Failed to spawn monitor")?;
+    let stdout = child
+        .stdout
+        .take()
+        .context("failed to take monitor stdout")?;
+
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut reader = BufReader::new(stdout);
+        let mut line = String::new();
+        while let Ok(n) = reader.read_line(&mut line) {
+            if n == 0 {
+                break;
+            }
+            let _ = tx.send(line.clone());
+            line.clear();
+        }
+    });
+
+    // Skip the "Monitoring session..." line
+    let _ = rx.recv_timeout(Duration::from_secs(5));
+
+    let mut output = String::new();
+    let start = std::time::Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+        if let Ok(line) = rx.recv_timeout(Duration::from_millis(100)) {
+            output.push_str(&line);
+        }
+    }
+
+    // A live event after the replay still comes through
+    server.send(&json!({ "jsonrpc": "2.0", "id": 33333, "method": "ping" }))?;
+    let _response = server.recv()?;
+    let start = std::time::Instant::now();
+    while start.elapsed() < Duration::from_secs(2) && !output.contains("33333") {
+        if let Ok(line) = rx.recv_timeout(Duration::from_millis(100)) {
+            output.push_str(&line);
+        }
+    }
+
+    let _ = child.kill();
+    let _ = child.wait();
+
+    assert!(
+        !output.contains("11111"),
+        "--since 1 should not replay older events: {output}"
+    );
+    assert!(
+        output.contains("22222"),
+        "--since 1 should replay the latest matching event: {output}"
+    );
+    assert!(
+        output.contains("33333"),
+        "Monitor should follow new events after the replay: {output}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_logs_shows_server_stderr() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("script.sh"), "#!/bin/bash\necho hi\n")?;
+    let root = dir.path().to_str().context("path")?;
+    let lsp = format!(
+        "shellscript:{} --stderr-line hello-from-mockls",
+        env!("CARGO_BIN_EXE_mockls")
+    );
+    let mut server = ServerProcess::spawn_with_args(&["--root", root, "--lsp", &lsp])?;
+    let session_id = server.get_session_id()?;
+
+    let logs = |lang: &str| {
+        Command::new(env!("CARGO_BIN_EXE_catenary"))
+            .args(["logs", lang, &session_id, "--no-follow"])
+            .output()
+    };
+
+    // The server is spawned eagerly for the .sh file; wait for its output
+    let start = std::time::Instant::now();
+    let mut stdout = String::new();
+    while start.elapsed() < Duration::from_secs(10) {
+        stdout = String::from_utf8_lossy(&logs("shellscript")?.stdout).to_string();
+        if stdout.contains("hello-from-mockls") {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    assert_eq!(stdout, "hello-from-mockls\n");
+
+    let output = logs("rust")?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("available: shellscript"),
+        "should list the logs that exist: {stderr}"
+    );
+    Ok(())
+}
+
 #[test]
 fn test_monitor_uses_arrows() -> Result<()> {
     use std::sync::mpsc;
@@ -557,3 +1286,234 @@ fn test_monitor_uses_arrows() -> Result<()> {
     );
     Ok(())
 }
+
+/// Runs a `catenary` subcommand against the given `--config-home`, feeding
+/// `stdin` to it.
+fn run_catenary(home: &str, args: &[&str], stdin: &str) -> Result<std::process::Output> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .args(["--config-home", home])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to spawn catenary")?;
+    child
+        .stdin
+        .take()
+        .context("Failed to get stdin")?
+        .write_all(stdin.as_bytes())?;
+    Ok(child.wait_with_output()?)
+}
+
+#[test]
+fn test_lock_list_shows_held_locks() -> Result<()> {
+    let home = tempfile::tempdir()?;
+    let home = home.path().to_str().context("path")?;
+
+    let output = run_catenary(home, &["lock", "list"], "")?;
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "No file locks held\n"
+    );
+
+    let hook = json!({
+        "session_id": "sess-a",
+        "tool_name": "Edit",
+        "tool_input": { "file_path": "/tmp/catenary_lock_list.rs" },
+        "cwd": "/tmp"
+    });
+    run_catenary(
+        home,
+        &["acquire", "--format", "claude", "--timeout", "5"],
+        &hook.to_string(),
+    )?;
+
+    let output = run_catenary(home, &["lock", "list"], "")?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.starts_with("/tmp/catenary_lock_list.rs \u{2014} sess-a \u{2014} acquired "),
+        "got: {stdout}"
+    );
+
+    let output = run_catenary(home, &["lock", "list", "--json"], "")?;
+    let locks: Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(locks[0]["file"], "/tmp/catenary_lock_list.rs");
+    assert_eq!(locks[0]["owner"], "sess-a");
+    assert_eq!(locks[0]["mode"], "exclusive");
+    Ok(())
+}
+
+#[test]
+fn test_sessions_dir_env_holds_locks() -> Result<()> {
+    let home = tempfile::tempdir()?;
+    let sessions = tempfile::tempdir()?;
+
+    let hook = json!({
+        "session_id": "sess-dir",
+        "tool_name": "Edit",
+        "tool_input": { "file_path": "/tmp/catenary_sessions_dir_lock.rs" },
+        "cwd": "/tmp"
+    });
+    let mut child = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .args(["--config-home", home.path().to_str().context("path")?])
+        .args(["acquire", "--format", "claude", "--timeout", "5"])
+        .env("CATENARY_SESSIONS_DIR", sessions.path())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("Failed to spawn catenary")?;
+    child
+        .stdin
+        .take()
+        .context("Failed to get stdin")?
+        .write_all(hook.to_string().as_bytes())?;
+    child.wait_with_output()?;
+
+    let held = std::fs::read_dir(sessions.path().join("locks"))?.count();
+    assert!(held > 0, "lock should be under the sessions dir");
+    assert!(
+        !home.path().join("locks").exists(),
+        "lock should not fall back to the config home"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_lock_release_force() -> Result<()> {
+    let home = tempfile::tempdir()?;
+    let home = home.path().to_str().context("path")?;
+    let file = "/tmp/catenary_lock_release.rs";
+
+    let hook = json!({
+        "session_id": "sess-a",
+        "tool_name": "Edit",
+        "tool_input": { "file_path": file },
+        "cwd": "/tmp"
+    });
+    run_catenary(
+        home,
+        &["acquire", "--format", "claude", "--timeout", "5"],
+        &hook.to_string(),
+    )?;
+
+    // A live lock needs --force
+    let output = run_catenary(home, &["lock", "release", "--file", file], "")?;
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("locked by sess-a"), "got: {stderr}");
+
+    let output = run_catenary(home, &["lock", "release", "--file", file, "--force"], "")?;
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        format!("Released lock on {file} (held by sess-a)\n")
+    );
+
+    let output = run_catenary(home, &["lock", "list"], "")?;
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "No file locks held\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_lock_track_write_suppresses_self_edit_warning() -> Result<()> {
+    let home = tempfile::tempdir()?;
+    let dir = tempfile::tempdir()?;
+    let home = home.path().to_str().context("path")?;
+    let file = dir.path().join("edited.rs");
+    std::fs::write(&file, "original\n")?;
+
+    let hook = json!({
+        "session_id": "sess-a",
+        "tool_name": "Edit",
+        "tool_input": { "file_path": file.to_str().context("path")? },
+        "cwd": dir.path().to_str().context("path")?
+    })
+    .to_string();
+    let acquire = || {
+        run_catenary(
+            home,
+            &["acquire", "--format", "claude", "--timeout", "5"],
+            &hook,
+        )
+    };
+
+    run_catenary(home, &["lock", "track-write"], &hook)?;
+    thread::sleep(Duration::from_millis(50));
+    std::fs::write(&file, "changed elsewhere\n")?;
+    let stdout = String::from_utf8_lossy(&acquire()?.stdout).into_owned();
+    assert!(
+        stdout.contains("modified by another agent"),
+        "an untracked change should warn: {stdout}"
+    );
+
+    // The agent's own edit, recorded by the hook, does not
+    thread::sleep(Duration::from_millis(50));
+    std::fs::write(&file, "own edit\n")?;
+    run_catenary(home, &["lock", "track-write"], &hook)?;
+    let stdout = String::from_utf8_lossy(&acquire()?.stdout).into_owned();
+    assert_eq!(stdout, "");
+    Ok(())
+}
+
+#[test]
+fn test_config_show_applies_cli_overrides() -> Result<()> {
+    let home = tempfile::tempdir()?;
+    let config_file = home.path().join("explicit.toml");
+    std::fs::write(
+        &config_file,
+        "idle_timeout = 60\n\n[languages]\n\"*.php\" = \"php\"\n",
+    )?;
+    let config_file = config_file.to_str().context("path")?;
+    let home = home.path().to_str().context("path")?;
+
+    let output = run_catenary(home, &["config", "--config", config_file], "")?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    assert!(
+        stdout.lines().any(|line| line == config_file),
+        "file list should include the explicit config: {stdout}"
+    );
+
+    let overrides = [
+        "--config",
+        config_file,
+        "--idle-timeout",
+        "7",
+        "--lsp",
+        "php:intelephense --stdio",
+        "--lsp-env",
+        "php:API_TOKEN=hunter2-secret",
+    ];
+    let output = run_catenary(home, &[&["config", "--show"], &overrides[..]].concat(), "")?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    assert!(
+        !stdout.contains("hunter2-secret"),
+        "env values should be masked: {stdout}"
+    );
+    let shown: toml::Value = toml::from_str(&stdout)?;
+    assert_eq!(shown["idle_timeout"].as_integer(), Some(7));
+    assert_eq!(shown["languages"]["*.php"].as_str(), Some("php"));
+    assert_eq!(
+        shown["server"]["php"]["command"].as_str(),
+        Some("intelephense")
+    );
+    assert_eq!(
+        shown["server"]["php"]["env"]["API_TOKEN"].as_str(),
+        Some("***")
+    );
+
+    let output = run_catenary(
+        home,
+        &[&["config", "--show", "--json"], &overrides[..]].concat(),
+        "",
+    )?;
+    assert!(!String::from_utf8_lossy(&output.stdout).contains("hunter2-secret"));
+    let shown: Value = serde_json::from_slice(&output.stdout)?;
+    assert_eq!(shown["idle_timeout"], 7);
+    assert_eq!(shown["server"]["php"]["args"], json!(["--stdio"]));
+    Ok(())
+}
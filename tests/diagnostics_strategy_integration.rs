@@ -207,6 +207,97 @@ fn test_diagnostics_token_monitor_path() -> Result<()> {
     Ok(())
 }
 
+/// `diagnostics_wait_strategy = "settle"` in the user config bypasses the
+/// progress-based strategies and waits for the generation to go quiet.
+///
+/// mockls publishes a second round with an extra error 1s after the first.
+/// The progress strategy waits out 2s of silence and sees it; settling after
+/// 300ms returns before it arrives.
+#[test]
+fn test_diagnostics_settle_strategy_from_config() -> Result<()> {
+    let diagnostics_with = |config: &str| -> Result<String> {
+        let dir = tempfile::tempdir()?;
+        let config_dir = dir.path().join("catenary");
+        std::fs::create_dir_all(&config_dir)?;
+        std::fs::write(config_dir.join("config.toml"), config)?;
+        let file = dir.path().join("test.sh");
+        std::fs::write(&file, "#!/bin/bash\necho hello\n")?;
+
+        let mut bridge = BridgeProcess::spawn(
+            &["--late-diagnostics", "1000"],
+            dir.path().to_str().context("path")?,
+        )?;
+        bridge.initialize()?;
+
+        let response = bridge.call_diagnostics(1, file.to_str().context("path")?)?;
+        Ok(response
+            .pointer("/result/content/0/text")
+            .and_then(Value::as_str)
+            .unwrap_or("")
+            .to_string())
+    };
+
+    let settled =
+        diagnostics_with("diagnostics_wait_strategy = \"settle\"\ndiagnostics_settle_ms = 300\n")?;
+    assert!(
+        settled.contains("mock diagnostic"),
+        "Settle strategy should return diagnostics. Got: {settled}"
+    );
+    assert!(
+        !settled.contains("marked error"),
+        "Settle strategy should return before the late round. Got: {settled}"
+    );
+
+    let progress = diagnostics_with("diagnostics_wait_strategy = \"progress\"\n")?;
+    assert!(
+        progress.contains("marked error"),
+        "Progress strategy should wait for the late round. Got: {progress}"
+    );
+
+    Ok(())
+}
+
+/// `force_refresh` closes and re-opens the document; the fresh `didOpen`
+/// must produce a new publication rather than leaving the cache empty.
+#[test]
+fn test_diagnostics_force_refresh() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let file = dir.path().join("test.sh");
+    std::fs::write(&file, "#!/bin/bash\necho hello\n")?;
+    let file = file.to_str().context("path")?;
+
+    let mut bridge = BridgeProcess::spawn(&[], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    let response = bridge.call_diagnostics(1, file)?;
+    let text = response
+        .pointer("/result/content/0/text")
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    assert!(text.contains("mock diagnostic"), "Got: {text}");
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {
+            "name": "diagnostics",
+            "arguments": { "file": file, "force_refresh": true }
+        }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response
+        .pointer("/result/content/0/text")
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    assert!(
+        text.contains("mock diagnostic"),
+        "Force refresh should return re-published diagnostics. Got: {response}"
+    );
+
+    Ok(())
+}
+
 /// mockls with `--drop-after 2`: crashes after 2 responses (initialize
 /// + shutdown or first tool call). Verifies `ServerDied` is handled.
 #[test]
@@ -464,6 +555,119 @@ async fn test_diagnostics_stale_notify_socket() -> Result<()> {
     Ok(())
 }
 
+/// Runs `catenary acquire` with the given hook JSON and returns its stdout.
+fn run_acquire_hook(hook: &Value, root: &str, state_home: &str) -> Result<String> {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_catenary"))
+        .args(["acquire", "--format", "claude", "--timeout", "5"])
+        .env("XDG_CONFIG_HOME", root)
+        .env("XDG_STATE_HOME", state_home)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to spawn acquire")?;
+    child
+        .stdin
+        .take()
+        .context("Failed to get stdin")?
+        .write_all(hook.to_string().as_bytes())?;
+    let output = child.wait_with_output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// With `block_on_errors`, the pre-tool hook previews the proposed edit and
+/// denies it when it introduces new errors, leaving the file untouched.
+#[test]
+fn test_block_on_errors_denies_breaking_edit() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let state_dir = tempfile::tempdir()?;
+    let config_dir = dir.path().join("catenary");
+    std::fs::create_dir_all(&config_dir)?;
+    std::fs::write(config_dir.join("config.toml"), "block_on_errors = true\n")?;
+    let file = dir.path().join("test.sh");
+    std::fs::write(&file, "#!/bin/bash\necho ok\n")?;
+
+    let root_str = dir.path().to_str().context("path")?;
+    let state_str = state_dir.path().to_str().context("state path")?;
+    let file_str = file.to_str().context("file path")?;
+    let mut bridge =
+        BridgeProcess::spawn_with_state_home(&["--error-marker", "BROKEN"], root_str, state_str)?;
+    bridge.initialize()?;
+    find_notify_socket(&state_dir.path().join("catenary").join("sessions"))?;
+
+    let edit = |new_string: &str| {
+        json!({
+            "session_id": "block-test",
+            "tool_name": "Edit",
+            "cwd": root_str,
+            "tool_input": {
+                "file_path": file_str,
+                "old_string": "echo ok",
+                "new_string": new_string
+            }
+        })
+    };
+
+    let denied = run_acquire_hook(&edit("echo BROKEN"), root_str, state_str)?;
+    assert!(
+        denied.contains("\"permissionDecision\":\"deny\"") && denied.contains("1 new error"),
+        "Breaking edit should be denied. Got: {denied}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&file)?,
+        "#!/bin/bash\necho ok\n",
+        "Preview must not touch the file on disk"
+    );
+
+    let allowed = run_acquire_hook(&edit("echo fine"), root_str, state_str)?;
+    assert!(
+        allowed.is_empty(),
+        "Clean edit should be allowed. Got: {allowed}"
+    );
+
+    Ok(())
+}
+
+/// The notify hook path tells the server's file watching about the change,
+/// so servers that only re-check on `workspace/didChangeWatchedFiles` still
+/// report diagnostics.
+#[tokio::test]
+async fn test_notify_sends_did_change_watched_files() -> Result<()> {
+    use tokio::io::AsyncWriteExt as _;
+    use tokio::net::UnixStream;
+
+    let dir = tempfile::tempdir()?;
+    let state_dir = tempfile::tempdir()?;
+    let file = dir.path().join("watched.sh");
+    std::fs::write(&file, "#!/bin/bash\necho watched\n")?;
+
+    let root_str = dir.path().to_str().context("path")?;
+    let state_str = state_dir.path().to_str().context("state path")?;
+    let mut bridge = BridgeProcess::spawn_with_state_home(
+        &["--diagnostics-on-watched-files"],
+        root_str,
+        state_str,
+    )?;
+    bridge.initialize()?;
+    let socket_path = find_notify_socket(&state_dir.path().join("catenary").join("sessions"))?;
+
+    let stream = UnixStream::connect(&socket_path).await?;
+    let (reader, mut writer) = tokio::io::split(stream);
+    let request = json!({ "file": file.to_str().context("file path")? });
+    writer.write_all(format!("{request}\n").as_bytes()).await?;
+    writer.shutdown().await?;
+
+    let mut response = String::new();
+    tokio::io::AsyncReadExt::read_to_string(&mut tokio::io::BufReader::new(reader), &mut response)
+        .await?;
+
+    assert!(
+        response.contains("mock diagnostic"),
+        "server should have been told the file changed. Got: {response}"
+    );
+    Ok(())
+}
+
 /// Scans the sessions directory for a `notify.sock` file.
 fn find_notify_socket(sessions_dir: &std::path::Path) -> Result<PathBuf> {
     // Poll briefly for the socket to appear (bridge may still be starting)
@@ -2,7 +2,7 @@
 // Copyright (C) 2026 Mark Wells <contact@markwells.dev>
 
 #![deny(clippy::unwrap_used, clippy::expect_used, clippy::panic)]
-//! Integration tests for file I/O tools: `list_directory`.
+//! Integration tests for file I/O tools: `list_directory` and `diff`.
 
 use std::io::{BufRead, BufReader, Write};
 use std::process::{Command, Stdio};
@@ -240,3 +240,79 @@ fn test_list_directory_symlink_shown_not_followed() -> Result<()> {
     );
     Ok(())
 }
+
+/// Runs git in `dir` with a fixed identity, failing on a non-zero exit.
+fn git(dir: &std::path::Path, args: &[&str]) -> Result<()> {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to run git")?;
+    if !status.success() {
+        bail!("git {args:?} failed");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_diff_shows_working_tree_changes() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    git(dir.path(), &["init", "-q"])?;
+    std::fs::write(dir.path().join("notes.txt"), "old line\n")?;
+    std::fs::write(dir.path().join("other.txt"), "untouched\n")?;
+    git(dir.path(), &["add", "."])?;
+    git(dir.path(), &["commit", "-q", "-m", "initial"])?;
+    std::fs::write(dir.path().join("notes.txt"), "new line\n")?;
+    std::fs::write(dir.path().join("other.txt"), "changed too\n")?;
+
+    let mut bridge = BridgeProcess::spawn(&dir.path().to_string_lossy())?;
+    bridge.initialize()?;
+
+    let text = bridge.call_tool_text("diff", &json!({}))?;
+    assert!(
+        text.contains("-old line"),
+        "Diff should show removal: {text}"
+    );
+    assert!(
+        text.contains("+new line"),
+        "Diff should show addition: {text}"
+    );
+    assert!(
+        text.contains("other.txt"),
+        "Diff should cover all files: {text}"
+    );
+
+    let notes = dir.path().join("notes.txt");
+    let text = bridge.call_tool_text(
+        "diff",
+        &json!({ "path": notes.to_string_lossy().to_string(), "ref": "HEAD" }),
+    )?;
+    assert!(text.contains("+new line"), "Filtered diff: {text}");
+    assert!(
+        !text.contains("other.txt"),
+        "Path filter should exclude other files: {text}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_diff_rejects_bad_input() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    git(dir.path(), &["init", "-q"])?;
+
+    let mut bridge = BridgeProcess::spawn(&dir.path().to_string_lossy())?;
+    bridge.initialize()?;
+
+    let result = bridge.call_tool("diff", &json!({ "path": "/etc" }))?;
+    let is_error = result.get("isError").and_then(serde_json::Value::as_bool);
+    assert_eq!(is_error, Some(true), "Outside-root path should be an error");
+
+    let result = bridge.call_tool("diff", &json!({ "ref": "--output=/tmp/x" }))?;
+    let is_error = result.get("isError").and_then(serde_json::Value::as_bool);
+    assert_eq!(is_error, Some(true), "Option-like ref should be rejected");
+    Ok(())
+}
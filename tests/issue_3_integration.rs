@@ -77,7 +77,7 @@ version = "0.1.0"
     writeln!(stdin, "{initialized_notif}").context("Failed to write to stdin")?;
 
     // Give it a moment to finish initial indexing
-    std::thread::sleep(std::time::Duration::from_millis(5000));
+    std::thread::sleep(std::time::Duration::from_secs(5));
 
     // 4. Update file to introduce error
     std::fs::write(&file_path, "fn main() { let x: i32 = \"string\"; }")?;
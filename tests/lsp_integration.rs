@@ -16,8 +16,7 @@ fn command_exists(cmd: &str) -> bool {
     Command::new("which")
         .arg(cmd)
         .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+        .is_ok_and(|o| o.status.success())
 }
 
 /// Skip test if bash-language-server is not installed
@@ -45,7 +44,7 @@ macro_rules! require_rust_analyzer {
     () => {
         // rust-analyzer via rustup proxy may exist but not work
         let output = Command::new("rust-analyzer").arg("--version").output();
-        if output.is_err() || !output.map(|o| o.status.success()).unwrap_or(false) {
+        if output.is_err() || !output.is_ok_and(|o| o.status.success()) {
             tracing::warn!("Skipping test: rust-analyzer not installed or not working");
             return Ok(());
         }
@@ -322,3 +321,55 @@ async fn test_mockls_document_lifecycle() -> Result<()> {
     client.shutdown().await?;
     Ok(())
 }
+
+#[tokio::test]
+async fn test_mockls_diagnostics_for_unopened_file() -> Result<()> {
+    let dir = tempdir()?;
+    let opened = dir.path().join("opened.sh");
+    let unopened = dir.path().join("unopened.sh");
+    std::fs::write(&opened, "#!/bin/bash\necho hi\n")?;
+    std::fs::write(&unopened, "#!/bin/bash\n")?;
+
+    let bin = env!("CARGO_BIN_EXE_mockls");
+    let mut client = catenary_mcp::lsp::LspClient::spawn(
+        bin,
+        &["--publish-related", "unopened.sh"],
+        "shellscript",
+        catenary_mcp::session::EventBroadcaster::noop()?,
+    )?;
+    client.initialize(&[dir.path().to_path_buf()], None).await?;
+
+    let opened_uri: lsp_types::Uri = format!("file://{}", opened.display()).parse()?;
+    let unopened_uri: lsp_types::Uri = format!("file://{}", unopened.display()).parse()?;
+
+    client
+        .did_open(lsp_types::DidOpenTextDocumentParams {
+            text_document: lsp_types::TextDocumentItem {
+                uri: opened_uri.clone(),
+                language_id: "shellscript".to_string(),
+                version: 1,
+                text: "#!/bin/bash\necho hi\n".to_string(),
+            },
+        })
+        .await?;
+
+    // Both publications arrive back-to-back; poll until the second lands.
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while client.get_diagnostics(&unopened_uri).await.is_empty()
+        && std::time::Instant::now() < deadline
+    {
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+
+    assert!(
+        !client.get_diagnostics(&unopened_uri).await.is_empty(),
+        "Diagnostics for the unopened file should be cached"
+    );
+
+    let all = client.all_diagnostics().await;
+    let uris: Vec<&str> = all.iter().map(|(uri, _)| uri.as_str()).collect();
+    assert_eq!(uris, vec![opened_uri.as_str(), unopened_uri.as_str()]);
+
+    client.shutdown().await?;
+    Ok(())
+}
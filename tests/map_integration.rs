@@ -150,6 +150,93 @@ fn test_codebase_map_basic() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_codebase_map_respects_catenaryignore() -> Result<()> {
+    let temp = tempfile::tempdir()?;
+    std::fs::write(temp.path().join(".catenaryignore"), "generated/\n*.pb.rs\n")?;
+    std::fs::write(temp.path().join("kept.rs"), "fn main() {}")?;
+    std::fs::write(temp.path().join("schema.pb.rs"), "fn generated() {}")?;
+    std::fs::create_dir(temp.path().join("generated"))?;
+    std::fs::write(temp.path().join("generated/huge.rs"), "fn generated() {}")?;
+
+    let mut bridge = BridgeProcess::spawn(temp.path().to_str().context("invalid path")?, None)?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": {
+            "name": "codebase_map",
+            "arguments": {
+                "path": temp.path().to_str().context("invalid path")?,
+                "max_depth": 5,
+                "include_symbols": false
+            }
+        }
+    }))?;
+
+    let response = bridge.recv()?;
+    let content = response["result"]["content"][0]["text"]
+        .as_str()
+        .context("Missing text in content")?;
+
+    assert!(content.contains("kept.rs"), "{content}");
+    assert!(!content.contains("schema.pb.rs"), "{content}");
+    assert!(!content.contains("generated"), "{content}");
+    Ok(())
+}
+
+#[test]
+fn test_codebase_map_symbols_stay_with_their_files() -> Result<()> {
+    let temp = tempfile::tempdir()?;
+    // More files than concurrent fetches, so workers pick up several each
+    for i in 0..20 {
+        std::fs::write(
+            temp.path().join(format!("script{i:02}.sh")),
+            format!("function defined_in_{i:02}() {{\n  echo {i}\n}}\n"),
+        )?;
+    }
+
+    let lsp = format!("shellscript:{}", env!("CARGO_BIN_EXE_mockls"));
+    let mut bridge =
+        BridgeProcess::spawn(temp.path().to_str().context("invalid path")?, Some(&lsp))?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": {
+            "name": "codebase_map",
+            "arguments": {
+                "path": temp.path().to_str().context("invalid path")?,
+                "include_symbols": true,
+                "detail_level": "signatures"
+            }
+        }
+    }))?;
+
+    let response = bridge.recv()?;
+    let content = response["result"]["content"][0]["text"]
+        .as_str()
+        .context("Missing text in content")?;
+    let lines: Vec<&str> = content.lines().collect();
+
+    for i in 0..20 {
+        let file_line = lines
+            .iter()
+            .position(|l| l.trim() == format!("script{i:02}.sh"))
+            .context(format!("script{i:02}.sh missing from map: {content}"))?;
+        let symbol_line = lines.get(file_line + 1).copied().unwrap_or_default();
+        assert!(
+            symbol_line.contains(&format!("defined_in_{i:02}")),
+            "symbols of script{i:02}.sh should follow it: {content}"
+        );
+    }
+    Ok(())
+}
+
 #[test]
 fn test_codebase_map_with_symbols() -> Result<()> {
     // Requires bash-language-server
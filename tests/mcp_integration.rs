@@ -19,8 +19,7 @@ fn command_exists(cmd: &str) -> bool {
     Command::new("which")
         .arg(cmd)
         .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
+        .is_ok_and(|o| o.status.success())
 }
 
 /// Find line and column (0-indexed) of a substring
@@ -68,7 +67,7 @@ macro_rules! require_rust_analyzer {
         let output = std::process::Command::new("rust-analyzer")
             .arg("--version")
             .output();
-        if output.is_err() || !output.map(|o| o.status.success()).unwrap_or(false) {
+        if output.is_err() || !output.is_ok_and(|o| o.status.success()) {
             tracing::warn!("Skipping test: rust-analyzer not installed or not working");
             return Ok(());
         }
@@ -88,7 +87,12 @@ impl BridgeProcess {
     }
 
     fn spawn_multi_root(lsp_commands: &[&str], roots: &[&str]) -> Result<Self> {
+        Self::spawn_with_flags(lsp_commands, roots, &[])
+    }
+
+    fn spawn_with_flags(lsp_commands: &[&str], roots: &[&str], flags: &[&str]) -> Result<Self> {
         let mut cmd = Command::new(env!("CARGO_BIN_EXE_catenary"));
+        cmd.args(flags);
 
         for lsp in lsp_commands {
             cmd.arg("--lsp").arg(lsp);
@@ -309,15 +313,27 @@ fn test_mcp_tools_list() -> Result<()> {
         "hover",
         "definition",
         "type_definition",
+        "document_highlight",
         "implementation",
         "find_references",
+        "investigate",
         "document_symbols",
         "search",
+        "completion",
         "code_actions",
+        "diagnostic_fixes",
+        "explain_diagnostic",
+        "code_lens",
+        "folding_ranges",
+        "inlay_hints",
+        "prepare_rename",
         "rename",
         "diagnostics",
+        "workspace_diagnostics",
         "call_hierarchy",
         "type_hierarchy",
+        "health_check",
+        "capabilities",
     ];
 
     for expected in &expected_tools {
@@ -1915,59 +1931,1750 @@ fn test_mockls_hover_across_profiles() -> Result<()> {
 }
 
 #[test]
-fn test_mockls_definition_across_profiles() -> Result<()> {
-    let profiles: &[(&str, &str)] = &[("clean", ""), ("workspace-folders", "--workspace-folders")];
+fn test_mockls_completion_grouped_by_kind() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("completion.sh");
+    std::fs::write(&test_file, "function greet\nlet count\n")?;
+    let test_file = test_file.to_str().context("path")?;
 
-    for (name, flags) in profiles {
-        let test_file = "/tmp/mockls_def_test.sh";
-        std::fs::write(
-            test_file,
-            "#!/bin/bash\nfn my_function() { echo hi; }\nmy_function\n",
-        )?;
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
 
-        let lsp = mockls_lsp_arg("shellscript", flags);
-        let mut bridge = BridgeProcess::spawn(&[&lsp], "/tmp")?;
-        bridge.initialize()?;
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "tools/call",
+        "params": {
+            "name": "completion",
+            "arguments": {
+                "file": test_file,
+                "line": 1,
+                "character": 0,
+                "kinds": ["function", "keyword"],
+                "group": true
+            }
+        }
+    }))?;
+
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+
+    assert!(text.contains("## Function (1)\ngreet"), "got: {text}");
+    assert!(
+        text.contains("## Keyword (3)\nif\nfor\nwhile"),
+        "got: {text}"
+    );
+    // The header echoes the cursor line, which itself mentions `count`
+    let (_, items) = text.split_once("\n\n").context("missing header")?;
+    assert!(
+        !items.contains("count"),
+        "variables should be filtered: {text}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mockls_completion_sends_trigger_context() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("trigger.sh");
+    std::fs::write(&test_file, "let count\ncount.\n")?;
+    let test_file = test_file.to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
 
+    let mut complete = |id: u64, character: u32| -> Result<String> {
         bridge.send(&json!({
             "jsonrpc": "2.0",
-            "id": 4,
+            "id": id,
             "method": "tools/call",
             "params": {
-                "name": "definition",
-                "arguments": {
-                    "file": test_file,
-                    "line": 2,
-                    "character": 0
-                }
+                "name": "completion",
+                "arguments": { "file": test_file, "line": 1, "character": character }
             }
         }))?;
-
         let response = bridge.recv()?;
-        let result = &response["result"];
-        assert!(
-            result["isError"].is_null() || result["isError"] == false,
-            "Profile {name}: definition failed: {response:?}"
-        );
+        Ok(response["result"]["content"][0]["text"]
+            .as_str()
+            .context(format!("missing text: {response:?}"))?
+            .to_string())
+    };
 
-        let content = result["content"]
-            .as_array()
-            .context(format!("Profile {name}: missing content array"))?;
-        assert!(
-            !content.is_empty(),
-            "Profile {name}: expected definition content"
-        );
+    // After the `.` trigger character the server sees a trigger context
+    let text = complete(3, 6)?;
+    assert!(text.starts_with("Line: count.\n"), "got: {text}");
+    assert!(text.contains("member"), "got: {text}");
 
-        let text = content[0]["text"]
+    // Mid-token the partial identifier is reported and the request is invoked
+    let text = complete(4, 3)?;
+    assert!(text.contains("Prefix: cou"), "got: {text}");
+    assert!(!text.contains("member"), "got: {text}");
+    assert!(text.contains("count"), "got: {text}");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_completion_resolves_top_items() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("resolve.sh");
+    std::fs::write(&test_file, "let count\nlet total\nc\n")?;
+    let test_file = test_file.to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {
+            "name": "completion",
+            "arguments": {
+                "file": test_file, "line": 2, "character": 1,
+                "kinds": ["variable"], "resolve_top": 1
+            }
+        }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+
+    let (_, details) = text
+        .split_once("## Details\n")
+        .context(format!("missing details: {text}"))?;
+    assert_eq!(
+        details, "count [Variable]\n  mockls docs for `count`",
+        "only the first item is resolved: {text}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_rename_non_dry_run_requires_allow_edits() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("rename.sh");
+    std::fs::write(&test_file, "let count\n")?;
+    let test_file = test_file.to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "tools/call",
+        "params": {
+            "name": "rename",
+            "arguments": {
+                "file": test_file,
+                "line": 0,
+                "character": 4,
+                "new_name": "total",
+                "dry_run": false
+            }
+        }
+    }))?;
+    let response = bridge.recv()?;
+    assert_eq!(
+        response["result"]["isError"], true,
+        "dry_run: false should be refused without allow_edits: {response:?}"
+    );
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert!(text.contains("requires allow_edits"), "got: {text}");
+    assert_eq!(std::fs::read_to_string(test_file)?, "let count\n");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_diagnostic_fixes_resolves_without_applying() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("fixes.sh");
+    let content = "#!/bin/bash\necho BROKEN\n";
+    std::fs::write(&test_file, content)?;
+    let test_file_str = test_file.to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "--error-marker BROKEN");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    // Populate the diagnostics cache first
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": { "name": "diagnostics", "arguments": { "file": test_file_str } }
+    }))?;
+    bridge.recv()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "tools/call",
+        "params": {
+            "name": "diagnostic_fixes",
+            "arguments": { "file": test_file_str, "line": 1, "character": 0 }
+        }
+    }))?;
+
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+
+    assert!(text.contains("marked error"), "got: {text}");
+    assert!(
+        text.contains(
+            "1. Remove error marker (quickfix) [preferred] \u{2014} 1 edit(s) in 1 file(s)"
+        ),
+        "quickfix should be resolved to show its edit: {text}"
+    );
+    assert!(
+        text.contains("2. [Command] Explain with mockls"),
+        "got: {text}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&test_file)?,
+        content,
+        "listing fixes must not apply them"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mockls_diagnostic_fixes_captures_command_edits() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("command_fixes.sh");
+    let content = "#!/bin/bash\necho BROKEN\n";
+    std::fs::write(&test_file, content)?;
+    let test_file_str = test_file.to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "--error-marker BROKEN --command-fixes");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": { "name": "diagnostics", "arguments": { "file": test_file_str } }
+    }))?;
+    bridge.recv()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "tools/call",
+        "params": {
+            "name": "diagnostic_fixes",
+            "arguments": { "file": test_file_str, "line": 1, "character": 0 }
+        }
+    }))?;
+
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+
+    assert!(
+        text.contains(
+            "1. Remove error marker (quickfix) [preferred] \u{2014} 1 edit(s) in 1 file(s)"
+        ),
+        "the edit sent via workspace/applyEdit should be shown: {text}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&test_file)?,
+        content,
+        "capturing a command's edit must not apply it"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mockls_organize_imports() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let unsorted = dir.path().join("unsorted.sh");
+    let content = "#!/bin/bash\nimport zeta\nimport alpha\necho done\n";
+    std::fs::write(&unsorted, content)?;
+    let sorted = dir.path().join("sorted.sh");
+    std::fs::write(&sorted, "#!/bin/bash\nimport alpha\nimport zeta\n")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "--organize-imports");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    let mut organize = |id: u64, file: &std::path::Path| -> Result<String> {
+        bridge.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": {
+                "name": "organize_imports",
+                "arguments": { "file": file.to_str().context("path")? }
+            }
+        }))?;
+        let response = bridge.recv()?;
+        Ok(response["result"]["content"][0]["text"]
             .as_str()
-            .context(format!("Profile {name}: missing text"))?;
-        assert!(
-            text.contains(test_file),
-            "Profile {name}: definition should contain file path, got: {text}"
-        );
+            .context(format!("missing text: {response:?}"))?
+            .to_string())
+    };
 
-        std::fs::remove_file(test_file).ok();
-    }
+    let text = organize(2, &unsorted)?;
+    assert!(
+        text.starts_with("Organize imports"),
+        "should name the action: {text}"
+    );
+    assert!(
+        text.contains("L2:1-L4:1: import alpha\\nimport zeta\\n"),
+        "should show the resolved edit: {text}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&unsorted)?,
+        content,
+        "organize_imports must not modify the file"
+    );
+
+    let text = organize(3, &sorted)?;
+    assert_eq!(text, "No import organization available");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_apply_edit() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let script = dir.path().join("apply.sh");
+    std::fs::write(&script, "#!/bin/bash\nold_name=1\necho $old_name\n")?;
+    let imports = dir.path().join("imports.sh");
+    std::fs::write(&imports, "#!/bin/bash\nimport zeta\nimport alpha\n")?;
+    let config_dir = dir.path().join("catenary");
+    std::fs::create_dir_all(&config_dir)?;
+    std::fs::write(config_dir.join("config.toml"), "allow_edits = true\n")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "--prepare-rename --organize-imports");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    let mut call = |id: u64, name: &str, arguments: Value| -> Result<Value> {
+        bridge.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": { "name": name, "arguments": arguments }
+        }))?;
+        Ok(bridge.recv()?["result"].clone())
+    };
+
+    // Rename inputs compute and apply the edit in one call
+    let result = call(
+        2,
+        "apply_edit",
+        json!({
+            "file": script.to_str().context("path")?,
+            "line": 1,
+            "character": 2,
+            "new_name": "new_name"
+        }),
+    )?;
+    let text = result["content"][0]["text"].as_str().context("text")?;
+    assert!(
+        text.starts_with("Applied 2 edit(s) to 1 file(s)"),
+        "got: {text}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&script)?,
+        "#!/bin/bash\nnew_name=1\necho $new_name\n"
+    );
+
+    // A dry run previews the summary the real apply prints
+    let rename = |dry_run: bool| -> Result<Value> {
+        Ok(json!({
+            "file": script.to_str().context("path")?,
+            "line": 1,
+            "character": 2,
+            "new_name": "renamed",
+            "dry_run": dry_run
+        }))
+    };
+    let result = call(7, "rename", rename(true)?)?;
+    let preview = result["content"][0]["text"]
+        .as_str()
+        .context("text")?
+        .to_string();
+    assert_eq!(
+        std::fs::read_to_string(&script)?,
+        "#!/bin/bash\nnew_name=1\necho $new_name\n"
+    );
+
+    // rename with dry_run: false applies through the same path
+    let result = call(6, "rename", rename(false)?)?;
+    let text = result["content"][0]["text"].as_str().context("text")?;
+    assert!(
+        text.starts_with("Applied 2 edit(s) to 1 file(s)"),
+        "got: {text}"
+    );
+    let summary = text.strip_prefix("Applied ").context("summary")?;
+    assert!(
+        preview.starts_with(&format!(
+            "Would apply {summary}\nDry run \u{2014} no files were modified.\n"
+        )),
+        "preview: {preview}\napplied: {text}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&script)?,
+        "#!/bin/bash\nrenamed=1\necho $renamed\n"
+    );
+
+    // A previewed edit is applied by id, once
+    let result = call(
+        3,
+        "organize_imports",
+        json!({ "file": imports.to_str().context("path")? }),
+    )?;
+    let text = result["content"][0]["text"].as_str().context("text")?;
+    let edit_id = text
+        .rsplit_once("Edit ID: ")
+        .and_then(|(_, rest)| rest.split_whitespace().next())
+        .context(format!("missing edit id: {text}"))?
+        .to_string();
+    let result = call(4, "apply_edit", json!({ "edit_id": edit_id }))?;
+    assert!(
+        result["isError"].is_null() || result["isError"] == false,
+        "got: {result:?}"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&imports)?,
+        "#!/bin/bash\nimport alpha\nimport zeta\n"
+    );
+    let result = call(5, "apply_edit", json!({ "edit_id": edit_id }))?;
+    assert_eq!(
+        result["isError"], true,
+        "an applied id must not apply twice"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mockls_apply_edit_disabled_by_default() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let script = dir.path().join("apply.sh");
+    let content = "#!/bin/bash\nold_name=1\n";
+    std::fs::write(&script, content)?;
+
+    let lsp = mockls_lsp_arg("shellscript", "--prepare-rename");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))?;
+    let response = bridge.recv()?;
+    let tools = response["result"]["tools"].as_array().context("tools")?;
+    assert!(
+        tools.iter().all(|t| t["name"] != "apply_edit"),
+        "apply_edit should not be listed without allow_edits"
+    );
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "tools/call",
+        "params": {
+            "name": "apply_edit",
+            "arguments": {
+                "file": script.to_str().context("path")?,
+                "line": 1,
+                "character": 2,
+                "new_name": "new_name"
+            }
+        }
+    }))?;
+    let response = bridge.recv()?;
+    assert_eq!(response["result"]["isError"], true, "got: {response:?}");
+    assert_eq!(std::fs::read_to_string(&script)?, content);
+    Ok(())
+}
+
+#[test]
+fn test_mockls_execute_command_allowlist() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("run.sh"), "#!/bin/bash\necho hi\n")?;
+    let config_dir = dir.path().join("catenary");
+    std::fs::create_dir_all(&config_dir)?;
+    std::fs::write(
+        config_dir.join("config.toml"),
+        "allowed_server_commands = [\"mockls.echo\"]\n",
+    )?;
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }))?;
+    let response = bridge.recv()?;
+    let tools = response["result"]["tools"].as_array().context("tools")?;
+    assert!(
+        tools.iter().any(|t| t["name"] == "execute_command"),
+        "execute_command should be listed with an allowlist"
+    );
+
+    let mut call = |id: u64, command: &str| -> Result<Value> {
+        bridge.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": {
+                "name": "execute_command",
+                "arguments": {
+                    "language": "shellscript",
+                    "command": command,
+                    "arguments": [1, "two"]
+                }
+            }
+        }))?;
+        Ok(bridge.recv()?["result"].clone())
+    };
+
+    let result = call(3, "mockls.echo")?;
+    let text = result["content"][0]["text"].as_str().context("text")?;
+    let value: Value = serde_json::from_str(text)?;
+    assert_eq!(value, json!([1, "two"]));
+
+    let result = call(4, "mockls.removeMarker")?;
+    assert_eq!(result["isError"], true, "got: {result:?}");
+    let text = result["content"][0]["text"].as_str().context("text")?;
+    assert!(text.contains("not allowed"), "got: {text}");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_execute_command_isolate_roots() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let root_a = dir.path().join("a");
+    let root_b = dir.path().join("b");
+    for root in [&root_a, &root_b] {
+        std::fs::create_dir_all(root)?;
+        std::fs::write(root.join("run.sh"), "#!/bin/bash\necho hi\n")?;
+    }
+    // XDG_CONFIG_HOME is the first root
+    let config_dir = root_a.join("catenary");
+    std::fs::create_dir_all(&config_dir)?;
+    std::fs::write(
+        config_dir.join("config.toml"),
+        format!(
+            "allowed_server_commands = [\"mockls.workspaceFolders\"]\n\n\
+             [server.shellscript]\ncommand = \"{}\"\nisolate_roots = true\n",
+            env!("CARGO_BIN_EXE_mockls")
+        ),
+    )?;
+
+    let mut bridge = BridgeProcess::spawn_multi_root(
+        &[],
+        &[
+            root_a.to_str().context("path")?,
+            root_b.to_str().context("path")?,
+        ],
+    )?;
+    bridge.initialize()?;
+
+    let mut call = |id: u64, file: Option<&std::path::Path>| -> Result<Value> {
+        let mut arguments =
+            json!({ "language": "shellscript", "command": "mockls.workspaceFolders" });
+        if let Some(file) = file {
+            arguments["file"] = json!(file);
+        }
+        bridge.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": { "name": "execute_command", "arguments": arguments }
+        }))?;
+        Ok(bridge.recv()?["result"].clone())
+    };
+
+    for (id, root) in [(2, &root_b), (3, &root_a)] {
+        let result = call(id, Some(&root.join("run.sh")))?;
+        let text = result["content"][0]["text"].as_str().context("text")?;
+        let folders: Value = serde_json::from_str(text)?;
+        assert_eq!(folders.as_array().map(Vec::len), Some(1), "got: {folders}");
+        assert_eq!(
+            folders[0]["uri"],
+            json!(format!("file://{}", root.display())),
+            "command should run on the server of {}",
+            root.display()
+        );
+    }
+
+    let result = call(4, None)?;
+    assert_eq!(result["isError"], true, "got: {result:?}");
+    let text = result["content"][0]["text"].as_str().context("text")?;
+    assert!(text.contains("pass file"), "got: {text}");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_request_timeout_from_config() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let script = dir.path().join("slow.sh");
+    std::fs::write(&script, "#!/bin/bash\necho hello\n")?;
+    let config_dir = dir.path().join("catenary");
+    std::fs::create_dir_all(&config_dir)?;
+    std::fs::write(config_dir.join("config.toml"), "[timeouts]\nhover = 1\n")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "--hang-on textDocument/hover");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    let started = std::time::Instant::now();
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {
+            "name": "hover",
+            "arguments": { "file": script.to_str().context("path")?, "line": 1, "character": 1 }
+        }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert!(text.contains("timed out after 1s"), "got: {text}");
+    assert!(
+        started.elapsed() < Duration::from_secs(15),
+        "hover should not wait for the 30s default"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mockls_explain_diagnostic() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("explain.sh");
+    std::fs::write(
+        &test_file,
+        "#!/bin/bash\nfunction helper() { :; }\nhelper BROKEN\n",
+    )?;
+    let test_file_str = test_file.to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "--error-marker BROKEN");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    // Populate the diagnostics cache first
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": { "name": "diagnostics", "arguments": { "file": test_file_str } }
+    }))?;
+    bridge.recv()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "tools/call",
+        "params": {
+            "name": "explain_diagnostic",
+            "arguments": { "file": test_file_str, "line": 2, "character": 0 }
+        }
+    }))?;
+
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+
+    assert!(text.contains("marked error"), "got: {text}");
+    assert!(text.contains("\nHover:\n"), "got: {text}");
+    // `helper` on the diagnostic's line resolves to its declaration above
+    assert!(
+        text.contains(&format!("\nDefinition:\n  {test_file_str}:2:1\n")),
+        "got: {text}"
+    );
+    assert!(text.contains("1. Remove error marker"), "got: {text}");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_companion_server_fans_out() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("companion.sh");
+    std::fs::write(&test_file, "#!/bin/bash\necho FIXME\n")?;
+    let test_file_str = test_file.to_str().context("path")?;
+
+    let bin = env!("CARGO_BIN_EXE_mockls");
+    let config_dir = dir.path().join("catenary");
+    std::fs::create_dir_all(&config_dir)?;
+    std::fs::write(
+        config_dir.join("config.toml"),
+        format!(
+            "[server.shellscript]\ncommand = \"{bin}\"\n\n\
+             [server.lint]\ncommand = \"{bin}\"\n\
+             args = [\"--error-marker\", \"FIXME\"]\n\
+             languages = [\"shellscript\"]\n"
+        ),
+    )?;
+
+    let mut bridge = BridgeProcess::spawn(&[], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": { "name": "diagnostics", "arguments": { "file": test_file_str } }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert_eq!(
+        text.matches("mock diagnostic").count(),
+        2,
+        "both servers should report: {text}"
+    );
+    assert!(
+        text.contains("marked error"),
+        "companion diagnostics missing: {text}"
+    );
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "tools/call",
+        "params": {
+            "name": "code_actions",
+            "arguments": { "file": test_file_str, "line": 1, "character": 5 }
+        }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert!(text.contains("Remove error marker"), "got: {text}");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_document_highlight() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("highlight.sh");
+    std::fs::write(&test_file, "#!/bin/bash\ncount=1\necho $count\n")?;
+    let test_file_str = test_file.to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {
+            "name": "document_highlight",
+            "arguments": { "file": test_file_str, "line": 1, "character": 0 }
+        }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+
+    assert_eq!(text, "L2:1 [Write]\nL3:7 [Read]");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_selection_range() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("select.sh");
+    std::fs::write(&test_file, "#!/bin/bash\necho hello\n")?;
+    let test_file_str = test_file.to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {
+            "name": "selection_range",
+            "arguments": { "file": test_file_str, "line": 1, "character": 7 }
+        }
+    }))?;
+
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    // mockls nests the word under the cursor inside its line
+    assert_eq!(text, "L2:6-L2:11\nL2:1-L2:11");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_folding_ranges() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("fold.sh");
+    std::fs::write(
+        &test_file,
+        "#!/bin/bash\n# Greets.\n# Twice.\ngreet() {\n  echo hi\n  echo hi\n}\n",
+    )?;
+    let test_file_str = test_file.to_str().context("path")?;
+    let root = dir.path().to_str().context("path")?;
+
+    let call = json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": { "name": "folding_ranges", "arguments": { "file": test_file_str } }
+    });
+
+    let lsp = mockls_lsp_arg("shellscript", "--folding-ranges");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], root)?;
+    bridge.initialize()?;
+    bridge.send(&call)?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert_eq!(text, "L2-L3 comment\nL4-L7 region");
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], root)?;
+    bridge.initialize()?;
+    bridge.send(&call)?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert_eq!(text, "Folding ranges not supported");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_code_lens() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("lens.sh");
+    std::fs::write(
+        &test_file,
+        "#!/bin/bash\nfunction greet() {\n  echo hi\n}\nfunction wave() {\n  greet\n}\ngreet\n",
+    )?;
+    let test_file_str = test_file.to_str().context("path")?;
+    let root = dir.path().to_str().context("path")?;
+
+    let call = json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": { "name": "code_lens", "arguments": { "file": test_file_str } }
+    });
+
+    let lsp = mockls_lsp_arg("shellscript", "--code-lens");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], root)?;
+    bridge.initialize()?;
+    bridge.send(&call)?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert_eq!(text, "L2: 2 references\nL5: 0 references");
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], root)?;
+    bridge.initialize()?;
+    bridge.send(&call)?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert_eq!(text, "Code lens not supported");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_crashed_server_restarts_and_reopens_documents() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("crash.sh");
+    std::fs::write(&test_file, "#!/bin/bash\necho hello\n")?;
+    let test_file_str = test_file.to_str().context("path")?;
+    let root = dir.path().to_str().context("path")?;
+
+    // mockls exits after answering initialize and one hover.
+    let lsp = mockls_lsp_arg("shellscript", "--drop-after 2");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], root)?;
+    bridge.initialize()?;
+
+    let mut hover = |id: u64| -> Result<String> {
+        bridge.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": {
+                "name": "hover",
+                "arguments": { "file": test_file_str, "line": 1, "character": 0 }
+            }
+        }))?;
+        let response = bridge.recv()?;
+        response["result"]["content"][0]["text"]
+            .as_str()
+            .map(str::to_string)
+            .context(format!("missing text: {response:?}"))
+    };
+    let first = hover(2)?;
+    assert!(first.contains("echo"), "got: {first}");
+    std::thread::sleep(Duration::from_millis(200));
+
+    // The replacement server only knows the file if it was reopened.
+    let second = hover(3)?;
+    assert_eq!(second, first);
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 4,
+        "method": "tools/call",
+        "params": { "name": "status", "arguments": {} }
+    }))?;
+    let response = bridge.recv()?;
+    let status = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert!(status.contains("restarts: 1"), "got: {status}");
+    // The replacement hits `--drop-after` too; the summary says so up front.
+    assert_eq!(
+        status.lines().nth(1),
+        Some("0/1 server ready; shellscript dead"),
+        "got: {status}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mockls_investigate() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("investigate.sh");
+    std::fs::write(
+        &test_file,
+        "#!/bin/bash\nfunction greet() {\n  echo hi\n}\ngreet\ngreet\n",
+    )?;
+    let test_file_str = test_file.to_str().context("path")?;
+    let root = dir.path().to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], root)?;
+    bridge.initialize()?;
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {
+            "name": "investigate",
+            "arguments": { "symbol": "greet", "file": test_file_str }
+        }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert_eq!(
+        text,
+        "Symbol: greet\n\
+         Definition: investigate.sh:2:1\n\
+         \x20 2 | function greet() {\n\
+         \x20 3 |   echo hi\n\
+         \x20 4 | }\n\
+         \x20 5 | greet\n\
+         \x20 6 | greet\n\
+         References: 2 in 1 file\n\
+         \x20 investigate.sh: 2"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mockls_symbol_index_serves_later_sessions() -> Result<()> {
+    let workspace = tempfile::tempdir()?;
+    let home = tempfile::tempdir()?;
+    std::fs::write(home.path().join("config.toml"), "symbol_index = true\n")?;
+    let lib = workspace.path().join("lib.sh");
+    std::fs::write(&lib, "#!/bin/bash\nfunction build_index() {\n  true\n}\n")?;
+    let lib_str = lib.to_str().context("path")?;
+    let root = workspace.path().to_str().context("path")?;
+    let home_str = home.path().to_str().context("path")?;
+    let lsp = mockls_lsp_arg("shellscript", "");
+
+    // First session: looking at the file records its symbols
+    {
+        let mut bridge =
+            BridgeProcess::spawn_with_flags(&[&lsp], &[root], &["--config-home", home_str])?;
+        bridge.initialize()?;
+        bridge.send(&json!({
+            "jsonrpc": "2.0",
+            "id": 2,
+            "method": "tools/call",
+            "params": {
+                "name": "document_symbols",
+                "arguments": { "file": lib_str }
+            }
+        }))?;
+        let response = bridge.recv()?;
+        assert!(
+            response["result"]["isError"].is_null(),
+            "document_symbols failed: {response:?}"
+        );
+    }
+
+    // Second session: mockls only knows open documents, so the symbol can
+    // only come from the index
+    let mut bridge =
+        BridgeProcess::spawn_with_flags(&[&lsp], &[root], &["--config-home", home_str])?;
+    bridge.initialize()?;
+    let mut search = |id: u64| -> Result<String> {
+        bridge.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": {
+                "name": "search",
+                "arguments": { "queries": ["build_index"] }
+            }
+        }))?;
+        let response = bridge.recv()?;
+        Ok(response["result"]["content"][0]["text"]
+            .as_str()
+            .context(format!("missing text: {response:?}"))?
+            .to_string())
+    };
+    let text = search(3)?;
+    assert!(
+        text.contains("build_index [Function]") && text.contains("lib.sh:2"),
+        "Expected indexed symbol, got: {text}"
+    );
+
+    // Editing the file outside Catenary invalidates the entry
+    std::fs::write(&lib, "#!/bin/bash\nfunction renamed() {\n  true\n}\n")?;
+    let file = std::fs::File::options().write(true).open(&lib)?;
+    file.set_modified(std::time::SystemTime::now() + Duration::from_secs(5))?;
+    let text = search(4)?;
+    assert!(
+        !text.contains("build_index [Function]"),
+        "Stale entry should be dropped, got: {text}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mockls_incremental_sync_applies_edits() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("sync.sh");
+    std::fs::write(
+        &test_file,
+        "#!/bin/bash\nfunction greet() {\n  echo hé\n}\nfunction wave() {\n  greet\n}\ngreet\n",
+    )?;
+    let test_file_str = test_file.to_str().context("path")?;
+    let root = dir.path().to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "--code-lens --incremental-sync");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], root)?;
+    bridge.initialize()?;
+
+    let mut lenses = |id: u64| -> Result<String> {
+        bridge.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": { "name": "code_lens", "arguments": { "file": test_file_str } }
+        }))?;
+        let response = bridge.recv()?;
+        response["result"]["content"][0]["text"]
+            .as_str()
+            .map(str::to_string)
+            .context(format!("missing text: {response:?}"))
+    };
+    assert_eq!(lenses(2)?, "L2: 2 references\nL5: 0 references");
+
+    // The edit follows a multibyte character, so a range counted in bytes
+    // instead of UTF-16 units would land in the wrong place.
+    std::thread::sleep(Duration::from_millis(50));
+    std::fs::write(
+        &test_file,
+        "#!/bin/bash\nfunction greet() {\n  echo hé greet\n}\nfunction wave() {\n  greet\n}\ngreet\n",
+    )?;
+    assert_eq!(lenses(3)?, "L2: 3 references\nL5: 0 references");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_inlay_hints() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("hints.sh");
+    std::fs::write(&test_file, "#!/bin/bash\nname=world\necho $name\ncount=3\n")?;
+    let test_file_str = test_file.to_str().context("path")?;
+    let root = dir.path().to_str().context("path")?;
+
+    let call = json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {
+            "name": "inlay_hints",
+            "arguments": { "file": test_file_str, "start_line": 0, "end_line": 2 }
+        }
+    });
+
+    let lsp = mockls_lsp_arg("shellscript", "--inlay-hints");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], root)?;
+    bridge.initialize()?;
+    bridge.send(&call)?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert_eq!(text, "L2:5 : string", "line 3 is outside the range");
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], root)?;
+    bridge.initialize()?;
+    bridge.send(&call)?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert_eq!(text, "Inlay hints not supported");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_workspace_diagnostics() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let broken = dir.path().join("broken.sh");
+    std::fs::write(&broken, "#!/bin/bash\necho BROKEN\necho BROKEN\n")?;
+    let clean = dir.path().join("clean.sh");
+    std::fs::write(&clean, "#!/bin/bash\necho ok\n")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "--error-marker BROKEN");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    for (id, file) in [(2, &broken), (3, &clean)] {
+        bridge.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": {
+                "name": "diagnostics",
+                "arguments": { "file": file.to_str().context("path")? }
+            }
+        }))?;
+        bridge.recv()?;
+    }
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 4,
+        "method": "tools/call",
+        "params": { "name": "workspace_diagnostics", "arguments": {} }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+
+    assert!(text.starts_with("[shellscript]\n"), "got: {text}");
+    // mockls always publishes one line-count warning per document.
+    assert!(
+        text.contains("broken.sh: 2 errors, 1 warnings\n"),
+        "got: {text}"
+    );
+    assert!(
+        text.contains("clean.sh: 0 errors, 1 warnings\n"),
+        "got: {text}"
+    );
+    assert!(text.ends_with("Total: 2 errors, 2 warnings"), "got: {text}");
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 5,
+        "method": "tools/call",
+        "params": { "name": "workspace_diagnostics", "arguments": { "severity": "error" } }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert!(!text.contains("clean.sh"), "got: {text}");
+    assert!(text.ends_with("Total: 2 errors, 0 warnings"), "got: {text}");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_diagnostics_workspace_includes_unopened_files() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let opened = dir.path().join("opened.sh");
+    std::fs::write(&opened, "#!/bin/bash\necho hi\n")?;
+    std::fs::write(dir.path().join("unopened.sh"), "#!/bin/bash\n")?;
+    let opened = opened.to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "--publish-related unopened.sh");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    let call = |id: u64, workspace: bool| {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": {
+                "name": "diagnostics",
+                "arguments": { "file": opened, "workspace": workspace }
+            }
+        })
+    };
+
+    bridge.send(&call(2, false))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert!(!text.contains("unopened.sh"), "got: {text}");
+    assert!(
+        response["result"]["structuredContent"]
+            .get("workspace")
+            .is_none()
+    );
+
+    // The related publication follows the opened file's; retry until it lands.
+    let mut id = 3;
+    let response = loop {
+        bridge.send(&call(id, true))?;
+        let response = bridge.recv()?;
+        let text = response["result"]["content"][0]["text"].as_str();
+        if text.is_some_and(|t| t.contains("unopened.sh")) || id > 50 {
+            break response;
+        }
+        id += 1;
+        std::thread::sleep(Duration::from_millis(100));
+    };
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    let unopened = dir.path().join("unopened.sh");
+    assert!(
+        text.ends_with(&format!(
+            "\n\n{}:\n  1:1: [warning] mockls: mockls: mock diagnostic (0 lines)",
+            unopened.display()
+        )),
+        "got: {text}"
+    );
+    let workspace = &response["result"]["structuredContent"]["workspace"];
+    assert_eq!(
+        workspace.as_array().map(Vec::len),
+        Some(1),
+        "got: {workspace}"
+    );
+    assert_eq!(workspace[0]["file"], json!(unopened));
+    Ok(())
+}
+
+#[test]
+fn test_mockls_prepare_rename() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("rename.sh");
+    std::fs::write(&test_file, "#!/bin/bash\ngreeting=hello\necho $greeting\n")?;
+    let test_file_str = test_file.to_str().context("path")?;
+    let root = dir.path().to_str().context("path")?;
+
+    let call = |id: u64, line: u32, character: u32| {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": {
+                "name": "prepare_rename",
+                "arguments": { "file": test_file_str, "line": line, "character": character }
+            }
+        })
+    };
+
+    let lsp = mockls_lsp_arg("shellscript", "--prepare-rename");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], root)?;
+    bridge.initialize()?;
+
+    bridge.send(&call(2, 2, 8))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert_eq!(text, "Can rename `greeting` at L3:7-L3:15");
+
+    bridge.send(&call(3, 2, 4))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert_eq!(text, "Cannot rename at this location");
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], root)?;
+    bridge.initialize()?;
+    bridge.send(&call(2, 2, 8))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert_eq!(text, "Prepare rename not supported");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_code_actions_at_cursor() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("cursor.sh");
+    std::fs::write(&test_file, "#!/bin/bash\necho BROKEN\n")?;
+    let test_file_str = test_file.to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "--error-marker BROKEN");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {
+            "name": "code_actions",
+            "arguments": { "file": test_file_str, "line": 1, "character": 7 }
+        }
+    }))?;
+
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+
+    assert!(
+        text.starts_with("Range: [1,5]-[1,11] (selection range)"),
+        "cursor should widen to the word under it: {text}"
+    );
+    assert!(
+        text.contains("[Command] Explain with mockls"),
+        "got: {text}"
+    );
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "tools/call",
+        "params": {
+            "name": "code_actions",
+            "arguments": { "file": test_file_str, "start_line": 1 }
+        }
+    }))?;
+    let response = bridge.recv()?;
+    assert_eq!(response["result"]["isError"], true, "got: {response:?}");
+    Ok(())
+}
+
+#[test]
+fn test_mockls_crlf_positions() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("crlf.sh");
+    std::fs::write(
+        &test_file,
+        "#!/bin/bash\r\nfn my_function() { echo hi; }\r\n  my_function\r\n",
+    )?;
+    let test_file_str = test_file.to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {
+            "name": "hover",
+            "arguments": { "file": test_file_str, "line": 2, "character": 2 }
+        }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert!(text.contains("my_function"), "hover on CRLF line: {text}");
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 3,
+        "method": "tools/call",
+        "params": {
+            "name": "definition",
+            "arguments": { "file": test_file_str, "line": 2, "character": 4 }
+        }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+    assert!(
+        text.contains("crlf.sh:2:1"),
+        "definition on CRLF line: {text}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_health_check_reports_servers() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("probe.sh"), "echo hi\n")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    let mut text = String::new();
+    for id in 2..40 {
+        bridge.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": { "name": "health_check", "arguments": {} }
+        }))?;
+        let response = bridge.recv()?;
+        text = response["result"]["content"][0]["text"]
+            .as_str()
+            .context(format!("missing text: {response:?}"))?
+            .to_string();
+        if text.contains("shellscript Ready") {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+    }
+
+    assert!(text.starts_with("Catenary "), "got: {text}");
+    assert!(text.contains("\nUptime: "), "got: {text}");
+    assert!(text.contains("Servers: shellscript Ready"), "got: {text}");
+    Ok(())
+}
+
+#[test]
+fn test_capabilities_lists_supported_tools() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("probe.sh"), "echo hi\n")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], dir.path().to_str().context("path")?)?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": { "name": "capabilities", "arguments": {} }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context(format!("missing text: {response:?}"))?;
+
+    assert!(
+        text.starts_with("shellscript: hover definition "),
+        "got: {text}"
+    );
+    assert!(
+        !text.contains("call_hierarchy"),
+        "mockls does not advertise call hierarchy: {text}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_init_timeout_waits_for_background_spawn() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("probe.sh"), "echo hi\n")?;
+    std::fs::write(dir.path().join("probe.py"), "print('hi')\n")?;
+
+    // Slow handshakes keep the background spawn running past initialize.
+    // Servers start one after another, so a call that only waited for the
+    // first would see python alone.
+    let python = mockls_lsp_arg("python", "--response-delay 500");
+    let shell = mockls_lsp_arg("shellscript", "--response-delay 500");
+    let mut bridge = BridgeProcess::spawn_with_flags(
+        &[&python, &shell],
+        &[dir.path().to_str().context("path")?],
+        &["--init-timeout", "10"],
+    )?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": { "name": "capabilities", "arguments": {} }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context("text")?;
+    let servers: Vec<&str> = text
+        .lines()
+        .filter_map(|line| line.split(':').next())
+        .collect();
+    assert_eq!(
+        servers,
+        vec!["python", "shellscript"],
+        "the call should wait for the spawn: {text}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_init_timeout_first_call_succeeds() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let file = dir.path().join("probe.sh");
+    std::fs::write(&file, "echo hi\n")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let mut bridge = BridgeProcess::spawn_with_flags(
+        &[&lsp],
+        &[dir.path().to_str().context("path")?],
+        &["--init-timeout", "5"],
+    )?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 2,
+        "method": "tools/call",
+        "params": {
+            "name": "hover",
+            "arguments": {
+                "file": file.to_str().context("path")?,
+                "line": 0,
+                "character": 0
+            }
+        }
+    }))?;
+    let response = bridge.recv()?;
+
+    assert!(
+        response["result"]["isError"].is_null(),
+        "first call should not fail: {response:?}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_strict_roots_rejects_outside_paths() -> Result<()> {
+    let root = tempfile::tempdir()?;
+    let outside = tempfile::tempdir()?;
+    let inside_file = root.path().join("inside.sh");
+    let outside_file = outside.path().join("outside.sh");
+    std::fs::write(&inside_file, "echo hi\n")?;
+    std::fs::write(&outside_file, "echo hi\n")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "");
+    let root_str = root.path().to_str().context("path")?;
+    let mut bridge = BridgeProcess::spawn_with_flags(&[&lsp], &[root_str], &["--strict-roots"])?;
+    bridge.initialize()?;
+
+    let calls = [
+        (
+            "hover",
+            json!({ "file": outside_file, "line": 0, "character": 0 }),
+        ),
+        ("document_symbols", json!({ "file": outside_file })),
+        ("codebase_map", json!({ "path": outside.path() })),
+    ];
+    for (id, (tool, arguments)) in (10..).zip(calls) {
+        bridge.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": { "name": tool, "arguments": arguments }
+        }))?;
+        let response = bridge.recv()?;
+        let text = response["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap_or_default();
+        assert_eq!(response["result"]["isError"], true, "{tool}: {response:?}");
+        assert!(
+            text.contains("outside workspace roots"),
+            "{tool} should be rejected: {text}"
+        );
+    }
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 20,
+        "method": "tools/call",
+        "params": {
+            "name": "hover",
+            "arguments": { "file": inside_file, "line": 0, "character": 0 }
+        }
+    }))?;
+    let response = bridge.recv()?;
+    assert!(
+        response["result"]["isError"].is_null() || response["result"]["isError"] == false,
+        "inside paths are still allowed: {response:?}"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_mockls_definition_across_profiles() -> Result<()> {
+    let profiles: &[(&str, &str)] = &[("clean", ""), ("workspace-folders", "--workspace-folders")];
+
+    for (name, flags) in profiles {
+        let test_file = "/tmp/mockls_def_test.sh";
+        std::fs::write(
+            test_file,
+            "#!/bin/bash\nfn my_function() { echo hi; }\nmy_function\n",
+        )?;
+
+        let lsp = mockls_lsp_arg("shellscript", flags);
+        let mut bridge = BridgeProcess::spawn(&[&lsp], "/tmp")?;
+        bridge.initialize()?;
+
+        bridge.send(&json!({
+            "jsonrpc": "2.0",
+            "id": 4,
+            "method": "tools/call",
+            "params": {
+                "name": "definition",
+                "arguments": {
+                    "file": test_file,
+                    "line": 2,
+                    "character": 0
+                }
+            }
+        }))?;
+
+        let response = bridge.recv()?;
+        let result = &response["result"];
+        assert!(
+            result["isError"].is_null() || result["isError"] == false,
+            "Profile {name}: definition failed: {response:?}"
+        );
+
+        let content = result["content"]
+            .as_array()
+            .context(format!("Profile {name}: missing content array"))?;
+        assert!(
+            !content.is_empty(),
+            "Profile {name}: expected definition content"
+        );
+
+        let text = content[0]["text"]
+            .as_str()
+            .context(format!("Profile {name}: missing text"))?;
+        assert!(
+            text.contains(test_file),
+            "Profile {name}: definition should contain file path, got: {text}"
+        );
+
+        std::fs::remove_file(test_file).ok();
+    }
+    Ok(())
+}
+
+#[test]
+fn test_definition_falls_back_to_workspace_symbol() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("script.sh");
+    std::fs::write(
+        &test_file,
+        "#!/bin/bash\nfn my_function() { echo hi; }\nmy_function\n",
+    )?;
+    let test_file = test_file.to_str().context("path")?;
+    let root = dir.path().to_str().context("path")?;
+
+    let lsp = mockls_lsp_arg("shellscript", "--no-definition");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], root)?;
+    bridge.initialize()?;
+
+    let call = |bridge: &mut BridgeProcess, id: u64, arguments: Value| -> Result<String> {
+        bridge.send(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "tools/call",
+            "params": { "name": "definition", "arguments": arguments }
+        }))?;
+        let response = bridge.recv()?;
+        Ok(response["result"]["content"][0]["text"]
+            .as_str()
+            .context("missing text")?
+            .to_string())
+    };
+
+    let text = call(
+        &mut bridge,
+        4,
+        json!({ "symbol": "my_function", "file": test_file }),
+    )?;
+    assert!(
+        text.starts_with(&format!("{test_file}:2:")),
+        "should report the workspace symbol location: {text}"
+    );
+    assert!(
+        text.contains("approximate"),
+        "should flag the match: {text}"
+    );
+
+    // Without a symbol name there is nothing to fall back on
+    let text = call(
+        &mut bridge,
+        5,
+        json!({ "file": test_file, "line": 2, "character": 0 }),
+    )?;
+    assert_eq!(text, "No definition found");
+    Ok(())
+}
+
+#[test]
+fn test_hover_retries_while_server_indexes() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let test_file = dir.path().join("script.sh");
+    std::fs::write(&test_file, "#!/bin/bash\nmy_function\n")?;
+    let root = dir.path().to_str().context("path")?;
+
+    // The server re-indexes whenever a file is opened and answers hover with
+    // null meanwhile. Opening waits up to 5s for analysis to settle; the
+    // default retries cover the rest.
+    let lsp = mockls_lsp_arg("shellscript", "--index-on-open 5500");
+    let mut bridge = BridgeProcess::spawn(&[&lsp], root)?;
+    bridge.initialize()?;
+
+    bridge.send(&json!({
+        "jsonrpc": "2.0",
+        "id": 4,
+        "method": "tools/call",
+        "params": {
+            "name": "hover",
+            "arguments": {
+                "file": test_file.to_str().context("path")?,
+                "line": 1,
+                "character": 3
+            }
+        }
+    }))?;
+    let response = bridge.recv()?;
+    let text = response["result"]["content"][0]["text"]
+        .as_str()
+        .context("missing text")?;
+    assert!(
+        text.contains("my_function"),
+        "hover should be retried until indexing ends: {text}"
+    );
     Ok(())
 }
 
@@ -90,3 +90,45 @@ fn test_monitor_raw_messages() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_monitor_shows_server_messages() -> Result<()> {
+    let session = Session::create("/tmp/monitor-server-message-test")?;
+    let session_id = session.info.id.clone();
+    let dir = tempfile::tempdir()?;
+
+    let mut client = catenary_mcp::lsp::LspClient::spawn(
+        env!("CARGO_BIN_EXE_mockls"),
+        &["--show-message", "project requires reload"],
+        "shellscript",
+        session.broadcaster(),
+    )?;
+    client.initialize(&[dir.path().to_path_buf()], None).await?;
+
+    // The message arrives asynchronously after `initialized`
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    let found = loop {
+        let found = session::monitor_events(&session_id)?.any(|e| {
+            matches!(
+                &e.kind,
+                EventKind::ServerMessage { language, level, message }
+                    if language == "shellscript"
+                        && level == "warning"
+                        && message == "project requires reload"
+            )
+        });
+        if found || std::time::Instant::now() > deadline {
+            break found;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    };
+
+    client.shutdown().await?;
+    drop(session);
+
+    assert!(
+        found,
+        "window/showMessage was not forwarded as a ServerMessage event"
+    );
+    Ok(())
+}